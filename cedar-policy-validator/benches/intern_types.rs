@@ -0,0 +1,110 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Measures the heap memory held by a large generated schema before and after
+//! `SchemaFragment::intern_types`. Run with
+//! `cargo bench -p cedar-policy-validator --bench intern_types`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cedar_policy_validator::SchemaFragment;
+
+/// Wraps the system allocator, keeping count of the bytes currently
+/// allocated.
+struct CountingAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// A schema in the style of a generated one: many entity types with the same
+/// handful of scalar, record and set-typed attributes, and the same tags.
+fn generated_schema(entity_types: usize) -> serde_json::Value {
+    let shape = serde_json::json!({
+        "type": "Record",
+        "attributes": {
+            "name": { "type": "String" },
+            "description": { "type": "String", "required": false },
+            "age": { "type": "Long" },
+            "active": { "type": "Boolean" },
+            "address": {
+                "type": "Record",
+                "attributes": {
+                    "street": { "type": "String" },
+                    "city": { "type": "String" },
+                    "zip": { "type": "String" }
+                }
+            },
+            "emails": { "type": "Set", "element": { "type": "String" } },
+            "scores": { "type": "Set", "element": { "type": "Long" } },
+            "groups": {
+                "type": "Set",
+                "element": {
+                    "type": "Set",
+                    "element": {
+                        "type": "Record",
+                        "attributes": {
+                            "name": { "type": "String" },
+                            "owners": { "type": "Set", "element": { "type": "String" } }
+                        }
+                    }
+                }
+            }
+        }
+    });
+    let entity_types: serde_json::Map<_, _> = (0..entity_types)
+        .map(|i| {
+            let entity_type = serde_json::json!({
+                "shape": shape,
+                "tags": { "type": "Set", "element": { "type": "String" } }
+            });
+            (format!("Type{i}"), entity_type)
+        })
+        .collect();
+    serde_json::json!({
+        "App": { "entityTypes": entity_types, "actions": {} }
+    })
+}
+
+fn main() {
+    for entity_types in [1_000, 10_000, 50_000] {
+        let src = generated_schema(entity_types).to_string();
+        let base = LIVE_BYTES.load(Ordering::Relaxed);
+        let mut fragment = SchemaFragment::from_json_str(&src).expect("valid schema");
+        let before = LIVE_BYTES.load(Ordering::Relaxed) - base;
+        fragment.intern_types();
+        let after = LIVE_BYTES.load(Ordering::Relaxed) - base;
+        println!(
+            "{entity_types:>6} entity types: {before:>11} bytes before interning, \
+             {after:>11} bytes after ({:.1}% saved)",
+            100.0 * (before - after) as f64 / before as f64
+        );
+    }
+}
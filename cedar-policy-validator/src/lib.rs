@@ -135,6 +135,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
                 (
@@ -142,6 +146,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
             ],
@@ -155,6 +163,7 @@ mod test {
                     }),
                     member_of: None,
                     attributes: None,
+                    since: None,
                 },
             )],
         );
@@ -487,6 +487,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![group_type.into()],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
                 (
@@ -494,6 +498,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
                 (
@@ -501,6 +509,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![bin_type.into()],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
                 (
@@ -508,6 +520,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
             ],
@@ -521,6 +537,7 @@ mod test {
                     }),
                     member_of: None,
                     attributes: None,
+                    since: None,
                 },
             )],
         );
@@ -564,6 +581,10 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    additional_attributes_definition: None,
+                    enumeration: None,
+                    tags: None,
+                    since: None,
                 },
             )],
             [],
@@ -599,6 +620,10 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    additional_attributes_definition: None,
+                    enumeration: None,
+                    tags: None,
+                    since: None,
                 },
             )],
             [],
@@ -686,6 +711,7 @@ mod test {
                     applies_to: None,
                     member_of: None,
                     attributes: None,
+                    since: None,
                 },
             )],
         );
@@ -720,6 +746,10 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    additional_attributes_definition: None,
+                    enumeration: None,
+                    tags: None,
+                    since: None,
                 },
             )],
             [],
@@ -752,6 +782,10 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    additional_attributes_definition: None,
+                    enumeration: None,
+                    tags: None,
+                    since: None,
                 },
             )],
             [],
@@ -784,6 +818,10 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    additional_attributes_definition: None,
+                    enumeration: None,
+                    tags: None,
+                    since: None,
                 },
             )],
             [],
@@ -828,6 +866,7 @@ mod test {
                     applies_to: None,
                     member_of: None,
                     attributes: None,
+                    since: None,
                 },
             )],
         );
@@ -1026,6 +1065,7 @@ mod test {
                     applies_to: None,
                     member_of: None,
                     attributes: None,
+                    since: None,
                 },
             )],
         );
@@ -1063,6 +1103,7 @@ mod test {
                     applies_to: None,
                     member_of: None,
                     attributes: None,
+                    since: None,
                 },
             )],
         );
@@ -1100,6 +1141,7 @@ mod test {
                     applies_to: None,
                     member_of: None,
                     attributes: None,
+                    since: None,
                 },
             )],
         );
@@ -1135,6 +1177,10 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    additional_attributes_definition: None,
+                    enumeration: None,
+                    tags: None,
+                    since: None,
                 },
             )],
             [],
@@ -1170,6 +1216,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
                 (
@@ -1177,6 +1227,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
             ],
@@ -1190,6 +1244,7 @@ mod test {
                     }),
                     member_of: Some(vec![]),
                     attributes: None,
+                    since: None,
                 },
             )],
         )
@@ -1378,6 +1433,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
                 (
@@ -1385,6 +1444,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![resource_parent_type.into()],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
                 (
@@ -1392,6 +1455,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![resource_grandparent_type.into()],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
                 (
@@ -1399,6 +1466,10 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
                     },
                 ),
             ],
@@ -1416,6 +1487,7 @@ mod test {
                             id: action_parent_name.into(),
                         }]),
                         attributes: None,
+                        since: None,
                     },
                 ),
                 (
@@ -1427,6 +1499,7 @@ mod test {
                             id: action_grandparent_name.into(),
                         }]),
                         attributes: None,
+                        since: None,
                     },
                 ),
                 (
@@ -1435,6 +1508,7 @@ mod test {
                         applies_to: None,
                         member_of: Some(vec![]),
                         attributes: None,
+                        since: None,
                     },
                 ),
             ],
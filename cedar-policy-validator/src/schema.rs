@@ -212,6 +212,9 @@ impl ValidatorNamespaceDef {
         namespace_def: NamespaceDefinition,
         action_behavior: ActionBehavior,
     ) -> Result<ValidatorNamespaceDef> {
+        let mut namespace_def = namespace_def;
+        namespace_def.expand_imports()?;
+
         // Check that each entity types and action is only declared once.
         let mut e_types_ids: HashSet<SmolStr> = HashSet::new();
         for name in namespace_def.entity_types.keys() {
@@ -522,8 +525,13 @@ impl ValidatorNamespaceDef {
                 Ok((
                     attr,
                     (
-                        Self::try_schema_type_into_validator_type(schema_namespace, ty.ty)?,
-                        ty.required,
+                        Self::try_schema_type_into_validator_type(
+                            schema_namespace,
+                            ty.ty.into_inner(),
+                        )?,
+                        // An attribute with a `requiredIf` condition may be
+                        // absent, so the validator treats it as optional.
+                        ty.required && ty.required_if.is_none(),
                     ),
                 ))
             })
@@ -640,11 +648,13 @@ impl ValidatorNamespaceDef {
         schema_ty: SchemaType,
     ) -> Result<WithUnresolvedTypeDefs<Type>> {
         match schema_ty {
-            SchemaType::Type(SchemaTypeVariant::String) => Ok(Type::primitive_string().into()),
-            SchemaType::Type(SchemaTypeVariant::Long) => Ok(Type::primitive_long().into()),
+            SchemaType::Type(SchemaTypeVariant::String { .. }) => {
+                Ok(Type::primitive_string().into())
+            }
+            SchemaType::Type(SchemaTypeVariant::Long { .. }) => Ok(Type::primitive_long().into()),
             SchemaType::Type(SchemaTypeVariant::Boolean) => Ok(Type::primitive_boolean().into()),
             SchemaType::Type(SchemaTypeVariant::Set { element }) => Ok(
-                Self::try_schema_type_into_validator_type(default_namespace, *element)?
+                Self::try_schema_type_into_validator_type(default_namespace, element.into_inner())?
                     .map(Type::set),
             ),
             SchemaType::Type(SchemaTypeVariant::Record {
@@ -670,11 +680,34 @@ impl ValidatorNamespaceDef {
                 .map_err(SchemaError::EntityTypeParseError)?;
                 Ok(Type::named_entity_reference(entity_type_name).into())
             }
-            SchemaType::Type(SchemaTypeVariant::Extension { name }) => {
+            SchemaType::Type(SchemaTypeVariant::Extension { name, ip_kind }) => {
+                if ip_kind.is_some() && name != "ipaddr" {
+                    return Err(SchemaError::IpKindOnNonIpaddr(name.to_string()));
+                }
                 let extension_type_name =
                     name.parse().map_err(SchemaError::ExtensionTypeParseError)?;
                 Ok(Type::extension(extension_type_name).into())
             }
+            SchemaType::Type(SchemaTypeVariant::Union { options }) => {
+                let options = options
+                    .into_iter()
+                    .map(|option| {
+                        Self::try_schema_type_into_validator_type(
+                            default_namespace,
+                            option.into_inner(),
+                        )
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(WithUnresolvedTypeDefs::new(move |typ_defs| {
+                    let options = options
+                        .into_iter()
+                        .map(|option| option.resolve_type_defs(typ_defs))
+                        .collect::<Result<Vec<_>>>()?;
+                    Type::union_of(options).ok_or(SchemaError::UnsupportedSchemaFeature(
+                        UnsupportedFeature::UnionOfUnrelatedTypes,
+                    ))
+                }))
+            }
             SchemaType::TypeDef { type_name } => {
                 let defined_type_name = Self::parse_possibly_qualified_name_with_default_namespace(
                     &type_name,
@@ -716,6 +749,10 @@ impl ValidatorSchemaFragment {
         fragment: SchemaFragment,
         action_behavior: ActionBehavior,
     ) -> Result<Self> {
+        let fragment = fragment.with_additional_attributes_merged()?;
+        fragment.validate_examples()?;
+        fragment.validate_required_if()?;
+        fragment.validate_units()?;
         Ok(Self(
             fragment
                 .0
@@ -755,7 +792,7 @@ impl std::str::FromStr for ValidatorSchema {
     type Err = SchemaError;
 
     fn from_str(s: &str) -> Result<Self> {
-        serde_json::from_str::<SchemaFragment>(s)?.try_into()
+        SchemaFragment::from_json_str(s)?.try_into()
     }
 }
 
@@ -1665,6 +1702,64 @@ mod test {
         assert!(schema.is_ok());
     }
 
+    // A fragment mixing the empty namespace with a named namespace, where
+    // declarations in the empty namespace reference the named namespace.
+    #[test]
+    fn test_empty_namespace_round_trip() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["App::Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "home": { "type": "Entity", "name": "App::Group" }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["App::Group"]
+                        },
+                        "memberOf": [{ "id": "read", "type": "App::Action" }]
+                    }
+                }
+            },
+            "App": {
+                "entityTypes": { "Group": {} },
+                "actions": { "read": {} }
+            }
+        }))
+        .expect("Parse Error");
+
+        let json = serde_json::to_value(&fragment).expect("Serialize Error");
+        let round_tripped = SchemaFragment::from_json_value(json).expect("Parse Error");
+        assert_eq!(round_tripped, fragment);
+
+        let schema: ValidatorSchema = round_tripped.try_into().expect("Schema Error");
+        let user = schema
+            .get_entity_type(&"User".parse().unwrap())
+            .expect("`User` should be declared without a namespace");
+        assert_eq!(
+            user.attr("home").map(|a| &a.attr_type),
+            Some(&Type::named_entity_reference("App::Group".parse().unwrap()))
+        );
+        let group = schema
+            .get_entity_type(&"App::Group".parse().unwrap())
+            .expect("`App::Group` should be declared");
+        assert!(group.descendants.contains(&"User".parse().unwrap()));
+        let read = schema
+            .get_action_id(&EntityUID::from_str(r#"App::Action::"read""#).unwrap())
+            .expect("`App::Action::\"read\"` should be declared");
+        assert!(read
+            .descendants
+            .contains(&EntityUID::from_str(r#"Action::"view""#).unwrap()));
+    }
+
     // Duplicate entity "Photo"
     #[test]
     fn test_from_schema_file_duplicate_entity() {
@@ -14,36 +14,305 @@
  * limitations under the License.
  */
 
-use cedar_policy_core::entities::JSONValue;
+use cedar_policy_core::{
+    entities::JSONValue,
+    parser::{parse_euid, parse_ident, parse_name},
+};
+use itertools::Itertools;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use smol_str::SmolStr;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
 
-use crate::Result;
+use crate::{Result, SchemaError};
+
+mod access;
+mod actions;
+mod ambiguity;
+pub use ambiguity::SchemaWarning;
+#[cfg(feature = "json5")]
+mod annotated;
+mod autofix;
+pub use autofix::{AppliedFix, SchemaFix};
+mod changelog;
+mod constants;
+mod cross_refs;
+pub use cross_refs::CrossRefIndex;
+mod csv;
+mod diff;
+pub use diff::*;
+mod entities;
+mod enums;
+mod explain;
+pub use explain::{ActionExplanation, EntityTypeExplanation};
+mod fixtures;
+mod flatten;
+mod hierarchy;
+mod imports;
+mod infer;
+mod inline;
+mod interface;
+pub use flatten::DEFAULT_NAMESPACE_DELIMITER;
+pub use interface::{InterfaceChange, InterfaceDiff, InterfaceSide, InterfaceValue};
+mod json_schema;
+mod limits;
+pub use limits::*;
+mod lint;
+mod lockfile;
+pub use lockfile::*;
+mod merge;
+mod normalize;
+mod pattern;
+pub use pattern::StringPattern;
+mod permissions;
+pub use lint::*;
+pub use merge::MergeStrategy;
+pub use permissions::{PermissionMatrix, Role};
+mod profile;
+mod projection;
+pub use profile::SchemaProfile;
+mod reconcile;
+pub use reconcile::{ReconciliationFinding, ReconciliationReport};
+mod references;
+mod rename;
+mod resolve;
+mod rust_codegen;
+mod scope;
+pub use scope::ScopeVar;
+mod since;
+pub use rust_codegen::RustGenOpts;
+mod summary;
+mod tags;
+mod type_path;
+mod units;
+pub use type_path::*;
+mod validate;
+pub use validate::*;
+mod values;
+pub use values::{AttributeValidationMode, ScalarKind};
 
 /// A SchemaFragment describe the types for a given instance of Cedar.
 /// SchemaFragments are composed of Entity Types and Action Types. The
 /// schema fragment is split into multiple namespace definitions, eac including
 /// a namespace name which is applied to all entity types (and the implicit
 /// `Action` entity type for all actions) in the schema.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// However a fragment is deserialized, the imports and then the constants of
+/// each namespace are expanded as it is read, so its string enumerations hold
+/// values rather than constant references. Serializing a fragment escapes
+/// values starting with `$`, so that reading it back gives an equal fragment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaFragment(pub HashMap<SmolStr, NamespaceDefinition>);
+
+/// The namespaces of a `SchemaFragment` as written, before expansion.
+#[derive(Deserialize)]
 #[serde(transparent)]
-pub struct SchemaFragment(
+struct WrittenSchemaFragment(
     #[serde(with = "::serde_with::rust::maps_duplicate_key_is_error")]
-    pub  HashMap<SmolStr, NamespaceDefinition>,
+    HashMap<SmolStr, NamespaceDefinition>,
 );
 
+impl<'de> Deserialize<'de> for SchemaFragment {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Self::expanded(WrittenSchemaFragment::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for SchemaFragment {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0
+            .iter()
+            .map(|(ns, ns_def)| (ns, ns_def.escape_constants()))
+            .collect::<HashMap<_, _>>()
+            .serialize(serializer)
+    }
+}
+
 impl SchemaFragment {
     /// Create a `SchemaFragment` from a JSON value (which should be an object
     /// of the appropriate shape).
     pub fn from_json_value(json: serde_json::Value) -> Result<Self> {
-        serde_json::from_value(json).map_err(Into::into)
+        Self::expanded(serde_json::from_value(json)?)
+    }
+
+    /// Create a `SchemaFragment` directly from a file. A UTF-8 byte order
+    /// mark at the start of the file is skipped, and a file containing
+    /// nothing else but whitespace is rejected with
+    /// `SchemaError::EmptySchemaFile`.
+    pub fn from_file(mut file: impl std::io::Read) -> Result<Self> {
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)
+            .map_err(serde_json::Error::io)?;
+        let contents = contents.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&contents);
+        if contents.iter().all(u8::is_ascii_whitespace) {
+            return Err(SchemaError::EmptySchemaFile);
+        }
+        Self::from_file_strict(contents)
+    }
+
+    /// Create a `SchemaFragment` directly from a file, which must contain
+    /// exactly a JSON document, without the byte order mark that `from_file`
+    /// allows.
+    pub fn from_file_strict(file: impl std::io::Read) -> Result<Self> {
+        Self::expanded(serde_json::from_reader(file)?)
+    }
+
+    /// Create a `SchemaFragment` from a string containing a JSON document.
+    pub fn from_json_str(s: &str) -> Result<Self> {
+        Self::expanded(serde_json::from_str(s)?)
+    }
+
+    /// The fragment `written`, with the imports and then the constants of
+    /// each namespace expanded. Every constructor, and `Deserialize`, ends
+    /// here, so a fragment is expanded exactly once.
+    fn expanded(written: WrittenSchemaFragment) -> Result<Self> {
+        let mut fragment = Self(written.0);
+        fragment.expand_imports()?;
+        fragment.expand_constants()?;
+        Ok(fragment)
+    }
+
+    /// This fragment as a JSON value, with the keys of every object,
+    /// including the namespace names, in sorted order. Parsing the value with
+    /// `from_json_value` gives back an equal fragment.
+    pub fn to_json_value(&self) -> Result<serde_json::Value> {
+        let mut json = serde_json::to_value(self)?;
+        sort_json_keys(&mut json);
+        Ok(json)
     }
 
-    /// Create a `SchemaFragment` directly from a file.
-    pub fn from_file(file: impl std::io::Read) -> Result<Self> {
-        serde_json::from_reader(file).map_err(Into::into)
+    /// This fragment as pretty-printed JSON, with keys ordered as by
+    /// `to_json_value`. This is also what `Display` writes.
+    pub fn to_json_string(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.to_json_value()?)?)
+    }
+
+    /// Deduplicate structurally equal types in this fragment so that they
+    /// share a single allocation. Every type nested inside a declared type
+    /// (set element types, record attribute types and union options, at any
+    /// depth) is shared with every equal nested type in the fragment,
+    /// including those in entity tags and `additionalAttributesDefinition`s.
+    /// This is most effective for large generated schemas which repeat the
+    /// same attribute types many times. The fragment is unchanged otherwise.
+    pub fn intern_types(&mut self) {
+        let mut interner = TypeInterner::default();
+        for (ns, ns_def) in self.0.iter_mut() {
+            for (_, ty) in ns_def.declared_type_paths_mut(ns) {
+                interner.intern_nested(ty);
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for SchemaFragment {
+    type Err = SchemaError;
+
+    /// Parse a `SchemaFragment` as by `SchemaFragment::from_json_str`.
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_json_str(s)
+    }
+}
+
+impl std::fmt::Display for SchemaFragment {
+    /// Pretty-printed JSON for this fragment, as by `to_json_string`. The
+    /// keys of every object, including the namespace names, are written in
+    /// sorted order, so the output does not depend on the iteration order of
+    /// the maps holding the declarations.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_json_string().map_err(|_| std::fmt::Error)?)
+    }
+}
+
+/// Reorder the keys of every object in `json` into sorted order.
+fn sort_json_keys(json: &mut serde_json::Value) {
+    match json {
+        serde_json::Value::Object(map) => {
+            let mut entries = std::mem::take(map).into_iter().collect_vec();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (key, mut value) in entries {
+                sort_json_keys(&mut value);
+                map.insert(key, value);
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(sort_json_keys),
+        _ => (),
+    }
+}
+
+impl SchemaFragment {
+    /// Find the declaration of the common type `name` as referenced from a type
+    /// declared in namespace `ns`. Returns the namespace containing the
+    /// declaration (which may differ from `ns` if `name` is qualified) together
+    /// with the declared type.
+    pub(crate) fn lookup_common_type(
+        &self,
+        ns: &str,
+        name: &str,
+    ) -> Option<(&SmolStr, &SchemaType)> {
+        let qualified = fully_qualify(ns, name);
+        let (def_ns, basename) = qualified.rsplit_once("::").unwrap_or(("", &qualified));
+        let (def_ns, ns_def) = self.0.get_key_value(def_ns)?;
+        Some((def_ns, ns_def.common_types.get(basename)?))
+    }
+}
+
+/// Qualify a type name written in namespace `ns` in the same way the schema
+/// does: names which already include a namespace are left untouched, and names
+/// written in the empty namespace remain unqualified.
+pub(crate) fn fully_qualify(ns: &str, name: &str) -> SmolStr {
+    if ns.is_empty() || name.contains("::") {
+        name.into()
+    } else {
+        format!("{ns}::{name}").into()
+    }
+}
+
+/// Holds the canonical shared copy of every nested type seen while interning
+/// a `SchemaFragment`.
+#[derive(Default)]
+struct TypeInterner {
+    types: BTreeSet<SharedSchemaType>,
+}
+
+impl TypeInterner {
+    /// Replace `ty` with the canonical copy of an equal type if there is one,
+    /// or make it the canonical copy otherwise.
+    fn intern(&mut self, ty: &mut SharedSchemaType) {
+        match self.types.get(&**ty) {
+            Some(canonical) => *ty = canonical.clone(),
+            None => {
+                // Intern the types nested in `ty` first, so that they are
+                // shared even though `ty` itself is not.
+                self.intern_nested(ty);
+                self.types.insert(ty.clone());
+            }
+        }
+    }
+
+    fn intern_nested(&mut self, ty: &mut SchemaType) {
+        match ty {
+            SchemaType::Type(SchemaTypeVariant::Set { element }) => self.intern(element),
+            SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+                for attr in attributes.values_mut() {
+                    self.intern(&mut attr.ty);
+                }
+            }
+            SchemaType::Type(SchemaTypeVariant::Union { options }) => {
+                for option in options {
+                    self.intern(option);
+                }
+            }
+            _ => (),
+        }
     }
 }
 
@@ -62,6 +331,25 @@ pub struct NamespaceDefinition {
     pub entity_types: HashMap<SmolStr, EntityType>,
     #[serde(with = "::serde_with::rust::maps_duplicate_key_is_error")]
     pub actions: HashMap<SmolStr, ActionType>,
+    /// Short names which may be used in this namespace in place of fully
+    /// qualified entity type or common type names, e.g. `"Album"` for
+    /// `"PhotoApp::Photos::Albums::Album"`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(with = "::serde_with::rust::maps_duplicate_key_is_error")]
+    pub imports: HashMap<SmolStr, SmolStr>,
+    /// The profile this namespace keeps to. See
+    /// `SchemaFragment::validate_against_profile`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<SchemaProfile>,
+    /// Named lists of strings which string enumerations in this namespace,
+    /// and other constants, may use by writing `$` followed by the name. A
+    /// value which itself starts with `$` is written with `$$`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(with = "::serde_with::rust::maps_duplicate_key_is_error")]
+    pub constants: HashMap<SmolStr, Vec<SmolStr>>,
 }
 
 impl NamespaceDefinition {
@@ -73,7 +361,173 @@ impl NamespaceDefinition {
             common_types: HashMap::new(),
             entity_types: entity_types.into_iter().collect(),
             actions: actions.into_iter().collect(),
+            imports: HashMap::new(),
+            profile: None,
+            constants: HashMap::new(),
+        }
+    }
+
+    /// Check that no two actions declared in this namespace, and no two
+    /// entries in a single `memberOf` list, refer to the same action once
+    /// qualified with the `Action` entity type. For example, an action declared
+    /// as `"view"` collides with one declared as `"Action::\"view\""`, and a
+    /// `memberOf` entry `{"id": "view"}` collides with
+    /// `{"id": "view", "type": "Action"}`. The error reports both spellings.
+    pub fn validate_action_uniqueness(&self) -> Result<()> {
+        let mut declared: HashMap<String, &SmolStr> = HashMap::new();
+        for name in self.actions.keys().sorted() {
+            let qualified = ActionEntityUID::from_declared_name(name).to_string();
+            if let Some(prev) = declared.insert(qualified.clone(), name) {
+                return Err(SchemaError::ActionIdCollision(
+                    qualified,
+                    vec![prev.to_string(), name.to_string()],
+                ));
+            }
+        }
+
+        for (_, action) in self.actions.iter().sorted_by_key(|(name, _)| *name) {
+            let mut parents: HashMap<String, &ActionEntityUID> = HashMap::new();
+            for parent in action.member_of.iter().flatten() {
+                if let Some(prev) = parents.insert(parent.to_string(), parent) {
+                    return Err(SchemaError::ActionIdCollision(
+                        parent.to_string(),
+                        vec![prev.spelling(), parent.spelling()],
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that no common type declared in this namespace has the same
+    /// name as an entity type or an action declared in it, since a reference
+    /// to that name would be ambiguous. Common types are checked in sorted
+    /// order, and the first conflict is reported as
+    /// `SchemaError::NameShadowing`.
+    pub fn validate_no_name_shadowing(&self) -> Result<()> {
+        for name in self.common_types.keys().sorted() {
+            let other = if self.entity_types.contains_key(name) {
+                "an entity type"
+            } else if self.actions.contains_key(name) {
+                "an action"
+            } else {
+                continue;
+            };
+            return Err(SchemaError::NameShadowing(
+                name.to_string(),
+                other.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Run the checks which can be made on this namespace on its own, without
+    /// the rest of the schema.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_action_uniqueness()?;
+        self.validate_no_self_embedding()
+    }
+
+    /// Check that no common type contains itself by value, through any
+    /// nesting of records, sets and unions. Common types are expanded into
+    /// their definitions when a schema is built, so such a type cannot be
+    /// used, even where its values could be written down because the
+    /// recursion goes through a set (which may be empty) or an optional
+    /// attribute. Containing an `Entity` reference to any type is allowed.
+    /// The name of this namespace is not known here, so only unqualified
+    /// common type names are followed; use
+    /// `SchemaFragment::validate_no_self_embedding` to follow qualified names
+    /// as well.
+    fn validate_no_self_embedding(&self) -> Result<()> {
+        let lookup = |_: &str, name: &str| self.common_types.get(name).map(|ty| ("", ty));
+        for name in self.common_types.keys().sorted() {
+            check_embedding(&lookup, "", &self.common_types[name], name)?;
         }
+        Ok(())
+    }
+}
+
+impl SchemaFragment {
+    /// Check that no common type in this fragment contains itself by value,
+    /// as by `NamespaceDefinition::validate`, but following common type
+    /// names written with a namespace, including names of common types in
+    /// other namespaces. The first problem found is reported as
+    /// `SchemaError::SelfEmbeddingCommonType`, with the common type name
+    /// fully qualified.
+    pub fn validate_no_self_embedding(&self) -> Result<()> {
+        let lookup = |ns: &str, name: &str| {
+            let (def_ns, ty) = self.lookup_common_type(ns, name)?;
+            Some((def_ns.as_str(), ty))
+        };
+        for (ns, ns_def) in self.0.iter().sorted_by_key(|(ns, _)| *ns) {
+            for name in ns_def.common_types.keys().sorted() {
+                check_embedding(&lookup, ns, &ns_def.common_types[name], name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check that the common type `name`, declared in namespace `ns` as `ty`, does
+/// not contain itself by value. `lookup` finds the declaration of a common
+/// type written in a namespace, as `SchemaFragment::lookup_common_type` does.
+fn check_embedding<'a>(
+    lookup: &impl Fn(&str, &str) -> Option<(&'a str, &'a SchemaType)>,
+    ns: &'a str,
+    ty: &'a SchemaType,
+    name: &str,
+) -> Result<()> {
+    let qualified = fully_qualify(ns, name);
+    let path = TypePath::new(TypePathRoot::CommonType(qualified.clone()));
+    check_embedding_inner(lookup, ns, ty, &path, &mut vec![qualified])
+}
+
+fn check_embedding_inner<'a>(
+    lookup: &impl Fn(&str, &str) -> Option<(&'a str, &'a SchemaType)>,
+    ns: &'a str,
+    ty: &'a SchemaType,
+    path: &TypePath,
+    expanding: &mut Vec<SmolStr>,
+) -> Result<()> {
+    match ty {
+        SchemaType::TypeDef { type_name } => {
+            let Some((def_ns, def)) = lookup(ns, type_name) else {
+                return Ok(());
+            };
+            let qualified = fully_qualify(ns, type_name);
+            if expanding.first() == Some(&qualified) {
+                return Err(SchemaError::SelfEmbeddingCommonType(
+                    qualified.to_string(),
+                    path.clone(),
+                ));
+            }
+            // Any other cycle is reported when starting from a type on it.
+            if expanding.contains(&qualified) {
+                return Ok(());
+            }
+            expanding.push(qualified);
+            check_embedding_inner(lookup, def_ns, def, path, expanding)?;
+            expanding.pop();
+            Ok(())
+        }
+        SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+            let path = path.child(TypePathSegment::SetElement);
+            check_embedding_inner(lookup, ns, element, &path, expanding)
+        }
+        SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+            for (attr, attr_ty) in attributes {
+                let path = path.child(TypePathSegment::Attribute(attr.clone()));
+                check_embedding_inner(lookup, ns, &attr_ty.ty, &path, expanding)?;
+            }
+            Ok(())
+        }
+        SchemaType::Type(SchemaTypeVariant::Union { options }) => {
+            for option in options {
+                check_embedding_inner(lookup, ns, option, path, expanding)?;
+            }
+            Ok(())
+        }
+        SchemaType::Type(_) => Ok(()),
     }
 }
 
@@ -89,13 +543,98 @@ impl std::fmt::Display for NamespaceDefinition {
 /// entities can be members of groups of what types, and what attributes
 /// can/should be included on entities of each type.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[serde(try_from = "LiteralEntityType")]
+#[serde(into = "LiteralEntityType")]
 pub struct EntityType {
+    pub member_of_types: Vec<SmolStr>,
+    pub shape: AttributesOrContext,
+    /// Attributes added on top of `shape`, which is usually a common type
+    /// shared with other entity types. See `EntityType::resolved_shape`.
+    pub additional_attributes_definition: Option<AttributesOrContext>,
+    /// The ids of every entity of this type, for an entity type with a fixed
+    /// set of entities. See `EntityType::enum_values`. An entity type
+    /// declared with `enum` cannot also declare `memberOfTypes` or `shape`.
+    pub enumeration: Option<Vec<SmolStr>>,
+    /// The type of the value of every tag on entities of this type, whose
+    /// keys are strings. Entities of types declared without `tags` have no
+    /// tags. See `EntityType::tags`.
+    pub tags: Option<SchemaType>,
+    /// The version of the schema this entity type was introduced in.
+    pub since: Option<Version>,
+}
+
+/// An entity type as it is written in a schema file, recording whether
+/// `memberOfTypes` and `shape` were written at all, so that they can be
+/// rejected for an entity type declared with `enum`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LiteralEntityType {
     #[serde(default)]
     #[serde(rename = "memberOfTypes")]
-    pub member_of_types: Vec<SmolStr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    member_of_types: Option<Vec<SmolStr>>,
     #[serde(default)]
-    pub shape: AttributesOrContext,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shape: Option<AttributesOrContext>,
+    #[serde(default)]
+    #[serde(rename = "additionalAttributesDefinition")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    additional_attributes_definition: Option<AttributesOrContext>,
+    #[serde(default)]
+    #[serde(rename = "enum")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enumeration: Option<Vec<SmolStr>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<SchemaType>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    since: Option<Version>,
+}
+
+impl TryFrom<LiteralEntityType> for EntityType {
+    type Error = String;
+
+    fn try_from(entity_type: LiteralEntityType) -> std::result::Result<Self, String> {
+        if let Some(values) = &entity_type.enumeration {
+            let declared = [
+                ("memberOfTypes", entity_type.member_of_types.is_some()),
+                ("shape", entity_type.shape.is_some()),
+            ];
+            if let Some((key, _)) = declared.into_iter().find(|(_, declared)| *declared) {
+                return Err(format!(
+                    "entity type declared with `enum` {} cannot also declare `{key}`",
+                    serde_json::to_string(values).unwrap_or_default()
+                ));
+            }
+        }
+        Ok(Self {
+            member_of_types: entity_type.member_of_types.unwrap_or_default(),
+            shape: entity_type.shape.unwrap_or_default(),
+            additional_attributes_definition: entity_type.additional_attributes_definition,
+            enumeration: entity_type.enumeration,
+            tags: entity_type.tags,
+            since: entity_type.since,
+        })
+    }
+}
+
+impl From<EntityType> for LiteralEntityType {
+    fn from(entity_type: EntityType) -> Self {
+        // An entity type declared with `enum` is written without the
+        // `memberOfTypes` and `shape` it cannot declare, so that it parses
+        // again. Other entity types always write both.
+        let is_enum = entity_type.enumeration.is_some();
+        Self {
+            member_of_types: Some(entity_type.member_of_types)
+                .filter(|parents| !is_enum || !parents.is_empty()),
+            shape: Some(entity_type.shape).filter(|shape| !is_enum || *shape != Default::default()),
+            additional_attributes_definition: entity_type.additional_attributes_definition,
+            enumeration: entity_type.enumeration,
+            tags: entity_type.tags,
+            since: entity_type.since,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -137,6 +676,10 @@ pub struct ActionType {
     #[serde(default)]
     #[serde(rename = "memberOf")]
     pub member_of: Option<Vec<ActionEntityUID>>,
+    /// The version of the schema this action was introduced in.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<Version>,
 }
 
 /// The apply spec specifies what principals and resources an action can be used
@@ -160,7 +703,7 @@ pub struct ApplySpec {
     pub context: AttributesOrContext,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct ActionEntityUID {
     pub id: SmolStr,
@@ -171,9 +714,82 @@ pub struct ActionEntityUID {
 }
 
 impl ActionEntityUID {
+    /// An action entity UID with the default action entity type. This accepts
+    /// any `id`, since action ids declared in a schema file need not be
+    /// identifiers (e.g., `"view photo"`), so there is nothing to assert here;
+    /// use `new_checked` to construct a UID from untrusted input.
     pub fn default_type(id: SmolStr) -> Self {
         Self { id, ty: None }
     }
+
+    /// An action entity UID with id `id` and, if given, entity type `ty`.
+    /// Fails with `SchemaError::InvalidActionEntityUID` if `id` is empty or not
+    /// a valid identifier, or if `ty` is not a valid entity type name (possibly
+    /// qualified by a namespace) whose last component is `Action`.
+    pub fn new_checked(id: impl Into<SmolStr>, ty: Option<impl Into<SmolStr>>) -> Result<Self> {
+        let id = id.into();
+        let ty = ty.map(Into::into);
+        if id.is_empty() {
+            return Err(SchemaError::InvalidActionEntityUID(
+                "action id must not be empty".to_string(),
+            ));
+        }
+        if parse_ident(&id).is_err() {
+            return Err(SchemaError::InvalidActionEntityUID(format!(
+                "`{id}` is not a valid identifier"
+            )));
+        }
+        if let Some(ty) = &ty {
+            match parse_name(ty) {
+                Ok(name) if name.basename().as_ref() == "Action" => (),
+                Ok(_) => {
+                    return Err(SchemaError::InvalidActionEntityUID(format!(
+                        "`{ty}` is not an action entity type"
+                    )))
+                }
+                Err(_) => {
+                    return Err(SchemaError::InvalidActionEntityUID(format!(
+                        "`{ty}` is not a valid entity type name"
+                    )))
+                }
+            }
+        }
+        Ok(Self { id, ty })
+    }
+
+    /// Interpret the name used as a key in the `actions` map of a namespace.
+    /// Usually this is just the action id, but a key written as an action
+    /// entity UID (`Action::"view"` or `Action::view`) is split into its type
+    /// and id so that it can be compared against other spellings.
+    pub(crate) fn from_declared_name(name: &SmolStr) -> Self {
+        if let Ok(euid) = parse_euid(name) {
+            if euid.is_action() {
+                return Self {
+                    id: AsRef::<SmolStr>::as_ref(euid.eid()).clone(),
+                    ty: Some(euid.entity_type().to_string().into()),
+                };
+            }
+        }
+        if let Some((ty, id)) = name.rsplit_once("::") {
+            if parse_name(ty).is_ok_and(|ty| ty.basename().as_ref() == "Action") {
+                return Self {
+                    id: id.into(),
+                    ty: Some(ty.into()),
+                };
+            }
+        }
+        Self::default_type(name.clone())
+    }
+
+    /// The action id as it was written in the schema, used for error messages:
+    /// the `Display` form (`Action::"view"`) if a type was given, or just the
+    /// id otherwise, as for the keys of the `actions` map.
+    fn spelling(&self) -> String {
+        match self.ty {
+            Some(_) => self.to_string(),
+            None => self.id.to_string(),
+        }
+    }
 }
 
 impl std::fmt::Display for ActionEntityUID {
@@ -193,8 +809,10 @@ impl std::fmt::Display for ActionEntityUID {
 // This enum is `untagged` with these variants as a workaround to a serde
 // limitation. It is not possible to have the known variants on one enum, and
 // then, have catch-all variant for any unrecognized tag in the same enum that
-// captures the name of the unrecognized tag.
+// captures the name of the unrecognized tag. It is deserialized through
+// `TryFrom<serde_json::Value>`, for the same reason.
 #[serde(untagged)]
+#[serde(try_from = "serde_json::Value")]
 pub enum SchemaType {
     Type(SchemaTypeVariant),
     TypeDef {
@@ -203,21 +821,157 @@ pub enum SchemaType {
     },
 }
 
+// A type is parsed by first looking at its `type`, so that a type with a
+// builtin name is always parsed as a `SchemaTypeVariant` and an error in it is
+// reported as is. An `untagged` parse would instead fall back on parsing a
+// `TypeDef` named after the builtin type, hiding the error. Checks which span
+// several properties of a type are done here as well.
+impl TryFrom<serde_json::Value> for SchemaType {
+    type Error = serde_json::Error;
+
+    fn try_from(json: serde_json::Value) -> std::result::Result<Self, serde_json::Error> {
+        use serde::de::Error;
+        let type_name = match json.get("type") {
+            Some(serde_json::Value::String(type_name)) => SmolStr::from(type_name),
+            Some(other) => {
+                return Err(Error::custom(format!(
+                    "expected the name of a type for `type`, found {other}"
+                )))
+            }
+            None if json.is_object() => return Err(Error::missing_field("type")),
+            None => return Err(Error::custom(format!("expected a type, found {json}"))),
+        };
+        if !SCHEMA_TYPE_VARIANT_TAGS.contains(&type_name.as_str()) {
+            return Ok(Self::TypeDef { type_name });
+        }
+        match serde_json::from_value(json)? {
+            SchemaTypeVariant::Long {
+                min: Some(min),
+                max: Some(max),
+            } if min > max => Err(Error::custom(format!(
+                "`min` ({min}) is greater than `max` ({max}) for type `Long`"
+            ))),
+            variant => Ok(Self::Type(variant)),
+        }
+    }
+}
+
 impl From<SchemaTypeVariant> for SchemaType {
     fn from(variant: SchemaTypeVariant) -> Self {
         Self::Type(variant)
     }
 }
 
+impl SchemaTypeVariant {
+    /// A set type with elements of type `element`.
+    pub fn set(element: SchemaType) -> Self {
+        Self::Set {
+            element: element.into(),
+        }
+    }
+}
+
+/// A `SchemaType` nested inside another declaration: the element type of a
+/// set, the type of a record attribute or an option of a union. It
+/// dereferences to the type it holds, which `SchemaFragment::intern_types`
+/// may share with other equal types. Mutating it through `DerefMut` gives it
+/// its own copy first, so the sharing is never observable.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct SharedSchemaType(Arc<SchemaType>);
+
+impl SharedSchemaType {
+    /// Wrap `ty`, which is not shared with any other type.
+    pub fn new(ty: SchemaType) -> Self {
+        Self(Arc::new(ty))
+    }
+
+    /// The type held by this wrapper, copied if it is shared.
+    pub fn into_inner(self) -> SchemaType {
+        Arc::unwrap_or_clone(self.0)
+    }
+}
+
+impl std::fmt::Debug for SharedSchemaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::ops::Deref for SharedSchemaType {
+    type Target = SchemaType;
+
+    fn deref(&self) -> &SchemaType {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for SharedSchemaType {
+    fn deref_mut(&mut self) -> &mut SchemaType {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+impl AsRef<SchemaType> for SharedSchemaType {
+    fn as_ref(&self) -> &SchemaType {
+        self
+    }
+}
+
+impl std::borrow::Borrow<SchemaType> for SharedSchemaType {
+    fn borrow(&self) -> &SchemaType {
+        self
+    }
+}
+
+impl From<SchemaType> for SharedSchemaType {
+    fn from(ty: SchemaType) -> Self {
+        Self::new(ty)
+    }
+}
+
+impl From<SchemaTypeVariant> for SharedSchemaType {
+    fn from(variant: SchemaTypeVariant) -> Self {
+        Self::new(variant.into())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(tag = "type")]
 #[serde(deny_unknown_fields)]
 pub enum SchemaTypeVariant {
-    String,
-    Long,
+    String {
+        /// The only values allowed, for a string enumeration. An entry
+        /// starting with `$` names a constant declared in the namespace, and
+        /// stands for its values, while an entry starting with `$$` stands
+        /// for itself without the first `$`; see
+        /// `SchemaFragment::expand_constants`.
+        #[serde(rename = "enum")]
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        enumeration: Option<Vec<SmolStr>>,
+        /// A regular expression which values must match, e.g.
+        /// `^[a-z]+$`. See `StringPattern`.
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pattern: Option<StringPattern>,
+    },
+    /// A long, which must be at least `min` and at most `max` when these are
+    /// given, e.g. `{ "type": "Long", "min": 0, "max": 150 }` for an age.
+    Long {
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min: Option<i64>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max: Option<i64>,
+    },
     Boolean,
+    /// A set of values of type `element`. Use `SchemaTypeVariant::set` and
+    /// `SchemaType::set_element` to build and inspect set types.
     Set {
-        element: Box<SchemaType>,
+        element: SharedSchemaType,
     },
     Record {
         #[serde(with = "serde_with::rust::maps_duplicate_key_is_error")]
@@ -231,7 +985,39 @@ pub enum SchemaTypeVariant {
     },
     Extension {
         name: SmolStr,
+        /// For `ipaddr` only, whether values must be networks or single
+        /// addresses. Either is accepted when this is absent.
+        #[serde(rename = "ipKind")]
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ip_kind: Option<IpKind>,
     },
+    /// A value of any one of `options`, e.g. an `owner` attribute which may
+    /// be a `User` or a `Service`.
+    ///
+    /// A union is a supertype of each of its options. When policies are
+    /// typechecked, it has the least upper bound of its options as its type,
+    /// so a union of entity types behaves like an entity of any of those
+    /// types: only attributes declared on every option, with compatible
+    /// types, may be accessed without a `has` check. The options of unions
+    /// used by policies must therefore all be entity types, all be booleans,
+    /// or all be the same type. When values are validated against the schema, a value is
+    /// accepted if it matches any option.
+    Union {
+        options: Vec<SharedSchemaType>,
+    },
+}
+
+/// The granularity required of `ipaddr` values, as given by the `ipKind`
+/// property of an `ipaddr` extension type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpKind {
+    /// A network in CIDR notation, e.g., `10.0.0.0/8`.
+    Cidr,
+    /// A single address, e.g., `10.0.0.1`. An address written with a full
+    /// length prefix, e.g., `10.0.0.1/32`, is also a single address.
+    Address,
 }
 
 // The possible tags for a SchemaType as written in a schema JSON document. Used
@@ -249,25 +1035,161 @@ pub(crate) static SCHEMA_TYPE_VARIANT_TAGS: &[&str] = &[
     "Record",
     "Entity",
     "Extension",
+    "Union",
 ];
 
 impl SchemaType {
+    /// The element type of this type if it is a set type, or `None`
+    /// otherwise. Typedefs are not followed.
+    pub fn set_element(&self) -> Option<&SchemaType> {
+        match self {
+            Self::Type(SchemaTypeVariant::Set { element }) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// Call `f` on this type and then on every type nested inside it (set
+    /// element types, record attribute types and union options). Typedefs are
+    /// not followed.
+    pub(crate) fn walk<'a>(&'a self, f: &mut impl FnMut(&'a SchemaType)) {
+        f(self);
+        match self {
+            Self::Type(SchemaTypeVariant::Set { element }) => element.walk(f),
+            Self::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+                for attr in attributes.values() {
+                    attr.ty.walk(f);
+                }
+            }
+            Self::Type(SchemaTypeVariant::Union { options }) => {
+                for option in options {
+                    option.walk(f);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Equality which treats two records with no attributes as equal even if
+    /// only one of them has `additionalAttributes` set. This applies at any
+    /// depth, so sets of empty records and records with empty record
+    /// attributes are compared the same way. Records with at least one
+    /// attribute, and everything else about the types (including whether
+    /// attributes are required), are compared exactly as by `==`. Typedefs
+    /// are compared by name, without being resolved.
+    pub fn eq_lenient(&self, other: &SchemaType) -> bool {
+        match (self, other) {
+            (
+                Self::Type(SchemaTypeVariant::Set { element: a }),
+                Self::Type(SchemaTypeVariant::Set { element: b }),
+            ) => a.eq_lenient(b),
+            (
+                Self::Type(SchemaTypeVariant::Record {
+                    attributes: a,
+                    additional_attributes: a_open,
+                }),
+                Self::Type(SchemaTypeVariant::Record {
+                    attributes: b,
+                    additional_attributes: b_open,
+                }),
+            ) => {
+                if a.is_empty() && b.is_empty() {
+                    return true;
+                }
+                a_open == b_open
+                    && a.len() == b.len()
+                    && a.iter().zip(b).all(|((a_name, a_ty), (b_name, b_ty))| {
+                        a_name == b_name
+                            && a_ty.ty.eq_lenient(&b_ty.ty)
+                            && a_ty.required == b_ty.required
+                            && a_ty.computed == b_ty.computed
+                            && a_ty.example == b_ty.example
+                            && a_ty.required_if == b_ty.required_if
+                            && a_ty.unit == b_ty.unit
+                    })
+            }
+            _ => self == other,
+        }
+    }
+
     /// Is this `SchemaType` an extension type, or does it contain one
     /// (recursively)? Returns `None` if this is a `TypeDef` because we can't
     /// easily properly check the type of a typedef, accounting for namespaces,
-    /// without first converting to a `Type`.
+    /// without first converting to a `Type`. Use `is_extension_in` to look
+    /// typedefs up in a set of common types.
     pub fn is_extension(&self) -> Option<bool> {
+        self.is_extension_in(&HashMap::new())
+    }
+
+    /// Is this `SchemaType` an extension type, or does it contain one
+    /// (recursively), where each `TypeDef` is resolved by looking up its name,
+    /// exactly as written, in `common_types`? Returns `None` if it uses a
+    /// `TypeDef` which is not in `common_types` or which is defined in terms
+    /// of itself.
+    pub fn is_extension_in(&self, common_types: &HashMap<SmolStr, SchemaType>) -> Option<bool> {
+        self.is_extension_inner(common_types, &mut Vec::new())
+    }
+
+    fn is_extension_inner(
+        &self,
+        common_types: &HashMap<SmolStr, SchemaType>,
+        expanding: &mut Vec<SmolStr>,
+    ) -> Option<bool> {
         match self {
             Self::Type(SchemaTypeVariant::Extension { .. }) => Some(true),
-            Self::Type(SchemaTypeVariant::Set { element }) => element.is_extension(),
+            Self::Type(SchemaTypeVariant::Set { element }) => {
+                element.is_extension_inner(common_types, expanding)
+            }
             Self::Type(SchemaTypeVariant::Record { attributes, .. }) => {
-                attributes
-                    .values()
-                    .fold(Some(false), |a, e| match e.ty.is_extension() {
+                attributes.values().try_fold(false, |a, e| {
+                    match e.ty.is_extension_inner(common_types, expanding) {
                         Some(true) => Some(true),
-                        Some(false) => a,
+                        Some(false) => Some(a),
                         None => None,
-                    })
+                    }
+                })
+            }
+            Self::Type(SchemaTypeVariant::Union { options }) => {
+                options.iter().try_fold(false, |a, e| {
+                    match e.is_extension_inner(common_types, expanding) {
+                        Some(true) => Some(true),
+                        Some(false) => Some(a),
+                        None => None,
+                    }
+                })
+            }
+            Self::Type(_) => Some(false),
+            Self::TypeDef { type_name } => {
+                if expanding.contains(type_name) {
+                    return None;
+                }
+                let def = common_types.get(type_name)?;
+                expanding.push(type_name.clone());
+                let is_extension = def.is_extension_inner(common_types, expanding);
+                expanding.pop();
+                is_extension
+            }
+        }
+    }
+
+    /// Is this `SchemaType` an entity type, or does it contain one
+    /// (recursively)? Returns `None` if this is a `TypeDef`, for the same
+    /// reason as `is_extension`.
+    pub fn contains_entity_ref(&self) -> Option<bool> {
+        fn any<'a>(mut tys: impl Iterator<Item = &'a SchemaType>) -> Option<bool> {
+            tys.try_fold(false, |a, e| match e.contains_entity_ref() {
+                Some(true) => Some(true),
+                Some(false) => Some(a),
+                None => None,
+            })
+        }
+        match self {
+            Self::Type(SchemaTypeVariant::Entity { .. }) => Some(true),
+            Self::Type(SchemaTypeVariant::Set { element }) => element.contains_entity_ref(),
+            Self::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+                any(attributes.values().map(|attr| &*attr.ty))
+            }
+            Self::Type(SchemaTypeVariant::Union { options }) => {
+                any(options.iter().map(|option| &**option))
             }
             Self::Type(_) => Some(false),
             Self::TypeDef { .. } => None,
@@ -281,12 +1203,22 @@ impl<'a> arbitrary::Arbitrary<'a> for SchemaType {
         use cedar_policy_core::ast::Name;
         use std::collections::HashSet;
 
-        Ok(SchemaType::Type(match u.int_in_range::<u8>(1..=8)? {
-            1 => SchemaTypeVariant::String,
-            2 => SchemaTypeVariant::Long,
+        Ok(SchemaType::Type(match u.int_in_range::<u8>(1..=9)? {
+            1 => SchemaTypeVariant::String {
+                enumeration: None,
+                pattern: None,
+            },
+            2 => {
+                let min: Option<i64> = u.arbitrary()?;
+                let max: Option<i64> = u.arbitrary()?;
+                SchemaTypeVariant::Long {
+                    min,
+                    max: max.map(|max| max.max(min.unwrap_or(max))),
+                }
+            }
             3 => SchemaTypeVariant::Boolean,
             4 => SchemaTypeVariant::Set {
-                element: Box::new(u.arbitrary()?),
+                element: SharedSchemaType::new(u.arbitrary()?),
             },
             5 => {
                 let attributes = {
@@ -309,10 +1241,23 @@ impl<'a> arbitrary::Arbitrary<'a> for SchemaType {
             }
             7 => SchemaTypeVariant::Extension {
                 name: "ipaddr".into(),
+                ip_kind: u.arbitrary::<Option<bool>>()?.map(|cidr| {
+                    if cidr {
+                        IpKind::Cidr
+                    } else {
+                        IpKind::Address
+                    }
+                }),
             },
             8 => SchemaTypeVariant::Extension {
                 name: "decimal".into(),
+                ip_kind: None,
             },
+            9 => {
+                let mut options = vec![u.arbitrary()?];
+                options.extend(u.arbitrary::<Vec<SharedSchemaType>>()?);
+                SchemaTypeVariant::Union { options }
+            }
             n => panic!("bad index: {n}"),
         }))
     }
@@ -322,9 +1267,12 @@ impl<'a> arbitrary::Arbitrary<'a> for SchemaType {
 }
 
 /// Used to describe the type of a record or entity attribute. It contains a the
-/// type of the attribute and whether the attribute is required. The type is
+/// type of the attribute, whether the attribute is required, whether it is
+/// computed, and optionally an example value for the attribute. The type is
 /// flattened for serialization, so, in JSON format, this appears as a regular
-/// type with one extra property `required`.
+/// type with extra properties `required`, `computed` and `example`. These are
+/// consumed here, before the remaining properties are passed on to
+/// `SchemaType`.
 ///
 /// Note that we can't add #[serde(deny_unknown_fields)] here because we are
 /// using #[serde(tag = "type")] in ty:SchemaType which is flattened here.
@@ -335,13 +1283,91 @@ impl<'a> arbitrary::Arbitrary<'a> for SchemaType {
 /// (`<https://github.com/serde-rs/serde/issues/1600>`). This should be ok because
 /// unknown fields for TypeOfAttribute should be passed to SchemaType where
 /// they will be denied (`<https://github.com/serde-rs/serde/issues/1600>`).
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct TypeOfAttribute {
     #[serde(flatten)]
-    pub ty: SchemaType,
+    pub ty: SharedSchemaType,
     #[serde(default = "record_attribute_required_default")]
     pub required: bool,
+    /// Computed attributes are filled in by the application rather than
+    /// supplied by clients. See `AttributeValidationMode` for how they are
+    /// validated.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub computed: bool,
+    /// An example value for the attribute, for use in generated
+    /// documentation. It is checked against the attribute type when the
+    /// schema is validated.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub example: Option<JSONValue>,
+    /// A condition on a sibling attribute under which this attribute is
+    /// required. When present, the attribute is required exactly when the
+    /// condition holds, and `required` is ignored.
+    #[serde(rename = "requiredIf")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub required_if: Option<RequiredIf>,
+    /// The unit a numeric attribute is measured in, e.g. `ms`, for use by
+    /// applications rendering or converting the value. It may only be given
+    /// for `Long` and `decimal` attributes, and is otherwise ignored by
+    /// validation.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub unit: Option<SmolStr>,
+}
+
+/// The condition in a `requiredIf` property of a record attribute: the
+/// attribute is required when the sibling `attribute` is present and has the
+/// value `equals`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequiredIf {
+    /// The name of a sibling attribute in the same record.
+    pub attribute: SmolStr,
+    /// The value the sibling attribute must have for this attribute to be
+    /// required.
+    pub equals: JSONValue,
+}
+
+// `JSONValue` is not `Ord` (its records are `HashMap`s), so examples are
+// compared by their JSON serialization with object keys sorted.
+impl PartialOrd for TypeOfAttribute {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TypeOfAttribute {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let required_if_key = |required_if: &Option<RequiredIf>| {
+            required_if.as_ref().map(|required_if| {
+                (
+                    required_if.attribute.clone(),
+                    example_sort_key(&Some(required_if.equals.clone())),
+                )
+            })
+        };
+        (&self.ty, self.required, self.computed)
+            .cmp(&(&other.ty, other.required, other.computed))
+            .then_with(|| example_sort_key(&self.example).cmp(&example_sort_key(&other.example)))
+            .then_with(|| {
+                required_if_key(&self.required_if).cmp(&required_if_key(&other.required_if))
+            })
+            .then_with(|| self.unit.cmp(&other.unit))
+    }
+}
+
+fn example_sort_key(example: &Option<JSONValue>) -> Option<String> {
+    example.as_ref().map(|example| {
+        let mut value = serde_json::to_value(example).unwrap_or(serde_json::Value::Null);
+        value.sort_all_objects();
+        value.to_string()
+    })
 }
 
 /// Defines the default value for `additionalAttributes` on records and
@@ -527,6 +1553,501 @@ mod test {
         assert_eq!(namespace, "foo::foo::bar::baz".to_string());
     }
 
+    #[test]
+    fn test_intern_types_shares_nested_types() {
+        let mut schema: SchemaFragment = serde_json::from_value(serde_json::json!({
+            "": {
+                "commonTypes": {
+                    "Tags": { "type": "Set", "element": { "type": "String" } }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "emails": { "type": "Set", "element": { "type": "String" } },
+                                "groups": {
+                                    "type": "Set",
+                                    "element": { "type": "Set", "element": { "type": "String" } }
+                                },
+                                "home": {
+                                    "type": "Record",
+                                    "attributes": { "city": { "type": "String" } }
+                                }
+                            }
+                        },
+                        "additionalAttributesDefinition": {
+                            "type": "Record",
+                            "attributes": {
+                                "work": {
+                                    "type": "Record",
+                                    "attributes": { "city": { "type": "String" } }
+                                }
+                            }
+                        },
+                        "tags": { "type": "Set", "element": { "type": "String" } }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let before = schema.0[""].clone();
+        schema.intern_types();
+        let ns_def = &schema.0[""];
+        assert_eq!(ns_def, &before);
+
+        fn attributes(ty: &SchemaType) -> &BTreeMap<SmolStr, TypeOfAttribute> {
+            match ty {
+                SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => attributes,
+                _ => panic!("expected a record type"),
+            }
+        }
+        let user = &ns_def.entity_types["User"];
+        let shape = attributes(&user.shape.0);
+        let additional = attributes(
+            &user
+                .additional_attributes_definition
+                .as_ref()
+                .expect("declared")
+                .0,
+        );
+        let set_element = |ty: &SchemaType| ty.set_element().expect("a set type") as *const _;
+        let string = &*shape["name"].ty as *const _;
+        assert_eq!(set_element(&ns_def.common_types["Tags"]), string);
+        assert_eq!(set_element(&shape["emails"].ty), string);
+        assert_eq!(
+            set_element(shape["groups"].ty.set_element().expect("a set type")),
+            string
+        );
+        assert_eq!(set_element(user.tags.as_ref().expect("declared")), string);
+        assert_eq!(
+            &*attributes(&shape["home"].ty)["city"].ty as *const _,
+            string
+        );
+        // Equal records are shared as a whole.
+        assert!(std::ptr::eq(&*shape["home"].ty, &*additional["work"].ty));
+    }
+
+    #[test]
+    fn test_action_uniqueness_declared_spellings() {
+        let ns_def: NamespaceDefinition = serde_json::from_value(serde_json::json!({
+            "entityTypes": {},
+            "actions": {
+                "view": {},
+                "Action::\"view\"": {}
+            }
+        }))
+        .expect("Parse Error");
+        match ns_def.validate_action_uniqueness() {
+            Err(SchemaError::ActionIdCollision(action, spellings)) => {
+                assert_eq!(action, r#"Action::"view""#);
+                assert_eq!(spellings, vec![r#"Action::"view""#, "view"]);
+            }
+            r => panic!("Expected an action id collision, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_action_uniqueness_member_of_spellings() {
+        let ns_def: NamespaceDefinition = serde_json::from_value(serde_json::json!({
+            "entityTypes": {},
+            "actions": {
+                "read": {},
+                "view": {
+                    "memberOf": [{"id": "read"}, {"id": "read", "type": "Action"}]
+                }
+            }
+        }))
+        .expect("Parse Error");
+        match ns_def.validate_action_uniqueness() {
+            Err(SchemaError::ActionIdCollision(action, spellings)) => {
+                assert_eq!(action, r#"Action::"read""#);
+                assert_eq!(spellings, vec!["read", r#"Action::"read""#]);
+            }
+            r => panic!("Expected an action id collision, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_action_uniqueness_distinct_actions() {
+        let ns_def: NamespaceDefinition = serde_json::from_value(serde_json::json!({
+            "entityTypes": {},
+            "actions": {
+                "view": { "memberOf": [{"id": "read"}, {"id": "read", "type": "Other::Action"}] },
+                "read": {},
+                "Action::edit": {}
+            }
+        }))
+        .expect("Parse Error");
+        ns_def
+            .validate_action_uniqueness()
+            .expect("Expected no collisions");
+    }
+
+    #[test]
+    fn test_action_entity_uid_new_checked() {
+        let uid = ActionEntityUID::new_checked("viewPhoto", Some("App::Action"))
+            .expect("valid action entity UID");
+        assert_eq!(uid.ty.as_deref(), Some("App::Action"));
+        ActionEntityUID::new_checked("view", None::<SmolStr>).expect("valid action entity UID");
+
+        for (id, ty) in [
+            ("", None),
+            ("view photo", None),
+            ("App::view", None),
+            ("view", Some("App::User")),
+            ("view", Some("App::")),
+            ("view", Some("not a name")),
+        ] {
+            assert!(matches!(
+                ActionEntityUID::new_checked(id, ty),
+                Err(SchemaError::InvalidActionEntityUID(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_self_embedding_common_type() {
+        let ns: NamespaceDefinition = serde_json::from_value(serde_json::json!({
+            "commonTypes": {
+                "Node": {
+                    "type": "Record",
+                    "attributes": {
+                        "children": { "type": "Set", "element": { "type": "Wrapper" } },
+                        "parent": { "type": "Entity", "name": "Node" }
+                    }
+                },
+                "Wrapper": {
+                    "type": "Record",
+                    "attributes": { "node": { "type": "Node" } }
+                }
+            },
+            "entityTypes": {},
+            "actions": {}
+        }))
+        .expect("Parse Error");
+        match ns.validate() {
+            Err(SchemaError::SelfEmbeddingCommonType(name, path)) => {
+                assert_eq!(name, "Node");
+                assert_eq!(path.to_string(), "type Node.children[].node");
+            }
+            r => panic!("Expected a self-embedding error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_self_embedding_qualified_names() {
+        let fragment = SchemaFragment::from_json_value(serde_json::json!({
+            "App": {
+                "commonTypes": {
+                    "Node": {
+                        "type": "Record",
+                        "attributes": {
+                            "children": { "type": "Set", "element": { "type": "App::Node" } }
+                        }
+                    },
+                    "Left": {
+                        "type": "Record",
+                        "attributes": { "right": { "type": "Other::Right", "required": false } }
+                    }
+                },
+                "entityTypes": {},
+                "actions": {}
+            },
+            "Other": {
+                "commonTypes": {
+                    "Right": {
+                        "type": "Record",
+                        "attributes": { "left": { "type": "App::Left" } }
+                    }
+                },
+                "entityTypes": {},
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        // A single namespace does not know its own name, so only the
+        // fragment can follow the qualified names.
+        fragment.0["App"]
+            .validate()
+            .expect("qualified names are not followed");
+        match fragment.validate_no_self_embedding() {
+            Err(SchemaError::SelfEmbeddingCommonType(name, path)) => {
+                assert_eq!(name, "App::Left");
+                assert_eq!(path.to_string(), "type App::Left.right.left");
+            }
+            r => panic!("Expected a self-embedding error, got {:?}", r),
+        }
+        let mut fragment = fragment;
+        fragment.0.remove("Other");
+        match fragment.validate_no_self_embedding() {
+            Err(SchemaError::SelfEmbeddingCommonType(name, path)) => {
+                assert_eq!(name, "App::Node");
+                assert_eq!(path.to_string(), "type App::Node.children[]");
+            }
+            r => panic!("Expected a self-embedding error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_entity_reference_is_not_embedding() {
+        let ns: NamespaceDefinition = serde_json::from_value(serde_json::json!({
+            "commonTypes": {
+                "Node": {
+                    "type": "Record",
+                    "attributes": {
+                        "next": { "type": "Entity", "name": "Node" },
+                        "tags": { "type": "Set", "element": { "type": "String" } }
+                    }
+                }
+            },
+            "entityTypes": { "Node": { "shape": { "type": "Node" } } },
+            "actions": {}
+        }))
+        .expect("Parse Error");
+        ns.validate().expect("entity references should be allowed");
+    }
+
+    #[test]
+    fn test_eq_lenient() {
+        let record = |attrs: serde_json::Value, open: bool| -> SchemaType {
+            serde_json::from_value(serde_json::json!({
+                "type": "Record",
+                "attributes": attrs,
+                "additionalAttributes": open
+            }))
+            .expect("Parse Error")
+        };
+        let empty_open = record(serde_json::json!({}), true);
+        let empty_closed = record(serde_json::json!({}), false);
+        assert_ne!(empty_open, empty_closed);
+        assert!(empty_open.eq_lenient(&empty_closed));
+
+        let set = |element: &SchemaType| SchemaType::from(SchemaTypeVariant::set(element.clone()));
+        assert!(set(&empty_open).eq_lenient(&set(&empty_closed)));
+        let nested = |open| {
+            let meta = serde_json::json!({
+                "type": "Record",
+                "attributes": {},
+                "additionalAttributes": open
+            });
+            record(serde_json::json!({ "meta": meta }), false)
+        };
+        assert!(nested(true).eq_lenient(&nested(false)));
+
+        let attrs = serde_json::json!({ "a": { "type": "Long" } });
+        assert!(!record(attrs.clone(), true).eq_lenient(&record(attrs.clone(), false)));
+        assert!(record(attrs.clone(), true).eq_lenient(&record(attrs, true)));
+    }
+
+    #[test]
+    fn test_name_shadowing() {
+        let ns: NamespaceDefinition = serde_json::from_value(serde_json::json!({
+            "commonTypes": {
+                "Address": { "type": "Record", "attributes": {} }
+            },
+            "entityTypes": {
+                "Address": {},
+                "User": {
+                    "shape": {
+                        "type": "Record",
+                        "attributes": { "home": { "type": "Address" } }
+                    }
+                }
+            },
+            "actions": {}
+        }))
+        .expect("Parse Error");
+        match ns.validate_no_name_shadowing() {
+            Err(SchemaError::NameShadowing(name, other)) => {
+                assert_eq!(name, "Address");
+                assert_eq!(other, "an entity type");
+            }
+            r => panic!("Expected a name shadowing error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_is_extension_in() {
+        let common_types: HashMap<SmolStr, SchemaType> =
+            serde_json::from_value(serde_json::json!({
+                "Money": { "type": "Extension", "name": "decimal" },
+                "Prices": { "type": "Set", "element": { "type": "Money" } },
+                "Name": { "type": "String" },
+                "Loop": { "type": "Record", "attributes": { "next": { "type": "Loop" } } }
+            }))
+            .expect("Parse Error");
+        let typedef = |name: &str| SchemaType::TypeDef {
+            type_name: name.into(),
+        };
+        assert_eq!(typedef("Prices").is_extension(), None);
+        assert_eq!(typedef("Prices").is_extension_in(&common_types), Some(true));
+        assert_eq!(typedef("Name").is_extension_in(&common_types), Some(false));
+        assert_eq!(typedef("Missing").is_extension_in(&common_types), None);
+        assert_eq!(typedef("Loop").is_extension_in(&common_types), None);
+    }
+
+    #[test]
+    fn test_union_type() {
+        let owner: SchemaType = serde_json::from_value(serde_json::json!({
+            "type": "Union",
+            "options": [
+                { "type": "Entity", "name": "User" },
+                { "type": "Entity", "name": "Service" }
+            ]
+        }))
+        .expect("Parse Error");
+        assert_eq!(owner.contains_entity_ref(), Some(true));
+        assert_eq!(owner.is_extension(), Some(false));
+        let round_trip: SchemaType =
+            serde_json::from_value(serde_json::to_value(&owner).expect("Serialize Error"))
+                .expect("Parse Error");
+        assert_eq!(round_trip, owner);
+
+        let schema = |owner: serde_json::Value| {
+            crate::ValidatorSchema::from_json_value(serde_json::json!({
+                "": {
+                    "entityTypes": {
+                        "User": {},
+                        "Service": {},
+                        "Doc": {
+                            "shape": {
+                                "type": "Record",
+                                "attributes": { "owner": owner }
+                            }
+                        }
+                    },
+                    "actions": {}
+                }
+            }))
+        };
+        schema(serde_json::to_value(&owner).expect("Serialize Error"))
+            .expect("a union of entity types is supported");
+        assert!(matches!(
+            schema(serde_json::json!({
+                "type": "Union",
+                "options": [{ "type": "Long" }, { "type": "String" }]
+            })),
+            Err(SchemaError::UnsupportedSchemaFeature(
+                crate::UnsupportedFeature::UnionOfUnrelatedTypes
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_long_bounds() {
+        let age: SchemaType =
+            serde_json::from_value(serde_json::json!({ "type": "Long", "min": 0, "max": 150 }))
+                .expect("Parse Error");
+        assert_eq!(
+            age,
+            SchemaType::Type(SchemaTypeVariant::Long {
+                min: Some(0),
+                max: Some(150)
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&age).expect("Serialize Error"),
+            serde_json::json!({ "type": "Long", "min": 0, "max": 150 })
+        );
+        let unbounded: SchemaType =
+            serde_json::from_value(serde_json::json!({ "type": "Long" })).expect("Parse Error");
+        assert_eq!(
+            serde_json::to_value(unbounded).expect("Serialize Error"),
+            serde_json::json!({ "type": "Long" })
+        );
+
+        let err = serde_json::from_value::<SchemaType>(
+            serde_json::json!({ "type": "Long", "min": 10, "max": 1 }),
+        )
+        .expect_err("`min` is greater than `max`");
+        assert_eq!(
+            err.to_string(),
+            "`min` (10) is greater than `max` (1) for type `Long`"
+        );
+        assert!(matches!(
+            SchemaFragment::from_json_value(serde_json::json!({
+                "": {
+                    "entityTypes": {
+                        "User": {
+                            "shape": {
+                                "type": "Record",
+                                "attributes": {
+                                    "age": { "type": "Long", "min": 10, "max": 1 }
+                                }
+                            }
+                        }
+                    },
+                    "actions": {}
+                }
+            })),
+            Err(SchemaError::ParseFileFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_fully_qualify_empty_namespace() {
+        assert_eq!(fully_qualify("", "User"), "User");
+        assert_eq!(fully_qualify("", "App::User"), "App::User");
+        assert_eq!(fully_qualify("App", "User"), "App::User");
+        assert_eq!(fully_qualify("App", "Other::User"), "Other::User");
+    }
+
+    #[test]
+    fn test_lookup_common_type_empty_namespace() {
+        let schema: SchemaFragment = serde_json::from_value(serde_json::json!({
+            "": {
+                "commonTypes": { "Name": { "type": "String" } },
+                "entityTypes": {},
+                "actions": {}
+            },
+            "App": {
+                "commonTypes": { "Name": { "type": "Long" } },
+                "entityTypes": {},
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let lookup = |ns, name| {
+            schema
+                .lookup_common_type(ns, name)
+                .map(|(ns, ty)| (ns.to_string(), ty.clone()))
+        };
+        assert_eq!(
+            lookup("", "Name"),
+            Some((
+                "".into(),
+                SchemaType::Type(SchemaTypeVariant::String {
+                    enumeration: None,
+                    pattern: None,
+                })
+            ))
+        );
+        assert_eq!(
+            lookup("", "App::Name"),
+            Some((
+                "App".into(),
+                SchemaType::Type(SchemaTypeVariant::Long {
+                    min: None,
+                    max: None
+                })
+            ))
+        );
+        assert_eq!(
+            lookup("App", "Name"),
+            Some((
+                "App".into(),
+                SchemaType::Type(SchemaTypeVariant::Long {
+                    min: None,
+                    max: None
+                })
+            ))
+        );
+        assert_eq!(lookup("Other", "Name"), None);
+    }
+
     #[test]
     #[should_panic]
     fn test_schema_file_with_misspelled_required() {
@@ -607,4 +2128,92 @@ mod test {
         let schema: NamespaceDefinition = serde_json::from_str(src).expect("Expected valid schema");
         println!("{:#?}", schema);
     }
+
+    #[test]
+    fn test_from_file_with_bom() {
+        let src = "\u{feff}\n  { \"App\": { \"entityTypes\": { \"User\": {} }, \"actions\": {} } }";
+        let fragment = SchemaFragment::from_file(src.as_bytes()).expect("BOM should be skipped");
+        assert!(fragment.0["App"].entity_types.contains_key("User"));
+        assert!(matches!(
+            SchemaFragment::from_file_strict(src.as_bytes()),
+            Err(SchemaError::ParseFileFormat(_))
+        ));
+        for empty in ["", "\u{feff}", "\u{feff} \r\n\t"] {
+            assert!(matches!(
+                SchemaFragment::from_file(empty.as_bytes()),
+                Err(SchemaError::EmptySchemaFile)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_display_schema_fragment() {
+        let fragment = SchemaFragment::from_json_value(serde_json::json!({
+            "Zoo": { "entityTypes": { "Keeper": {}, "Animal": {} }, "actions": {} },
+            "App": {
+                "entityTypes": {},
+                "actions": { "view": {}, "edit": {} }
+            }
+        }))
+        .expect("Parse Error");
+        let printed = fragment.to_string();
+        assert_eq!(printed, fragment.clone().to_string());
+        let position = |needle: &str| printed.find(needle).expect("name is printed");
+        assert!(position("\"App\"") < position("\"Zoo\""));
+        assert!(position("\"edit\"") < position("\"view\""));
+        assert!(position("\"Animal\"") < position("\"Keeper\""));
+        let parsed = SchemaFragment::from_json_value(
+            serde_json::from_str(&printed).expect("Display output is JSON"),
+        )
+        .expect("Parse Error");
+        assert_eq!(parsed, fragment);
+    }
+
+    #[test]
+    fn test_to_json_value() {
+        let entity_type: EntityType =
+            serde_json::from_value(serde_json::json!({})).expect("Parse Error");
+        let fragment = SchemaFragment(HashMap::from([(
+            "App".into(),
+            NamespaceDefinition::new(
+                [
+                    ("User".into(), entity_type.clone()),
+                    ("Group".into(), entity_type),
+                ],
+                [],
+            ),
+        )]));
+        let json = fragment.to_json_value().expect("Serialize Error");
+        assert_eq!(
+            json["App"]["entityTypes"]
+                .as_object()
+                .expect("entity types are an object")
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["Group", "User"]
+        );
+        assert_eq!(
+            SchemaFragment::from_json_value(json).expect("Parse Error"),
+            fragment
+        );
+        assert_eq!(
+            fragment.to_json_string().expect("Serialize Error"),
+            fragment.to_string()
+        );
+    }
+
+    #[test]
+    fn test_from_json_str() {
+        let src = r#"{ "App": { "entityTypes": { "User": {} }, "actions": { "view": {} } } }"#;
+        let fragment = SchemaFragment::from_json_str(src).expect("Parse Error");
+        assert!(fragment.0["App"].entity_types.contains_key("User"));
+        assert_eq!(
+            src.parse::<SchemaFragment>().expect("Parse Error"),
+            fragment
+        );
+        assert!(matches!(
+            "{ \"App\": ".parse::<SchemaFragment>(),
+            Err(SchemaError::ParseFileFormat(_))
+        ));
+    }
 }
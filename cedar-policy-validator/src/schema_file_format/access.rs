@@ -0,0 +1,178 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Over-approximation of the actions under which an attribute may be read,
+//! for auditing which operations can reach sensitive data.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{fully_qualify, ActionEntityUID, SchemaFragment, TypePath, TypePathRoot};
+
+impl SchemaFragment {
+    /// The actions which could authorize reading the attribute at `path`,
+    /// in sorted order. For an attribute of an entity type, these are the
+    /// actions whose principal or resource types, or the types those are
+    /// transitively members of, include the entity type, and actions which
+    /// apply to any principal or resource. For an attribute of an action
+    /// context, this is the action itself. Paths starting from a common type,
+    /// and paths which do not name a type in this fragment, give no actions.
+    ///
+    /// This over-approximates the actions under which the attribute can be
+    /// read, since it does not look at policies.
+    pub fn actions_touching_attribute(&self, path: &TypePath) -> Vec<ActionEntityUID> {
+        let mut exists = false;
+        self.walk_value_types(&mut |visited, _, _| exists |= visited == path);
+        if !exists {
+            return Vec::new();
+        }
+        match path.root() {
+            TypePathRoot::EntityType(owner) | TypePathRoot::EntityTags(owner) => {
+                let ancestors = self.member_of_closure();
+                self.actions_for(|ty| ancestors.get(ty).is_some_and(|a| a.contains(owner)))
+            }
+            TypePathRoot::ActionContext { ty, id } => vec![ActionEntityUID {
+                id: id.clone(),
+                ty: Some(ty.clone()),
+            }],
+            TypePathRoot::CommonType(_) => Vec::new(),
+        }
+    }
+
+    /// Every action declared in this fragment with a principal or resource
+    /// type satisfying `pred`, which is passed fully qualified entity type
+    /// names, in sorted order. Actions applying to any principal or any
+    /// resource are always included.
+    fn actions_for(&self, pred: impl Fn(&SmolStr) -> bool) -> Vec<ActionEntityUID> {
+        let mut found = Vec::new();
+        for (ns, ns_def) in self.0.iter().sorted_by_key(|(ns, _)| *ns) {
+            for (id, action) in ns_def.actions.iter().sorted_by_key(|(id, _)| *id) {
+                let matches = |tys: &Option<Vec<SmolStr>>| {
+                    tys.as_ref()
+                        .is_none_or(|tys| tys.iter().any(|ty| pred(&fully_qualify(ns, ty))))
+                };
+                let applies = action.applies_to.as_ref().is_none_or(|applies_to| {
+                    matches(&applies_to.principal_types) || matches(&applies_to.resource_types)
+                });
+                if applies {
+                    found.push(ActionEntityUID {
+                        id: id.clone(),
+                        ty: Some(fully_qualify(ns, "Action")),
+                    });
+                }
+            }
+        }
+        found
+    }
+
+    /// Map from each entity type declared in this fragment to itself and
+    /// every entity type it is transitively a member of, by fully qualified
+    /// name. `memberOfTypes` entries naming undeclared entity types are
+    /// included, but not followed.
+    fn member_of_closure(&self) -> BTreeMap<SmolStr, BTreeSet<SmolStr>> {
+        let parents: BTreeMap<SmolStr, Vec<SmolStr>> = self
+            .0
+            .iter()
+            .flat_map(|(ns, ns_def)| {
+                ns_def.entity_types.iter().map(move |(name, entity_type)| {
+                    let parents = entity_type
+                        .member_of_types
+                        .iter()
+                        .map(|parent| fully_qualify(ns, parent))
+                        .collect();
+                    (fully_qualify(ns, name), parents)
+                })
+            })
+            .collect();
+        parents
+            .keys()
+            .map(|start| {
+                let mut seen = BTreeSet::from([start.clone()]);
+                let mut stack = vec![start];
+                while let Some(ty) = stack.pop() {
+                    for parent in parents.get(ty).into_iter().flatten() {
+                        if seen.insert(parent.clone()) {
+                            stack.push(parent);
+                        }
+                    }
+                }
+                (start.clone(), seen)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::schema_file_format::TypePathSegment;
+
+    #[test]
+    fn actions_touching_attribute() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Team"] },
+                    "Team": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "budget": { "type": "Long" } }
+                        }
+                    },
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] } },
+                    "upload": { "appliesTo": { "principalTypes": ["Photo"], "resourceTypes": ["Photo"] } },
+                    "audit": {},
+                    "review": {
+                        "appliesTo": {
+                            "principalTypes": ["Photo"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "reason": { "type": "String" } }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let ids = |path: TypePath| {
+            fragment
+                .actions_touching_attribute(&path)
+                .into_iter()
+                .map(|uid| uid.id)
+                .collect::<Vec<_>>()
+        };
+        let budget = TypePath::new(TypePathRoot::EntityType("App::Team".into()))
+            .child(TypePathSegment::Attribute("budget".into()));
+        assert_eq!(ids(budget), vec!["audit", "review", "view"]);
+        let reason = TypePath::new(TypePathRoot::ActionContext {
+            ty: "App::Action".into(),
+            id: "review".into(),
+        })
+        .child(TypePathSegment::Attribute("reason".into()));
+        assert_eq!(ids(reason), vec!["review"]);
+        let missing = TypePath::new(TypePathRoot::EntityType("App::Team".into()))
+            .child(TypePathSegment::Attribute("name".into()));
+        assert_eq!(ids(missing), Vec::<SmolStr>::new());
+    }
+}
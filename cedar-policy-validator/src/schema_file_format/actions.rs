@@ -0,0 +1,377 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Queries over the action group hierarchy declared by the `memberOf` lists
+//! of actions in a single namespace.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::json;
+use smol_str::SmolStr;
+
+use super::{references::qualified_action, NamespaceDefinition};
+use crate::{Result, SchemaError};
+
+impl NamespaceDefinition {
+    /// Map from each action declared in this namespace, which is named `ns`,
+    /// to the declared actions it is a direct member of, with both sorted by
+    /// name. A `memberOf` entry is qualified as by `qualified_action`, so it
+    /// is followed if it has no `type` or gives the action type of this
+    /// namespace, e.g. `App::Action` in namespace `App`. Other entries are
+    /// left out.
+    pub(crate) fn action_parents(&self, ns: &str) -> BTreeMap<&SmolStr, BTreeSet<&SmolStr>> {
+        let declared: BTreeMap<SmolStr, &SmolStr> = self
+            .actions
+            .keys()
+            .map(|name| (qualified_action(ns, None, name), name))
+            .collect();
+        self.actions
+            .iter()
+            .map(|(name, action)| {
+                let parents = action
+                    .member_of
+                    .iter()
+                    .flatten()
+                    .filter_map(|parent| {
+                        declared
+                            .get(&qualified_action(ns, parent.ty.as_ref(), &parent.id))
+                            .copied()
+                    })
+                    .collect();
+                (name, parents)
+            })
+            .collect()
+    }
+
+    /// Map from each action declared in this namespace, which is named `ns`,
+    /// to the actions which are direct members of it, with both sorted by
+    /// name. `memberOf` entries are followed as by `action_parents`. Fails
+    /// with `SchemaError::CycleInActionHierarchy` if the hierarchy has a
+    /// cycle.
+    pub(crate) fn action_children(&self, ns: &str) -> Result<BTreeMap<&SmolStr, Vec<&SmolStr>>> {
+        let parents = self.action_parents(ns);
+        if find_action_cycle(&parents).is_some() {
+            return Err(SchemaError::CycleInActionHierarchy);
+        }
+        let mut children: BTreeMap<&SmolStr, Vec<&SmolStr>> =
+            parents.keys().map(|name| (*name, Vec::new())).collect();
+        for (name, parents) in &parents {
+            for parent in parents {
+                children.entry(parent).or_default().push(name);
+            }
+        }
+        Ok(children)
+    }
+
+    /// Check that no action declared in this namespace, which is named `ns`,
+    /// is transitively a member of itself through `memberOf`, including an
+    /// action which lists itself. `memberOf` entries are followed as by
+    /// `action_parents`. Fails with `SchemaError::CycleInActionGroups`
+    /// naming the actions on a cycle by their qualified action entity UIDs,
+    /// in order, with the first repeated at the end.
+    pub fn check_action_hierarchy_acyclic(&self, ns: &str) -> Result<()> {
+        match find_action_cycle(&self.action_parents(ns)) {
+            Some(cycle) => Err(SchemaError::CycleInActionGroups(
+                cycle
+                    .into_iter()
+                    .map(|name| qualified_action(ns, None, name).to_string())
+                    .collect(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that no action in this namespace, which is named `ns`, is nested
+    /// more than `max_depth` levels below a root of the action group
+    /// hierarchy, where the roots are the actions which are not members of
+    /// any action in this namespace and have depth 0. `memberOf` entries are
+    /// followed as by `action_parents`. Fails with
+    /// `SchemaError::CycleInActionHierarchy` if the hierarchy has a cycle,
+    /// and otherwise with `SchemaError::ActionHierarchyTooDeep` giving the
+    /// longest chain from the first action by name which is too deep.
+    pub fn validate_action_hierarchy_depth(&self, ns: &str, max_depth: usize) -> Result<()> {
+        let children = self.action_children(ns)?;
+        let mut parents: BTreeMap<&SmolStr, Vec<&SmolStr>> = BTreeMap::new();
+        for (parent, members) in &children {
+            for member in members {
+                parents.entry(*member).or_default().push(*parent);
+            }
+        }
+        // The longest chain from each action to a root, computed parents
+        // first. There are no cycles, so this terminates.
+        fn longest<'a>(
+            name: &'a SmolStr,
+            parents: &BTreeMap<&'a SmolStr, Vec<&'a SmolStr>>,
+            chains: &mut BTreeMap<&'a SmolStr, Vec<&'a SmolStr>>,
+        ) -> Vec<&'a SmolStr> {
+            if let Some(chain) = chains.get(name) {
+                return chain.clone();
+            }
+            let mut chain = vec![name];
+            let mut best = Vec::new();
+            for parent in parents.get(name).into_iter().flatten() {
+                let parent_chain = longest(parent, parents, chains);
+                if parent_chain.len() > best.len() {
+                    best = parent_chain;
+                }
+            }
+            chain.extend(best);
+            chains.insert(name, chain.clone());
+            chain
+        }
+        let mut chains = BTreeMap::new();
+        for name in children.keys() {
+            let chain = longest(name, &parents, &mut chains);
+            if chain.len() > max_depth + 1 {
+                return Err(SchemaError::ActionHierarchyTooDeep(
+                    chain.iter().map(|name| name.to_string()).collect(),
+                    max_depth,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The action group hierarchy of this namespace, which is named `ns`, as a
+    /// JSON forest, for display. Each node is an object `{"id": ..., "children": [...]}`
+    /// whose children are the actions which are direct members of it. The
+    /// roots are the actions which are not members of any action in this
+    /// namespace, and an action which is a member of several actions appears
+    /// under each of them. `memberOf` entries are followed as by
+    /// `action_parents`. Fails if the hierarchy has a cycle.
+    pub fn action_tree(&self, ns: &str) -> Result<serde_json::Value> {
+        let children = self.action_children(ns)?;
+        let has_parent = children.values().flatten().collect::<BTreeSet<_>>();
+        fn node(name: &SmolStr, children: &BTreeMap<&SmolStr, Vec<&SmolStr>>) -> serde_json::Value {
+            let child_nodes = children[name]
+                .iter()
+                .map(|child| node(child, children))
+                .collect::<Vec<_>>();
+            json!({ "id": name, "children": child_nodes })
+        }
+        Ok(children
+            .keys()
+            .filter(|name| !has_parent.contains(name))
+            .map(|name| node(name, &children))
+            .collect())
+    }
+}
+
+/// The first cycle found by a depth first search of `parents`, starting from
+/// each action in turn and following its parents in order, as the actions on
+/// the cycle with the first repeated at the end.
+fn find_action_cycle<'a>(
+    parents: &BTreeMap<&'a SmolStr, BTreeSet<&'a SmolStr>>,
+) -> Option<Vec<&'a SmolStr>> {
+    let mut finished = BTreeSet::new();
+    for start in parents.keys() {
+        if finished.contains(start) {
+            continue;
+        }
+        let mut on_stack = vec![*start];
+        let mut stack = vec![parents[start].iter()];
+        while let Some(next) = stack.last_mut().map(Iterator::next) {
+            match next {
+                Some(parent) => {
+                    if let Some(pos) = on_stack.iter().position(|on| on == parent) {
+                        let mut cycle = on_stack.split_off(pos);
+                        cycle.push(parent);
+                        return Some(cycle);
+                    }
+                    if !finished.contains(parent) {
+                        on_stack.push(parent);
+                        stack.push(parents[parent].iter());
+                    }
+                }
+                None => {
+                    stack.pop();
+                    finished.extend(on_stack.pop());
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn namespace(actions: serde_json::Value) -> NamespaceDefinition {
+        serde_json::from_value(json!({ "entityTypes": {}, "actions": actions }))
+            .expect("Parse Error")
+    }
+
+    #[test]
+    fn tree() {
+        let ns = namespace(json!({
+            "read": {},
+            "write": {},
+            "view": { "memberOf": [{ "id": "read" }] },
+            "list": { "memberOf": [{ "id": "read" }, { "id": "write", "type": "Action" }] },
+            "delete": { "memberOf": [{ "id": "write" }, { "id": "admin", "type": "Other::Action" }] },
+        }));
+        assert_eq!(
+            ns.action_tree("").expect("hierarchy is acyclic"),
+            json!([
+                {
+                    "id": "read",
+                    "children": [
+                        { "id": "list", "children": [] },
+                        { "id": "view", "children": [] }
+                    ]
+                },
+                {
+                    "id": "write",
+                    "children": [
+                        { "id": "delete", "children": [] },
+                        { "id": "list", "children": [] }
+                    ]
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn qualified_member_of() {
+        let ns = namespace(json!({
+            "read": {},
+            "view": { "memberOf": [{ "id": "read", "type": "App::Action" }] },
+            "list": { "memberOf": [{ "id": "read", "type": "Action" }] }
+        }));
+        // In namespace `App`, `Action` names the action type of the empty
+        // namespace, and `App::Action` the one of this namespace.
+        assert_eq!(
+            ns.action_children("App").unwrap()[&SmolStr::from("read")],
+            vec!["view"]
+        );
+        assert_eq!(
+            ns.action_children("").unwrap()[&SmolStr::from("read")],
+            vec!["list"]
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        let ns = namespace(json!({
+            "a": { "memberOf": [{ "id": "c" }] },
+            "b": { "memberOf": [{ "id": "a" }] },
+            "c": { "memberOf": [{ "id": "b" }] },
+            "d": {}
+        }));
+        assert!(matches!(
+            ns.action_tree(""),
+            Err(SchemaError::CycleInActionHierarchy)
+        ));
+    }
+
+    #[test]
+    fn check_action_hierarchy_acyclic() {
+        namespace(json!({
+            "read": {},
+            "view": { "memberOf": [{ "id": "read" }, { "id": "read", "type": "Other::Action" }] },
+            "list": { "memberOf": [{ "id": "view", "type": "Action" }, { "id": "read" }] }
+        }))
+        .check_action_hierarchy_acyclic("")
+        .expect("hierarchy is acyclic");
+        assert_eq!(
+            namespace(json!({
+                "a": { "memberOf": [{ "id": "c" }] },
+                "b": { "memberOf": [{ "id": "a", "type": "Action" }] },
+                "c": { "memberOf": [{ "id": "b" }] },
+                "d": { "memberOf": [{ "id": "a" }] }
+            }))
+            .check_action_hierarchy_acyclic("")
+            .expect_err("hierarchy has a cycle")
+            .to_string(),
+            r#"Cycle in action groups: Action::"a" -> Action::"c" -> Action::"b" -> Action::"a""#
+        );
+        assert!(matches!(
+            namespace(json!({ "all": { "memberOf": [{ "id": "all" }] } }))
+                .check_action_hierarchy_acyclic(""),
+            Err(SchemaError::CycleInActionGroups(cycle))
+                if cycle == [r#"Action::"all""#, r#"Action::"all""#]
+        ));
+        // The cycle is only closed by an entry with an explicit type, which
+        // every traversal of the hierarchy follows.
+        let cyclic = namespace(json!({
+            "a": { "memberOf": [{ "id": "b", "type": "Action" }] },
+            "b": { "memberOf": [{ "id": "a" }] }
+        }));
+        assert!(cyclic.check_action_hierarchy_acyclic("").is_err());
+        assert!(matches!(
+            cyclic.action_children(""),
+            Err(SchemaError::CycleInActionHierarchy)
+        ));
+
+        // In namespace `App`, an entry with the type `App::Action` names an
+        // action of this namespace, and one with the type `Action` names an
+        // action of the empty namespace.
+        let app = namespace(json!({
+            "a": { "memberOf": [{ "id": "b", "type": "App::Action" }] },
+            "b": { "memberOf": [{ "id": "a" }] }
+        }));
+        assert_eq!(
+            app.check_action_hierarchy_acyclic("App")
+                .expect_err("hierarchy has a cycle")
+                .to_string(),
+            r#"Cycle in action groups: App::Action::"a" -> App::Action::"b" -> App::Action::"a""#
+        );
+        assert!(app.validate_action_hierarchy_depth("App", 4).is_err());
+        assert!(app.action_tree("App").is_err());
+        let app = namespace(json!({
+            "a": { "memberOf": [{ "id": "b", "type": "Action" }] },
+            "b": { "memberOf": [{ "id": "a" }] }
+        }));
+        app.check_action_hierarchy_acyclic("App")
+            .expect("hierarchy is acyclic");
+        app.validate_action_hierarchy_depth("App", 1)
+            .expect("hierarchy is 1 deep");
+        assert_eq!(
+            app.action_tree("App").expect("hierarchy is acyclic"),
+            json!([{ "id": "a", "children": [{ "id": "b", "children": [] }] }])
+        );
+    }
+
+    #[test]
+    fn hierarchy_depth() {
+        let ns = namespace(json!({
+            "a1": {},
+            "a2": { "memberOf": [{ "id": "a1" }] },
+            "a3": { "memberOf": [{ "id": "a2" }] },
+            "a4": { "memberOf": [{ "id": "a3" }] },
+            "a5": { "memberOf": [{ "id": "a4" }] },
+            "a6": { "memberOf": [{ "id": "a5" }, { "id": "a1" }] },
+            "view": { "memberOf": [{ "id": "a1" }] }
+        }));
+        ns.validate_action_hierarchy_depth("", 5)
+            .expect("hierarchy is 5 deep");
+        match ns.validate_action_hierarchy_depth("", 4) {
+            Err(SchemaError::ActionHierarchyTooDeep(chain, 4)) => {
+                assert_eq!(chain, vec!["a6", "a5", "a4", "a3", "a2", "a1"])
+            }
+            r => panic!("Expected a too deep hierarchy, got {r:?}"),
+        }
+        let cyclic = namespace(json!({
+            "a": { "memberOf": [{ "id": "b" }] },
+            "b": { "memberOf": [{ "id": "a" }] }
+        }));
+        assert!(matches!(
+            cyclic.validate_action_hierarchy_depth("", 4),
+            Err(SchemaError::CycleInActionHierarchy)
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Detection of dotted attribute paths which can name more than one
+//! attribute, and so may have more than one type.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{
+    diff::describe_type, fully_qualify, SchemaFragment, SchemaLocation, SchemaType,
+    SchemaTypeVariant, Severity, TypePath, TypePathRoot, ValidationFinding,
+};
+
+/// An advisory finding about a schema, i.e., a `ValidationFinding` with
+/// `Severity::Warning`.
+pub type SchemaWarning = ValidationFinding;
+
+impl SchemaFragment {
+    /// Warn about entity types where an attribute path written with dots,
+    /// e.g. `a.b`, can be read in more than one way with different results.
+    /// A path is read by looking up each attribute in a record, or in the
+    /// attributes of a referenced entity, so `a.b` names both the attribute
+    /// `b` of the record or entity in attribute `a`, and an attribute which is
+    /// itself named `a.b`. One warning is reported for each such path that
+    /// has more than one type.
+    ///
+    /// Entity types whose shape cannot be resolved are skipped; they are
+    /// reported by `validate_all`.
+    pub fn validate_path_unambiguity(&self) -> Vec<SchemaWarning> {
+        let shapes: BTreeMap<SmolStr, SchemaType> = self
+            .0
+            .iter()
+            .flat_map(|(ns, ns_def)| {
+                ns_def.entity_types.iter().filter_map(move |(name, et)| {
+                    let shape = self.resolve_type(ns, &et.shape.0).ok()?;
+                    Some((fully_qualify(ns, name), shape))
+                })
+            })
+            .collect();
+        let mut warnings = Vec::new();
+        for (name, shape) in &shapes {
+            let mut paths = BTreeMap::new();
+            collect_paths(&shapes, shape, "", &mut vec![name], &mut paths);
+            for (path, types) in paths {
+                if types.len() > 1 {
+                    warnings.push(ValidationFinding::new(
+                        Severity::Warning,
+                        SchemaLocation::Type(TypePath::new(TypePathRoot::EntityType(name.clone()))),
+                        format!(
+                            "attribute path `{path}` is ambiguous: it may have type {}",
+                            types.iter().map(|ty| format!("`{ty}`")).join(" or ")
+                        ),
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+}
+
+/// Record in `paths` the type of every dotted attribute path, starting with
+/// `prefix`, that can be read from a value of type `ty`. Entity types in
+/// `following` are not followed again, to avoid looping forever.
+fn collect_paths<'a>(
+    shapes: &'a BTreeMap<SmolStr, SchemaType>,
+    ty: &'a SchemaType,
+    prefix: &str,
+    following: &mut Vec<&'a SmolStr>,
+    paths: &mut BTreeMap<String, BTreeSet<String>>,
+) {
+    match ty {
+        SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+            for (attr, attr_ty) in attributes {
+                let path = if prefix.is_empty() {
+                    attr.to_string()
+                } else {
+                    format!("{prefix}.{attr}")
+                };
+                paths
+                    .entry(path.clone())
+                    .or_default()
+                    .insert(describe_type(&attr_ty.ty));
+                collect_paths(shapes, &attr_ty.ty, &path, following, paths);
+            }
+        }
+        SchemaType::Type(SchemaTypeVariant::Entity { name }) if !following.contains(&name) => {
+            if let Some((name, shape)) = shapes.get_key_value(name) {
+                following.push(name);
+                collect_paths(shapes, shape, prefix, following, paths);
+                following.pop();
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn dotted_attribute_name() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "a": { "type": "Record", "attributes": { "b": { "type": "String" } } },
+                                "a.b": { "type": "Long" },
+                                "team": { "type": "Entity", "name": "Team" },
+                                "team.owner": { "type": "Entity", "name": "User" }
+                            }
+                        }
+                    },
+                    "Team": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "owner": { "type": "Entity", "name": "User" } }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let warnings = fragment
+            .validate_path_unambiguity()
+            .iter()
+            .map(ToString::to_string)
+            .collect_vec();
+        // `team.owner` can be read two ways, but both give the same type. The
+        // ambiguity in `User` is also reachable from `Team` through `owner`.
+        assert_eq!(
+            warnings,
+            vec![
+                "warning at `App::Team`: attribute path `owner.a.b` is ambiguous: it may have \
+                 type `Long` or `String`",
+                "warning at `App::User`: attribute path `a.b` is ambiguous: it may have type \
+                 `Long` or `String`"
+            ]
+        );
+    }
+}
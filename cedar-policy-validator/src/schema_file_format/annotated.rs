@@ -0,0 +1,304 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A JSON5 rendering of a schema fragment annotated with comments describing
+//! what it means after resolution, for debugging.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+use itertools::Itertools;
+use serde_json::Value;
+
+use super::{diff::describe_type, SchemaFragment, SchemaType, SchemaTypeVariant, TypePathRoot};
+
+/// Comments to add to the rendering, keyed by the JSON pointer segments of
+/// the value they describe.
+#[derive(Default)]
+struct Annotations {
+    /// Comments written on the same line, after the value.
+    trailing: BTreeMap<Vec<String>, Vec<String>>,
+    /// Comments written on their own lines at the start of an object.
+    leading: BTreeMap<Vec<String>, Vec<String>>,
+}
+
+impl SchemaFragment {
+    /// This fragment as JSON5, with comments describing what it means after
+    /// resolution: the resolved type after every common type reference,
+    /// `default` after `required` and `additionalAttributes` flags with
+    /// their default values, and a note on actions which apply to any
+    /// principal or resource. Names are sorted, so the output is stable.
+    ///
+    /// This is a diagnostic aid. Comments aside, the output is the JSON
+    /// serialization of the fragment, but it is not guaranteed to parse back
+    /// into the same fragment.
+    pub fn to_annotated_debug_string(&self) -> String {
+        let value = serde_json::to_value(self).unwrap_or(Value::Null);
+        let mut annotations = Annotations::default();
+        for (ns, ns_def) in &self.0 {
+            let ns = ns.as_str();
+            for (id, action) in &ns_def.actions {
+                let Some(applies_to) = &action.applies_to else {
+                    annotations.leading.insert(
+                        pointer(&[ns, "actions", id]),
+                        vec!["no `appliesTo`: applies to any principal and resource".to_string()],
+                    );
+                    continue;
+                };
+                let mut notes = Vec::new();
+                for (key, kind, tys) in [
+                    ("principalTypes", "principal", &applies_to.principal_types),
+                    ("resourceTypes", "resource", &applies_to.resource_types),
+                ] {
+                    if tys.is_none() {
+                        notes.push(format!("no `{key}`: applies to any {kind}"));
+                    }
+                }
+                if !notes.is_empty() {
+                    annotations
+                        .leading
+                        .insert(pointer(&[ns, "actions", id, "appliesTo"]), notes);
+                }
+            }
+            // An entity type's `additionalAttributesDefinition` follows its
+            // shape, with the same path.
+            let mut seen = HashSet::new();
+            for (path, ty) in ns_def.declared_type_paths(ns) {
+                let first = seen.insert(path.root().clone());
+                let name = path.owner();
+                let segments = match path.root() {
+                    TypePathRoot::CommonType(_) => vec![ns, "commonTypes", name],
+                    TypePathRoot::EntityType(_) if first => vec![ns, "entityTypes", name, "shape"],
+                    TypePathRoot::EntityType(_) => {
+                        vec![ns, "entityTypes", name, "additionalAttributesDefinition"]
+                    }
+                    TypePathRoot::EntityTags(_) => vec![ns, "entityTypes", name, "tags"],
+                    TypePathRoot::ActionContext { .. } => {
+                        vec![ns, "actions", name, "appliesTo", "context"]
+                    }
+                };
+                self.annotate_type(ns, ty, pointer(&segments), &mut annotations);
+            }
+        }
+        let mut out = String::new();
+        write_value(&mut out, &value, &mut Vec::new(), 0, &annotations);
+        out.push('\n');
+        out
+    }
+
+    /// Add the comments for `ty`, written in namespace `ns` at `path`, and
+    /// the types nested inside it, to `annotations`.
+    fn annotate_type(
+        &self,
+        ns: &str,
+        ty: &SchemaType,
+        path: Vec<String>,
+        annotations: &mut Annotations,
+    ) {
+        let child = |segments: &[&str]| {
+            let mut child = path.clone();
+            child.extend(segments.iter().map(ToString::to_string));
+            child
+        };
+        match ty {
+            SchemaType::TypeDef { .. } => {
+                let comment = match self.resolve_type(ns, ty) {
+                    Ok(resolved) => format!("resolved: {}", describe_type(&resolved)),
+                    Err(e) => format!("unresolved: {e}"),
+                };
+                annotations.trailing.entry(path).or_default().push(comment);
+            }
+            SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+                self.annotate_type(ns, element, child(&["element"]), annotations);
+            }
+            SchemaType::Type(SchemaTypeVariant::Record {
+                attributes,
+                additional_attributes,
+            }) => {
+                if !additional_attributes {
+                    annotations.trailing.insert(
+                        child(&["additionalAttributes"]),
+                        vec!["default".to_string()],
+                    );
+                }
+                for (attr, attr_ty) in attributes {
+                    if attr_ty.required {
+                        annotations.trailing.insert(
+                            child(&["attributes", attr, "required"]),
+                            vec!["default".to_string()],
+                        );
+                    }
+                    self.annotate_type(ns, &attr_ty.ty, child(&["attributes", attr]), annotations);
+                }
+            }
+            SchemaType::Type(_) => (),
+        }
+    }
+}
+
+fn pointer(segments: &[&str]) -> Vec<String> {
+    segments.iter().map(ToString::to_string).collect()
+}
+
+/// Whether the keys of the object at `path` are names, which are sorted in
+/// the output, rather than fixed keys, which keep their order.
+fn has_name_keys(path: &[String]) -> bool {
+    path.is_empty()
+        || matches!(
+            path.last().map(String::as_str),
+            Some(
+                "commonTypes" | "entityTypes" | "actions" | "attributes" | "constants" | "imports"
+            )
+        )
+}
+
+/// Write `value`, found at `path`, to `out` at `indent` levels of
+/// indentation, with the comments in `annotations`. The caller writes any
+/// trailing comments for `value` itself.
+fn write_value(
+    out: &mut String,
+    value: &Value,
+    path: &mut Vec<String>,
+    indent: usize,
+    annotations: &Annotations,
+) {
+    let pad = |indent: usize| "  ".repeat(indent);
+    let entries: Vec<(Option<&String>, &Value)> = match value {
+        Value::Object(map) if !map.is_empty() || annotations.leading.contains_key(path) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            if has_name_keys(path) {
+                entries.sort_by_key(|(k, _)| *k);
+            }
+            entries.into_iter().map(|(k, v)| (Some(k), v)).collect()
+        }
+        Value::Array(items) if !items.is_empty() => items.iter().map(|v| (None, v)).collect(),
+        _ => {
+            out.push_str(&value.to_string());
+            return;
+        }
+    };
+    let (open, close) = if value.is_array() {
+        ('[', ']')
+    } else {
+        ('{', '}')
+    };
+    out.push(open);
+    for comment in annotations.leading.get(path).into_iter().flatten() {
+        let _ = write!(out, "\n{}// {comment}", pad(indent + 1));
+    }
+    let last = entries.len().saturating_sub(1);
+    for (i, (key, item)) in entries.into_iter().enumerate() {
+        out.push('\n');
+        out.push_str(&pad(indent + 1));
+        path.push(key.cloned().unwrap_or_else(|| i.to_string()));
+        if let Some(key) = key {
+            let _ = write!(out, "{}: ", Value::String(key.clone()));
+        }
+        write_value(out, item, path, indent + 1, annotations);
+        if i != last {
+            out.push(',');
+        }
+        if let Some(comments) = annotations.trailing.get(path) {
+            let _ = write!(out, " // {}", comments.iter().join("; "));
+        }
+        path.pop();
+    }
+    let _ = write!(out, "\n{}{close}", pad(indent));
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn to_annotated_debug_string() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": { "Amount": { "type": "Extension", "name": "decimal" } },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "balance": { "type": "Amount" },
+                                "nickname": { "type": "String", "required": false }
+                            }
+                        },
+                        "tags": { "type": "Amount" }
+                    }
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User"] } }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let expected = r#"{
+  "App": {
+    "commonTypes": {
+      "Amount": {
+        "type": "Extension",
+        "name": "decimal"
+      }
+    },
+    "entityTypes": {
+      "User": {
+        "memberOfTypes": [],
+        "shape": {
+          "type": "Record",
+          "attributes": {
+            "balance": {
+              "type": "Amount",
+              "required": true // default
+            }, // resolved: decimal
+            "nickname": {
+              "type": "String",
+              "required": false
+            }
+          },
+          "additionalAttributes": false // default
+        },
+        "tags": {
+          "type": "Amount"
+        } // resolved: decimal
+      }
+    },
+    "actions": {
+      "view": {
+        "attributes": null,
+        "appliesTo": {
+          // no `resourceTypes`: applies to any resource
+          "resourceTypes": null,
+          "principalTypes": [
+            "User"
+          ],
+          "context": {
+            "type": "Record",
+            "attributes": {},
+            "additionalAttributes": false // default
+          }
+        },
+        "memberOf": null
+      }
+    }
+  }
+}
+"#;
+        assert_eq!(fragment.to_annotated_debug_string(), expected);
+    }
+}
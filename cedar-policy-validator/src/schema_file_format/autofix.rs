@@ -0,0 +1,387 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Automatic fixes for declarations with an obvious, meaning-preserving
+//! rewrite, applied on request by `SchemaFragment::autofix`.
+
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+
+use super::references::extension_names;
+use super::{
+    fully_qualify, ApplySpec, AttributesOrContext, LintProfile, NamespaceDefinition,
+    SchemaFragment, SchemaType, SchemaTypeVariant,
+};
+
+/// The fixes `SchemaFragment::autofix` can apply. None of them change what
+/// the schema means, and none are applied unless enabled with
+/// `LintProfile::with_fix`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SchemaFix {
+    /// An extension type whose name differs from the name of an extension
+    /// only in case, e.g. `Decimal`, is renamed to that extension. Names
+    /// which match no extension in any case are left alone.
+    ExtensionCasing,
+    /// An action without `appliesTo` is given one with an empty context
+    /// which applies to any principal and resource, and an action without
+    /// `memberOf` is given an empty `memberOf` list.
+    ImplicitDefaults,
+    /// Repeated values are removed from string `enum`s and from the `enum`
+    /// of entity types, keeping the first occurrence of each value.
+    DuplicateEnumValues,
+    /// The `type` of an action `memberOf` entry is dropped when it names the
+    /// action entity type of the action's own namespace, which is the type
+    /// used when none is given.
+    ActionQualification,
+}
+
+impl SchemaFix {
+    /// All fixes, in the order they are applied.
+    pub fn all() -> impl Iterator<Item = SchemaFix> {
+        [
+            Self::ExtensionCasing,
+            Self::ImplicitDefaults,
+            Self::DuplicateEnumValues,
+            Self::ActionQualification,
+        ]
+        .into_iter()
+    }
+}
+
+/// A single change made by `SchemaFragment::autofix`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedFix {
+    fix: SchemaFix,
+    namespace: SmolStr,
+    message: String,
+}
+
+impl AppliedFix {
+    /// The fix which made this change.
+    pub fn fix(&self) -> SchemaFix {
+        self.fix
+    }
+
+    /// The namespace containing the declaration which was changed.
+    pub fn namespace(&self) -> &SmolStr {
+        &self.namespace
+    }
+
+    /// Description of the change.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for AppliedFix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "fixed[{:?}]: {}", self.fix, self.message)?;
+        if !self.namespace.is_empty() {
+            write!(f, " in namespace `{}`", self.namespace)?;
+        }
+        Ok(())
+    }
+}
+
+impl SchemaFragment {
+    /// A copy of this fragment with every fix enabled by `profile` applied,
+    /// along with a record of each change made. Fixes are applied in the
+    /// order of `SchemaFix::all`, and namespaces and declarations are visited
+    /// in sorted order, so the record is deterministic. Applying the same
+    /// fixes to the fixed fragment changes nothing.
+    pub fn autofix(&self, profile: &LintProfile) -> (SchemaFragment, Vec<AppliedFix>) {
+        let mut fixed = self.clone();
+        let mut applied = Vec::new();
+        for fix in SchemaFix::all().filter(|fix| profile.fix_enabled(*fix)) {
+            for (namespace, ns_def) in fixed.0.iter_mut().sorted_by_key(|(ns, _)| *ns) {
+                for message in ns_def.apply_fix(namespace, fix) {
+                    applied.push(AppliedFix {
+                        fix,
+                        namespace: namespace.clone(),
+                        message,
+                    });
+                }
+            }
+        }
+        (fixed, applied)
+    }
+}
+
+impl NamespaceDefinition {
+    /// Apply a single fix to this namespace, which is named `ns`, returning
+    /// a description of each change made.
+    fn apply_fix(&mut self, ns: &str, fix: SchemaFix) -> Vec<String> {
+        let mut messages = Vec::new();
+        match fix {
+            SchemaFix::ExtensionCasing => {
+                let extensions = extension_names();
+                self.for_each_variant_mut(ns, &mut |decl, variant| {
+                    if let SchemaTypeVariant::Extension { name, .. } = variant {
+                        if extensions.contains(name.as_str()) {
+                            return;
+                        }
+                        if let Some(ext) =
+                            extensions.iter().find(|ext| ext.eq_ignore_ascii_case(name))
+                        {
+                            messages.push(format!(
+                                "renamed extension type `{name}` to `{ext}` in {decl}"
+                            ));
+                            *name = ext.into();
+                        }
+                    }
+                });
+            }
+            SchemaFix::ImplicitDefaults => {
+                for (name, action) in self.actions.iter_mut().sorted_by_key(|(name, _)| *name) {
+                    if action.applies_to.is_none() {
+                        action.applies_to = Some(ApplySpec {
+                            resource_types: None,
+                            principal_types: None,
+                            context: AttributesOrContext::default(),
+                        });
+                        messages.push(format!("gave action `{name}` an explicit `appliesTo`"));
+                    }
+                    if action.member_of.is_none() {
+                        action.member_of = Some(Vec::new());
+                        messages.push(format!("gave action `{name}` an explicit empty `memberOf`"));
+                    }
+                }
+            }
+            SchemaFix::DuplicateEnumValues => {
+                self.for_each_variant_mut(ns, &mut |decl, variant| {
+                    if let SchemaTypeVariant::String {
+                        enumeration: Some(values),
+                        ..
+                    } = variant
+                    {
+                        if let Some(removed) = dedup(values) {
+                            messages.push(format!(
+                                "removed repeated string `enum` values {removed} in {decl}"
+                            ));
+                        }
+                    }
+                });
+                for (name, entity_type) in self
+                    .entity_types
+                    .iter_mut()
+                    .sorted_by_key(|(name, _)| *name)
+                {
+                    if let Some(removed) = entity_type.enumeration.as_mut().and_then(dedup) {
+                        messages.push(format!(
+                            "removed repeated `enum` values {removed} of entity type `{name}`"
+                        ));
+                    }
+                }
+            }
+            SchemaFix::ActionQualification => {
+                let own = fully_qualify(ns, "Action");
+                for (name, action) in self.actions.iter_mut().sorted_by_key(|(name, _)| *name) {
+                    for parent in action.member_of.iter_mut().flatten() {
+                        if parent.ty.as_ref() == Some(&own) {
+                            parent.ty = None;
+                            messages.push(format!(
+                                "dropped the `type` of the `memberOf` entry `{}` of action \
+                                 `{name}`, since `{own}` is the default",
+                                parent.id
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        messages
+    }
+
+    /// Call `f` on every type variant declared in this namespace, which is
+    /// named `ns`, including those nested inside other types, along with a
+    /// description of the declaration it is part of. Declarations are
+    /// visited in sorted order.
+    fn for_each_variant_mut(&mut self, ns: &str, f: &mut impl FnMut(&str, &mut SchemaTypeVariant)) {
+        for (path, ty) in self.declared_type_paths_mut(ns) {
+            let decl = path.declaration();
+            walk_variants_mut(ty, &mut |variant| f(&decl, variant));
+        }
+    }
+}
+
+/// Call `f` on `ty`, if it is not a common type reference, and on every type
+/// nested inside it.
+fn walk_variants_mut(ty: &mut SchemaType, f: &mut impl FnMut(&mut SchemaTypeVariant)) {
+    let SchemaType::Type(variant) = ty else {
+        return;
+    };
+    f(variant);
+    match variant {
+        SchemaTypeVariant::Set { element } => walk_variants_mut(element, f),
+        SchemaTypeVariant::Record { attributes, .. } => {
+            for attr_ty in attributes.values_mut() {
+                walk_variants_mut(&mut attr_ty.ty, f);
+            }
+        }
+        SchemaTypeVariant::Union { options } => {
+            for option in options {
+                walk_variants_mut(option, f);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Remove repeated values from `values`, keeping the first occurrence of
+/// each, and describe the values which were repeated, if any.
+fn dedup(values: &mut Vec<SmolStr>) -> Option<String> {
+    let mut seen = HashSet::new();
+    let mut removed = Vec::new();
+    values.retain(|value| {
+        let first = seen.insert(value.clone());
+        if !first {
+            removed.push(value.clone());
+        }
+        first
+    });
+    if removed.is_empty() {
+        None
+    } else {
+        Some(
+            removed
+                .iter()
+                .unique()
+                .map(|value| format!("{value:?}"))
+                .join(", "),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn fragment() -> SchemaFragment {
+        SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Status": { "type": "String", "enum": ["open", "closed", "open"] }
+                },
+                "entityTypes": {
+                    "Role": { "enum": ["admin", "viewer", "admin"] },
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "balance": { "type": "Extension", "name": "Decimal" },
+                                "hosts": {
+                                    "type": "Set",
+                                    "element": { "type": "Extension", "name": "IPAddr" }
+                                },
+                                "score": { "type": "Extension", "name": "Score" }
+                            }
+                        },
+                        "tags": { "type": "String", "enum": ["low", "high", "low"] }
+                    }
+                },
+                "actions": {
+                    "read": {},
+                    "view": { "memberOf": [{ "id": "read", "type": "App::Action" }] }
+                }
+            }
+        }))
+        .expect("Parse Error")
+    }
+
+    #[test]
+    fn autofix() {
+        let profile = SchemaFix::all()
+            .filter(|fix| *fix != SchemaFix::ImplicitDefaults)
+            .fold(LintProfile::default(), LintProfile::with_fix);
+        let (fixed, applied) = fragment().autofix(&profile);
+        assert_eq!(
+            applied.iter().map(AppliedFix::message).collect::<Vec<_>>(),
+            vec![
+                "renamed extension type `Decimal` to `decimal` in entity type `User`",
+                "renamed extension type `IPAddr` to `ipaddr` in entity type `User`",
+                "removed repeated string `enum` values \"open\" in common type `Status`",
+                "removed repeated string `enum` values \"low\" in tags of entity type `User`",
+                "removed repeated `enum` values \"admin\" of entity type `Role`",
+                "dropped the `type` of the `memberOf` entry `read` of action `view`, since \
+                 `App::Action` is the default",
+            ]
+        );
+        let expected = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Status": { "type": "String", "enum": ["open", "closed"] }
+                },
+                "entityTypes": {
+                    "Role": { "enum": ["admin", "viewer"] },
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "balance": { "type": "Extension", "name": "decimal" },
+                                "hosts": {
+                                    "type": "Set",
+                                    "element": { "type": "Extension", "name": "ipaddr" }
+                                },
+                                "score": { "type": "Extension", "name": "Score" }
+                            }
+                        },
+                        "tags": { "type": "String", "enum": ["low", "high"] }
+                    }
+                },
+                "actions": {
+                    "read": {},
+                    "view": { "memberOf": [{ "id": "read" }] }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(fixed, expected);
+
+        let (refixed, applied) = fixed.autofix(&profile);
+        assert!(applied.is_empty());
+        assert_eq!(refixed, fixed);
+    }
+
+    #[test]
+    fn implicit_defaults() {
+        let profile = LintProfile::default().with_fix(SchemaFix::ImplicitDefaults);
+        let (fixed, applied) = fragment().autofix(&profile);
+        assert_eq!(
+            applied.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "fixed[ImplicitDefaults]: gave action `read` an explicit `appliesTo` in \
+                 namespace `App`",
+                "fixed[ImplicitDefaults]: gave action `read` an explicit empty `memberOf` in \
+                 namespace `App`",
+                "fixed[ImplicitDefaults]: gave action `view` an explicit `appliesTo` in \
+                 namespace `App`",
+            ]
+        );
+        assert!(fixed.autofix(&profile).1.is_empty());
+    }
+
+    #[test]
+    fn no_fixes_enabled() {
+        let (fixed, applied) = fragment().autofix(&LintProfile::default());
+        assert!(applied.is_empty());
+        assert_eq!(fixed, fragment());
+    }
+}
@@ -0,0 +1,172 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A markdown changelog between two versions of a schema fragment.
+
+use super::references::qualified_action;
+use super::{fully_qualify, DiffDeclaration, SchemaChange, SchemaChangeKind, SchemaFragment};
+
+impl SchemaFragment {
+    /// A markdown changelog going from `old` to `new`, with the changes found
+    /// by `diff` listed under `Added`, `Removed`, `Changed (breaking)` and
+    /// `Changed (non-breaking)` headings, as classified by
+    /// `SchemaChangeKind::is_breaking`. Added entity types and actions show
+    /// their `since` version, if they have one. Sections with no changes are
+    /// left out, and changes appear in the order `diff` reports them, so the
+    /// output only depends on the two fragments.
+    pub fn changelog(old: &SchemaFragment, new: &SchemaFragment) -> String {
+        let diff = old.diff(new);
+        let mut sections: [(&str, Vec<String>); 4] = [
+            ("Added", Vec::new()),
+            ("Removed", Vec::new()),
+            ("Changed (breaking)", Vec::new()),
+            ("Changed (non-breaking)", Vec::new()),
+        ];
+        for change in diff.changes() {
+            let declaration = describe_declaration(change);
+            let (section, entry) = match change.kind().describe() {
+                None if *change.kind() == SchemaChangeKind::Added => {
+                    let entry = match new.since(change) {
+                        Some(since) => format!("{declaration} (since {since})"),
+                        None => declaration,
+                    };
+                    (0, entry)
+                }
+                None => (1, declaration),
+                Some(line) if change.kind().is_breaking() => {
+                    (2, format!("{declaration}: `{line}`"))
+                }
+                Some(line) => (3, format!("{declaration}: `{line}`")),
+            };
+            sections[section].1.push(entry);
+        }
+        let mut out = "# Schema changelog\n".to_string();
+        if diff.is_empty() {
+            out.push_str("\nNo changes.\n");
+        }
+        for (heading, entries) in sections {
+            if !entries.is_empty() {
+                out.push_str(&format!("\n## {heading}\n\n"));
+                for entry in entries {
+                    out.push_str(&format!("- {entry}\n"));
+                }
+            }
+        }
+        out
+    }
+
+    /// The `since` version of the declaration `change` is about, if it is an
+    /// entity type or action in this fragment with one.
+    fn since(&self, change: &SchemaChange) -> Option<String> {
+        let ns_def = self.0.get(change.namespace())?;
+        let since = match change.declaration() {
+            DiffDeclaration::CommonType(_) => None,
+            DiffDeclaration::EntityType(name) => ns_def.entity_types.get(name)?.since.as_ref(),
+            DiffDeclaration::Action(id) => ns_def.actions.get(id)?.since.as_ref(),
+        };
+        since.map(ToString::to_string)
+    }
+}
+
+/// The declaration `change` is about, with its fully qualified name.
+fn describe_declaration(change: &SchemaChange) -> String {
+    let ns = change.namespace();
+    match change.declaration() {
+        DiffDeclaration::CommonType(name) => format!("type `{}`", fully_qualify(ns, name)),
+        DiffDeclaration::EntityType(name) => format!("entity `{}`", fully_qualify(ns, name)),
+        DiffDeclaration::Action(id) => format!("action `{}`", qualified_action(ns, None, id)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn changelog() {
+        let old = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "age": { "type": "String" }
+                            }
+                        }
+                    },
+                    "Legacy": {}
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User"] } }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let new = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "age": { "type": "Long" },
+                                "email": { "type": "String", "required": false }
+                            }
+                        }
+                    },
+                    "Photo": { "since": "1.3.0" }
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User", "Photo"] } },
+                    "share": {}
+                }
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(
+            SchemaFragment::changelog(&old, &new),
+            r#"# Schema changelog
+
+## Added
+
+- entity `App::Photo` (since 1.3.0)
+- action `App::Action::"share"`
+
+## Removed
+
+- entity `App::Legacy`
+
+## Changed (breaking)
+
+- entity `App::User`: `~ age: String -> Long`
+
+## Changed (non-breaking)
+
+- entity `App::User`: `+ email?: String`
+- action `App::Action::"view"`: `+ principalTypes Photo`
+"#
+        );
+        assert_eq!(
+            SchemaFragment::changelog(&new, &new),
+            "# Schema changelog\n\nNo changes.\n"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Expansion of references to the `constants` of a namespace in string
+//! enumerations. A reference is written as `$` followed by the name of the
+//! constant, and a value starting with `$` is escaped by writing it with `$$`.
+
+use std::convert::Infallible;
+
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{
+    NamespaceDefinition, SchemaFragment, SchemaType, SchemaTypeVariant, SharedSchemaType,
+    TypeOfAttribute,
+};
+use crate::{Result, SchemaError};
+
+impl NamespaceDefinition {
+    /// The values of the constant `name` declared in this namespace, with
+    /// any references to other constants replaced by their values. Fails
+    /// with `SchemaError::UndeclaredConstant` if `name`, or a constant it
+    /// refers to, is not declared, and with `SchemaError::CycleInConstants`
+    /// if it is defined in terms of itself.
+    pub fn resolved_constant(&self, name: &str) -> Result<Vec<SmolStr>> {
+        self.expand_values(&[format!("${name}").into()], &mut Vec::new())
+    }
+
+    /// Replace every `$` reference to a constant, in the string enumerations
+    /// of this namespace and in its constants, with the values of that
+    /// constant, and every `$$` escape with the value it stands for. The
+    /// namespace is left unchanged if a reference cannot be expanded, as
+    /// described for `resolved_constant`.
+    ///
+    /// Expanded values may themselves start with `$`, so a namespace must
+    /// only be expanded once. `SchemaFragment` does this when it is
+    /// deserialized.
+    pub fn expand_constants(&mut self) -> Result<()> {
+        let mut expanded =
+            self.map_enumerations(|values| self.expand_values(values, &mut Vec::new()))?;
+        for name in self.constants.keys().sorted() {
+            expanded
+                .constants
+                .insert(name.clone(), self.resolved_constant(name)?);
+        }
+        *self = expanded;
+        Ok(())
+    }
+
+    /// This namespace with every value starting with `$`, in its string
+    /// enumerations and in its constants, escaped as `$$`, so that expanding
+    /// the result gives back this namespace.
+    pub(crate) fn escape_constants(&self) -> Self {
+        let escape = |values: &[SmolStr]| -> std::result::Result<_, Infallible> {
+            Ok(values
+                .iter()
+                .map(|value| match value.strip_prefix('$') {
+                    Some(_) => format!("${value}").into(),
+                    None => value.clone(),
+                })
+                .collect())
+        };
+        let Ok(mut escaped) = self.map_enumerations(escape);
+        for values in escaped.constants.values_mut() {
+            let Ok(escaped_values) = escape(values);
+            *values = escaped_values;
+        }
+        escaped
+    }
+
+    /// This namespace with `f` applied to the values of every string
+    /// enumeration in the types it declares, as listed by
+    /// `declared_type_paths`.
+    fn map_enumerations<E>(
+        &self,
+        f: impl Fn(&[SmolStr]) -> std::result::Result<Vec<SmolStr>, E>,
+    ) -> std::result::Result<Self, E> {
+        let mut mapped = self.clone();
+        // The paths are not needed, so the namespace qualifying them does
+        // not matter.
+        for (_, ty) in mapped.declared_type_paths_mut("") {
+            *ty = map_enumerations(ty, &f)?;
+        }
+        Ok(mapped)
+    }
+
+    /// `values` with each `$` reference replaced by the values of the
+    /// constant it names, and each `$$` escape by the value it stands for.
+    /// `expanding` holds the constants being expanded, to detect cycles.
+    fn expand_values(
+        &self,
+        values: &[SmolStr],
+        expanding: &mut Vec<SmolStr>,
+    ) -> Result<Vec<SmolStr>> {
+        let mut expanded = Vec::new();
+        for value in values {
+            let Some(name) = value.strip_prefix('$') else {
+                expanded.push(value.clone());
+                continue;
+            };
+            if name.starts_with('$') {
+                expanded.push(name.into());
+                continue;
+            }
+            if expanding.iter().any(|n| n == name) {
+                return Err(SchemaError::CycleInConstants(name.to_string()));
+            }
+            let values = self
+                .constants
+                .get(name)
+                .ok_or_else(|| SchemaError::UndeclaredConstant(name.to_string()))?;
+            expanding.push(name.into());
+            expanded.extend(self.expand_values(values, expanding)?);
+            expanding.pop();
+        }
+        Ok(expanded)
+    }
+}
+
+/// `ty` with `f` applied to the values of each of its string enumerations.
+fn map_enumerations<E>(
+    ty: &SchemaType,
+    f: &impl Fn(&[SmolStr]) -> std::result::Result<Vec<SmolStr>, E>,
+) -> std::result::Result<SchemaType, E> {
+    Ok(match ty {
+        SchemaType::Type(SchemaTypeVariant::String {
+            enumeration: Some(values),
+            pattern,
+        }) => SchemaType::Type(SchemaTypeVariant::String {
+            enumeration: Some(f(values)?),
+            pattern: pattern.clone(),
+        }),
+        SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+            SchemaType::Type(SchemaTypeVariant::Set {
+                element: SharedSchemaType::new(map_enumerations(element, f)?),
+            })
+        }
+        SchemaType::Type(SchemaTypeVariant::Union { options }) => {
+            SchemaType::Type(SchemaTypeVariant::Union {
+                options: options
+                    .iter()
+                    .map(|option| Ok(map_enumerations(option, f)?.into()))
+                    .collect::<std::result::Result<_, _>>()?,
+            })
+        }
+        SchemaType::Type(SchemaTypeVariant::Record {
+            attributes,
+            additional_attributes,
+        }) => SchemaType::Type(SchemaTypeVariant::Record {
+            attributes: attributes
+                .iter()
+                .map(|(attr, attr_ty)| {
+                    let attr_ty = TypeOfAttribute {
+                        ty: map_enumerations(&attr_ty.ty, f)?.into(),
+                        ..attr_ty.clone()
+                    };
+                    Ok((attr.clone(), attr_ty))
+                })
+                .collect::<std::result::Result<_, _>>()?,
+            additional_attributes: *additional_attributes,
+        }),
+        _ => ty.clone(),
+    })
+}
+
+impl SchemaFragment {
+    /// Expand the constant references in every namespace in this fragment, as
+    /// `NamespaceDefinition::expand_constants` does. Fragments are expanded
+    /// when they are deserialized, so this is only needed, and must only be
+    /// called once, for fragments built in code.
+    pub fn expand_constants(&mut self) -> Result<()> {
+        self.0
+            .values_mut()
+            .try_for_each(NamespaceDefinition::expand_constants)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::AttributeValidationMode;
+
+    #[test]
+    fn expand_constants() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "constants": {
+                    "open": ["active", "pending"],
+                    "statuses": ["$open", "closed"]
+                },
+                "entityTypes": {
+                    "Ticket": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "status": { "type": "String", "enum": ["$statuses"] },
+                                "history": {
+                                    "type": "Set",
+                                    "element": { "type": "String", "enum": ["$open", "archived"] }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let ns_def = &fragment.0["App"];
+        assert_eq!(
+            ns_def.constants["statuses"],
+            vec!["active", "pending", "closed"]
+        );
+        assert_eq!(
+            ns_def.resolved_constant("statuses").expect("declared"),
+            vec!["active", "pending", "closed"]
+        );
+        let SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) =
+            &ns_def.entity_types["Ticket"].shape.0
+        else {
+            panic!("Expected a record shape");
+        };
+        assert_eq!(
+            *attributes["status"].ty,
+            SchemaType::Type(SchemaTypeVariant::String {
+                enumeration: Some(vec!["active".into(), "pending".into(), "closed".into()]),
+                pattern: None,
+            })
+        );
+        assert_eq!(
+            *attributes["history"].ty,
+            SchemaType::Type(SchemaTypeVariant::Set {
+                element: SharedSchemaType::new(SchemaType::Type(SchemaTypeVariant::String {
+                    enumeration: Some(vec!["active".into(), "pending".into(), "archived".into()]),
+                    pattern: None,
+                })),
+            })
+        );
+        let ticket = &ns_def.entity_types["Ticket"];
+        let validate = |status: &str| {
+            ticket.validate_attributes(
+                &json!({ "status": status, "history": [] }),
+                &fragment,
+                "App",
+                AttributeValidationMode::StoredValidation,
+            )
+        };
+        assert!(validate("closed").is_ok());
+        assert!(matches!(
+            validate("archived"),
+            Err(SchemaError::ValueTypeMismatch(path, msg))
+                if path == "attrs.status"
+                    && msg == r#"expected one of "active", "pending", "closed", found "archived""#
+        ));
+    }
+
+    #[test]
+    fn escaped_values() {
+        let json = json!({
+            "App": {
+                "constants": { "currencies": ["$$USD", "EUR"] },
+                "entityTypes": {
+                    "Price": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "currency": { "type": "String", "enum": ["$currencies", "$$GBP"] }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        });
+        let fragment = SchemaFragment::from_json_value(json).expect("Parse Error");
+        let ns_def = &fragment.0["App"];
+        assert_eq!(ns_def.constants["currencies"], vec!["$USD", "EUR"]);
+        let SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) =
+            &ns_def.entity_types["Price"].shape.0
+        else {
+            panic!("Expected a record shape");
+        };
+        assert_eq!(
+            *attributes["currency"].ty,
+            SchemaType::Type(SchemaTypeVariant::String {
+                enumeration: Some(vec!["$USD".into(), "EUR".into(), "$GBP".into()]),
+                pattern: None,
+            })
+        );
+
+        let written = fragment.to_json_value().expect("Serializable");
+        assert_eq!(
+            written["App"]["entityTypes"]["Price"]["shape"]["attributes"]["currency"]["enum"],
+            json!(["$$USD", "EUR", "$$GBP"])
+        );
+        assert_eq!(
+            SchemaFragment::from_json_value(written).expect("Parse Error"),
+            fragment
+        );
+    }
+
+    #[test]
+    fn every_entry_point_expands() {
+        let src = json!({
+            "": {
+                "constants": { "statuses": ["open", "closed"] },
+                "entityTypes": {
+                    "Ticket": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "status": { "type": "String", "enum": ["$statuses"] } }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        })
+        .to_string();
+        let expected = SchemaFragment::from_json_str(&src).expect("Parse Error");
+        assert_eq!(
+            serde_json::from_str::<SchemaFragment>(&src).expect("Parse Error"),
+            expected
+        );
+        assert_eq!(
+            SchemaFragment::from_file(src.as_bytes()).expect("Parse Error"),
+            expected
+        );
+        assert!(src.parse::<crate::ValidatorSchema>().is_ok());
+        assert!(matches!(
+            src.replace("$statuses", "$missing")
+                .parse::<crate::ValidatorSchema>(),
+            Err(SchemaError::UndeclaredConstant(name)) if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn bad_constants() {
+        let parse = |constants: serde_json::Value| {
+            SchemaFragment::from_json_value(json!({
+                "App": {
+                    "constants": constants,
+                    "entityTypes": {
+                        "Ticket": {
+                            "shape": {
+                                "type": "Record",
+                                "attributes": { "status": { "type": "String", "enum": ["$statuses"] } }
+                            }
+                        }
+                    },
+                    "actions": {}
+                }
+            }))
+        };
+        assert!(matches!(
+            parse(json!({})),
+            Err(SchemaError::UndeclaredConstant(name)) if name == "statuses"
+        ));
+        assert!(matches!(
+            parse(json!({ "statuses": ["$more"], "more": ["open", "$statuses"] })),
+            Err(SchemaError::CycleInConstants(_))
+        ));
+    }
+}
@@ -0,0 +1,208 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! An index from the actions and entity types declared in a schema fragment
+//! to the policies which mention them.
+
+use std::collections::BTreeMap;
+
+use cedar_policy_core::ast::{EntityType as PolicyEntityType, PolicyID, Template};
+use smol_str::SmolStr;
+
+use super::references::SchemaReference;
+use super::SchemaFragment;
+use crate::expr_iterator::policy_entity_uids;
+
+/// Which policies mention each action and entity type, built by
+/// `SchemaFragment::cross_reference_policies`. Actions are keyed by their
+/// fully qualified entity UID (e.g., `App::Action::"view"`) and entity types
+/// by their fully qualified name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrossRefIndex {
+    actions: BTreeMap<SmolStr, Vec<PolicyID>>,
+    entity_types: BTreeMap<SmolStr, Vec<PolicyID>>,
+    undeclared_actions: BTreeMap<SmolStr, Vec<PolicyID>>,
+    undeclared_entity_types: BTreeMap<SmolStr, Vec<PolicyID>>,
+}
+
+impl CrossRefIndex {
+    /// The policies mentioning a declared action, or `None` if the action is
+    /// not declared in the schema. Declared actions which no policy mentions
+    /// have no policies.
+    pub fn policies_for_action(&self, action: &str) -> Option<&[PolicyID]> {
+        self.actions.get(action).map(Vec::as_slice)
+    }
+
+    /// The policies mentioning a declared entity type, or `None` if the entity
+    /// type is not declared in the schema.
+    pub fn policies_for_entity_type(&self, entity_type: &str) -> Option<&[PolicyID]> {
+        self.entity_types.get(entity_type).map(Vec::as_slice)
+    }
+
+    /// Every declared action with the policies mentioning it.
+    pub fn actions(&self) -> impl Iterator<Item = (&SmolStr, &[PolicyID])> {
+        self.actions.iter().map(|(k, v)| (k, v.as_slice()))
+    }
+
+    /// Every declared entity type with the policies mentioning it.
+    pub fn entity_types(&self) -> impl Iterator<Item = (&SmolStr, &[PolicyID])> {
+        self.entity_types.iter().map(|(k, v)| (k, v.as_slice()))
+    }
+
+    /// Actions mentioned by policies but not declared in the schema, with the
+    /// policies mentioning them.
+    pub fn undeclared_actions(&self) -> impl Iterator<Item = (&SmolStr, &[PolicyID])> {
+        self.undeclared_actions
+            .iter()
+            .map(|(k, v)| (k, v.as_slice()))
+    }
+
+    /// Entity types mentioned by policies but not declared in the schema, with
+    /// the policies mentioning them.
+    pub fn undeclared_entity_types(&self) -> impl Iterator<Item = (&SmolStr, &[PolicyID])> {
+        self.undeclared_entity_types
+            .iter()
+            .map(|(k, v)| (k, v.as_slice()))
+    }
+}
+
+impl SchemaFragment {
+    /// Build an index from each action and entity type declared in this
+    /// fragment to the policies which mention it, either in the policy scope
+    /// or as an entity literal in a condition. Actions and entity types which
+    /// policies mention but this fragment does not declare are collected
+    /// separately rather than treated as an error. Policies are listed in the
+    /// order they are given, each at most once per action or entity type.
+    pub fn cross_reference_policies(&self, policies: &[Template]) -> CrossRefIndex {
+        let mut index = CrossRefIndex::default();
+        for declaration in self.declarations() {
+            match declaration {
+                SchemaReference::Action(euid) => {
+                    index.actions.insert(euid, Vec::new());
+                }
+                SchemaReference::EntityType(name) => {
+                    index.entity_types.insert(name, Vec::new());
+                }
+                SchemaReference::CommonType(_) => (),
+            }
+        }
+        for policy in policies {
+            for euid in policy_entity_uids(policy) {
+                let PolicyEntityType::Concrete(ty) = euid.entity_type() else {
+                    continue;
+                };
+                let (key, declared, undeclared) = if euid.is_action() {
+                    (
+                        euid.to_string(),
+                        &mut index.actions,
+                        &mut index.undeclared_actions,
+                    )
+                } else {
+                    (
+                        ty.to_string(),
+                        &mut index.entity_types,
+                        &mut index.undeclared_entity_types,
+                    )
+                };
+                let ids = match declared.get_mut(key.as_str()) {
+                    Some(ids) => ids,
+                    None => undeclared.entry(key.into()).or_default(),
+                };
+                if !ids.contains(policy.id()) {
+                    ids.push(policy.id().clone());
+                }
+            }
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cedar_policy_core::parser::parse_policy_template;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn cross_references() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": { "User": {}, "Photo": {}, "Album": {} },
+                "actions": { "view": {}, "edit": {}, "delete": {} }
+            }
+        }))
+        .expect("Parse Error");
+        let policies = [
+            (
+                "p0",
+                r#"permit(principal == App::User::"alice", action == App::Action::"view", resource);"#,
+            ),
+            (
+                "p1",
+                r#"permit(principal, action in [App::Action::"view", App::Action::"edit"], resource)
+                   when { resource in App::Album::"trips" };"#,
+            ),
+            (
+                "p2",
+                r#"forbid(principal, action == App::Action::"share", resource == Legacy::Doc::"x");"#,
+            ),
+        ]
+        .map(|(id, text)| parse_policy_template(Some(id.to_string()), text).expect("Parse Error"));
+        let index = fragment.cross_reference_policies(&policies);
+
+        let ids = |ids: Option<&[PolicyID]>| {
+            ids.expect("declared")
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(index.policies_for_action(r#"App::Action::"view""#)),
+            ["p0", "p1"]
+        );
+        assert_eq!(
+            ids(index.policies_for_action(r#"App::Action::"edit""#)),
+            ["p1"]
+        );
+        assert!(ids(index.policies_for_action(r#"App::Action::"delete""#)).is_empty());
+        assert_eq!(ids(index.policies_for_entity_type("App::User")), ["p0"]);
+        assert_eq!(ids(index.policies_for_entity_type("App::Album")), ["p1"]);
+        assert!(ids(index.policies_for_entity_type("App::Photo")).is_empty());
+        assert_eq!(index.policies_for_action(r#"App::Action::"share""#), None);
+
+        let undeclared = |it: &mut dyn Iterator<Item = (&SmolStr, &[PolicyID])>| {
+            it.map(|(k, v)| {
+                (
+                    k.to_string(),
+                    v.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            undeclared(&mut index.undeclared_actions()),
+            [(
+                r#"App::Action::"share""#.to_string(),
+                vec!["p2".to_string()]
+            )]
+        );
+        assert_eq!(
+            undeclared(&mut index.undeclared_entity_types()),
+            [("Legacy::Doc".to_string(), vec!["p2".to_string()])]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Export of the attributes of every entity type as CSV.
+
+use itertools::Itertools;
+
+use super::{SchemaFragment, SchemaType, SchemaTypeVariant};
+
+/// The header row written by `SchemaFragment::to_csv`.
+const CSV_HEADER: [&str; 6] = [
+    "namespace",
+    "entity type",
+    "attribute path",
+    "type",
+    "required",
+    "sensitive",
+];
+
+impl SchemaFragment {
+    /// Export the attributes of every entity type as CSV, with a header row
+    /// and then one row per attribute giving the namespace, the entity type
+    /// as declared, the attribute path, its type, whether it is required, and
+    /// whether it is sensitive. Rows end with `\n`, and fields are quoted as
+    /// described in RFC 4180.
+    ///
+    /// Attributes of nested records get their own rows, with the path written
+    /// using dots, e.g. `address.city`. A set is written as `set-of` followed
+    /// by its element type, and the attributes of a set of records are
+    /// written with `[]` in their path, e.g. `emails[].address`. Common types
+    /// are inlined, unless they cannot be resolved, in which case the shape
+    /// is written as declared. The schema format does not record whether an
+    /// attribute is sensitive, so that column is left empty for the reader to
+    /// fill in. Namespaces, entity types and attributes are in sorted order.
+    pub fn to_csv(&self) -> String {
+        let mut csv = csv_row(CSV_HEADER);
+        for (ns, ns_def) in self.0.iter().sorted_by_key(|(ns, _)| *ns) {
+            for (name, entity_type) in ns_def.entity_types.iter().sorted_by_key(|(n, _)| *n) {
+                let shape = self
+                    .resolve_type(ns, &entity_type.shape.0)
+                    .unwrap_or_else(|_| entity_type.shape.0.clone());
+                write_attribute_rows(&mut csv, &[ns, name], &shape, "");
+            }
+        }
+        csv
+    }
+}
+
+/// Append a row to `csv` for each attribute of `ty`, if it is a record or a
+/// set of records, and of any records nested inside it. Each row starts with
+/// the fields in `prefix` and attribute paths start with `path`.
+fn write_attribute_rows(csv: &mut String, prefix: &[&str], ty: &SchemaType, path: &str) {
+    match ty {
+        SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+            for (attr, attr_ty) in attributes {
+                let attr_path = if path.is_empty() {
+                    attr.to_string()
+                } else {
+                    format!("{path}.{attr}")
+                };
+                let required = attr_ty.required.to_string();
+                let ty = csv_type(&attr_ty.ty);
+                csv.push_str(&csv_row(prefix.iter().copied().chain([
+                    attr_path.as_str(),
+                    &ty,
+                    &required,
+                    "",
+                ])));
+                write_attribute_rows(csv, prefix, &attr_ty.ty, &attr_path);
+            }
+        }
+        SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+            write_attribute_rows(csv, prefix, element, &format!("{path}[]"));
+        }
+        _ => (),
+    }
+}
+
+/// The name of `ty` as written in the type column.
+fn csv_type(ty: &SchemaType) -> String {
+    match ty {
+        SchemaType::TypeDef { type_name } => type_name.to_string(),
+        SchemaType::Type(SchemaTypeVariant::String { .. }) => "String".to_string(),
+        SchemaType::Type(SchemaTypeVariant::Long { .. }) => "Long".to_string(),
+        SchemaType::Type(SchemaTypeVariant::Boolean) => "Boolean".to_string(),
+        SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+            format!("set-of {}", csv_type(element))
+        }
+        SchemaType::Type(SchemaTypeVariant::Record { .. }) => "Record".to_string(),
+        SchemaType::Type(SchemaTypeVariant::Entity { name })
+        | SchemaType::Type(SchemaTypeVariant::Extension { name, .. }) => name.to_string(),
+        SchemaType::Type(SchemaTypeVariant::Union { options }) => {
+            options.iter().map(|option| csv_type(option)).join(" | ")
+        }
+    }
+}
+
+/// A CSV row, including the line ending.
+pub(crate) fn csv_row<'a>(fields: impl IntoIterator<Item = &'a str>) -> String {
+    let mut row = fields.into_iter().map(csv_field).join(",");
+    row.push('\n');
+    row
+}
+
+/// Quote `field` if it contains a character with a special meaning in CSV,
+/// doubling any quotes inside it.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn to_csv() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Address": {
+                        "type": "Record",
+                        "attributes": { "city": { "type": "String", "required": false } }
+                    }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "home": { "type": "Address" },
+                                "emails": {
+                                    "type": "Set",
+                                    "element": {
+                                        "type": "Record",
+                                        "attributes": { "address": { "type": "String" } }
+                                    }
+                                },
+                                "manager": { "type": "Entity", "name": "User" },
+                                "say \"hi\", then": { "type": "Set", "element": { "type": "Long" } }
+                            }
+                        }
+                    },
+                    "Group": {}
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(
+            fragment.to_csv(),
+            "namespace,entity type,attribute path,type,required,sensitive\n\
+             App,User,emails,set-of Record,true,\n\
+             App,User,emails[].address,String,true,\n\
+             App,User,home,Record,true,\n\
+             App,User,home.city,String,false,\n\
+             App,User,manager,App::User,true,\n\
+             App,User,\"say \"\"hi\"\", then\",set-of Long,true,\n"
+        );
+    }
+}
@@ -0,0 +1,884 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Structural differences between two versions of a schema fragment, and a
+//! compact text rendering of them.
+
+use std::collections::{BTreeSet, HashMap};
+
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{
+    fully_qualify, ActionType, EntityType, NamespaceDefinition, SchemaFragment, SchemaType,
+    SchemaTypeVariant, TypeOfAttribute, TypePath, TypePathRoot, TypePathSegment,
+};
+
+/// A declaration that changed between two schema fragments. Names are as
+/// declared, i.e., not qualified by the namespace.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DiffDeclaration {
+    /// A common type declaration.
+    CommonType(SmolStr),
+    /// An entity type declaration.
+    EntityType(SmolStr),
+    /// An action declaration.
+    Action(SmolStr),
+}
+
+impl std::fmt::Display for DiffDeclaration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CommonType(name) => write!(f, "type {name}"),
+            Self::EntityType(name) => write!(f, "entity {name}"),
+            Self::Action(id) => write!(f, "action {id}"),
+        }
+    }
+}
+
+/// A list of names in a declaration whose entries are compared as a set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DiffList {
+    /// The `memberOfTypes` of an entity type.
+    MemberOfTypes,
+    /// The `memberOf` of an action, with each entry written as an action
+    /// entity UID.
+    MemberOf,
+    /// The `principalTypes` an action applies to.
+    PrincipalTypes,
+    /// The `resourceTypes` an action applies to.
+    ResourceTypes,
+    /// The `enum` values of an entity type.
+    Enum,
+}
+
+impl std::fmt::Display for DiffList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::MemberOfTypes => "memberOfTypes",
+            Self::MemberOf => "memberOf",
+            Self::PrincipalTypes => "principalTypes",
+            Self::ResourceTypes => "resourceTypes",
+            Self::Enum => "enum",
+        })
+    }
+}
+
+/// What changed about a declaration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaChangeKind {
+    /// The declaration was added.
+    Added,
+    /// The declaration was removed.
+    Removed,
+    /// A record attribute was added.
+    AttributeAdded {
+        /// The path to the new attribute.
+        path: TypePath,
+        /// The type and requiredness of the new attribute.
+        attribute: TypeOfAttribute,
+    },
+    /// A record attribute was removed.
+    AttributeRemoved {
+        /// The path to the removed attribute.
+        path: TypePath,
+        /// The type and requiredness of the removed attribute.
+        attribute: TypeOfAttribute,
+    },
+    /// An attribute became required or optional.
+    RequiredChanged {
+        /// The path to the attribute.
+        path: TypePath,
+        /// Whether the attribute is now required.
+        required: bool,
+    },
+    /// A record started or stopped allowing additional attributes.
+    AdditionalAttributesChanged {
+        /// The path to the record.
+        path: TypePath,
+        /// Whether the record now allows additional attributes.
+        additional_attributes: bool,
+    },
+    /// The type at a path changed in a way not described by any of the
+    /// finer grained changes.
+    TypeChanged {
+        /// The path to the type.
+        path: TypePath,
+        /// The type before the change.
+        old: SchemaType,
+        /// The type after the change.
+        new: SchemaType,
+    },
+    /// An entry was added to a list of names.
+    ListEntryAdded {
+        /// The list the entry was added to.
+        list: DiffList,
+        /// The added entry.
+        entry: SmolStr,
+    },
+    /// An entry was removed from a list of names.
+    ListEntryRemoved {
+        /// The list the entry was removed from.
+        list: DiffList,
+        /// The removed entry.
+        entry: SmolStr,
+    },
+    /// A property without a finer grained change changed, was added or was
+    /// removed, e.g. the `since` of a declaration or the `unit` of an
+    /// attribute.
+    PropertyChanged {
+        /// The path to the attribute the property belongs to, or `None` for
+        /// a property of the declaration itself.
+        path: Option<TypePath>,
+        /// The property, as written in a schema file.
+        property: SmolStr,
+    },
+}
+
+impl SchemaChangeKind {
+    /// Whether this change may make entity data or policies which were valid
+    /// under the old schema invalid under the new one. Removing anything,
+    /// changing a type, adding a required attribute, closing a record, and
+    /// making an attribute required (which may invalidate data) or optional
+    /// (which may invalidate policies reading it without `has`) are
+    /// breaking, as is changing any other property except the documentation
+    /// only `since` and `example`. Adding declarations, optional attributes
+    /// and list entries, and opening a record, are not.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            Self::Added | Self::ListEntryAdded { .. } => false,
+            Self::PropertyChanged { property, .. } => property != "since" && property != "example",
+            Self::AttributeAdded { attribute, .. } => attribute.required,
+            Self::AdditionalAttributesChanged {
+                additional_attributes,
+                ..
+            } => !additional_attributes,
+            Self::Removed
+            | Self::AttributeRemoved { .. }
+            | Self::RequiredChanged { .. }
+            | Self::TypeChanged { .. }
+            | Self::ListEntryRemoved { .. } => true,
+        }
+    }
+
+    /// A one line description of a change inside a declaration, as written by
+    /// `SchemaDiff::to_text` after the sign, or `None` for the addition or
+    /// removal of the declaration itself.
+    pub(crate) fn describe(&self) -> Option<String> {
+        Some(match self {
+            Self::Added | Self::Removed => return None,
+            Self::AttributeAdded { path, attribute } => {
+                format!("+ {}", describe_attribute(path, attribute))
+            }
+            Self::AttributeRemoved { path, attribute } => {
+                format!("- {}", describe_attribute(path, attribute))
+            }
+            Self::RequiredChanged { path, required } => {
+                let (old, new) = if *required {
+                    ("optional", "required")
+                } else {
+                    ("required", "optional")
+                };
+                format!("~ {}{old} -> {new}", path_prefix(path))
+            }
+            Self::AdditionalAttributesChanged {
+                path,
+                additional_attributes,
+            } => format!(
+                "~ {}additionalAttributes {} -> {additional_attributes}",
+                path_prefix(path),
+                !additional_attributes
+            ),
+            Self::TypeChanged { path, old, new } => format!(
+                "~ {}{} -> {}",
+                path_prefix(path),
+                describe_type(old),
+                describe_type(new)
+            ),
+            Self::ListEntryAdded { list, entry } => format!("+ {list} {entry}"),
+            Self::ListEntryRemoved { list, entry } => format!("- {list} {entry}"),
+            Self::PropertyChanged { path, property } => format!(
+                "~ {}{property}",
+                path.as_ref().map(path_prefix).unwrap_or_default()
+            ),
+        })
+    }
+}
+
+/// A single change to a declaration in a namespace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaChange {
+    namespace: SmolStr,
+    declaration: DiffDeclaration,
+    kind: SchemaChangeKind,
+}
+
+impl SchemaChange {
+    /// The namespace containing the changed declaration.
+    pub fn namespace(&self) -> &SmolStr {
+        &self.namespace
+    }
+
+    /// The changed declaration.
+    pub fn declaration(&self) -> &DiffDeclaration {
+        &self.declaration
+    }
+
+    /// What changed.
+    pub fn kind(&self) -> &SchemaChangeKind {
+        &self.kind
+    }
+}
+
+/// The structural differences between two schema fragments, as computed by
+/// `SchemaFragment::diff`. Changes are ordered by namespace, then by
+/// declaration (common types, entity types, actions), so the order is stable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    changes: Vec<SchemaChange>,
+}
+
+impl SchemaDiff {
+    /// All changes, in order.
+    pub fn changes(&self) -> &[SchemaChange] {
+        &self.changes
+    }
+
+    /// True when the two fragments are structurally identical, except
+    /// possibly for the `additionalAttributes` of records with no declared
+    /// attributes, which is not reported.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Render the changes as text in the style of a unified diff. Changes are
+    /// grouped under a `namespace` line and then under the declaration they
+    /// belong to. Added lines start with `+`, removed lines with `-`, and
+    /// lines changed in place with `~`, e.g.
+    ///
+    /// ```text
+    /// namespace App
+    ///   + entity Photo
+    ///   entity User
+    ///     + email: String
+    ///     ~ age: String -> Long
+    /// ```
+    ///
+    /// The output is empty when there are no changes.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let mut namespace = None;
+        let mut declaration = None;
+        for change in &self.changes {
+            if namespace != Some(&change.namespace) {
+                if change.namespace.is_empty() {
+                    out.push_str("namespace (empty)\n");
+                } else {
+                    out.push_str(&format!("namespace {}\n", change.namespace));
+                }
+                namespace = Some(&change.namespace);
+                declaration = None;
+            }
+            let Some(line) = change.kind.describe() else {
+                let sign = if change.kind == SchemaChangeKind::Added {
+                    '+'
+                } else {
+                    '-'
+                };
+                out.push_str(&format!("  {sign} {}\n", change.declaration));
+                continue;
+            };
+            if declaration != Some(&change.declaration) {
+                out.push_str(&format!("  {}\n", change.declaration));
+                declaration = Some(&change.declaration);
+            }
+            out.push_str(&format!("    {line}\n"));
+        }
+        out
+    }
+}
+
+/// The attribute path written relative to the declaration, followed by `: `,
+/// or nothing for the declared type itself.
+fn path_prefix(path: &TypePath) -> String {
+    let relative = path.segments().iter().join("");
+    let relative = match path.root() {
+        TypePathRoot::ActionContext { .. } => format!("context{relative}"),
+        TypePathRoot::EntityTags(_) => format!("tags{relative}"),
+        _ => relative.trim_start_matches('.').to_string(),
+    };
+    if relative.is_empty() {
+        relative
+    } else {
+        format!("{relative}: ")
+    }
+}
+
+fn describe_attribute(path: &TypePath, attribute: &TypeOfAttribute) -> String {
+    let prefix = path_prefix(path);
+    let prefix = if attribute.required {
+        prefix
+    } else {
+        prefix.replacen(": ", "?: ", 1)
+    };
+    format!("{prefix}{}", describe_type(&attribute.ty))
+}
+
+/// A compact rendering of a type on one line.
+pub(crate) fn describe_type(ty: &SchemaType) -> String {
+    match ty {
+        SchemaType::TypeDef { type_name } => type_name.to_string(),
+        SchemaType::Type(SchemaTypeVariant::String {
+            enumeration: None,
+            pattern: None,
+        }) => "String".to_string(),
+        SchemaType::Type(SchemaTypeVariant::String {
+            enumeration,
+            pattern,
+        }) => format!(
+            "String {{ {} }}",
+            enumeration
+                .iter()
+                .map(|values| values.iter().map(|v| format!("\"{v}\"")).join(" | "))
+                .chain(
+                    pattern
+                        .iter()
+                        .map(|pattern| format!("pattern: \"{pattern}\""))
+                )
+                .join(", ")
+        ),
+        SchemaType::Type(SchemaTypeVariant::Long {
+            min: None,
+            max: None,
+        }) => "Long".to_string(),
+        SchemaType::Type(SchemaTypeVariant::Long { min, max }) => format!(
+            "Long {{ {} }}",
+            [("min", min), ("max", max)]
+                .into_iter()
+                .filter_map(|(bound, value)| Some(format!("{bound}: {}", value.as_ref()?)))
+                .join(", ")
+        ),
+        SchemaType::Type(SchemaTypeVariant::Boolean) => "Boolean".to_string(),
+        SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+            format!("Set<{}>", describe_type(element))
+        }
+        SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) if attributes.is_empty() => {
+            "{}".to_string()
+        }
+        SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => format!(
+            "{{ {} }}",
+            attributes
+                .iter()
+                .map(|(attr, attr_ty)| format!(
+                    "{attr}{}: {}",
+                    if attr_ty.required { "" } else { "?" },
+                    describe_type(&attr_ty.ty)
+                ))
+                .join(", ")
+        ),
+        SchemaType::Type(SchemaTypeVariant::Entity { name })
+        | SchemaType::Type(SchemaTypeVariant::Extension { name, .. }) => name.to_string(),
+        SchemaType::Type(SchemaTypeVariant::Union { options }) => options
+            .iter()
+            .map(|option| describe_type(option))
+            .join(" | "),
+    }
+}
+
+impl SchemaFragment {
+    /// The structural differences going from this fragment to `new`. Types
+    /// are compared as written: common types are not inlined, and names are
+    /// not qualified, so moving an attribute type into a common type is
+    /// reported as a change.
+    pub fn diff(&self, new: &SchemaFragment) -> SchemaDiff {
+        let empty = NamespaceDefinition::new([], []);
+        let namespaces: BTreeSet<&SmolStr> = self.0.keys().chain(new.0.keys()).collect();
+        let mut changes = Vec::new();
+        for ns in namespaces {
+            let old_def = self.0.get(ns).unwrap_or(&empty);
+            let new_def = new.0.get(ns).unwrap_or(&empty);
+            let mut differ = Differ {
+                ns,
+                changes: &mut changes,
+            };
+            differ.declarations(
+                &old_def.common_types,
+                &new_def.common_types,
+                DiffDeclaration::CommonType,
+                |d, name, old, new| {
+                    let path = TypePath::new(TypePathRoot::CommonType(fully_qualify(ns, name)));
+                    d.types(&DiffDeclaration::CommonType(name.clone()), &path, old, new);
+                },
+            );
+            differ.declarations(
+                &old_def.entity_types,
+                &new_def.entity_types,
+                DiffDeclaration::EntityType,
+                |d, name, old, new| d.entity_types(name, old, new),
+            );
+            differ.declarations(
+                &old_def.actions,
+                &new_def.actions,
+                DiffDeclaration::Action,
+                |d, id, old, new| d.actions(id, old, new),
+            );
+        }
+        SchemaDiff { changes }
+    }
+}
+
+struct Differ<'a> {
+    ns: &'a SmolStr,
+    changes: &'a mut Vec<SchemaChange>,
+}
+
+impl<'a> Differ<'a> {
+    fn push(&mut self, declaration: &DiffDeclaration, kind: SchemaChangeKind) {
+        self.changes.push(SchemaChange {
+            namespace: self.ns.clone(),
+            declaration: declaration.clone(),
+            kind,
+        });
+    }
+
+    fn declarations<T: PartialEq>(
+        &mut self,
+        old: &HashMap<SmolStr, T>,
+        new: &HashMap<SmolStr, T>,
+        declaration: impl Fn(SmolStr) -> DiffDeclaration,
+        mut changed: impl FnMut(&mut Self, &SmolStr, &T, &T),
+    ) {
+        let names: BTreeSet<&SmolStr> = old.keys().chain(new.keys()).collect();
+        for name in names {
+            match (old.get(name), new.get(name)) {
+                (Some(old), Some(new)) if old != new => changed(self, name, old, new),
+                (Some(_), None) => self.push(&declaration(name.clone()), SchemaChangeKind::Removed),
+                (None, Some(_)) => self.push(&declaration(name.clone()), SchemaChangeKind::Added),
+                _ => (),
+            }
+        }
+    }
+
+    fn entity_types(&mut self, name: &SmolStr, old: &EntityType, new: &EntityType) {
+        let declaration = DiffDeclaration::EntityType(name.clone());
+        let name = fully_qualify(self.ns, name);
+        let path = TypePath::new(TypePathRoot::EntityType(name.clone()));
+        self.types(&declaration, &path, &old.shape.0, &new.shape.0);
+        match (
+            &old.additional_attributes_definition,
+            &new.additional_attributes_definition,
+        ) {
+            (Some(old), Some(new)) => self.types(&declaration, &path, &old.0, &new.0),
+            (old, new) if old != new => {
+                self.property(&declaration, None, "additionalAttributesDefinition")
+            }
+            _ => (),
+        }
+        match (&old.tags, &new.tags) {
+            (Some(old), Some(new)) => self.types(
+                &declaration,
+                &TypePath::new(TypePathRoot::EntityTags(name)),
+                old,
+                new,
+            ),
+            (old, new) if old != new => self.property(&declaration, None, "tags"),
+            _ => (),
+        }
+        self.lists(
+            &declaration,
+            DiffList::MemberOfTypes,
+            old.member_of_types.iter().cloned(),
+            new.member_of_types.iter().cloned(),
+        );
+        if old.enumeration.is_some() != new.enumeration.is_some() {
+            self.property(&declaration, None, "enum");
+        }
+        self.lists(
+            &declaration,
+            DiffList::Enum,
+            old.enumeration.iter().flatten().cloned(),
+            new.enumeration.iter().flatten().cloned(),
+        );
+        if old.since != new.since {
+            self.property(&declaration, None, "since");
+        }
+    }
+
+    fn actions(&mut self, id: &SmolStr, old: &ActionType, new: &ActionType) {
+        let declaration = DiffDeclaration::Action(id.clone());
+        let path = TypePath::new(TypePathRoot::ActionContext {
+            ty: fully_qualify(self.ns, "Action"),
+            id: id.clone(),
+        });
+        let old_applies = old.applies_to.as_ref();
+        let new_applies = new.applies_to.as_ref();
+        let empty = SchemaType::Type(SchemaTypeVariant::Record {
+            attributes: Default::default(),
+            additional_attributes: false,
+        });
+        self.types(
+            &declaration,
+            &path,
+            old_applies.map_or(&empty, |a| &a.context.0),
+            new_applies.map_or(&empty, |a| &a.context.0),
+        );
+        for list in [DiffList::PrincipalTypes, DiffList::ResourceTypes] {
+            let entries = |applies_to: Option<&super::ApplySpec>| {
+                applies_to.and_then(|a| match list {
+                    DiffList::PrincipalTypes => a.principal_types.clone(),
+                    _ => a.resource_types.clone(),
+                })
+            };
+            let (old_entries, new_entries) = (entries(old_applies), entries(new_applies));
+            // An omitted list applies to any entity type, unlike an empty one.
+            if old_entries.is_some() != new_entries.is_some() {
+                self.property(&declaration, None, &list.to_string());
+            }
+            self.lists(
+                &declaration,
+                list,
+                old_entries.into_iter().flatten(),
+                new_entries.into_iter().flatten(),
+            );
+        }
+        self.lists(
+            &declaration,
+            DiffList::MemberOf,
+            old.member_of.iter().flatten().map(|p| p.to_string().into()),
+            new.member_of.iter().flatten().map(|p| p.to_string().into()),
+        );
+        if old.attributes != new.attributes {
+            self.property(&declaration, None, "attributes");
+        }
+        if old.since != new.since {
+            self.property(&declaration, None, "since");
+        }
+    }
+
+    fn property(&mut self, declaration: &DiffDeclaration, path: Option<&TypePath>, property: &str) {
+        self.push(
+            declaration,
+            SchemaChangeKind::PropertyChanged {
+                path: path.cloned(),
+                property: property.into(),
+            },
+        );
+    }
+
+    fn lists(
+        &mut self,
+        declaration: &DiffDeclaration,
+        list: DiffList,
+        old: impl Iterator<Item = SmolStr>,
+        new: impl Iterator<Item = SmolStr>,
+    ) {
+        let old: BTreeSet<SmolStr> = old.collect();
+        let new: BTreeSet<SmolStr> = new.collect();
+        for entry in old.difference(&new) {
+            let entry = entry.clone();
+            self.push(
+                declaration,
+                SchemaChangeKind::ListEntryRemoved { list, entry },
+            );
+        }
+        for entry in new.difference(&old) {
+            let entry = entry.clone();
+            self.push(
+                declaration,
+                SchemaChangeKind::ListEntryAdded { list, entry },
+            );
+        }
+    }
+
+    fn types(
+        &mut self,
+        declaration: &DiffDeclaration,
+        path: &TypePath,
+        old: &SchemaType,
+        new: &SchemaType,
+    ) {
+        match (old, new) {
+            (
+                SchemaType::Type(SchemaTypeVariant::Set { element: old }),
+                SchemaType::Type(SchemaTypeVariant::Set { element: new }),
+            ) => self.types(
+                declaration,
+                &path.child(TypePathSegment::SetElement),
+                old,
+                new,
+            ),
+            (
+                SchemaType::Type(SchemaTypeVariant::Record {
+                    attributes: old_attrs,
+                    additional_attributes: old_open,
+                }),
+                SchemaType::Type(SchemaTypeVariant::Record {
+                    attributes: new_attrs,
+                    additional_attributes: new_open,
+                }),
+            ) => {
+                // Openness of a record with no declared attributes is not
+                // reported, following `SchemaType::eq_lenient`.
+                if old_open != new_open && !(old_attrs.is_empty() && new_attrs.is_empty()) {
+                    self.push(
+                        declaration,
+                        SchemaChangeKind::AdditionalAttributesChanged {
+                            path: path.clone(),
+                            additional_attributes: *new_open,
+                        },
+                    );
+                }
+                let attrs: BTreeSet<&SmolStr> = old_attrs.keys().chain(new_attrs.keys()).collect();
+                for attr in attrs {
+                    let path = path.child(TypePathSegment::Attribute(attr.clone()));
+                    match (old_attrs.get(attr), new_attrs.get(attr)) {
+                        (Some(old), Some(new)) => {
+                            if old.required != new.required {
+                                self.push(
+                                    declaration,
+                                    SchemaChangeKind::RequiredChanged {
+                                        path: path.clone(),
+                                        required: new.required,
+                                    },
+                                );
+                            }
+                            self.types(declaration, &path, &old.ty, &new.ty);
+                            for (property, changed) in [
+                                ("computed", old.computed != new.computed),
+                                ("example", old.example != new.example),
+                                ("requiredIf", old.required_if != new.required_if),
+                                ("unit", old.unit != new.unit),
+                            ] {
+                                if changed {
+                                    self.property(declaration, Some(&path), property);
+                                }
+                            }
+                        }
+                        (Some(old), None) => self.push(
+                            declaration,
+                            SchemaChangeKind::AttributeRemoved {
+                                path,
+                                attribute: old.clone(),
+                            },
+                        ),
+                        (None, Some(new)) => self.push(
+                            declaration,
+                            SchemaChangeKind::AttributeAdded {
+                                path,
+                                attribute: new.clone(),
+                            },
+                        ),
+                        (None, None) => (),
+                    }
+                }
+            }
+            _ if old != new => self.push(
+                declaration,
+                SchemaChangeKind::TypeChanged {
+                    path: path.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                },
+            ),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn to_text() {
+        let old = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Team"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "age": { "type": "String" },
+                                "nickname": { "type": "String", "required": false },
+                                "legacyId": { "type": "Long" }
+                            }
+                        }
+                    },
+                    "Team": {},
+                    "Folder": {}
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Folder"] } },
+                    "delete": {}
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let new = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Team"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "age": { "type": "Long" },
+                                "nickname": { "type": "String" },
+                                "email": { "type": "String", "required": false }
+                            }
+                        }
+                    },
+                    "Team": {},
+                    "Folder": {},
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Folder", "Photo"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "ip": { "type": "Extension", "name": "ipaddr" } }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(
+            old.diff(&new).to_text(),
+            [
+                "namespace App",
+                "  + entity Photo",
+                "  entity User",
+                "    ~ age: String -> Long",
+                "    + email?: String",
+                "    - legacyId: Long",
+                "    ~ nickname: optional -> required",
+                "  - action delete",
+                "  action view",
+                "    + context.ip: ipaddr",
+                "    + resourceTypes Photo",
+                "",
+            ]
+            .join("\n")
+        );
+        assert!(new.diff(&new).is_empty());
+        assert_eq!(new.diff(&new).to_text(), "");
+    }
+
+    #[test]
+    fn every_property() {
+        let old = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "latency": { "type": "Long", "unit": "ms" },
+                                "level": { "type": "Long", "computed": true }
+                            }
+                        },
+                        "additionalAttributesDefinition": {
+                            "type": "Record",
+                            "attributes": { "email": { "type": "String" } }
+                        },
+                        "tags": { "type": "String" },
+                        "since": "1.0.0"
+                    },
+                    "Color": { "enum": ["red", "green"] },
+                    "Doc": {}
+                },
+                "actions": {
+                    "view": {
+                        "attributes": { "audited": true },
+                        "appliesTo": { "resourceTypes": ["Doc"] }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let new = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "latency": { "type": "Long", "unit": "s" },
+                                "level": { "type": "Long" }
+                            }
+                        },
+                        "additionalAttributesDefinition": {
+                            "type": "Record",
+                            "attributes": { "email": { "type": "Long" } }
+                        },
+                        "tags": { "type": "Long" },
+                        "since": "1.1.0"
+                    },
+                    "Color": { "enum": ["red", "blue"] },
+                    "Doc": { "tags": { "type": "String" } }
+                },
+                "actions": {
+                    "view": {
+                        "attributes": { "audited": false },
+                        "appliesTo": { "principalTypes": [], "resourceTypes": ["Doc"] }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.to_text(),
+            [
+                "namespace App",
+                "  entity Color",
+                "    - enum green",
+                "    + enum blue",
+                "  entity Doc",
+                "    ~ tags",
+                "  entity User",
+                "    ~ latency: unit",
+                "    ~ level: computed",
+                "    ~ email: String -> Long",
+                "    ~ tags: String -> Long",
+                "    ~ since",
+                "  action view",
+                "    ~ principalTypes",
+                "    ~ attributes",
+                "",
+            ]
+            .join("\n")
+        );
+        let non_breaking = diff
+            .changes()
+            .iter()
+            .filter(|change| !change.kind().is_breaking())
+            .filter_map(|change| change.kind().describe())
+            .collect::<Vec<_>>();
+        assert_eq!(non_breaking, ["+ enum blue", "~ since"]);
+        for (old, new) in [(&old, &new), (&new, &old)] {
+            assert!(!old.diff(new).is_empty());
+        }
+    }
+}
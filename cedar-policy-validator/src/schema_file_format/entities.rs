@@ -0,0 +1,201 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Checks of entity data in the Cedar JSON entity format against the entity
+//! type declarations of a schema fragment.
+
+use cedar_policy_core::entities::{EntityUidJSON, JsonDeserializationErrorContext};
+use itertools::Itertools;
+use serde_json::Value;
+
+use super::{AttributeValidationMode, EntityType, SchemaFragment, SchemaTypeVariant};
+use crate::{Result, SchemaError};
+
+impl EntityType {
+    /// True if entities of this type, which is declared in namespace `ns` of
+    /// `fragment`, can have no attributes at all, because its shape is a
+    /// closed record without attributes. A shape which cannot be resolved is
+    /// not sealed.
+    pub fn is_sealed(&self, fragment: &SchemaFragment, ns: &str) -> bool {
+        matches!(
+            self.resolved_shape(fragment, ns),
+            Ok(SchemaTypeVariant::Record {
+                attributes,
+                additional_attributes: false,
+            }) if attributes.is_empty()
+        )
+    }
+}
+
+impl SchemaFragment {
+    /// Check each entity in `entities`, which are in the Cedar JSON entity
+    /// format, against the declaration of its entity type in this fragment.
+    /// The entity type must be declared, an entity of a type declared with
+    /// `enum` must be one of its values and have no attributes, and an
+    /// entity of a sealed type (see `EntityType::is_sealed`) must have no
+    /// attributes. The attributes of other entities are checked as by
+    /// `EntityType::validate_attributes` with `mode`. Parents are not
+    /// checked. Fails with `SchemaError::InvalidEntityData` for the first
+    /// invalid entity, naming its uid, or its index if it has no valid uid.
+    pub fn validate_entities(
+        &self,
+        entities: &[Value],
+        mode: AttributeValidationMode,
+    ) -> Result<()> {
+        for (i, entity) in entities.iter().enumerate() {
+            let invalid = |entity: String, msg: String| SchemaError::InvalidEntityData(entity, msg);
+            let uid = entity
+                .get("uid")
+                .ok_or_else(|| invalid(format!("[{i}]"), "it has no `uid`".to_string()))?;
+            let uid = serde_json::from_value::<EntityUidJSON>(uid.clone())
+                .map_err(|e| e.to_string())
+                .and_then(|json| {
+                    json.into_euid(|| JsonDeserializationErrorContext::Context)
+                        .map_err(|e| e.to_string())
+                })
+                .map_err(|e| invalid(format!("[{i}]"), format!("its `uid` is not valid: {e}")))?;
+            let invalid = |msg: String| SchemaError::InvalidEntityData(uid.to_string(), msg);
+            let ty = uid.entity_type().to_string();
+            let (ns, basename) = ty.rsplit_once("::").unwrap_or(("", &ty));
+            let (ns, ns_def) = self
+                .0
+                .get_key_value(ns)
+                .filter(|(_, ns_def)| ns_def.entity_types.contains_key(basename))
+                .ok_or_else(|| invalid(format!("entity type `{ty}` is not declared")))?;
+            let entity_type = &ns_def.entity_types[basename];
+            ns_def
+                .validate_entity_uid(basename, uid.eid().as_ref())
+                .map_err(|e| invalid(e.to_string()))?;
+            let attrs = entity.get("attrs");
+            let kind = if entity_type.enum_values().is_some() {
+                Some("an enumerated")
+            } else if entity_type.is_sealed(self, ns) {
+                Some("a sealed")
+            } else {
+                None
+            };
+            match (kind, attrs) {
+                (Some(kind), Some(Value::Object(attrs))) if !attrs.is_empty() => {
+                    return Err(invalid(format!(
+                        "`{ty}` is {kind} entity type, so its entities have no attributes, \
+                         but this entity has {}",
+                        attrs
+                            .keys()
+                            .sorted()
+                            .map(|attr| format!("`{attr}`"))
+                            .join(", ")
+                    )));
+                }
+                (Some(_), _) => (),
+                (None, attrs) => entity_type
+                    .validate_attributes(
+                        attrs.unwrap_or(&Value::Object(Default::default())),
+                        self,
+                        ns,
+                        mode,
+                    )
+                    .map_err(|e| invalid(e.to_string()))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn fragment() -> SchemaFragment {
+        SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "Role": { "enum": ["admin", "viewer"] },
+                    "Tag": {},
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "name": { "type": "String" } }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error")
+    }
+
+    fn error(entities: Value) -> Option<(String, String)> {
+        let entities = entities.as_array().expect("entities are an array");
+        match fragment().validate_entities(entities, AttributeValidationMode::InputValidation) {
+            Ok(()) => None,
+            Err(SchemaError::InvalidEntityData(entity, msg)) => Some((entity, msg)),
+            Err(e) => panic!("Expected invalid entity data, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn valid_entities() {
+        assert_eq!(
+            error(json!([
+                { "uid": { "type": "App::Role", "id": "admin" }, "attrs": {}, "parents": [] },
+                { "uid": { "type": "App::Tag", "id": "red" } },
+                { "uid": { "type": "App::User", "id": "alice" }, "attrs": { "name": "Alice" } }
+            ])),
+            None
+        );
+    }
+
+    #[test]
+    fn sealed_entity_with_attributes() {
+        assert_eq!(
+            error(json!([
+                { "uid": { "type": "App::Tag", "id": "red" }, "attrs": { "hex": "#f00" } }
+            ])),
+            Some((
+                r#"App::Tag::"red""#.into(),
+                "`App::Tag` is a sealed entity type, so its entities have no attributes, but \
+                 this entity has `hex`"
+                    .into()
+            ))
+        );
+    }
+
+    #[test]
+    fn enum_entity_not_declared() {
+        assert_eq!(
+            error(json!([
+                { "uid": { "type": "App::Role", "id": "admin" } },
+                { "uid": { "type": "App::Role", "id": "owner" } }
+            ])),
+            Some((
+                r#"App::Role::"owner""#.into(),
+                "`owner` is not one of the declared entities of enumerated entity type `Role`"
+                    .into()
+            ))
+        );
+        assert_eq!(
+            error(json!([
+                { "uid": { "type": "App::Group", "id": "staff" } }
+            ])),
+            Some((
+                r#"App::Group::"staff""#.into(),
+                "entity type `App::Group` is not declared".into()
+            ))
+        );
+    }
+}
@@ -0,0 +1,134 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Entity types with a fixed set of entities known when the schema is
+//! written, declared with `enum`.
+
+use std::collections::HashSet;
+
+use smol_str::SmolStr;
+
+use super::{EntityType, NamespaceDefinition};
+use crate::{Result, SchemaError};
+
+impl EntityType {
+    /// The ids of every entity of this type, if it is declared with `enum`.
+    /// Entity types without `enum` may have entities with any id.
+    pub fn enum_values(&self) -> Option<&[SmolStr]> {
+        self.enumeration.as_deref()
+    }
+}
+
+impl NamespaceDefinition {
+    /// Check that `id` may be the id of an entity of the entity type `ty`,
+    /// which is named as it is declared in this namespace. Fails with
+    /// `SchemaError::EntityNotInEnum` if `ty` is declared with `enum` and
+    /// `id` is not one of its values, or with
+    /// `SchemaError::UndeclaredEntityTypes` if `ty` is not declared here.
+    pub fn validate_entity_uid(&self, ty: &str, id: &str) -> Result<()> {
+        let entity_type = self
+            .entity_types
+            .get(ty)
+            .ok_or_else(|| SchemaError::UndeclaredEntityTypes(HashSet::from([ty.to_string()])))?;
+        match entity_type.enum_values() {
+            Some(values) if !values.iter().any(|value| value == id) => {
+                Err(SchemaError::EntityNotInEnum(ty.to_string(), id.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::SchemaFragment;
+
+    #[test]
+    fn validate_entity_uid() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "Role": { "enum": ["admin", "viewer"] },
+                    "User": { "memberOfTypes": ["Role"] }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let ns_def = &fragment.0["App"];
+        assert_eq!(
+            ns_def.entity_types["Role"].enum_values(),
+            Some(&["admin".into(), "viewer".into()][..])
+        );
+        assert_eq!(ns_def.entity_types["User"].enum_values(), None);
+        assert!(ns_def.validate_entity_uid("Role", "admin").is_ok());
+        assert!(matches!(
+            ns_def.validate_entity_uid("Role", "owner"),
+            Err(SchemaError::EntityNotInEnum(ty, id)) if ty == "Role" && id == "owner"
+        ));
+        assert!(ns_def.validate_entity_uid("User", "alice").is_ok());
+        assert!(matches!(
+            ns_def.validate_entity_uid("Group", "admins"),
+            Err(SchemaError::UndeclaredEntityTypes(_))
+        ));
+    }
+
+    #[test]
+    fn enum_excludes_shape_and_parents() {
+        for (key, value) in [
+            ("memberOfTypes", json!([])),
+            ("shape", json!({ "type": "Record", "attributes": {} })),
+        ] {
+            let mut region = json!({ "enum": ["us-east-1", "eu-west-1"] });
+            region[key] = value;
+            let err = serde_json::from_value::<EntityType>(region)
+                .expect_err("`enum` excludes other declarations");
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    r#"entity type declared with `enum` ["us-east-1","eu-west-1"] cannot also declare `{key}`"#
+                )
+            );
+        }
+
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "Region": { "enum": ["us-east-1", "eu-west-1"] },
+                    "User": {}
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let json = fragment.to_json_value().expect("Serialize Error");
+        assert_eq!(
+            json["App"]["entityTypes"]["Region"],
+            json!({ "enum": ["us-east-1", "eu-west-1"] })
+        );
+        assert_eq!(
+            json["App"]["entityTypes"]["User"]["memberOfTypes"],
+            json!([])
+        );
+        assert_eq!(
+            SchemaFragment::from_json_value(json).expect("Parse Error"),
+            fragment
+        );
+    }
+}
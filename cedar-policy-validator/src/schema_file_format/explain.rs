@@ -0,0 +1,282 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Consolidated descriptions of a single entity type or action, gathering
+//! everything the schema says about it in one place.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use cedar_policy_core::entities::JSONValue;
+use serde::Serialize;
+use smol_str::SmolStr;
+
+use super::{fully_qualify, SchemaFragment, SchemaType, SchemaTypeVariant, TypeOfAttribute};
+use crate::{Result, SchemaError};
+
+/// Everything a schema says about an entity type, as produced by
+/// `SchemaFragment::explain_entity_type`. All names are fully qualified and
+/// common types are inlined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EntityTypeExplanation {
+    /// The fully qualified name of the entity type.
+    pub name: SmolStr,
+    /// The attributes of the entity type.
+    pub attributes: BTreeMap<SmolStr, TypeOfAttribute>,
+    /// The entity types that entities of this type may be members of.
+    #[serde(rename = "memberOfTypes")]
+    pub member_of_types: BTreeSet<SmolStr>,
+    /// Actions for which this entity type may be the principal, including
+    /// actions which apply to any principal.
+    #[serde(rename = "principalFor")]
+    pub principal_for: BTreeSet<SmolStr>,
+    /// Actions for which this entity type may be the resource, including
+    /// actions which apply to any resource.
+    #[serde(rename = "resourceFor")]
+    pub resource_for: BTreeSet<SmolStr>,
+    /// Paths to the positions in entity shapes and action contexts, including
+    /// set elements, whose type is this entity type. References from the
+    /// shape of this entity type itself are included.
+    #[serde(rename = "referencedBy")]
+    pub referenced_by: BTreeSet<SmolStr>,
+}
+
+/// Everything a schema says about an action, as produced by
+/// `SchemaFragment::explain_action`. All names are fully qualified and common
+/// types are inlined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActionExplanation {
+    /// The fully qualified entity UID of the action, e.g. `App::Action::"view"`.
+    pub name: SmolStr,
+    /// Principal types, or `None` when the action applies to any principal.
+    #[serde(rename = "principalTypes")]
+    pub principal_types: Option<BTreeSet<SmolStr>>,
+    /// Resource types, or `None` when the action applies to any resource.
+    #[serde(rename = "resourceTypes")]
+    pub resource_types: Option<BTreeSet<SmolStr>>,
+    /// The attributes of the action's context.
+    pub context: BTreeMap<SmolStr, TypeOfAttribute>,
+    /// The actions this action is directly a member of.
+    #[serde(rename = "memberOf")]
+    pub member_of: BTreeSet<SmolStr>,
+    /// The actions which are directly members of this action.
+    pub members: BTreeSet<SmolStr>,
+    /// Action attributes.
+    pub attributes: BTreeMap<SmolStr, JSONValue>,
+}
+
+impl SchemaFragment {
+    /// Describe the entity type `name`, which must be fully qualified unless
+    /// it is declared in the empty namespace. Fails with
+    /// `SchemaError::UndeclaredEntityTypes` if the entity type is not
+    /// declared, or if a common type cannot be resolved.
+    pub fn explain_entity_type(&self, name: &str) -> Result<EntityTypeExplanation> {
+        let lockfile = self.to_lockfile()?;
+        let (name, entity_type) = lockfile
+            .entity_types
+            .get_key_value(name)
+            .ok_or_else(|| SchemaError::UndeclaredEntityTypes(HashSet::from([name.to_string()])))?;
+        let applies_to_name = |types: &Option<BTreeSet<SmolStr>>| {
+            types.as_ref().is_none_or(|types| types.contains(name))
+        };
+        let mut referenced_by = BTreeSet::new();
+        self.walk_value_types(&mut |path, ns, ty| {
+            if let SchemaType::Type(SchemaTypeVariant::Entity { name: referenced }) = ty {
+                if fully_qualify(ns, referenced) == *name {
+                    referenced_by.insert(path.to_string().into());
+                }
+            }
+        });
+        Ok(EntityTypeExplanation {
+            name: name.clone(),
+            attributes: record_attributes(&entity_type.shape),
+            member_of_types: entity_type.member_of_types.clone(),
+            principal_for: lockfile
+                .actions
+                .iter()
+                .filter(|(_, action)| applies_to_name(&action.principal_types))
+                .map(|(uid, _)| uid.clone())
+                .collect(),
+            resource_for: lockfile
+                .actions
+                .iter()
+                .filter(|(_, action)| applies_to_name(&action.resource_types))
+                .map(|(uid, _)| uid.clone())
+                .collect(),
+            referenced_by,
+        })
+    }
+
+    /// Describe the action with entity UID `uid`, written fully qualified as
+    /// in `App::Action::"view"`. Fails with `SchemaError::UndeclaredActions`
+    /// if the action is not declared, or if a common type cannot be resolved.
+    pub fn explain_action(&self, uid: &str) -> Result<ActionExplanation> {
+        let lockfile = self.to_lockfile()?;
+        let (uid, action) = lockfile
+            .actions
+            .get_key_value(uid)
+            .ok_or_else(|| SchemaError::UndeclaredActions(HashSet::from([uid.to_string()])))?;
+        Ok(ActionExplanation {
+            name: uid.clone(),
+            principal_types: action.principal_types.clone(),
+            resource_types: action.resource_types.clone(),
+            context: record_attributes(&action.context),
+            member_of: action.member_of.clone(),
+            members: lockfile
+                .actions
+                .iter()
+                .filter(|(_, child)| child.member_of.contains(uid))
+                .map(|(child, _)| child.clone())
+                .collect(),
+            attributes: action.attributes.clone(),
+        })
+    }
+}
+
+/// The attributes of a resolved shape or context, which is a record unless
+/// the schema is invalid.
+fn record_attributes(ty: &SchemaType) -> BTreeMap<SmolStr, TypeOfAttribute> {
+    match ty {
+        SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => attributes.clone(),
+        _ => BTreeMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn fragment() -> SchemaFragment {
+        SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Owners": { "type": "Set", "element": { "type": "Entity", "name": "User" } }
+                },
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "manager": { "type": "Entity", "name": "User", "required": false }
+                            }
+                        }
+                    },
+                    "Group": {},
+                    "Photo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "owners": { "type": "Owners" } }
+                        }
+                    }
+                },
+                "actions": {
+                    "read": {},
+                    "view": {
+                        "memberOf": [{ "id": "read" }],
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Photo"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "delegate": { "type": "Entity", "name": "App::User" } }
+                            }
+                        }
+                    },
+                    "join": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Group"] } }
+                }
+            }
+        }))
+        .expect("Parse Error")
+    }
+
+    #[test]
+    fn explain_entity_type() {
+        let explanation = fragment()
+            .explain_entity_type("App::User")
+            .expect("User is declared");
+        assert_eq!(
+            explanation.attributes.keys().collect::<Vec<_>>(),
+            vec!["manager"]
+        );
+        assert!(!explanation.attributes["manager"].required);
+        assert_eq!(
+            explanation.member_of_types,
+            BTreeSet::from(["App::Group".into()])
+        );
+        assert_eq!(
+            explanation.principal_for,
+            BTreeSet::from([
+                r#"App::Action::"join""#.into(),
+                r#"App::Action::"read""#.into(),
+                r#"App::Action::"view""#.into(),
+            ])
+        );
+        assert_eq!(
+            explanation.resource_for,
+            BTreeSet::from([r#"App::Action::"read""#.into()])
+        );
+        assert_eq!(
+            explanation.referenced_by,
+            BTreeSet::from([
+                r#"App::Action::"view".context.delegate"#.into(),
+                "App::Photo.owners[]".into(),
+                "App::User.manager".into(),
+            ])
+        );
+        let json = serde_json::to_value(&explanation).expect("Serialize Error");
+        assert_eq!(json["memberOfTypes"], json!(["App::Group"]));
+    }
+
+    #[test]
+    fn explain_action() {
+        let fragment = fragment();
+        let view = fragment
+            .explain_action(r#"App::Action::"view""#)
+            .expect("view is declared");
+        assert_eq!(
+            view.principal_types,
+            Some(BTreeSet::from(["App::User".into()]))
+        );
+        assert_eq!(view.context.keys().collect::<Vec<_>>(), vec!["delegate"]);
+        assert_eq!(
+            view.member_of,
+            BTreeSet::from([r#"App::Action::"read""#.into()])
+        );
+        let read = fragment
+            .explain_action(r#"App::Action::"read""#)
+            .expect("read is declared");
+        assert_eq!(read.principal_types, None);
+        assert_eq!(
+            read.members,
+            BTreeSet::from([r#"App::Action::"view""#.into()])
+        );
+    }
+
+    #[test]
+    fn undeclared() {
+        let fragment = fragment();
+        assert!(matches!(
+            fragment.explain_entity_type("User"),
+            Err(SchemaError::UndeclaredEntityTypes(_))
+        ));
+        assert!(matches!(
+            fragment.explain_action("view"),
+            Err(SchemaError::UndeclaredActions(_))
+        ));
+    }
+}
@@ -0,0 +1,274 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Generation of a sample authorization request for every action, so that
+//! test harnesses can exercise each action without writing requests by hand.
+
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use serde_json::{json, Value};
+use smol_str::SmolStr;
+
+use super::{
+    fully_qualify, ActionEntityUID, ActionType, IpKind, SchemaFragment, SchemaType,
+    SchemaTypeVariant,
+};
+
+/// The entity id used for sample entities of types which do not enumerate
+/// their ids.
+const SAMPLE_ID: &str = "sample";
+
+impl SchemaFragment {
+    /// A sample request for every action declared in this fragment which
+    /// has one, keyed by the action. Each request is a JSON object with
+    /// `principal`, `action` and `resource` entity references, written as
+    /// `{ "type": .., "id": .. }`, and a `context` record.
+    ///
+    /// The principal and resource are of the first of the action's
+    /// `principalTypes` and `resourceTypes` in sorted order, or of the first
+    /// entity type declared in this fragment if the action applies to any.
+    /// Entities have the first id of their type's `enum`, or `"sample"`.
+    /// The context holds only the attributes which may be required, using
+    /// each attribute's `example` if it has one and otherwise the simplest
+    /// value of its type, such as `""`, `0` or an empty set.
+    ///
+    /// Actions for which no valid request can be made are left out; they are
+    /// listed by `actions_without_request_fixtures`.
+    pub fn generate_request_fixtures(&self) -> BTreeMap<ActionEntityUID, Value> {
+        self.actions_with_fixtures()
+            .filter_map(|(uid, fixture)| Some((uid, fixture?)))
+            .collect()
+    }
+
+    /// The actions declared in this fragment, in sorted order, for which
+    /// `generate_request_fixtures` cannot make a valid request: actions whose
+    /// `principalTypes` or `resourceTypes` is empty, actions which apply to
+    /// any principal or resource in a fragment with no entity types, and
+    /// actions whose context contains a type which cannot be resolved or
+    /// which has no value, such as an unknown extension type. A required
+    /// string with a `pattern` has a value only if one of its `enum` values,
+    /// or the empty string, matches the pattern.
+    pub fn actions_without_request_fixtures(&self) -> Vec<ActionEntityUID> {
+        self.actions_with_fixtures()
+            .filter(|(_, fixture)| fixture.is_none())
+            .map(|(uid, _)| uid)
+            .sorted()
+            .collect()
+    }
+
+    fn actions_with_fixtures(&self) -> impl Iterator<Item = (ActionEntityUID, Option<Value>)> + '_ {
+        self.0.iter().flat_map(move |(ns, ns_def)| {
+            ns_def.actions.iter().map(move |(id, action)| {
+                let uid = ActionEntityUID {
+                    id: id.clone(),
+                    ty: Some(fully_qualify(ns, "Action")),
+                };
+                let fixture = self.request_fixture(ns, &uid, action);
+                (uid, fixture)
+            })
+        })
+    }
+
+    fn request_fixture(
+        &self,
+        ns: &str,
+        uid: &ActionEntityUID,
+        action: &ActionType,
+    ) -> Option<Value> {
+        let applies_to = action.applies_to.as_ref();
+        let principal = self.sample_applies_to(ns, applies_to.map(|a| &a.principal_types))?;
+        let resource = self.sample_applies_to(ns, applies_to.map(|a| &a.resource_types))?;
+        let context = match applies_to {
+            Some(applies_to) => {
+                let context = self.resolve_type(ns, &applies_to.context.0).ok()?;
+                self.sample_value(&context)?
+            }
+            None => json!({}),
+        };
+        Some(json!({
+            "principal": self.sample_entity(&principal),
+            "action": { "type": uid.ty, "id": uid.id },
+            "resource": self.sample_entity(&resource),
+            "context": context,
+        }))
+    }
+
+    /// The fully qualified entity type to use for a principal or resource
+    /// allowed by `tys`, a list of `principalTypes` or `resourceTypes` which
+    /// is `None` when the action applies to any entity type.
+    fn sample_applies_to(&self, ns: &str, tys: Option<&Option<Vec<SmolStr>>>) -> Option<SmolStr> {
+        match tys.and_then(Option::as_ref) {
+            Some(tys) => tys.iter().map(|ty| fully_qualify(ns, ty)).min(),
+            None => self
+                .0
+                .iter()
+                .flat_map(|(ns, ns_def)| {
+                    ns_def
+                        .entity_types
+                        .keys()
+                        .map(move |name| fully_qualify(ns, name))
+                })
+                .min(),
+        }
+    }
+
+    /// A reference to a sample entity of the fully qualified type `ty`.
+    fn sample_entity(&self, ty: &SmolStr) -> Value {
+        let id = self
+            .0
+            .iter()
+            .flat_map(|(ns, ns_def)| {
+                ns_def
+                    .entity_types
+                    .iter()
+                    .filter(move |(name, _)| fully_qualify(ns, name) == *ty)
+            })
+            .find_map(|(_, entity_type)| entity_type.enumeration.as_ref()?.first().cloned())
+            .unwrap_or_else(|| SAMPLE_ID.into());
+        json!({ "type": ty, "id": id })
+    }
+
+    /// A sample value of `ty`, which must have been resolved, or `None` if
+    /// the type has no value which can be written down.
+    fn sample_value(&self, ty: &SchemaType) -> Option<Value> {
+        let SchemaType::Type(variant) = ty else {
+            return None;
+        };
+        Some(match variant {
+            SchemaTypeVariant::String {
+                enumeration,
+                pattern,
+            } => {
+                let matches = |s: &str| pattern.as_ref().is_none_or(|p| p.is_match(s));
+                match enumeration {
+                    Some(values) => json!(values.iter().find(|v| matches(v))?),
+                    None if matches("") => json!(""),
+                    None => return None,
+                }
+            }
+            SchemaTypeVariant::Long { min, max } => {
+                json!(0.clamp(min.unwrap_or(i64::MIN), max.unwrap_or(i64::MAX)))
+            }
+            SchemaTypeVariant::Boolean => json!(false),
+            SchemaTypeVariant::Set { .. } => json!([]),
+            SchemaTypeVariant::Record { attributes, .. } => {
+                let mut record = serde_json::Map::new();
+                for (attr, attr_ty) in attributes.iter().sorted_by_key(|(attr, _)| *attr) {
+                    // An attribute with a `requiredIf` condition may be
+                    // required depending on the values of its siblings, so
+                    // it is always included.
+                    if !attr_ty.required && attr_ty.required_if.is_none() {
+                        continue;
+                    }
+                    let value = match &attr_ty.example {
+                        Some(example) => serde_json::to_value(example).ok()?,
+                        None => self.sample_value(&attr_ty.ty)?,
+                    };
+                    record.insert(attr.to_string(), value);
+                }
+                Value::Object(record)
+            }
+            SchemaTypeVariant::Entity { name } => json!({ "__entity": self.sample_entity(name) }),
+            SchemaTypeVariant::Extension { name, ip_kind } => {
+                let arg = match (name.as_str(), ip_kind) {
+                    ("decimal", _) => "0.0",
+                    ("ipaddr", Some(IpKind::Cidr)) => "0.0.0.0/0",
+                    ("ipaddr", _) => "0.0.0.0",
+                    _ => return None,
+                };
+                json!({ "__extn": { "fn": name, "arg": arg } })
+            }
+            SchemaTypeVariant::Union { options } => options
+                .iter()
+                .find_map(|option| self.sample_value(option))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn generate_request_fixtures() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {},
+                    "Group": {},
+                    "Status": { "enum": ["active", "archived"] },
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User", "Group"],
+                            "resourceTypes": ["Photo"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "reason": { "type": "String", "example": "audit" },
+                                    "count": { "type": "Long" },
+                                    "tags": { "type": "Set", "element": { "type": "String" } },
+                                    "note": { "type": "String", "required": false },
+                                    "status": { "type": "Entity", "name": "Status" }
+                                }
+                            }
+                        }
+                    },
+                    "audit": {},
+                    "nothing": { "appliesTo": { "principalTypes": [], "resourceTypes": ["Photo"] } }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let fixtures = fragment.generate_request_fixtures();
+        let uid = |id: &str| ActionEntityUID {
+            id: id.into(),
+            ty: Some("App::Action".into()),
+        };
+        assert_eq!(
+            fixtures.keys().cloned().collect::<Vec<_>>(),
+            vec![uid("audit"), uid("view")]
+        );
+        assert_eq!(
+            fixtures[&uid("view")],
+            json!({
+                "principal": { "type": "App::Group", "id": "sample" },
+                "action": { "type": "App::Action", "id": "view" },
+                "resource": { "type": "App::Photo", "id": "sample" },
+                "context": {
+                    "count": 0,
+                    "reason": "audit",
+                    "status": { "__entity": { "type": "App::Status", "id": "active" } },
+                    "tags": []
+                }
+            })
+        );
+        fragment.0["App"].actions["view"]
+            .validate_context(&fixtures[&uid("view")]["context"], &fragment, "App")
+            .expect("the sample context should be valid");
+        assert_eq!(fixtures[&uid("audit")]["principal"]["type"], "App::Group");
+        assert_eq!(fixtures[&uid("audit")]["context"], json!({}));
+        assert_eq!(
+            fragment.actions_without_request_fixtures(),
+            vec![uid("nothing")]
+        );
+    }
+}
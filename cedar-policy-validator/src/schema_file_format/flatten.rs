@@ -0,0 +1,257 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversion of a schema fragment with several namespaces into a single
+//! namespace, for tools which do not support namespaces.
+
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{
+    fully_qualify, ActionEntityUID, ActionType, ApplySpec, AttributesOrContext, EntityType,
+    NamespaceDefinition, SchemaFragment, SchemaType, SchemaTypeVariant, SharedSchemaType,
+    TypeOfAttribute,
+};
+use crate::{Result, SchemaError};
+
+/// The delimiter conventionally passed to `SchemaFragment::flatten_namespaces`.
+/// With this delimiter, flattened names are spelled the same as fully
+/// qualified names.
+pub const DEFAULT_NAMESPACE_DELIMITER: &str = "::";
+
+impl SchemaFragment {
+    /// Combine every namespace in this fragment into a single namespace
+    /// definition, renaming each entity type, common type and action to its
+    /// fully qualified name with the namespace components joined by
+    /// `delimiter` (e.g., `App::User` becomes `App_User` with the delimiter
+    /// `_`, and the action `view` in namespace `App` becomes `App_view`). All
+    /// references are rewritten to match. It is an error for two declarations
+    /// to have the same name after flattening.
+    pub fn flatten_namespaces(&self, delimiter: &str) -> Result<NamespaceDefinition> {
+        let flat = |qualified: &str| -> SmolStr { qualified.split("::").join(delimiter).into() };
+        let flat_action = |ns: &str, id: &str| -> SmolStr {
+            if ns.is_empty() {
+                id.into()
+            } else {
+                format!("{}{delimiter}{id}", flat(ns)).into()
+            }
+        };
+        let mut flattened = NamespaceDefinition::new([], []);
+        for (ns, ns_def) in self.0.iter().sorted_by_key(|(ns, _)| *ns) {
+            let flat_type = |name: &SmolStr| flat(&fully_qualify(ns, name));
+            for (name, ty) in ns_def.common_types.iter().sorted_by_key(|(name, _)| *name) {
+                let name = flat_type(name);
+                let ty = rename_type(ty, &flat_type);
+                if flattened.common_types.insert(name.clone(), ty).is_some() {
+                    return Err(SchemaError::DuplicateCommonType(name.to_string()));
+                }
+            }
+            for (name, et) in ns_def.entity_types.iter().sorted_by_key(|(name, _)| *name) {
+                let name = flat_type(name);
+                let et = EntityType {
+                    member_of_types: et.member_of_types.iter().map(flat_type).collect(),
+                    shape: AttributesOrContext(rename_type(&et.shape.0, &flat_type)),
+                    additional_attributes_definition: et
+                        .additional_attributes_definition
+                        .as_ref()
+                        .map(|additional| {
+                            AttributesOrContext(rename_type(&additional.0, &flat_type))
+                        }),
+                    enumeration: et.enumeration.clone(),
+                    tags: et.tags.as_ref().map(|tags| rename_type(tags, &flat_type)),
+                    since: et.since.clone(),
+                };
+                if flattened.entity_types.insert(name.clone(), et).is_some() {
+                    return Err(SchemaError::DuplicateEntityType(name.to_string()));
+                }
+            }
+            for (name, action) in ns_def.actions.iter().sorted_by_key(|(name, _)| *name) {
+                let name = flat_action(ns, &ActionEntityUID::from_declared_name(name).id);
+                let action = ActionType {
+                    attributes: action.attributes.clone(),
+                    applies_to: action.applies_to.as_ref().map(|applies_to| ApplySpec {
+                        resource_types: applies_to
+                            .resource_types
+                            .as_ref()
+                            .map(|tys| tys.iter().map(flat_type).collect()),
+                        principal_types: applies_to
+                            .principal_types
+                            .as_ref()
+                            .map(|tys| tys.iter().map(flat_type).collect()),
+                        context: AttributesOrContext(rename_type(
+                            &applies_to.context.0,
+                            &flat_type,
+                        )),
+                    }),
+                    member_of: action.member_of.as_ref().map(|parents| {
+                        parents
+                            .iter()
+                            .map(|parent| {
+                                // An explicit action type names the namespace
+                                // the parent action is declared in.
+                                let parent_ns = match &parent.ty {
+                                    Some(ty) => ty.rsplit_once("::").map_or("", |(ns, _)| ns),
+                                    None => ns,
+                                };
+                                ActionEntityUID::default_type(flat_action(parent_ns, &parent.id))
+                            })
+                            .collect()
+                    }),
+                    since: action.since.clone(),
+                };
+                if flattened.actions.insert(name.clone(), action).is_some() {
+                    return Err(SchemaError::DuplicateAction(name.to_string()));
+                }
+            }
+        }
+        Ok(flattened)
+    }
+}
+
+/// `ty` with every entity type and common type name replaced by `rename`.
+pub(crate) fn rename_type(ty: &SchemaType, rename: &impl Fn(&SmolStr) -> SmolStr) -> SchemaType {
+    match ty {
+        SchemaType::TypeDef { type_name } => SchemaType::TypeDef {
+            type_name: rename(type_name),
+        },
+        SchemaType::Type(SchemaTypeVariant::Entity { name }) => {
+            SchemaType::Type(SchemaTypeVariant::Entity { name: rename(name) })
+        }
+        SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+            SchemaType::Type(SchemaTypeVariant::Set {
+                element: SharedSchemaType::new(rename_type(element, rename)),
+            })
+        }
+        SchemaType::Type(SchemaTypeVariant::Union { options }) => {
+            SchemaType::Type(SchemaTypeVariant::Union {
+                options: options
+                    .iter()
+                    .map(|option| rename_type(option, rename).into())
+                    .collect(),
+            })
+        }
+        SchemaType::Type(SchemaTypeVariant::Record {
+            attributes,
+            additional_attributes,
+        }) => SchemaType::Type(SchemaTypeVariant::Record {
+            attributes: attributes
+                .iter()
+                .map(|(attr, attr_ty)| {
+                    let attr_ty = TypeOfAttribute {
+                        ty: rename_type(&attr_ty.ty, rename).into(),
+                        ..attr_ty.clone()
+                    };
+                    (attr.clone(), attr_ty)
+                })
+                .collect(),
+            additional_attributes: *additional_attributes,
+        }),
+        SchemaType::Type(_) => ty.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn fragment() -> SchemaFragment {
+        SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": { "Name": { "type": "String" } },
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Org::Team"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "Name" },
+                                "friends": { "type": "Set", "element": { "type": "Entity", "name": "User" } }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Org::Team"] },
+                        "memberOf": [{ "id": "read", "type": "Org::Action" }]
+                    }
+                }
+            },
+            "Org": {
+                "entityTypes": { "Team": {} },
+                "actions": { "read": {} }
+            }
+        }))
+        .expect("Parse Error")
+    }
+
+    #[test]
+    fn flatten() {
+        let flattened = fragment().flatten_namespaces("_").expect("no collisions");
+        let expected: NamespaceDefinition = serde_json::from_value(json!({
+            "commonTypes": { "App_Name": { "type": "String" } },
+            "entityTypes": {
+                "App_User": {
+                    "memberOfTypes": ["Org_Team"],
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "name": { "type": "App_Name" },
+                            "friends": { "type": "Set", "element": { "type": "Entity", "name": "App_User" } }
+                        }
+                    }
+                },
+                "Org_Team": {}
+            },
+            "actions": {
+                "App_view": {
+                    "appliesTo": { "principalTypes": ["App_User"], "resourceTypes": ["Org_Team"] },
+                    "memberOf": [{ "id": "Org_read" }]
+                },
+                "Org_read": {}
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(flattened, expected);
+    }
+
+    #[test]
+    fn flattened_schema_is_valid() {
+        let flattened = fragment()
+            .flatten_namespaces(DEFAULT_NAMESPACE_DELIMITER)
+            .expect("no collisions");
+        assert!(flattened.entity_types.contains_key("App::User"));
+        let fragment = SchemaFragment(HashMap::from([("".into(), flattened)]));
+        SchemaFragment::validate_set(&[fragment]).expect("references should resolve");
+    }
+
+    #[test]
+    fn collision() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "A": { "entityTypes": { "B_C": {} }, "actions": {} },
+            "A_B": { "entityTypes": { "C": {} }, "actions": {} }
+        }))
+        .expect("Parse Error");
+        assert!(matches!(
+            fragment.flatten_namespaces("_"),
+            Err(SchemaError::DuplicateEntityType(name)) if name == "A_B_C"
+        ));
+    }
+}
@@ -0,0 +1,293 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Queries over the entity type hierarchy declared by the `memberOfTypes`
+//! lists of entity types in a single namespace.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use cedar_policy_core::ast::EntityUID;
+use smol_str::SmolStr;
+
+use super::{fully_qualify, NamespaceDefinition};
+use crate::{Result, SchemaError};
+
+impl NamespaceDefinition {
+    /// Map from each entity type declared in this namespace to the other
+    /// entity types it is a direct member of, with both sorted by name. Only
+    /// unqualified `memberOfTypes` entries naming entity types declared in
+    /// this namespace are included. An entity type listing itself is left
+    /// out: as in Cedar, where a `Group` may be a member of another `Group`,
+    /// this does not make a cycle.
+    pub(crate) fn entity_type_parents(&self) -> BTreeMap<&SmolStr, BTreeSet<&SmolStr>> {
+        self.entity_types
+            .iter()
+            .map(|(name, entity_type)| {
+                let parents = entity_type
+                    .member_of_types
+                    .iter()
+                    .filter(|parent| *parent != name)
+                    .filter_map(|parent| self.entity_types.get_key_value(parent))
+                    .map(|(parent, _)| parent)
+                    .collect();
+                (name, parents)
+            })
+            .collect()
+    }
+
+    /// Check that every entity in `parents` may be a parent of an entity of
+    /// the entity type `entity_ty`, which is named as it is declared in this
+    /// namespace, named `ns`, because its type is listed in the
+    /// `memberOfTypes` of `entity_ty`. Only direct membership counts: a type
+    /// `entity_ty` is only transitively a member of is not allowed.
+    /// `memberOfTypes` entries are qualified with `ns` before they are
+    /// compared with the parent types. Fails with
+    /// `SchemaError::DisallowedParentTypes` naming the qualified entity type
+    /// and listing the disallowed parent types in sorted order, or with
+    /// `SchemaError::UndeclaredEntityTypes` if `entity_ty` is not declared
+    /// here.
+    pub fn validate_entity_parents(
+        &self,
+        ns: &str,
+        entity_ty: &str,
+        parents: &[EntityUID],
+    ) -> Result<()> {
+        let entity_type = self.entity_types.get(entity_ty).ok_or_else(|| {
+            SchemaError::UndeclaredEntityTypes(HashSet::from([
+                fully_qualify(ns, entity_ty).to_string()
+            ]))
+        })?;
+        let allowed = entity_type
+            .member_of_types
+            .iter()
+            .map(|allowed| fully_qualify(ns, allowed))
+            .collect::<HashSet<_>>();
+        let disallowed = parents
+            .iter()
+            .map(|parent| parent.entity_type().to_string())
+            .filter(|ty| !allowed.contains(ty.as_str()))
+            .collect::<BTreeSet<_>>();
+        if disallowed.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaError::DisallowedParentTypes(
+                fully_qualify(ns, entity_ty).to_string(),
+                disallowed.into_iter().collect(),
+            ))
+        }
+    }
+
+    /// The entity types declared in this namespace, ordered so that every
+    /// entity type comes after all of the entity types it is a member of.
+    /// Of the entity types which could come next, the first by name is
+    /// taken, so the order is deterministic. `memberOfTypes` entries are
+    /// followed as by `entity_type_parents`, so an entity type which is a
+    /// member of itself is allowed. Fails with
+    /// `SchemaError::CycleInEntityTypeHierarchy` if the hierarchy has any
+    /// other cycle.
+    pub fn topo_sorted_entity_types(&self) -> Result<Vec<SmolStr>> {
+        let mut remaining = self.entity_type_parents();
+        let mut sorted = Vec::with_capacity(remaining.len());
+        while let Some(name) = remaining
+            .iter()
+            .find(|(_, parents)| parents.is_empty())
+            .map(|(name, _)| *name)
+        {
+            remaining.remove(name);
+            for parents in remaining.values_mut() {
+                parents.remove(name);
+            }
+            sorted.push(name.clone());
+        }
+        match remaining.first_key_value() {
+            None => Ok(sorted),
+            Some((start, _)) => Err(SchemaError::CycleInEntityTypeHierarchy(find_cycle(
+                start, &remaining,
+            ))),
+        }
+    }
+
+    /// Check that no entity type declared in this namespace is transitively
+    /// a member of itself through other entity types' `memberOfTypes`. An
+    /// entity type which lists itself is allowed, and `memberOfTypes`
+    /// entries are followed as by `entity_type_parents`. Fails with
+    /// `SchemaError::CycleInEntityTypeHierarchy` naming the entity types on
+    /// a cycle, in order. This is the same check `SchemaFragment::validate_all`
+    /// reports as an error.
+    pub fn check_entity_hierarchy_acyclic(&self) -> Result<()> {
+        self.topo_sorted_entity_types().map(|_| ())
+    }
+}
+
+/// A cycle through the graph `parents`, found by following the first parent
+/// of each entity type from `start`, where every entity type in `parents`
+/// has a parent which is also in `parents`. The first entity type of the
+/// cycle is repeated at its end.
+fn find_cycle(start: &SmolStr, parents: &BTreeMap<&SmolStr, BTreeSet<&SmolStr>>) -> Vec<String> {
+    let mut path: Vec<&SmolStr> = vec![start];
+    loop {
+        let Some(&next) = path.last().and_then(|name| parents[name].first()) else {
+            return Vec::new();
+        };
+        if let Some(pos) = path.iter().position(|name| *name == next) {
+            let mut cycle = path[pos..]
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>();
+            cycle.push(next.to_string());
+            return cycle;
+        }
+        path.push(next);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn namespace(entity_types: serde_json::Value) -> NamespaceDefinition {
+        serde_json::from_value(json!({ "entityTypes": entity_types, "actions": {} }))
+            .expect("Parse Error")
+    }
+
+    #[test]
+    fn topo_sorted_entity_types() {
+        let ns = namespace(json!({
+            "User": { "memberOfTypes": ["Team", "Org"] },
+            "Team": { "memberOfTypes": ["Org"] },
+            "Org": {},
+            "Album": { "memberOfTypes": ["Other::Folder"] },
+            "Photo": { "memberOfTypes": ["Album"] }
+        }));
+        assert_eq!(
+            ns.topo_sorted_entity_types().expect("hierarchy is acyclic"),
+            vec!["Album", "Org", "Photo", "Team", "User"]
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        let ns = namespace(json!({
+            "Album": { "memberOfTypes": ["Account"] },
+            "Account": { "memberOfTypes": ["Album"] },
+            "User": { "memberOfTypes": ["Account"] }
+        }));
+        assert_eq!(
+            ns.topo_sorted_entity_types()
+                .expect_err("hierarchy has a cycle")
+                .to_string(),
+            "Cycle in entity type hierarchy: Account -> Album -> Account"
+        );
+        let ns = namespace(json!({
+            "Folder": { "memberOfTypes": ["Folder", "Drive"] },
+            "Drive": {}
+        }));
+        assert_eq!(
+            ns.topo_sorted_entity_types()
+                .expect("an entity type may be a member of itself"),
+            vec!["Drive", "Folder"]
+        );
+    }
+
+    #[test]
+    fn check_entity_hierarchy_acyclic() {
+        namespace(json!({
+            "User": { "memberOfTypes": ["Team", "Org"] },
+            "Team": { "memberOfTypes": ["Org"] },
+            "Org": {}
+        }))
+        .check_entity_hierarchy_acyclic()
+        .expect("hierarchy is acyclic");
+        assert!(matches!(
+            namespace(json!({
+                "User": { "memberOfTypes": ["Account"] },
+                "Account": { "memberOfTypes": ["Album"] },
+                "Album": { "memberOfTypes": ["Account"] }
+            }))
+            .check_entity_hierarchy_acyclic(),
+            Err(SchemaError::CycleInEntityTypeHierarchy(cycle))
+                if cycle == ["Account", "Album", "Account"]
+        ));
+        namespace(json!({
+            "Folder": { "memberOfTypes": ["Folder", "Drive"] },
+            "Drive": {}
+        }))
+        .check_entity_hierarchy_acyclic()
+        .expect("an entity type may be a member of itself");
+    }
+
+    #[test]
+    fn validate_entity_parents() {
+        let ns = namespace(json!({
+            "User": { "memberOfTypes": ["Team"] },
+            "Team": { "memberOfTypes": ["Org"] },
+            "Org": {},
+            "Album": {}
+        }));
+        let uid = |ty: &str, id: &str| EntityUID::with_eid_and_type(ty, id).expect("valid uid");
+        ns.validate_entity_parents("", "User", &[uid("Team", "admins"), uid("Team", "ops")])
+            .expect("parents are teams");
+        ns.validate_entity_parents("", "Org", &[])
+            .expect("no parents");
+        match ns.validate_entity_parents(
+            "",
+            "User",
+            &[
+                uid("Team", "admins"),
+                uid("Org", "acme"),
+                uid("Album", "trips"),
+            ],
+        ) {
+            Err(SchemaError::DisallowedParentTypes(ty, parents)) => {
+                assert_eq!(ty, "User");
+                assert_eq!(parents, vec!["Album", "Org"]);
+            }
+            r => panic!("Expected disallowed parent types, got {r:?}"),
+        }
+        assert!(matches!(
+            ns.validate_entity_parents("", "Photo", &[]),
+            Err(SchemaError::UndeclaredEntityTypes(_))
+        ));
+    }
+
+    #[test]
+    fn validate_entity_parents_in_namespace() {
+        let ns = namespace(json!({
+            "User": { "memberOfTypes": ["Team", "Other::Group"] },
+            "Team": {}
+        }));
+        let uid = |ty: &str, id: &str| {
+            format!(r#"{ty}::"{id}""#)
+                .parse::<EntityUID>()
+                .expect("valid uid")
+        };
+        ns.validate_entity_parents(
+            "App",
+            "User",
+            &[uid("App::Team", "admins"), uid("Other::Group", "ops")],
+        )
+        .expect("parents are allowed");
+        match ns.validate_entity_parents("App", "User", &[uid("Team", "admins")]) {
+            Err(SchemaError::DisallowedParentTypes(ty, parents)) => {
+                assert_eq!(ty, "App::User");
+                assert_eq!(parents, vec!["Team"]);
+            }
+            r => panic!("Expected disallowed parent types, got {r:?}"),
+        }
+    }
+}
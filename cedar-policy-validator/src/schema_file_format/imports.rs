@@ -0,0 +1,143 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Expansion of the short names a namespace imports for types declared in
+//! other namespaces.
+
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{flatten::rename_type, NamespaceDefinition, SchemaFragment};
+use crate::{Result, SchemaError};
+
+impl NamespaceDefinition {
+    /// Replace every entity type or common type name in this namespace which
+    /// is an alias in `imports` with the name it is imported as. Expanded
+    /// names always contain `::`, so `fully_qualify` leaves them as they are
+    /// and expanding again has no effect. The `imports` themselves are kept.
+    ///
+    /// It is an error for an alias to have the same name as an entity type or
+    /// common type declared in this namespace, since it would be ambiguous
+    /// which of the two a reference means.
+    pub fn expand_imports(&mut self) -> Result<()> {
+        if self.imports.is_empty() {
+            return Ok(());
+        }
+        if let Some(alias) = self.imports.keys().sorted().find(|alias| {
+            self.entity_types.contains_key(*alias) || self.common_types.contains_key(*alias)
+        }) {
+            return Err(SchemaError::ImportShadowsDeclaration(alias.to_string()));
+        }
+        let imports = &self.imports;
+        let expand = |name: &SmolStr| -> SmolStr { imports.get(name).unwrap_or(name).clone() };
+        for ty in self.common_types.values_mut() {
+            *ty = rename_type(ty, &expand);
+        }
+        for entity_type in self.entity_types.values_mut() {
+            for parent in entity_type.member_of_types.iter_mut() {
+                *parent = expand(parent);
+            }
+            entity_type.shape.0 = rename_type(&entity_type.shape.0, &expand);
+            if let Some(additional) = &mut entity_type.additional_attributes_definition {
+                additional.0 = rename_type(&additional.0, &expand);
+            }
+            if let Some(tags) = &mut entity_type.tags {
+                *tags = rename_type(tags, &expand);
+            }
+        }
+        for applies_to in self
+            .actions
+            .values_mut()
+            .filter_map(|action| action.applies_to.as_mut())
+        {
+            for ty in applies_to
+                .principal_types
+                .iter_mut()
+                .chain(applies_to.resource_types.iter_mut())
+                .flatten()
+            {
+                *ty = expand(ty);
+            }
+            applies_to.context.0 = rename_type(&applies_to.context.0, &expand);
+        }
+        Ok(())
+    }
+}
+
+impl SchemaFragment {
+    /// Expand the imported aliases in every namespace in this fragment, as
+    /// `NamespaceDefinition::expand_imports` does. Fragments are expanded when
+    /// they are parsed, so this is only needed for fragments built in code.
+    pub fn expand_imports(&mut self) -> Result<()> {
+        self.0
+            .values_mut()
+            .try_for_each(NamespaceDefinition::expand_imports)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn alias_in_member_of_types() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "PhotoApp::Photos::Albums": { "entityTypes": { "Album": {} }, "actions": {} },
+            "PhotoApp": {
+                "imports": { "Album": "PhotoApp::Photos::Albums::Album" },
+                "entityTypes": {
+                    "Photo": {
+                        "memberOfTypes": ["Album"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "cover": { "type": "Entity", "name": "Album" } }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let photo = &fragment.0["PhotoApp"].entity_types["Photo"];
+        assert_eq!(
+            photo.member_of_types,
+            vec![SmolStr::from("PhotoApp::Photos::Albums::Album")]
+        );
+        SchemaFragment::validate_set(std::slice::from_ref(&fragment))
+            .expect("references should resolve");
+        let schema: crate::ValidatorSchema = fragment.try_into().expect("valid schema");
+        assert!(schema
+            .get_entity_type(&"PhotoApp::Photo".parse().unwrap())
+            .is_some());
+    }
+
+    #[test]
+    fn alias_shadowing_local_type() {
+        let result = SchemaFragment::from_json_value(json!({
+            "PhotoApp": {
+                "imports": { "Album": "Other::Album" },
+                "entityTypes": { "Album": {} },
+                "actions": {}
+            }
+        }));
+        match result {
+            Err(SchemaError::ImportShadowsDeclaration(alias)) => assert_eq!(alias, "Album"),
+            r => panic!("Expected shadowing error, got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,415 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Inference of a schema fragment from entity data in the Cedar JSON entity
+//! format.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use cedar_policy_core::entities::{EntityUidJSON, JsonDeserializationErrorContext};
+use serde_json::Value;
+use smol_str::SmolStr;
+
+use super::{
+    AttributesOrContext, EntityType, NamespaceDefinition, SchemaFragment, SchemaType,
+    SchemaTypeVariant, SharedSchemaType, TypeOfAttribute,
+};
+use crate::{Result, SchemaError};
+
+/// The type of the values seen at some position in the data. Unlike
+/// `SchemaType`, the element type of a set is unknown until a non-empty set
+/// has been seen.
+#[derive(Debug, Clone, PartialEq)]
+enum Inferred {
+    String,
+    Long,
+    Boolean,
+    Set(Option<Box<Inferred>>),
+    /// Attribute types, and whether the attribute was present in every
+    /// record seen.
+    Record(BTreeMap<SmolStr, (Inferred, bool)>),
+    Entity(SmolStr),
+    Extension(SmolStr),
+}
+
+impl Inferred {
+    fn describe(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Long => "long",
+            Self::Boolean => "boolean",
+            Self::Set(_) => "set",
+            Self::Record(_) => "record",
+            Self::Entity(_) => "entity",
+            Self::Extension(_) => "extension",
+        }
+    }
+
+    /// The narrowest type which covers both `self` and `other`.
+    fn widen(self, other: Inferred, path: &str) -> Result<Inferred> {
+        match (self, other) {
+            (Self::Set(None), Self::Set(elem)) | (Self::Set(elem), Self::Set(None)) => {
+                Ok(Self::Set(elem))
+            }
+            (Self::Set(Some(a)), Self::Set(Some(b))) => Ok(Self::Set(Some(Box::new(
+                a.widen(*b, &format!("{path}[]"))?,
+            )))),
+            (Self::Record(mut a), Self::Record(mut b)) => {
+                let mut widened = BTreeMap::new();
+                let attrs: BTreeSet<SmolStr> = a.keys().chain(b.keys()).cloned().collect();
+                for attr in attrs {
+                    let entry = match (a.remove(&attr), b.remove(&attr)) {
+                        (Some((a_ty, a_all)), Some((b_ty, b_all))) => {
+                            (a_ty.widen(b_ty, &format!("{path}.{attr}"))?, a_all && b_all)
+                        }
+                        (Some((ty, _)), None) | (None, Some((ty, _))) => (ty, false),
+                        (None, None) => continue,
+                    };
+                    widened.insert(attr, entry);
+                }
+                Ok(Self::Record(widened))
+            }
+            (a, b) if a == b => Ok(a),
+            (Self::Entity(a), Self::Entity(b)) => Err(inference_error(format!(
+                "`{path}` refers to entities of types `{a}` and `{b}`"
+            ))),
+            (Self::Extension(a), Self::Extension(b)) => Err(inference_error(format!(
+                "`{path}` has values of extension types `{a}` and `{b}`"
+            ))),
+            (a, b) => Err(inference_error(format!(
+                "`{path}` has both {} and {} values",
+                a.describe(),
+                b.describe()
+            ))),
+        }
+    }
+
+    fn into_schema_type(self, ns: &str, path: &str) -> Result<SchemaType> {
+        let variant = match self {
+            Self::String => SchemaTypeVariant::String {
+                enumeration: None,
+                pattern: None,
+            },
+            Self::Long => SchemaTypeVariant::Long {
+                min: None,
+                max: None,
+            },
+            Self::Boolean => SchemaTypeVariant::Boolean,
+            Self::Set(Some(element)) => SchemaTypeVariant::Set {
+                element: SharedSchemaType::new(element.into_schema_type(ns, &format!("{path}[]"))?),
+            },
+            Self::Set(None) => {
+                return Err(inference_error(format!(
+                    "the element type of `{path}` is unknown because it is always empty"
+                )))
+            }
+            Self::Record(attributes) => SchemaTypeVariant::Record {
+                attributes: attributes
+                    .into_iter()
+                    .map(|(attr, (ty, required))| {
+                        let ty = ty.into_schema_type(ns, &format!("{path}.{attr}"))?;
+                        Ok((
+                            attr,
+                            TypeOfAttribute {
+                                ty: ty.into(),
+                                required,
+                                computed: false,
+                                example: None,
+                                required_if: None,
+                                unit: None,
+                            },
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+                additional_attributes: false,
+            },
+            Self::Entity(name) => SchemaTypeVariant::Entity {
+                name: local_name(ns, &name)?,
+            },
+            Self::Extension(name) => SchemaTypeVariant::Extension {
+                name,
+                ip_kind: None,
+            },
+        };
+        Ok(SchemaType::Type(variant))
+    }
+}
+
+fn inference_error(msg: String) -> SchemaError {
+    SchemaError::EntityInference(msg)
+}
+
+/// `name`, a fully qualified entity type, as written in namespace `ns`.
+fn local_name(ns: &str, name: &str) -> Result<SmolStr> {
+    let local = if ns.is_empty() {
+        Some(name).filter(|name| !name.contains("::"))
+    } else {
+        name.strip_prefix(ns)
+            .and_then(|rest| rest.strip_prefix("::"))
+            .filter(|rest| !rest.contains("::"))
+    };
+    local.map(Into::into).ok_or_else(|| {
+        inference_error(format!(
+            "entity type `{name}` is not declared directly in namespace `{ns}`"
+        ))
+    })
+}
+
+/// The fully qualified type of the entity referenced by `uid`.
+fn entity_type_of(uid: &Value, path: &str) -> Result<SmolStr> {
+    serde_json::from_value::<EntityUidJSON>(uid.clone())
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            json.into_euid(|| JsonDeserializationErrorContext::Context)
+                .map_err(|e| e.to_string())
+        })
+        .map(|euid| euid.entity_type().to_string().into())
+        .map_err(|e| inference_error(format!("`{path}` is not an entity reference: {e}")))
+}
+
+fn infer_value(value: &Value, path: &str, referenced: &mut BTreeSet<SmolStr>) -> Result<Inferred> {
+    match value {
+        Value::String(_) => Ok(Inferred::String),
+        Value::Bool(_) => Ok(Inferred::Boolean),
+        Value::Number(n) if n.is_i64() => Ok(Inferred::Long),
+        Value::Number(_) => Err(inference_error(format!(
+            "`{path}` is a number which is not a 64-bit integer"
+        ))),
+        Value::Null => Err(inference_error(format!("`{path}` is null"))),
+        Value::Array(elements) => {
+            let mut element = None;
+            for value in elements {
+                let ty = infer_value(value, &format!("{path}[]"), referenced)?;
+                element = Some(match element {
+                    None => ty,
+                    Some(prev) => Inferred::widen(prev, ty, &format!("{path}[]"))?,
+                });
+            }
+            Ok(Inferred::Set(element.map(Box::new)))
+        }
+        Value::Object(fields) => {
+            if let Some(uid) = fields.get("__entity") {
+                let name = entity_type_of(uid, path)?;
+                referenced.insert(name.clone());
+                Ok(Inferred::Entity(name))
+            } else if let Some(extn) = fields.get("__extn") {
+                match extn.get("fn").and_then(Value::as_str) {
+                    Some(name) => Ok(Inferred::Extension(name.into())),
+                    None => Err(inference_error(format!(
+                        "`{path}` is an extension value without a function name"
+                    ))),
+                }
+            } else if fields.contains_key("__expr") {
+                Err(inference_error(format!(
+                    "`{path}` uses an `__expr` escape, which is not supported"
+                )))
+            } else {
+                fields
+                    .iter()
+                    .map(|(attr, value)| {
+                        let ty = infer_value(value, &format!("{path}.{attr}"), referenced)?;
+                        Ok((attr.into(), (ty, true)))
+                    })
+                    .collect::<Result<_>>()
+                    .map(Inferred::Record)
+            }
+        }
+    }
+}
+
+impl SchemaFragment {
+    /// Infer the smallest schema fragment under which every entity in
+    /// `entities`, an array in the Cedar JSON entity format, is valid. All
+    /// entity types seen, whether as entities or as references to entities,
+    /// are declared in namespace `ns`, so they must all be declared directly
+    /// in that namespace.
+    ///
+    /// Attribute types follow the values seen: strings, integers and booleans
+    /// become `String`, `Long` and `Boolean`, arrays become sets, objects
+    /// become records, and `__entity` and `__extn` escapes become entity and
+    /// extension types. An attribute is required if it is present on every
+    /// entity of the type (or in every record at that position), and
+    /// `memberOfTypes` lists the types of every parent seen. Entity types
+    /// which are only referenced are declared with no attributes. It is an
+    /// error for values at the same position to have different types, or for
+    /// a set to always be empty, since its element type cannot be inferred.
+    pub fn infer_from_entities(entities: &Value, ns: &str) -> Result<SchemaFragment> {
+        let entities = entities
+            .as_array()
+            .ok_or_else(|| inference_error("entity data is not an array".to_string()))?;
+        let mut shapes: BTreeMap<SmolStr, Inferred> = BTreeMap::new();
+        let mut parents: BTreeMap<SmolStr, BTreeSet<SmolStr>> = BTreeMap::new();
+        let mut referenced = BTreeSet::new();
+        for (i, entity) in entities.iter().enumerate() {
+            let uid = entity
+                .get("uid")
+                .ok_or_else(|| inference_error(format!("entity {i} has no `uid`")))?;
+            let ty = entity_type_of(uid, &format!("[{i}].uid"))?;
+            let attrs = match entity.get("attrs") {
+                Some(attrs) if attrs.is_object() => {
+                    infer_value(attrs, ty.as_str(), &mut referenced)?
+                }
+                Some(_) => {
+                    return Err(inference_error(format!(
+                        "the attributes of entity {i} are not an object"
+                    )))
+                }
+                None => Inferred::Record(BTreeMap::new()),
+            };
+            let shape = match shapes.remove(&ty) {
+                Some(prev) => prev.widen(attrs, &ty)?,
+                None => attrs,
+            };
+            shapes.insert(ty.clone(), shape);
+            let entity_parents = parents.entry(ty).or_default();
+            for (j, parent) in entity
+                .get("parents")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                let parent = entity_type_of(parent, &format!("[{i}].parents[{j}]"))?;
+                referenced.insert(parent.clone());
+                entity_parents.insert(parent);
+            }
+        }
+        for ty in referenced {
+            shapes
+                .entry(ty)
+                .or_insert_with(|| Inferred::Record(BTreeMap::new()));
+        }
+        let mut entity_types = HashMap::new();
+        for (ty, shape) in shapes {
+            let entity_type = EntityType {
+                member_of_types: parents
+                    .remove(&ty)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|parent| local_name(ns, parent))
+                    .collect::<Result<_>>()?,
+                shape: AttributesOrContext(shape.into_schema_type(ns, &ty)?),
+                additional_attributes_definition: None,
+                enumeration: None,
+                tags: None,
+                since: None,
+            };
+            entity_types.insert(local_name(ns, &ty)?, entity_type);
+        }
+        Ok(SchemaFragment(HashMap::from([(
+            ns.into(),
+            NamespaceDefinition::new(entity_types, []),
+        )])))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::AttributeValidationMode;
+
+    fn entities() -> Value {
+        json!([
+            {
+                "uid": { "type": "App::User", "id": "alice" },
+                "attrs": {
+                    "name": "Alice",
+                    "age": 30,
+                    "tags": [],
+                    "manager": { "__entity": { "type": "App::User", "id": "bob" } },
+                    "address": { "city": "Seattle", "zip": 98101 }
+                },
+                "parents": [{ "type": "App::Group", "id": "admins" }]
+            },
+            {
+                "uid": { "type": "App::User", "id": "bob" },
+                "attrs": {
+                    "name": "Bob",
+                    "tags": ["new"],
+                    "address": { "city": "Portland" }
+                },
+                "parents": []
+            }
+        ])
+    }
+
+    #[test]
+    fn infer() {
+        let fragment = SchemaFragment::infer_from_entities(&entities(), "App").expect("infers");
+        let expected = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "age": { "type": "Long", "required": false },
+                                "tags": { "type": "Set", "element": { "type": "String" } },
+                                "manager": { "type": "Entity", "name": "User", "required": false },
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "city": { "type": "String" },
+                                        "zip": { "type": "Long", "required": false }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "Group": {}
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(fragment, expected);
+
+        let user = &fragment.0["App"].entity_types["User"];
+        for entity in entities().as_array().unwrap() {
+            user.validate_attributes(
+                &entity["attrs"],
+                &fragment,
+                "App",
+                AttributeValidationMode::StoredValidation,
+            )
+            .expect("entity should validate");
+        }
+    }
+
+    #[test]
+    fn conflicting_types() {
+        let mut entities = entities();
+        entities[1]["attrs"]["age"] = json!("old");
+        match SchemaFragment::infer_from_entities(&entities, "App") {
+            Err(SchemaError::EntityInference(msg)) => {
+                assert_eq!(msg, "`App::User.age` has both long and string values")
+            }
+            r => panic!("Expected inference error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn wrong_namespace() {
+        assert!(matches!(
+            SchemaFragment::infer_from_entities(&entities(), "Other"),
+            Err(SchemaError::EntityInference(_))
+        ));
+    }
+}
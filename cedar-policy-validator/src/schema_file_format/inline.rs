@@ -0,0 +1,360 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Inlining of common types into the types which use them.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{
+    NamespaceDefinition, SchemaFragment, SchemaLocation, SchemaType, SchemaTypeVariant,
+    SchemaWarning, Severity, SharedSchemaType, TypeOfAttribute, TypePath, TypePathSegment,
+    ValidationFinding,
+};
+use crate::{Result, SchemaError};
+
+impl SchemaFragment {
+    /// This fragment with every common type replaced by its definition in
+    /// the entity shapes, `additionalAttributesDefinition`s, entity tags and
+    /// action contexts which use it, and with no common types declared. Entity type
+    /// names are fully qualified, as by `resolve_type`. Fails if a common
+    /// type is undeclared or defined in terms of itself.
+    ///
+    /// Inlining should not change the meaning of any type, so the effective
+    /// `required` flag of every attribute and `additionalAttributes` flag of
+    /// every record is compared before and after, since these default to
+    /// `true` and `false` when they are not written. A warning is returned
+    /// for each path where they differ.
+    pub fn inline_common_types(&self) -> Result<(SchemaFragment, Vec<SchemaWarning>)> {
+        let mut inlined = self.clone();
+        for (ns, ns_def) in inlined.0.iter_mut() {
+            ns_def.common_types.clear();
+            for (_, ty) in ns_def.declared_type_paths_mut(ns) {
+                *ty = self.resolve_type(ns, ty)?;
+            }
+        }
+        let warnings = flag_changes(&self.effective_flags(), &inlined.effective_flags());
+        Ok((inlined, warnings))
+    }
+
+    /// The `required` flag of every attribute and the `additionalAttributes`
+    /// flag of every record at which a value may appear, keyed by path and
+    /// the name of the flag.
+    fn effective_flags(&self) -> BTreeMap<(TypePath, &'static str), bool> {
+        let mut flags = BTreeMap::new();
+        self.walk_value_types(&mut |path, _, ty| {
+            if let SchemaType::Type(SchemaTypeVariant::Record {
+                attributes,
+                additional_attributes,
+            }) = ty
+            {
+                flags.insert(
+                    (path.clone(), "additionalAttributes"),
+                    *additional_attributes,
+                );
+                for (attr, attr_ty) in attributes {
+                    let attr_path = path.child(TypePathSegment::Attribute(attr.clone()));
+                    flags.insert((attr_path, "required"), attr_ty.required);
+                }
+            }
+        });
+        flags
+    }
+}
+
+impl NamespaceDefinition {
+    /// This namespace with every common type reference in its entity
+    /// shapes, `additionalAttributesDefinition`s, entity tags and action
+    /// contexts replaced by the definition from `commonTypes`, recursively, and with no common
+    /// types declared. Unlike `SchemaFragment::inline_common_types`, names
+    /// are looked up exactly as written in this namespace's `commonTypes`,
+    /// and entity type names are left as they are. Every common type is
+    /// resolved, including those which are not used. Fails with
+    /// `SchemaError::UndeclaredCommonType` for a name which is not declared,
+    /// or with `SchemaError::CycleInCommonTypeReferences` for a common type
+    /// defined in terms of itself.
+    pub fn resolve_common_types(&self) -> Result<NamespaceDefinition> {
+        for ty in self
+            .common_types
+            .iter()
+            .sorted_by_key(|(name, _)| *name)
+            .map(|(name, _)| SchemaType::TypeDef {
+                type_name: name.clone(),
+            })
+        {
+            inline_typedefs(&ty, &self.common_types, &mut Vec::new())?;
+        }
+        let mut resolved = self.clone();
+        resolved.common_types.clear();
+        for (_, ty) in resolved.declared_type_paths_mut("") {
+            *ty = inline_typedefs(ty, &self.common_types, &mut Vec::new())?;
+        }
+        Ok(resolved)
+    }
+}
+
+/// `ty` with every common type reference replaced by its definition in
+/// `common_types`, recursively. `expanding` holds the common types whose
+/// definitions are being inlined, to detect cycles.
+pub(crate) fn inline_typedefs(
+    ty: &SchemaType,
+    common_types: &HashMap<SmolStr, SchemaType>,
+    expanding: &mut Vec<SmolStr>,
+) -> Result<SchemaType> {
+    let variant = match ty {
+        SchemaType::TypeDef { type_name } => {
+            if expanding.contains(type_name) {
+                return Err(SchemaError::CycleInCommonTypeReferences(
+                    type_name.to_string(),
+                ));
+            }
+            let def = common_types.get(type_name).ok_or_else(|| {
+                SchemaError::UndeclaredCommonType(HashSet::from([type_name.to_string()]))
+            })?;
+            expanding.push(type_name.clone());
+            let inlined = inline_typedefs(def, common_types, expanding);
+            expanding.pop();
+            return inlined;
+        }
+        SchemaType::Type(SchemaTypeVariant::Set { element }) => SchemaTypeVariant::Set {
+            element: SharedSchemaType::new(inline_typedefs(element, common_types, expanding)?),
+        },
+        SchemaType::Type(SchemaTypeVariant::Union { options }) => SchemaTypeVariant::Union {
+            options: options
+                .iter()
+                .map(|option| Ok(inline_typedefs(option, common_types, expanding)?.into()))
+                .collect::<Result<_>>()?,
+        },
+        SchemaType::Type(SchemaTypeVariant::Record {
+            attributes,
+            additional_attributes,
+        }) => SchemaTypeVariant::Record {
+            attributes: attributes
+                .iter()
+                .map(|(attr, attr_ty)| {
+                    Ok((
+                        attr.clone(),
+                        TypeOfAttribute {
+                            ty: inline_typedefs(&attr_ty.ty, common_types, expanding)?.into(),
+                            ..attr_ty.clone()
+                        },
+                    ))
+                })
+                .collect::<Result<_>>()?,
+            additional_attributes: *additional_attributes,
+        },
+        SchemaType::Type(variant) => variant.clone(),
+    };
+    Ok(SchemaType::Type(variant))
+}
+
+/// A warning for each flag in both `before` and `after` with a different
+/// value.
+fn flag_changes(
+    before: &BTreeMap<(TypePath, &'static str), bool>,
+    after: &BTreeMap<(TypePath, &'static str), bool>,
+) -> Vec<SchemaWarning> {
+    before
+        .iter()
+        .filter_map(|(key @ (path, flag), was)| {
+            let now = after.get(key).filter(|now| *now != was)?;
+            Some(ValidationFinding::new(
+                Severity::Warning,
+                SchemaLocation::Type(path.clone()),
+                format!("`{flag}` was {was} before inlining common types but is {now} after"),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::TypePathRoot;
+
+    #[test]
+    fn inline_common_types() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Address": {
+                        "type": "Record",
+                        "attributes": {
+                            "city": { "type": "String" },
+                            "zip": { "type": "String", "required": false }
+                        },
+                        "additionalAttributes": true
+                    }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "home": { "type": "Address", "required": false } }
+                        },
+                        "tags": { "type": "Set", "element": { "type": "Address" } }
+                    }
+                },
+                "actions": {
+                    "ship": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "to": { "type": "Address" } }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let (inlined, warnings) = fragment.inline_common_types().expect("Inlining succeeds");
+        assert_eq!(warnings, Vec::new());
+        let ns_def = &inlined.0["App"];
+        assert!(ns_def.common_types.is_empty());
+        let mut typedefs = 0;
+        inlined.walk_value_types(&mut |_, _, ty| {
+            typedefs += usize::from(matches!(ty, SchemaType::TypeDef { .. }));
+        });
+        assert_eq!(typedefs, 0);
+        assert!(matches!(
+            ns_def.entity_types["User"].tags(),
+            Some(SchemaType::Type(SchemaTypeVariant::Set { .. }))
+        ));
+        assert_eq!(fragment.effective_flags(), inlined.effective_flags());
+    }
+
+    #[test]
+    fn resolve_common_types() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Name": { "type": "String" },
+                    "Names": { "type": "Set", "element": { "type": "Name" } },
+                    "Owner": { "type": "Entity", "name": "User" }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "aliases": { "type": "Names" } }
+                        }
+                    },
+                    "Photo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "owner": { "type": "Owner" } }
+                        },
+                        "tags": { "type": "Names" }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "by": { "type": "Name" } }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let resolved = fragment.0["App"]
+            .resolve_common_types()
+            .expect("Resolution succeeds");
+        let expected = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "aliases": { "type": "Set", "element": { "type": "String" } }
+                            }
+                        }
+                    },
+                    "Photo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "owner": { "type": "Entity", "name": "User" } }
+                        },
+                        "tags": { "type": "Set", "element": { "type": "String" } }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "by": { "type": "String" } }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(resolved, expected.0["App"]);
+    }
+
+    #[test]
+    fn resolve_common_types_errors() {
+        let ns_def = |common_types: serde_json::Value| -> NamespaceDefinition {
+            serde_json::from_value(json!({
+                "commonTypes": common_types,
+                "entityTypes": {},
+                "actions": {}
+            }))
+            .expect("Parse Error")
+        };
+        assert!(matches!(
+            ns_def(json!({ "A": { "type": "B" }, "B": { "type": "A" } })).resolve_common_types(),
+            Err(SchemaError::CycleInCommonTypeReferences(name)) if name == "A"
+        ));
+        assert!(matches!(
+            ns_def(json!({ "A": { "type": "Set", "element": { "type": "Missing" } } }))
+                .resolve_common_types(),
+            Err(SchemaError::UndeclaredCommonType(names)) if names.contains("Missing")
+        ));
+    }
+
+    #[test]
+    fn flag_changes_are_reported() {
+        let user = TypePath::new(TypePathRoot::EntityType("App::User".into()));
+        let zip = user.child(TypePathSegment::Attribute("zip".into()));
+        let before = BTreeMap::from([
+            ((user.clone(), "additionalAttributes"), false),
+            ((zip.clone(), "required"), false),
+        ]);
+        let after = BTreeMap::from([
+            ((user, "additionalAttributes"), false),
+            ((zip, "required"), true),
+        ]);
+        assert_eq!(
+            flag_changes(&before, &after)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["warning at `App::User.zip`: `required` was false before inlining common types but is true after"]
+        );
+    }
+}
@@ -0,0 +1,853 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Comparison of the externally observable interface of two schema
+//! fragments, ignoring how their declarations are organized.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{
+    diff::describe_type, SchemaFragment, SchemaType, SchemaTypeVariant, TypeOfAttribute, TypePath,
+    TypePathRoot, TypePathSegment,
+};
+use crate::Result;
+
+/// Which of two schema fragments being compared by
+/// `SchemaFragment::same_interface_as` something belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InterfaceSide {
+    /// The fragment `same_interface_as` was called on.
+    This,
+    /// The fragment passed to `same_interface_as`.
+    Other,
+}
+
+impl std::fmt::Display for InterfaceSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::This => "this schema",
+            Self::Other => "the other schema",
+        })
+    }
+}
+
+/// A difference between the interfaces of two schema fragments, as reported
+/// by `SchemaFragment::same_interface_as`. All names are fully qualified, and
+/// all types have common types inlined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceDiff {
+    /// A fragment could not be resolved, so the interfaces could not be
+    /// compared.
+    Unresolved {
+        /// The fragment which could not be resolved.
+        side: InterfaceSide,
+        /// Why the fragment could not be resolved.
+        message: String,
+    },
+    /// An entity type is declared in only one of the fragments.
+    EntityTypeOnlyIn {
+        /// The fragment declaring the entity type.
+        side: InterfaceSide,
+        /// The entity type.
+        name: SmolStr,
+    },
+    /// An entity type has a different shape in each fragment.
+    ShapeDiffers {
+        /// The entity type.
+        name: SmolStr,
+        /// The shape in this fragment.
+        this: SchemaType,
+        /// The shape in the other fragment.
+        other: SchemaType,
+    },
+    /// An entity type has different `enum` values in each fragment. `None`
+    /// means the entity type is not declared with `enum`.
+    EnumDiffers {
+        /// The entity type.
+        name: SmolStr,
+        /// The values in this fragment.
+        this: Option<BTreeSet<SmolStr>>,
+        /// The values in the other fragment.
+        other: Option<BTreeSet<SmolStr>>,
+    },
+    /// An entity type has a different tag type in each fragment. `None`
+    /// means entities of the type have no tags.
+    TagsDiffer {
+        /// The entity type.
+        name: SmolStr,
+        /// The tag type in this fragment.
+        this: Option<SchemaType>,
+        /// The tag type in the other fragment.
+        other: Option<SchemaType>,
+    },
+    /// An action is declared in only one of the fragments.
+    ActionOnlyIn {
+        /// The fragment declaring the action.
+        side: InterfaceSide,
+        /// The action entity UID.
+        name: SmolStr,
+    },
+    /// An action applies to different principal types in each fragment.
+    /// `None` means the action applies to any principal.
+    PrincipalTypesDiffer {
+        /// The action entity UID.
+        name: SmolStr,
+        /// The principal types in this fragment.
+        this: Option<BTreeSet<SmolStr>>,
+        /// The principal types in the other fragment.
+        other: Option<BTreeSet<SmolStr>>,
+    },
+    /// An action applies to different resource types in each fragment.
+    /// `None` means the action applies to any resource.
+    ResourceTypesDiffer {
+        /// The action entity UID.
+        name: SmolStr,
+        /// The resource types in this fragment.
+        this: Option<BTreeSet<SmolStr>>,
+        /// The resource types in the other fragment.
+        other: Option<BTreeSet<SmolStr>>,
+    },
+    /// An action has a different context in each fragment.
+    ContextDiffers {
+        /// The action entity UID.
+        name: SmolStr,
+        /// The context in this fragment.
+        this: SchemaType,
+        /// The context in the other fragment.
+        other: SchemaType,
+    },
+}
+
+impl std::fmt::Display for InterfaceDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let types = |tys: &Option<BTreeSet<SmolStr>>| match tys {
+            Some(tys) => format!("[{}]", tys.iter().join(", ")),
+            None => "any".to_string(),
+        };
+        let values = |values: &Option<BTreeSet<SmolStr>>| match values {
+            Some(values) => format!("[{}]", values.iter().join(", ")),
+            None => "no enum".to_string(),
+        };
+        let tags = |ty: &Option<SchemaType>| match ty {
+            Some(ty) => format!("tags `{}`", describe_type(ty)),
+            None => "no tags".to_string(),
+        };
+        match self {
+            Self::Unresolved { side, message } => write!(f, "{side} cannot be resolved: {message}"),
+            Self::EntityTypeOnlyIn { side, name } => {
+                write!(f, "entity type `{name}` is only declared in {side}")
+            }
+            Self::ShapeDiffers { name, this, other } => write!(
+                f,
+                "entity type `{name}` has shape `{}` in this schema but `{}` in the other",
+                describe_type(this),
+                describe_type(other)
+            ),
+            Self::EnumDiffers { name, this, other } => write!(
+                f,
+                "entity type `{name}` has {} in this schema but {} in the other",
+                values(this),
+                values(other)
+            ),
+            Self::TagsDiffer { name, this, other } => write!(
+                f,
+                "entity type `{name}` has {} in this schema but {} in the other",
+                tags(this),
+                tags(other)
+            ),
+            Self::ActionOnlyIn { side, name } => {
+                write!(f, "action `{name}` is only declared in {side}")
+            }
+            Self::PrincipalTypesDiffer { name, this, other } => write!(
+                f,
+                "action `{name}` applies to principal types {} in this schema but {} in the other",
+                types(this),
+                types(other)
+            ),
+            Self::ResourceTypesDiffer { name, this, other } => write!(
+                f,
+                "action `{name}` applies to resource types {} in this schema but {} in the other",
+                types(this),
+                types(other)
+            ),
+            Self::ContextDiffers { name, this, other } => write!(
+                f,
+                "action `{name}` has context `{}` in this schema but `{}` in the other",
+                describe_type(this),
+                describe_type(other)
+            ),
+        }
+    }
+}
+
+/// One side of an `InterfaceChange`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceValue {
+    /// The shape of an entity type, the context of an action, or a type
+    /// nested inside one, with common types inlined.
+    Type(SchemaType),
+    /// A record attribute.
+    Attribute(TypeOfAttribute),
+    /// Whether a record attribute is required.
+    Required(bool),
+    /// Whether a record allows additional attributes.
+    AdditionalAttributes(bool),
+    /// The `enum` values of an entity type, or `None` when it is not
+    /// declared with `enum`.
+    Enum(Option<BTreeSet<SmolStr>>),
+    /// The principal types of an action, or `None` when it applies to any
+    /// principal.
+    PrincipalTypes(Option<BTreeSet<SmolStr>>),
+    /// The resource types of an action, or `None` when it applies to any
+    /// resource.
+    ResourceTypes(Option<BTreeSet<SmolStr>>),
+}
+
+/// A change to the interface of a schema fragment, as reported by
+/// `SchemaFragment::interface_diff`. Declarations and attributes which are
+/// added have no `before`, and those which are removed have no `after`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceChange {
+    /// Where the change is. Changes to the declaration of an entity type or
+    /// action, including the `enum` values of an entity type and the
+    /// principal and resource types of an action, are at the root of its
+    /// shape or context. Changes to tags are under the tags of the entity
+    /// type.
+    pub path: TypePath,
+    /// The interface before the change.
+    pub before: Option<InterfaceValue>,
+    /// The interface after the change.
+    pub after: Option<InterfaceValue>,
+}
+
+impl SchemaFragment {
+    /// The changes to the interface going from this fragment to `other`,
+    /// where the interface is as compared by `same_interface_as`, so the
+    /// result is empty exactly when the two fragments have the same
+    /// interface. Unlike `diff`, this compares the fragments after
+    /// qualifying names and inlining common types, so moving declarations
+    /// into or out of common types gives no changes. Changes are ordered by
+    /// entity type and then action, each sorted by name, and are as fine
+    /// grained as in `diff`. Fails if either fragment cannot be resolved.
+    pub fn interface_diff(&self, other: &SchemaFragment) -> Result<Vec<InterfaceChange>> {
+        let this_lock = self.to_lockfile()?;
+        let other_lock = other.to_lockfile()?;
+        let mut changes = Vec::new();
+        for (name, both) in pair_up(&this_lock.entity_types, &other_lock.entity_types) {
+            let path = TypePath::new(TypePathRoot::EntityType(name.clone()));
+            let (before, after) = (both.0.map(|e| &e.shape), both.1.map(|e| &e.shape));
+            if let (Some(this), Some(other)) = both {
+                if this.enumeration != other.enumeration {
+                    changes.push(InterfaceChange {
+                        path: path.clone(),
+                        before: Some(InterfaceValue::Enum(this.enumeration.clone())),
+                        after: Some(InterfaceValue::Enum(other.enumeration.clone())),
+                    });
+                }
+            }
+            diff_types(&mut changes, &path, before, after);
+            if let (Some(this), Some(other)) = both {
+                if this.tags != other.tags {
+                    diff_types(
+                        &mut changes,
+                        &TypePath::new(TypePathRoot::EntityTags(name.clone())),
+                        this.tags.as_ref(),
+                        other.tags.as_ref(),
+                    );
+                }
+            }
+        }
+        for (name, both) in pair_up(&this_lock.actions, &other_lock.actions) {
+            let path = TypePath::new(action_context_root(name));
+            let (before, after) = (both.0.map(|a| &a.context), both.1.map(|a| &a.context));
+            if let (Some(this), Some(other)) = both {
+                if this.principal_types != other.principal_types {
+                    changes.push(InterfaceChange {
+                        path: path.clone(),
+                        before: Some(InterfaceValue::PrincipalTypes(this.principal_types.clone())),
+                        after: Some(InterfaceValue::PrincipalTypes(
+                            other.principal_types.clone(),
+                        )),
+                    });
+                }
+                if this.resource_types != other.resource_types {
+                    changes.push(InterfaceChange {
+                        path: path.clone(),
+                        before: Some(InterfaceValue::ResourceTypes(this.resource_types.clone())),
+                        after: Some(InterfaceValue::ResourceTypes(other.resource_types.clone())),
+                    });
+                }
+            }
+            diff_types(&mut changes, &path, before, after);
+        }
+        Ok(changes)
+    }
+
+    /// Check that this fragment and `other` expose the same interface: the
+    /// same entity types with the same shapes, `enum` values and tag types,
+    /// and the same actions applying to the same principal and resource
+    /// types with the same context. Names are compared fully qualified and common types are inlined, so moving
+    /// declarations between common types or namespace spellings which mean
+    /// the same thing does not change the interface. Entity type and action
+    /// hierarchies and action attributes are not part of the interface.
+    pub fn same_interface_as(
+        &self,
+        other: &SchemaFragment,
+    ) -> std::result::Result<(), Vec<InterfaceDiff>> {
+        let (this_lock, other_lock) = match (self.to_lockfile(), other.to_lockfile()) {
+            (Ok(this_lock), Ok(other_lock)) => (this_lock, other_lock),
+            (this_lock, other_lock) => {
+                let unresolved = [
+                    (InterfaceSide::This, this_lock),
+                    (InterfaceSide::Other, other_lock),
+                ];
+                return Err(unresolved
+                    .into_iter()
+                    .filter_map(|(side, lock)| {
+                        let message = lock.err()?.to_string();
+                        Some(InterfaceDiff::Unresolved { side, message })
+                    })
+                    .collect());
+            }
+        };
+        let mut diffs = Vec::new();
+        for (name, both) in pair_up(&this_lock.entity_types, &other_lock.entity_types) {
+            match both {
+                (Some(this), Some(other)) => {
+                    if this.shape != other.shape {
+                        diffs.push(InterfaceDiff::ShapeDiffers {
+                            name: name.clone(),
+                            this: this.shape.clone(),
+                            other: other.shape.clone(),
+                        });
+                    }
+                    if this.enumeration != other.enumeration {
+                        diffs.push(InterfaceDiff::EnumDiffers {
+                            name: name.clone(),
+                            this: this.enumeration.clone(),
+                            other: other.enumeration.clone(),
+                        });
+                    }
+                    if this.tags != other.tags {
+                        diffs.push(InterfaceDiff::TagsDiffer {
+                            name: name.clone(),
+                            this: this.tags.clone(),
+                            other: other.tags.clone(),
+                        });
+                    }
+                }
+                (this, _) => diffs.push(InterfaceDiff::EntityTypeOnlyIn {
+                    side: side_of(this.is_some()),
+                    name: name.clone(),
+                }),
+            }
+        }
+        for (name, both) in pair_up(&this_lock.actions, &other_lock.actions) {
+            let (this, other) = match both {
+                (Some(this), Some(other)) => (this, other),
+                (this, _) => {
+                    diffs.push(InterfaceDiff::ActionOnlyIn {
+                        side: side_of(this.is_some()),
+                        name: name.clone(),
+                    });
+                    continue;
+                }
+            };
+            if this.principal_types != other.principal_types {
+                diffs.push(InterfaceDiff::PrincipalTypesDiffer {
+                    name: name.clone(),
+                    this: this.principal_types.clone(),
+                    other: other.principal_types.clone(),
+                });
+            }
+            if this.resource_types != other.resource_types {
+                diffs.push(InterfaceDiff::ResourceTypesDiffer {
+                    name: name.clone(),
+                    this: this.resource_types.clone(),
+                    other: other.resource_types.clone(),
+                });
+            }
+            if this.context != other.context {
+                diffs.push(InterfaceDiff::ContextDiffers {
+                    name: name.clone(),
+                    this: this.context.clone(),
+                    other: other.context.clone(),
+                });
+            }
+        }
+        if diffs.is_empty() {
+            Ok(())
+        } else {
+            Err(diffs)
+        }
+    }
+}
+
+/// The root of the context of the action with the fully qualified entity UID
+/// `uid`, as written by `references::qualified_action`.
+fn action_context_root(uid: &str) -> TypePathRoot {
+    let (ty, id) = uid.split_once("::\"").unwrap_or(("", uid));
+    TypePathRoot::ActionContext {
+        ty: ty.into(),
+        id: id.strip_suffix('"').unwrap_or(id).into(),
+    }
+}
+
+/// Push the changes going from the type `before` at `path` to `after` onto
+/// `changes`, where a missing type means the declaration does not exist.
+fn diff_types(
+    changes: &mut Vec<InterfaceChange>,
+    path: &TypePath,
+    before: Option<&SchemaType>,
+    after: Option<&SchemaType>,
+) {
+    let mut push = |path: &TypePath, before, after| {
+        changes.push(InterfaceChange {
+            path: path.clone(),
+            before,
+            after,
+        })
+    };
+    let (before, after) = match (before, after) {
+        (Some(before), Some(after)) => (before, after),
+        (before, after) => {
+            let value = |ty: Option<&SchemaType>| ty.cloned().map(InterfaceValue::Type);
+            push(path, value(before), value(after));
+            return;
+        }
+    };
+    match (before, after) {
+        (
+            SchemaType::Type(SchemaTypeVariant::Set { element: before }),
+            SchemaType::Type(SchemaTypeVariant::Set { element: after }),
+        ) => diff_types(
+            changes,
+            &path.child(TypePathSegment::SetElement),
+            Some(before),
+            Some(after),
+        ),
+        (
+            SchemaType::Type(SchemaTypeVariant::Record {
+                attributes: before_attrs,
+                additional_attributes: before_open,
+            }),
+            SchemaType::Type(SchemaTypeVariant::Record {
+                attributes: after_attrs,
+                additional_attributes: after_open,
+            }),
+        ) => {
+            if before_open != after_open {
+                push(
+                    path,
+                    Some(InterfaceValue::AdditionalAttributes(*before_open)),
+                    Some(InterfaceValue::AdditionalAttributes(*after_open)),
+                );
+            }
+            for (attr, both) in pair_up(before_attrs, after_attrs) {
+                let attr_path = path.child(TypePathSegment::Attribute(attr.clone()));
+                let (before, after) = match both {
+                    (Some(before), Some(after)) => (before, after),
+                    (before, after) => {
+                        let value =
+                            |a: Option<&TypeOfAttribute>| a.cloned().map(InterfaceValue::Attribute);
+                        changes.push(InterfaceChange {
+                            path: attr_path,
+                            before: value(before),
+                            after: value(after),
+                        });
+                        continue;
+                    }
+                };
+                if before.required != after.required {
+                    changes.push(InterfaceChange {
+                        path: attr_path.clone(),
+                        before: Some(InterfaceValue::Required(before.required)),
+                        after: Some(InterfaceValue::Required(after.required)),
+                    });
+                }
+                // Anything else about the attribute, such as an example
+                // value, is reported as a change to the whole attribute.
+                let metadata = |a: &TypeOfAttribute| {
+                    (
+                        a.computed,
+                        a.example.clone(),
+                        a.required_if.clone(),
+                        a.unit.clone(),
+                    )
+                };
+                if metadata(before) != metadata(after) {
+                    changes.push(InterfaceChange {
+                        path: attr_path.clone(),
+                        before: Some(InterfaceValue::Attribute(before.clone())),
+                        after: Some(InterfaceValue::Attribute(after.clone())),
+                    });
+                }
+                diff_types(changes, &attr_path, Some(&before.ty), Some(&after.ty));
+            }
+        }
+        _ if before != after => push(
+            path,
+            Some(InterfaceValue::Type(before.clone())),
+            Some(InterfaceValue::Type(after.clone())),
+        ),
+        _ => (),
+    }
+}
+
+fn side_of(in_this: bool) -> InterfaceSide {
+    if in_this {
+        InterfaceSide::This
+    } else {
+        InterfaceSide::Other
+    }
+}
+
+/// The union of the keys of `this` and `other`, in sorted order, each paired
+/// with its value in each map.
+fn pair_up<'a, V>(
+    this: &'a BTreeMap<SmolStr, V>,
+    other: &'a BTreeMap<SmolStr, V>,
+) -> impl Iterator<Item = (&'a SmolStr, (Option<&'a V>, Option<&'a V>))> {
+    let names: BTreeSet<&SmolStr> = this.keys().chain(other.keys()).collect();
+    names
+        .into_iter()
+        .map(move |name| (name, (this.get(name), other.get(name))))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn refactored_schema() {
+        let original = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": { "city": { "type": "String" } }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["User"] } }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let refactored = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Address": { "type": "Record", "attributes": { "city": { "type": "String" } } },
+                    "Profile": {
+                        "type": "Record",
+                        "attributes": { "address": { "type": "Address", "required": true } }
+                    }
+                },
+                "entityTypes": { "User": { "shape": { "type": "Profile" } } },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["App::User"],
+                            "resourceTypes": ["User"],
+                            "context": { "type": "Record", "attributes": {} }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(original.same_interface_as(&refactored), Ok(()));
+        assert_eq!(refactored.same_interface_as(&original), Ok(()));
+    }
+
+    #[test]
+    fn changed_interface() {
+        let this = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "name": { "type": "String" } }
+                        }
+                    },
+                    "Team": {}
+                },
+                "actions": { "view": { "appliesTo": { "principalTypes": ["User"] } } }
+            }
+        }))
+        .expect("Parse Error");
+        let other = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "name": { "type": "String", "required": false } }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["User"] } },
+                    "edit": {}
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let diffs = this
+            .same_interface_as(&other)
+            .expect_err("interfaces differ")
+            .iter()
+            .map(ToString::to_string)
+            .collect_vec();
+        assert_eq!(
+            diffs,
+            vec![
+                "entity type `App::Team` is only declared in this schema",
+                "entity type `App::User` has shape `{ name: String }` in this schema but \
+                 `{ name?: String }` in the other",
+                r#"action `App::Action::"edit"` is only declared in the other schema"#,
+                r#"action `App::Action::"view"` applies to resource types any in this schema but [App::User] in the other"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn enum_and_tags() {
+        let this = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": { "Label": { "type": "String" } },
+                "entityTypes": {
+                    "Color": { "enum": ["red", "green"] },
+                    "Doc": { "tags": { "type": "Label" } }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let other = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "Color": { "enum": ["green", "blue"] },
+                    "Doc": { "tags": { "type": "Long" } }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let diffs = this
+            .same_interface_as(&other)
+            .expect_err("interfaces differ")
+            .iter()
+            .map(ToString::to_string)
+            .collect_vec();
+        assert_eq!(
+            diffs,
+            vec![
+                "entity type `App::Color` has [green, red] in this schema but [blue, green] in the other",
+                "entity type `App::Doc` has tags `String` in this schema but tags `Long` in the other",
+            ]
+        );
+        assert_eq!(
+            this.interface_diff(&other).expect("resolves"),
+            vec![
+                InterfaceChange {
+                    path: TypePath::new(TypePathRoot::EntityType("App::Color".into())),
+                    before: Some(InterfaceValue::Enum(Some(BTreeSet::from([
+                        "green".into(),
+                        "red".into()
+                    ])))),
+                    after: Some(InterfaceValue::Enum(Some(BTreeSet::from([
+                        "blue".into(),
+                        "green".into()
+                    ])))),
+                },
+                InterfaceChange {
+                    path: TypePath::new(TypePathRoot::EntityTags("App::Doc".into())),
+                    before: Some(InterfaceValue::Type(SchemaType::Type(
+                        SchemaTypeVariant::String {
+                            enumeration: None,
+                            pattern: None,
+                        }
+                    ))),
+                    after: Some(InterfaceValue::Type(SchemaType::Type(
+                        SchemaTypeVariant::Long {
+                            min: None,
+                            max: None,
+                        }
+                    ))),
+                },
+            ]
+        );
+        assert_ne!(
+            this.to_lockfile().expect("resolves").hash,
+            other.to_lockfile().expect("resolves").hash
+        );
+    }
+
+    #[test]
+    fn unresolved() {
+        let this = SchemaFragment::from_json_value(json!({
+            "App": { "entityTypes": { "User": { "shape": { "type": "Missing" } } }, "actions": {} }
+        }))
+        .expect("Parse Error");
+        assert!(matches!(
+            this.same_interface_as(&this).unwrap_err().as_slice(),
+            [
+                InterfaceDiff::Unresolved {
+                    side: InterfaceSide::This,
+                    ..
+                },
+                InterfaceDiff::Unresolved {
+                    side: InterfaceSide::Other,
+                    ..
+                }
+            ]
+        ));
+    }
+
+    #[test]
+    fn interface_diff() {
+        let this = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Address": { "type": "Record", "attributes": { "city": { "type": "String" } } }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "address": { "type": "Address" },
+                                "age": { "type": "Long" },
+                                "tags": { "type": "Set", "element": { "type": "String" } }
+                            }
+                        }
+                    }
+                },
+                "actions": { "view": { "appliesTo": { "principalTypes": ["User"] } } }
+            }
+        }))
+        .expect("Parse Error");
+        let inlined = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": { "city": { "type": "String" } }
+                                },
+                                "age": { "type": "Long" },
+                                "tags": { "type": "Set", "element": { "type": "String" } }
+                            }
+                        }
+                    }
+                },
+                "actions": { "view": { "appliesTo": { "principalTypes": ["App::User"] } } }
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(this.interface_diff(&inlined).expect("resolves"), vec![]);
+
+        let changed = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": { "city": { "type": "String", "required": false } }
+                                },
+                                "tags": { "type": "Set", "element": { "type": "Long" } }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["User"] } }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let user = TypePath::new(TypePathRoot::EntityType("App::User".into()));
+        let attr =
+            |path: &TypePath, name: &str| path.child(TypePathSegment::Attribute(name.into()));
+        let long = SchemaType::Type(SchemaTypeVariant::Long {
+            min: None,
+            max: None,
+        });
+        let changes = this.interface_diff(&changed).expect("resolves");
+        assert_eq!(
+            changes,
+            vec![
+                InterfaceChange {
+                    path: attr(&attr(&user, "address"), "city"),
+                    before: Some(InterfaceValue::Required(true)),
+                    after: Some(InterfaceValue::Required(false)),
+                },
+                InterfaceChange {
+                    path: attr(&user, "age"),
+                    before: Some(InterfaceValue::Attribute(TypeOfAttribute {
+                        ty: long.clone().into(),
+                        required: true,
+                        computed: false,
+                        example: None,
+                        required_if: None,
+                        unit: None,
+                    })),
+                    after: None,
+                },
+                InterfaceChange {
+                    path: attr(&user, "tags").child(TypePathSegment::SetElement),
+                    before: Some(InterfaceValue::Type(SchemaType::Type(
+                        SchemaTypeVariant::String {
+                            enumeration: None,
+                            pattern: None,
+                        }
+                    ))),
+                    after: Some(InterfaceValue::Type(long)),
+                },
+                InterfaceChange {
+                    path: TypePath::new(TypePathRoot::ActionContext {
+                        ty: "App::Action".into(),
+                        id: "view".into(),
+                    }),
+                    before: Some(InterfaceValue::ResourceTypes(None)),
+                    after: Some(InterfaceValue::ResourceTypes(Some(BTreeSet::from([
+                        "App::User".into()
+                    ])))),
+                },
+            ]
+        );
+    }
+}
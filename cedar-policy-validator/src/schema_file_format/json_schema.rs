@@ -0,0 +1,344 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversion from a JSON Schema document into a `SchemaFragment`, used to
+//! bootstrap a Cedar schema from existing JSON Schema entity definitions.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::{Map, Value};
+use smol_str::SmolStr;
+
+use super::{
+    AttributesOrContext, EntityType, NamespaceDefinition, SchemaFragment, SchemaType,
+    SchemaTypeVariant, SharedSchemaType, TypeOfAttribute,
+};
+use crate::{Result, SchemaError};
+
+/// Keywords which only annotate a JSON Schema and do not constrain the values
+/// it accepts, so they can be dropped without changing the meaning of the
+/// converted schema. Any keyword not handled by the conversion and not in this
+/// list is reported as unsupported.
+static ANNOTATION_KEYWORDS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "examples",
+    "default",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+];
+
+impl SchemaFragment {
+    /// Create a `SchemaFragment` with a single namespace `ns` from a JSON
+    /// Schema document. Each object schema under `$defs` (or `definitions`)
+    /// becomes an entity type, as does the root schema when it is an object
+    /// with a `title`. Object properties become entity attributes, with the
+    /// `required` list deciding which attributes are required. `string`,
+    /// `integer` and `boolean` map to the Cedar primitive types, arrays with
+    /// `items` map to `Set`, nested objects map to `Record`, and a `$ref` to
+    /// another definition maps to an entity reference.
+    ///
+    /// Constructs without a Cedar equivalent (e.g., `oneOf` or
+    /// `patternProperties`) are reported as an error rather than dropped.
+    pub fn from_json_schema(js: &Value, ns: &str) -> Result<SchemaFragment> {
+        let root = as_object(js, "#")?;
+        let mut entity_types = HashMap::new();
+        for defs_key in ["$defs", "definitions"] {
+            if let Some(defs) = root.get(defs_key) {
+                let path = format!("#/{defs_key}");
+                for (name, def) in as_object(defs, &path)? {
+                    let def_path = format!("{path}/{name}");
+                    let shape = convert_type(def, &def_path)?;
+                    if !matches!(shape, SchemaType::Type(SchemaTypeVariant::Record { .. })) {
+                        return Err(unsupported(&def_path, "definitions which are not objects"));
+                    }
+                    entity_types.insert(
+                        name.into(),
+                        EntityType {
+                            member_of_types: Vec::new(),
+                            shape: AttributesOrContext(shape),
+                            additional_attributes_definition: None,
+                            enumeration: None,
+                            tags: None,
+                            since: None,
+                        },
+                    );
+                }
+            }
+        }
+        if let Some(title) = root.get("title").and_then(Value::as_str) {
+            if root.get("type").and_then(Value::as_str) == Some("object") {
+                let root_without_defs = root
+                    .iter()
+                    .filter(|(k, _)| !matches!(k.as_str(), "$defs" | "definitions"))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect::<Map<_, _>>();
+                entity_types.insert(
+                    title.into(),
+                    EntityType {
+                        member_of_types: Vec::new(),
+                        shape: AttributesOrContext(convert_type(
+                            &Value::Object(root_without_defs),
+                            "#",
+                        )?),
+                        additional_attributes_definition: None,
+                        enumeration: None,
+                        tags: None,
+                        since: None,
+                    },
+                );
+            }
+        }
+        Ok(SchemaFragment(HashMap::from([(
+            ns.into(),
+            NamespaceDefinition::new(entity_types, []),
+        )])))
+    }
+}
+
+fn unsupported(path: &str, construct: &str) -> SchemaError {
+    SchemaError::UnsupportedJsonSchema(path.to_string(), construct.to_string())
+}
+
+fn as_object<'a>(js: &'a Value, path: &str) -> Result<&'a Map<String, Value>> {
+    js.as_object()
+        .ok_or_else(|| unsupported(path, "schemas which are not objects"))
+}
+
+/// Convert the JSON Schema at `path` into a `SchemaType`.
+fn convert_type(js: &Value, path: &str) -> Result<SchemaType> {
+    let schema = as_object(js, path)?;
+    let mut handled: Vec<&str> = vec!["type"];
+    let ty = if let Some(reference) = schema.get("$ref") {
+        handled.push("$ref");
+        let name = reference
+            .as_str()
+            .and_then(|r| {
+                r.strip_prefix("#/$defs/")
+                    .or_else(|| r.strip_prefix("#/definitions/"))
+            })
+            .ok_or_else(|| unsupported(path, "`$ref` to anything other than a local definition"))?;
+        SchemaTypeVariant::Entity { name: name.into() }
+    } else {
+        match schema.get("type") {
+            Some(Value::String(ty)) => match ty.as_str() {
+                "string" => SchemaTypeVariant::String {
+                    enumeration: None,
+                    pattern: None,
+                },
+                "integer" => SchemaTypeVariant::Long {
+                    min: None,
+                    max: None,
+                },
+                "boolean" => SchemaTypeVariant::Boolean,
+                "array" => {
+                    handled.push("items");
+                    let items = schema
+                        .get("items")
+                        .ok_or_else(|| unsupported(path, "arrays without `items`"))?;
+                    SchemaTypeVariant::Set {
+                        element: SharedSchemaType::new(convert_type(
+                            items,
+                            &format!("{path}/items"),
+                        )?),
+                    }
+                }
+                "object" => {
+                    handled.extend(["properties", "required", "additionalProperties"]);
+                    convert_object(schema, path)?
+                }
+                other => return Err(unsupported(path, &format!("type `{other}`"))),
+            },
+            Some(_) => return Err(unsupported(path, "`type` which is not a single string")),
+            None => return Err(unsupported(path, "schemas without a `type` or `$ref`")),
+        }
+    };
+    if let Some(keyword) = schema
+        .keys()
+        .find(|k| !handled.contains(&k.as_str()) && !ANNOTATION_KEYWORDS.contains(&k.as_str()))
+    {
+        return Err(unsupported(path, &format!("`{keyword}`")));
+    }
+    Ok(SchemaType::Type(ty))
+}
+
+fn convert_object(schema: &Map<String, Value>, path: &str) -> Result<SchemaTypeVariant> {
+    let required = match schema.get("required") {
+        Some(required) => required
+            .as_array()
+            .and_then(|names| names.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| unsupported(path, "`required` which is not a list of names"))?,
+        None => Vec::new(),
+    };
+    let additional_attributes = match schema.get("additionalProperties") {
+        Some(Value::Bool(b)) => *b,
+        Some(_) => return Err(unsupported(path, "`additionalProperties` with a schema")),
+        None => false,
+    };
+    let mut attributes: BTreeMap<SmolStr, TypeOfAttribute> = BTreeMap::new();
+    if let Some(properties) = schema.get("properties") {
+        let props_path = format!("{path}/properties");
+        for (name, prop) in as_object(properties, &props_path)? {
+            attributes.insert(
+                name.into(),
+                TypeOfAttribute {
+                    ty: convert_type(prop, &format!("{props_path}/{name}"))?.into(),
+                    required: required.contains(&name.as_str()),
+                    computed: false,
+                    example: None,
+                    required_if: None,
+                    unit: None,
+                },
+            );
+        }
+    }
+    if let Some(missing) = required.iter().find(|r| !attributes.contains_key(**r)) {
+        return Err(unsupported(
+            path,
+            &format!("required property `{missing}` without a declared type"),
+        ));
+    }
+    Ok(SchemaTypeVariant::Record {
+        attributes,
+        additional_attributes,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn convert_definitions() {
+        let fragment = SchemaFragment::from_json_schema(
+            &json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "$defs": {
+                    "User": {
+                        "type": "object",
+                        "description": "A user",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "age": { "type": "integer" },
+                            "tags": { "type": "array", "items": { "type": "string" } },
+                            "manager": { "$ref": "#/$defs/User" },
+                            "address": {
+                                "type": "object",
+                                "properties": { "city": { "type": "string" } },
+                                "required": ["city"]
+                            }
+                        },
+                        "required": ["name", "age"]
+                    }
+                }
+            }),
+            "App",
+        )
+        .expect("conversion should succeed");
+        let expected = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "age": { "type": "Long" },
+                                "tags": {
+                                    "type": "Set",
+                                    "element": { "type": "String" },
+                                    "required": false
+                                },
+                                "manager": { "type": "Entity", "name": "User", "required": false },
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": { "city": { "type": "String" } },
+                                    "required": false
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(fragment.0, expected.0);
+    }
+
+    #[test]
+    fn convert_titled_root() {
+        let fragment = SchemaFragment::from_json_schema(
+            &json!({
+                "title": "Photo",
+                "type": "object",
+                "properties": { "public": { "type": "boolean" } },
+                "additionalProperties": true
+            }),
+            "",
+        )
+        .expect("conversion should succeed");
+        assert_eq!(
+            fragment.0[""].entity_types["Photo"].shape.0,
+            SchemaType::Type(SchemaTypeVariant::Record {
+                attributes: BTreeMap::from([(
+                    "public".into(),
+                    TypeOfAttribute {
+                        ty: SchemaType::Type(SchemaTypeVariant::Boolean).into(),
+                        required: false,
+                        computed: false,
+                        example: None,
+                        required_if: None,
+                        unit: None
+                    }
+                )]),
+                additional_attributes: true
+            })
+        );
+    }
+
+    #[test]
+    fn unsupported_constructs() {
+        let cases = [
+            (
+                json!({ "type": "object", "properties": { "id": { "oneOf": [] } } }),
+                "#/$defs/T/properties/id",
+            ),
+            (
+                json!({ "type": "object", "patternProperties": { "^x": { "type": "string" } } }),
+                "#/$defs/T",
+            ),
+            (
+                json!({ "type": "object", "properties": { "n": { "type": "number" } } }),
+                "#/$defs/T/properties/n",
+            ),
+        ];
+        for (def, expected_path) in cases {
+            match SchemaFragment::from_json_schema(&json!({ "$defs": { "T": def } }), "") {
+                Err(SchemaError::UnsupportedJsonSchema(path, _)) => {
+                    assert_eq!(path, expected_path)
+                }
+                r => panic!("Expected an unsupported construct error, got {:?}", r),
+            }
+        }
+    }
+}
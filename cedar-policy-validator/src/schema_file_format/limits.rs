@@ -0,0 +1,253 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Size limits for schemas read from untrusted sources.
+
+use super::{NamespaceDefinition, SchemaFragment, SchemaType, SchemaTypeVariant};
+use crate::{Result, SchemaError};
+
+/// Limits on the size of a schema, checked by
+/// `SchemaFragment::from_json_value_with_limits`. The `Default` limits are
+/// far larger than any schema written by hand, but still small enough to
+/// bound the work done on a malicious schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of entity types, summed over all namespaces.
+    pub max_entity_types: usize,
+    /// Maximum number of actions, summed over all namespaces.
+    pub max_actions: usize,
+    /// Maximum number of record attributes, summed over all common types,
+    /// entity shapes and action contexts (including nested records).
+    pub max_attributes: usize,
+    /// Maximum nesting depth of the JSON document.
+    pub max_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_entity_types: 10_000,
+            max_actions: 10_000,
+            max_attributes: 100_000,
+            max_depth: 64,
+        }
+    }
+}
+
+impl SchemaFragment {
+    /// Create a `SchemaFragment` from a JSON value, as `from_json_value`
+    /// does, but fail with `SchemaError::SchemaLimitExceeded` if the schema
+    /// exceeds any of `limits`. The depth of the JSON document is checked
+    /// before it is parsed, and the other limits are checked immediately
+    /// after parsing, before any other processing of the schema.
+    pub fn from_json_value_with_limits(json: serde_json::Value, limits: Limits) -> Result<Self> {
+        check_limit("max_depth", json_depth(&json), limits.max_depth)?;
+        let fragment = Self::from_json_value(json)?;
+        let namespaces = fragment.0.values();
+        check_limit(
+            "max_entity_types",
+            namespaces.clone().map(|ns| ns.entity_types.len()).sum(),
+            limits.max_entity_types,
+        )?;
+        check_limit(
+            "max_actions",
+            namespaces.clone().map(|ns| ns.actions.len()).sum(),
+            limits.max_actions,
+        )?;
+        let mut attributes = 0;
+        for (_, ty) in fragment
+            .0
+            .iter()
+            .flat_map(|(ns, ns_def)| ns_def.declared_type_paths(ns))
+        {
+            ty.walk(&mut |ty| {
+                if let SchemaType::Type(SchemaTypeVariant::Record {
+                    attributes: attrs, ..
+                }) = ty
+                {
+                    attributes += attrs.len();
+                }
+            });
+        }
+        check_limit("max_attributes", attributes, limits.max_attributes)?;
+        Ok(fragment)
+    }
+}
+
+impl NamespaceDefinition {
+    /// Check that no record type in this namespace, whether an entity shape,
+    /// action context, common type, or a record nested inside one of these,
+    /// has more than `max_attributes` attributes. Fails with
+    /// `SchemaError::RecordTooWide` for the first such record found. Paths in
+    /// the error are relative to this namespace.
+    pub fn validate_record_width(&self, max_attributes: usize) -> Result<()> {
+        let mut too_wide = None;
+        for (mut path, ty) in self.declared_type_paths("") {
+            ty.walk_with_path(&mut path, &mut |path, ty| {
+                if let SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) = ty {
+                    if too_wide.is_none() && attributes.len() > max_attributes {
+                        too_wide = Some((path.clone(), attributes.len()));
+                    }
+                }
+            });
+            if let Some((path, count)) = too_wide {
+                return Err(SchemaError::RecordTooWide(path, count, max_attributes));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_limit(name: &str, actual: usize, limit: usize) -> Result<()> {
+    if actual > limit {
+        Err(SchemaError::SchemaLimitExceeded(name.to_string(), limit))
+    } else {
+        Ok(())
+    }
+}
+
+/// Nesting depth of a JSON value, computed without recursion so that a
+/// deeply nested document cannot overflow the stack. Scalars have depth 0.
+fn json_depth(json: &serde_json::Value) -> usize {
+    let mut max_depth = 0;
+    let mut stack = vec![(json, 0)];
+    while let Some((value, depth)) = stack.pop() {
+        max_depth = max_depth.max(depth);
+        match value {
+            serde_json::Value::Array(elements) => {
+                stack.extend(elements.iter().map(|v| (v, depth + 1)));
+            }
+            serde_json::Value::Object(fields) => {
+                stack.extend(fields.values().map(|v| (v, depth + 1)));
+            }
+            _ => (),
+        }
+    }
+    max_depth
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> serde_json::Value {
+        json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": { "city": { "type": "String" } }
+                                }
+                            }
+                        }
+                    },
+                    "Group": {}
+                },
+                "actions": { "view": {} }
+            }
+        })
+    }
+
+    #[test]
+    fn within_limits() {
+        SchemaFragment::from_json_value_with_limits(schema(), Limits::default())
+            .expect("schema is within the default limits");
+    }
+
+    #[test]
+    fn exceeded_limits() {
+        let cases = [
+            (
+                Limits {
+                    max_entity_types: 1,
+                    ..Limits::default()
+                },
+                "max_entity_types",
+            ),
+            (
+                Limits {
+                    max_actions: 0,
+                    ..Limits::default()
+                },
+                "max_actions",
+            ),
+            (
+                Limits {
+                    max_attributes: 2,
+                    ..Limits::default()
+                },
+                "max_attributes",
+            ),
+            (
+                Limits {
+                    max_depth: 8,
+                    ..Limits::default()
+                },
+                "max_depth",
+            ),
+        ];
+        for (limits, expected) in cases {
+            match SchemaFragment::from_json_value_with_limits(schema(), limits) {
+                Err(SchemaError::SchemaLimitExceeded(limit, _)) => assert_eq!(limit, expected),
+                r => panic!("Expected {expected} to be exceeded, got {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn record_width() {
+        let fragment = SchemaFragment::from_json_value(schema()).expect("Parse Error");
+        let ns_def = &fragment.0["App"];
+        ns_def
+            .validate_record_width(2)
+            .expect("no record has more than two attributes");
+        match ns_def.validate_record_width(1) {
+            Err(SchemaError::RecordTooWide(path, count, max)) => {
+                assert_eq!((path.to_string().as_str(), count, max), ("User", 2, 1));
+            }
+            r => panic!("Expected a record to be too wide, got {:?}", r),
+        }
+        let tags: NamespaceDefinition = serde_json::from_value(json!({
+            "entityTypes": {
+                "Doc": {
+                    "tags": {
+                        "type": "Record",
+                        "attributes": {
+                            "a": { "type": "Long" },
+                            "b": { "type": "Long" },
+                            "c": { "type": "Long" }
+                        }
+                    }
+                }
+            },
+            "actions": {}
+        }))
+        .expect("Parse Error");
+        match tags.validate_record_width(2) {
+            Err(SchemaError::RecordTooWide(path, 3, 2)) => {
+                assert_eq!(path.to_string(), "tags Doc")
+            }
+            r => panic!("Expected the tags to be too wide, got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,982 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Configurable lints over the schema file format. Lints never prevent a
+//! schema from being used; they flag declarations which are legal but likely
+//! to be mistakes or which go against common conventions.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+
+use super::{
+    fully_qualify, ApplySpec, NamespaceDefinition, SchemaFix, SchemaFragment, SchemaType,
+    SchemaTypeVariant, TypeOfAttribute, TypePath,
+};
+
+/// The individual lints which can be run over a `SchemaFragment`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SchemaLint {
+    /// An action has an empty `principalTypes` list, so it can never apply to
+    /// any request. Actions without `principalTypes`, which apply to every
+    /// principal, are not reported.
+    NoApplicablePrincipals,
+    /// An action has an empty `resourceTypes` list, so it can never apply to
+    /// any request. Actions without `resourceTypes`, which apply to every
+    /// resource, are not reported.
+    NoApplicableResources,
+    /// A record or entity shape is declared with `additionalAttributes`.
+    OpenRecord,
+    /// An entity type or common type name is not written in `UpperCamelCase`.
+    TypeNaming,
+    /// An open record wraps only a few scalar or extension attributes, which
+    /// suggests it was meant to be a scalar or extension type itself. The
+    /// number of attributes is set by
+    /// `LintProfile::with_open_wrapper_max_attributes`.
+    SuspiciousOpenWrapper,
+    /// An action has an attribute with the same name as an attribute of its
+    /// context, so `action.name` and `context.name` are easily confused in
+    /// policies. Contexts which cannot be resolved are skipped.
+    ActionAttributeContextCollision,
+    /// A set of booleans, which can hold at most `true` and `false` and was
+    /// usually meant to be a set of enumerated strings. Sets nested inside
+    /// other types, including other sets, are reported too.
+    SetOfBoolean,
+    /// An action applies to a principal or resource type which an action it
+    /// is a member of does not apply to, so policies on the parent do not
+    /// cover every request the child can be authorized for. An action
+    /// without `appliesTo`, `principalTypes` or `resourceTypes` applies to
+    /// every type. Every action an action is transitively a member of is
+    /// checked, following `memberOf` entries without a `type` or with the
+    /// action type of the namespace, e.g. `App::Action` in namespace `App`.
+    MemberActionWiderThanParent,
+    /// A string enumeration whose values all look like booleans, e.g.
+    /// `["true", "false"]`, which was usually meant to be a `Boolean`. Values
+    /// are compared ignoring case against the set given by
+    /// `LintProfile::with_boolean_like_values`, which by default is `true`,
+    /// `false`, `yes`, `no`, `0` and `1`.
+    BooleanAsStringEnum,
+}
+
+impl SchemaLint {
+    /// All lints, in the order they are run.
+    pub fn all() -> impl Iterator<Item = SchemaLint> {
+        [
+            Self::NoApplicablePrincipals,
+            Self::NoApplicableResources,
+            Self::OpenRecord,
+            Self::TypeNaming,
+            Self::SuspiciousOpenWrapper,
+            Self::ActionAttributeContextCollision,
+            Self::SetOfBoolean,
+            Self::MemberActionWiderThanParent,
+            Self::BooleanAsStringEnum,
+        ]
+        .into_iter()
+    }
+
+    /// The level used for this lint when a profile does not configure it.
+    pub fn default_level(self) -> LintLevel {
+        match self {
+            Self::NoApplicablePrincipals
+            | Self::NoApplicableResources
+            | Self::OpenRecord
+            | Self::SuspiciousOpenWrapper
+            | Self::ActionAttributeContextCollision
+            | Self::SetOfBoolean
+            | Self::MemberActionWiderThanParent
+            | Self::BooleanAsStringEnum => LintLevel::Warn,
+            Self::TypeNaming => LintLevel::Allow,
+        }
+    }
+}
+
+/// How a lint finding is treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LintLevel {
+    /// The lint is not run.
+    Allow,
+    /// Findings are reported but do not fail the report.
+    Warn,
+    /// Findings are reported and fail the report.
+    Deny,
+}
+
+/// Selects which lints are run and at what level, the thresholds used by
+/// heuristic lints, and the fixes `SchemaFragment::autofix` may apply. Lints
+/// which are not mentioned in the profile use their default level, so an
+/// empty profile (which is also the `Default`) runs the default set of lints
+/// and enables no fixes. A profile is serialized as an object with the keys
+/// `levels`, `openWrapperMaxAttributes`, `booleanLikeValues` and `fixes`,
+/// any of which may be left out to use its default, e.g.
+///
+/// ```json
+/// { "levels": { "OpenRecord": "deny" }, "fixes": ["ExtensionCasing"] }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct LintProfile {
+    #[serde(default)]
+    levels: BTreeMap<SchemaLint, LintLevel>,
+    #[serde(default = "open_wrapper_max_attributes_default")]
+    open_wrapper_max_attributes: usize,
+    #[serde(default = "boolean_like_values_default")]
+    boolean_like_values: BTreeSet<SmolStr>,
+    #[serde(default)]
+    fixes: BTreeSet<SchemaFix>,
+}
+
+impl Default for LintProfile {
+    fn default() -> Self {
+        Self {
+            levels: BTreeMap::new(),
+            open_wrapper_max_attributes: open_wrapper_max_attributes_default(),
+            boolean_like_values: boolean_like_values_default(),
+            fixes: BTreeSet::new(),
+        }
+    }
+}
+
+fn open_wrapper_max_attributes_default() -> usize {
+    1
+}
+
+fn boolean_like_values_default() -> BTreeSet<SmolStr> {
+    ["true", "false", "yes", "no", "0", "1"]
+        .into_iter()
+        .map(SmolStr::from)
+        .collect()
+}
+
+impl LintProfile {
+    /// Set the level for a lint, overriding its default.
+    pub fn with_level(mut self, lint: SchemaLint, level: LintLevel) -> Self {
+        self.levels.insert(lint, level);
+        self
+    }
+
+    /// The level at which a lint will be run with this profile.
+    pub fn level(&self, lint: SchemaLint) -> LintLevel {
+        self.levels
+            .get(&lint)
+            .copied()
+            .unwrap_or_else(|| lint.default_level())
+    }
+
+    /// Set the largest number of attributes an open record may have to be
+    /// reported by `SchemaLint::SuspiciousOpenWrapper`. The default is 1.
+    pub fn with_open_wrapper_max_attributes(mut self, max: usize) -> Self {
+        self.open_wrapper_max_attributes = max;
+        self
+    }
+
+    /// Set the values which `SchemaLint::BooleanAsStringEnum` treats as
+    /// booleans. They are compared with enumeration values ignoring case.
+    pub fn with_boolean_like_values(
+        mut self,
+        values: impl IntoIterator<Item = impl Into<SmolStr>>,
+    ) -> Self {
+        self.boolean_like_values = values
+            .into_iter()
+            .map(|value| value.into().to_lowercase().into())
+            .collect();
+        self
+    }
+
+    /// Allow `SchemaFragment::autofix` to apply `fix`.
+    pub fn with_fix(mut self, fix: SchemaFix) -> Self {
+        self.fixes.insert(fix);
+        self
+    }
+
+    /// True if `SchemaFragment::autofix` may apply `fix` with this profile.
+    pub fn fix_enabled(&self, fix: SchemaFix) -> bool {
+        self.fixes.contains(&fix)
+    }
+}
+
+/// A single problem found by a lint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    lint: SchemaLint,
+    level: LintLevel,
+    namespace: SmolStr,
+    path: Option<TypePath>,
+    message: String,
+}
+
+impl LintFinding {
+    /// The lint which produced this finding.
+    pub fn lint(&self) -> SchemaLint {
+        self.lint
+    }
+
+    /// The level the lint was run at.
+    pub fn level(&self) -> LintLevel {
+        self.level
+    }
+
+    /// The namespace containing the declaration this finding is about.
+    pub fn namespace(&self) -> &SmolStr {
+        &self.namespace
+    }
+
+    /// The type this finding is about, for lints which find problems in
+    /// types rather than in actions or declaration names.
+    pub fn path(&self) -> Option<&TypePath> {
+        self.path.as_ref()
+    }
+
+    /// Description of the problem.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self.level {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => "warning",
+            LintLevel::Deny => "error",
+        };
+        write!(f, "{level}[{:?}]: {}", self.lint, self.message)?;
+        if !self.namespace.is_empty() {
+            write!(f, " in namespace `{}`", self.namespace)?;
+        }
+        Ok(())
+    }
+}
+
+/// All findings from running the lints selected by a `LintProfile`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintReport {
+    findings: Vec<LintFinding>,
+}
+
+impl LintReport {
+    /// Iterate over the findings in the report.
+    pub fn findings(&self) -> impl Iterator<Item = &LintFinding> {
+        self.findings.iter()
+    }
+
+    /// True if no lint reported anything.
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// True if any finding came from a lint run at `LintLevel::Deny`.
+    pub fn has_denied(&self) -> bool {
+        self.findings.iter().any(|f| f.level == LintLevel::Deny)
+    }
+}
+
+impl SchemaFragment {
+    /// Run every lint enabled by `profile` over this fragment, collecting all
+    /// findings into a single report. Namespaces and declarations are visited
+    /// in sorted order so that the report is deterministic.
+    pub fn lint_with_profile(&self, profile: &LintProfile) -> LintReport {
+        let mut findings = Vec::new();
+        for lint in SchemaLint::all() {
+            let level = profile.level(lint);
+            if level == LintLevel::Allow {
+                continue;
+            }
+            for (namespace, ns_def) in self.0.iter().sorted_by_key(|(ns, _)| *ns) {
+                for (path, message) in ns_def.lint_messages(self, namespace, lint, profile) {
+                    findings.push(LintFinding {
+                        lint,
+                        level,
+                        namespace: namespace.clone(),
+                        path,
+                        message,
+                    });
+                }
+            }
+        }
+        LintReport { findings }
+    }
+}
+
+impl NamespaceDefinition {
+    /// Run a single lint over this namespace, which is named `ns` in
+    /// `fragment`, returning a message for each problem found, along with the
+    /// path to the type it was found in, if any.
+    fn lint_messages(
+        &self,
+        fragment: &SchemaFragment,
+        ns: &str,
+        lint: SchemaLint,
+        profile: &LintProfile,
+    ) -> Vec<(Option<TypePath>, String)> {
+        match lint {
+            SchemaLint::NoApplicablePrincipals | SchemaLint::NoApplicableResources => {
+                let principals = lint == SchemaLint::NoApplicablePrincipals;
+                let (key, kind) = if principals {
+                    ("principalTypes", "principal")
+                } else {
+                    ("resourceTypes", "resource")
+                };
+                self.actions
+                    .iter()
+                    .sorted_by_key(|(name, _)| *name)
+                    .filter(|(_, action)| {
+                        action.applies_to.as_ref().is_some_and(|applies_to| {
+                            let tys = if principals {
+                                &applies_to.principal_types
+                            } else {
+                                &applies_to.resource_types
+                            };
+                            tys.as_ref().is_some_and(Vec::is_empty)
+                        })
+                    })
+                    .map(|(name, _)| {
+                        let message = format!(
+                            "action `{name}` has an empty `{key}` list, so it applies to no \
+                             {kind} and cannot apply to any request"
+                        );
+                        (None, message)
+                    })
+                    .collect()
+            }
+            SchemaLint::OpenRecord => {
+                let mut messages: Vec<(Option<TypePath>, String)> = Vec::new();
+                for (mut path, ty) in self.declared_type_paths(ns) {
+                    let root = path.root().clone();
+                    let mut open = None;
+                    ty.walk_with_path(&mut path, &mut |path, ty| {
+                        if let SchemaType::Type(SchemaTypeVariant::Record {
+                            additional_attributes: true,
+                            ..
+                        }) = ty
+                        {
+                            open.get_or_insert_with(|| path.clone());
+                        }
+                    });
+                    // An entity type's `additionalAttributesDefinition` shares
+                    // the path of its shape, so only the first is reported.
+                    let reported = messages
+                        .last()
+                        .and_then(|(path, _)| path.as_ref())
+                        .is_some_and(|path| *path.root() == root);
+                    if let (Some(open), false) = (open, reported) {
+                        let message = format!(
+                            "{} contains a record with `additionalAttributes`",
+                            open.declaration()
+                        );
+                        messages.push((Some(open), message));
+                    }
+                }
+                messages
+            }
+            SchemaLint::TypeNaming => self
+                .entity_types
+                .keys()
+                .map(|name| ("entity type", name))
+                .chain(self.common_types.keys().map(|name| ("common type", name)))
+                .filter(|(_, name)| !is_upper_camel_case(name))
+                .sorted()
+                .map(|(kind, name)| {
+                    (
+                        None,
+                        format!("{kind} `{name}` is not written in UpperCamelCase"),
+                    )
+                })
+                .collect(),
+            SchemaLint::SuspiciousOpenWrapper => {
+                let mut messages = Vec::new();
+                for (mut path, ty) in self.declared_type_paths(ns) {
+                    ty.walk_with_path(&mut path, &mut |path, ty| {
+                        if let SchemaType::Type(SchemaTypeVariant::Record {
+                            attributes,
+                            additional_attributes: true,
+                        }) = ty
+                        {
+                            let scalar = |attr_ty: &TypeOfAttribute| {
+                                matches!(
+                                    *attr_ty.ty,
+                                    SchemaType::Type(
+                                        SchemaTypeVariant::String { .. }
+                                            | SchemaTypeVariant::Long { .. }
+                                            | SchemaTypeVariant::Boolean
+                                            | SchemaTypeVariant::Extension { .. }
+                                    )
+                                )
+                            };
+                            if !attributes.is_empty()
+                                && attributes.len() <= profile.open_wrapper_max_attributes
+                                && attributes.values().all(scalar)
+                            {
+                                let message = format!(
+                                    "open record at `{path}` only wraps scalar or extension \
+                                     attributes; it may have been meant as a scalar or \
+                                     extension type"
+                                );
+                                messages.push((Some(path.clone()), message));
+                            }
+                        }
+                    });
+                }
+                messages
+            }
+            SchemaLint::ActionAttributeContextCollision => {
+                let mut messages = Vec::new();
+                for (name, action) in self.actions.iter().sorted_by_key(|(name, _)| *name) {
+                    let (Some(attributes), Some(applies_to)) =
+                        (&action.attributes, &action.applies_to)
+                    else {
+                        continue;
+                    };
+                    let Ok(SchemaType::Type(SchemaTypeVariant::Record {
+                        attributes: context,
+                        ..
+                    })) = fragment.resolve_type(ns, &applies_to.context.0)
+                    else {
+                        continue;
+                    };
+                    for attr in attributes
+                        .keys()
+                        .filter(|attr| context.contains_key(*attr))
+                        .sorted()
+                    {
+                        messages.push((
+                            None,
+                            format!(
+                                "action `{name}` has an attribute `{attr}` and a context \
+                                 attribute `{attr}`; `action.{attr}` and `context.{attr}` are \
+                                 easily confused"
+                            ),
+                        ));
+                    }
+                }
+                messages
+            }
+            SchemaLint::SetOfBoolean => {
+                let mut messages = Vec::new();
+                for (mut path, ty) in self.declared_type_paths(ns) {
+                    ty.walk_with_path(&mut path, &mut |path, ty| {
+                        if let SchemaType::Type(SchemaTypeVariant::Set { element }) = ty {
+                            if matches!(**element, SchemaType::Type(SchemaTypeVariant::Boolean)) {
+                                let message = format!(
+                                    "set of booleans at `{path}` can hold at most `true` and \
+                                     `false`; it may have been meant as a set of strings"
+                                );
+                                messages.push((Some(path.clone()), message));
+                            }
+                        }
+                    });
+                }
+                messages
+            }
+            SchemaLint::MemberActionWiderThanParent => {
+                let mut messages = Vec::new();
+                let action_parents = self.action_parents(ns);
+                for (child, action) in self.actions.iter().sorted_by_key(|(name, _)| *name) {
+                    // Every action `child` is transitively a member of, other
+                    // than itself, in sorted order.
+                    let mut ancestors = BTreeSet::new();
+                    let mut stack = action_parents[child].iter().copied().collect_vec();
+                    while let Some(parent) = stack.pop() {
+                        if parent != child && ancestors.insert(parent) {
+                            stack.extend(action_parents[parent].iter().copied());
+                        }
+                    }
+                    for parent in ancestors {
+                        let parent_action = &self.actions[parent];
+                        let child_applies_to = action.applies_to.as_ref();
+                        let parent_applies_to = parent_action.applies_to.as_ref();
+                        for principals in [true, false] {
+                            let kind = if principals { "principal" } else { "resource" };
+                            if let Some(excess) =
+                                applies_subset(ns, child_applies_to, parent_applies_to, principals)
+                            {
+                                let excess = match excess {
+                                    Some(excess) => format!(
+                                        "the {kind} types [{}]",
+                                        excess.iter().map(|ty| format!("`{ty}`")).join(", ")
+                                    ),
+                                    None => format!("every {kind} type"),
+                                };
+                                messages.push((
+                                    None,
+                                    format!(
+                                        "action `{child}` is a member of `{parent}` but applies \
+                                         to {excess}, which `{parent}` does not"
+                                    ),
+                                ));
+                            }
+                        }
+                    }
+                }
+                messages
+            }
+            SchemaLint::BooleanAsStringEnum => {
+                let mut messages = Vec::new();
+                for (mut path, ty) in self.declared_type_paths(ns) {
+                    ty.walk_with_path(&mut path, &mut |path, ty| {
+                        if let SchemaType::Type(SchemaTypeVariant::String {
+                            enumeration: Some(values),
+                            ..
+                        }) = ty
+                        {
+                            let boolean_like = |value: &SmolStr| {
+                                let value = value.to_lowercase();
+                                profile
+                                    .boolean_like_values
+                                    .iter()
+                                    .any(|like| like.to_lowercase() == value)
+                            };
+                            if !values.is_empty() && values.iter().all(boolean_like) {
+                                let message = format!(
+                                    "string enumeration at `{path}` only has the values [{}], \
+                                     which look like booleans; it may have been meant as a \
+                                     `Boolean`",
+                                    values.iter().map(|value| format!("`{value}`")).join(", ")
+                                );
+                                messages.push((Some(path.clone()), message));
+                            }
+                        }
+                    });
+                }
+                messages
+            }
+        }
+    }
+}
+
+/// Whether the principal types, or resource types if `principals` is false,
+/// which the `appliesTo` of `child` allows are a subset of those `parent`
+/// allows, where both are written in namespace `ns` and a missing list
+/// allows every entity type. Returns `None` if they are, and otherwise the
+/// sorted, fully qualified entity types `child` allows but `parent` does
+/// not, or `Some(None)` if `child` allows every entity type and `parent`
+/// does not.
+fn applies_subset(
+    ns: &str,
+    child: Option<&ApplySpec>,
+    parent: Option<&ApplySpec>,
+    principals: bool,
+) -> Option<Option<Vec<SmolStr>>> {
+    let tys = |applies_to: Option<&ApplySpec>| {
+        let applies_to = applies_to?;
+        if principals {
+            applies_to.principal_types.clone()
+        } else {
+            applies_to.resource_types.clone()
+        }
+    };
+    let (child, parent) = (tys(child), tys(parent)?);
+    let Some(child) = child else {
+        return Some(None);
+    };
+    let parent = parent
+        .iter()
+        .map(|ty| fully_qualify(ns, ty))
+        .collect::<BTreeSet<_>>();
+    let excess = child
+        .iter()
+        .map(|ty| fully_qualify(ns, ty))
+        .filter(|ty| !parent.contains(ty))
+        .sorted()
+        .dedup()
+        .collect::<Vec<_>>();
+    (!excess.is_empty()).then_some(Some(excess))
+}
+
+fn is_upper_camel_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) && !name.contains('_')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fragment() -> SchemaFragment {
+        SchemaFragment::from_json_value(serde_json::json!({
+            "App": {
+                "commonTypes": {
+                    "open_ctx": { "type": "Record", "attributes": {}, "additionalAttributes": true }
+                },
+                "entityTypes": {
+                    "User": {},
+                    "photo": {}
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": [], "resourceTypes": ["photo"] } },
+                    "edit": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["photo"] } }
+                }
+            }
+        }))
+        .expect("Parse Error")
+    }
+
+    #[test]
+    fn default_profile() {
+        let report = fragment().lint_with_profile(&LintProfile::default());
+        let lints = report.findings().map(LintFinding::lint).collect::<Vec<_>>();
+        assert_eq!(
+            lints,
+            vec![SchemaLint::NoApplicablePrincipals, SchemaLint::OpenRecord]
+        );
+        let paths = report
+            .findings()
+            .map(|f| f.path().map(ToString::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec![None, Some("type App::open_ctx".to_string())]);
+        assert!(!report.has_denied());
+    }
+
+    #[test]
+    fn configured_profile() {
+        let profile: LintProfile = serde_json::from_value(serde_json::json!({
+            "levels": { "NoApplicablePrincipals": "allow", "TypeNaming": "deny" }
+        }))
+        .expect("Parse Error");
+        let report = fragment().lint_with_profile(&profile);
+        let messages = report
+            .findings()
+            .map(|f| (f.lint(), f.message().to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    SchemaLint::OpenRecord,
+                    "common type `open_ctx` contains a record with `additionalAttributes`"
+                        .to_string()
+                ),
+                (
+                    SchemaLint::TypeNaming,
+                    "common type `open_ctx` is not written in UpperCamelCase".to_string()
+                ),
+                (
+                    SchemaLint::TypeNaming,
+                    "entity type `photo` is not written in UpperCamelCase".to_string()
+                ),
+            ]
+        );
+        assert!(report.has_denied());
+    }
+
+    #[test]
+    fn profile_round_trips() {
+        let profile = LintProfile::default()
+            .with_level(SchemaLint::OpenRecord, LintLevel::Deny)
+            .with_level(SchemaLint::TypeNaming, LintLevel::Warn)
+            .with_open_wrapper_max_attributes(2)
+            .with_boolean_like_values(["On", "off"])
+            .with_fix(SchemaFix::ExtensionCasing);
+        let json = serde_json::to_value(&profile).expect("Serialize Error");
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "levels": { "OpenRecord": "deny", "TypeNaming": "warn" },
+                "openWrapperMaxAttributes": 2,
+                "booleanLikeValues": ["off", "on"],
+                "fixes": ["ExtensionCasing"]
+            })
+        );
+        let parsed: LintProfile = serde_json::from_value(json).expect("Parse Error");
+        assert_eq!(parsed, profile);
+
+        let parsed: LintProfile =
+            serde_json::from_value(serde_json::json!({})).expect("Parse Error");
+        assert_eq!(parsed, LintProfile::default());
+        let parsed: LintProfile =
+            serde_json::from_value(serde_json::json!({ "fixes": ["DuplicateEnumValues"] }))
+                .expect("Parse Error");
+        assert!(parsed.fix_enabled(SchemaFix::DuplicateEnumValues));
+        assert!(!parsed.fix_enabled(SchemaFix::ExtensionCasing));
+        assert!(serde_json::from_value::<LintProfile>(serde_json::json!({
+            "OpenRecord": "deny"
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn suspicious_open_wrapper() {
+        let fragment = SchemaFragment::from_json_value(serde_json::json!({
+            "App": {
+                "entityTypes": {
+                    "Host": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "addr": {
+                                    "type": "Record",
+                                    "attributes": { "ip": { "type": "Extension", "name": "ipaddr" } },
+                                    "additionalAttributes": true
+                                },
+                                "meta": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "a": { "type": "String" },
+                                        "b": { "type": "Long" }
+                                    },
+                                    "additionalAttributes": true
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let wrappers = |profile: LintProfile| {
+            fragment
+                .lint_with_profile(&profile)
+                .findings()
+                .filter(|f| f.lint() == SchemaLint::SuspiciousOpenWrapper)
+                .map(|f| f.path().expect("Finding has a path").to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(wrappers(LintProfile::default()), vec!["App::Host.addr"]);
+        assert_eq!(
+            wrappers(LintProfile::default().with_open_wrapper_max_attributes(2)),
+            vec!["App::Host.addr", "App::Host.meta"]
+        );
+    }
+
+    #[test]
+    fn no_applicable_principals_or_resources() {
+        let fragment = SchemaFragment::from_json_value(serde_json::json!({
+            "App": {
+                "entityTypes": { "User": {} },
+                "actions": {
+                    "nobody": { "appliesTo": { "principalTypes": [], "resourceTypes": ["User"] } },
+                    "nothing": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": [] } },
+                    "anyone": { "appliesTo": { "resourceTypes": ["User"] } },
+                    "anything": {}
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let findings = fragment
+            .lint_with_profile(&LintProfile::default())
+            .findings()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            vec![
+                "warning[NoApplicablePrincipals]: action `nobody` has an empty `principalTypes` \
+                 list, so it applies to no principal and cannot apply to any request in \
+                 namespace `App`",
+                "warning[NoApplicableResources]: action `nothing` has an empty `resourceTypes` \
+                 list, so it applies to no resource and cannot apply to any request in \
+                 namespace `App`",
+            ]
+        );
+    }
+
+    #[test]
+    fn action_attribute_context_collision() {
+        let fragment = SchemaFragment::from_json_value(serde_json::json!({
+            "App": {
+                "commonTypes": {
+                    "Ctx": {
+                        "type": "Record",
+                        "attributes": {
+                            "region": { "type": "String" },
+                            "ip": { "type": "Extension", "name": "ipaddr" }
+                        }
+                    }
+                },
+                "entityTypes": { "User": {} },
+                "actions": {
+                    "view": {
+                        "attributes": { "region": "us-east-1", "level": 1 },
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["User"], "context": { "type": "Ctx" } }
+                    },
+                    "edit": {
+                        "attributes": { "level": 2 },
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["User"], "context": { "type": "Ctx" } }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let messages = fragment
+            .lint_with_profile(&LintProfile::default())
+            .findings()
+            .filter(|f| f.lint() == SchemaLint::ActionAttributeContextCollision)
+            .map(|f| f.message().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "action `view` has an attribute `region` and a context attribute `region`; \
+                 `action.region` and `context.region` are easily confused"
+            ]
+        );
+    }
+
+    #[test]
+    fn set_of_boolean() {
+        let fragment = SchemaFragment::from_json_value(serde_json::json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "flags": { "type": "Set", "element": { "type": "Boolean" } },
+                                "matrix": {
+                                    "type": "Set",
+                                    "element": { "type": "Set", "element": { "type": "Boolean" } }
+                                },
+                                "tags": { "type": "Set", "element": { "type": "String" } }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let paths = |profile: LintProfile| {
+            fragment
+                .lint_with_profile(&profile)
+                .findings()
+                .filter(|f| f.lint() == SchemaLint::SetOfBoolean)
+                .map(|f| f.path().expect("Finding has a path").to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            paths(LintProfile::default()),
+            vec!["App::User.flags", "App::User.matrix[]"]
+        );
+        assert!(paths(
+            LintProfile::default().with_level(SchemaLint::SetOfBoolean, LintLevel::Allow)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn boolean_as_string_enum() {
+        let fragment = SchemaFragment::from_json_value(serde_json::json!({
+            "App": {
+                "commonTypes": {
+                    "Answer": { "type": "String", "enum": ["Yes", "No"] }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "active": { "type": "String", "enum": ["true", "false"] },
+                                "flags": {
+                                    "type": "Set",
+                                    "element": { "type": "String", "enum": ["0", "1"] }
+                                },
+                                "status": { "type": "String", "enum": ["on", "off"] },
+                                "name": { "type": "String" }
+                            }
+                        },
+                        "tags": { "type": "String", "enum": ["Yes", "No"] }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let paths = |profile: LintProfile| {
+            fragment
+                .lint_with_profile(&profile)
+                .findings()
+                .filter(|f| f.lint() == SchemaLint::BooleanAsStringEnum)
+                .map(|f| f.path().expect("Finding has a path").to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            paths(LintProfile::default()),
+            vec![
+                "type App::Answer",
+                "App::User.active",
+                "App::User.flags[]",
+                "tags App::User"
+            ]
+        );
+        assert_eq!(
+            paths(LintProfile::default().with_boolean_like_values(["ON", "off"])),
+            vec!["App::User.status"]
+        );
+        assert!(paths(
+            LintProfile::default().with_level(SchemaLint::BooleanAsStringEnum, LintLevel::Allow)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn member_action_wider_than_parent() {
+        let fragment = SchemaFragment::from_json_value(serde_json::json!({
+            "App": {
+                "entityTypes": { "User": {}, "Photo": {}, "Album": {} },
+                "actions": {
+                    "read": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] } },
+                    "view": {
+                        "appliesTo": { "principalTypes": ["App::User"], "resourceTypes": ["Photo", "Album"] },
+                        "memberOf": [{ "id": "read" }]
+                    },
+                    "list": {
+                        "appliesTo": { "resourceTypes": ["Photo"] },
+                        "memberOf": [{ "id": "read" }]
+                    },
+                    "any": {},
+                    "edit": {
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Album"] },
+                        "memberOf": [{ "id": "any" }]
+                    },
+                    "thumbnail": {
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] },
+                        "memberOf": [{ "id": "view", "type": "App::Action" }]
+                    },
+                    "crop": {
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Album"] },
+                        "memberOf": [{ "id": "edit", "type": "Action" }]
+                    },
+                    "export": {
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Album"] },
+                        "memberOf": [{ "id": "view", "type": "App::Action" }]
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let messages = fragment
+            .lint_with_profile(&LintProfile::default())
+            .findings()
+            .filter(|f| f.lint() == SchemaLint::MemberActionWiderThanParent)
+            .map(|f| f.message().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "action `export` is a member of `read` but applies to the resource types \
+                 [`App::Album`], which `read` does not",
+                "action `list` is a member of `read` but applies to every principal type, \
+                 which `read` does not",
+                "action `view` is a member of `read` but applies to the resource types \
+                 [`App::Album`], which `read` does not",
+            ]
+        );
+    }
+}
@@ -0,0 +1,253 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Defines `Lockfile`, a canonical snapshot of everything a schema fragment
+//! declares, used to detect when the meaning of a schema has changed.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use cedar_policy_core::entities::JSONValue;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use smol_str::SmolStr;
+
+use super::{
+    fully_qualify, references::qualified_action, ActionEntityUID, ApplySpec, AttributesOrContext,
+    SchemaFragment, SchemaType,
+};
+use crate::Result;
+
+/// An entity type in a `Lockfile`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedEntityType {
+    /// Fully qualified parent entity types.
+    #[serde(rename = "memberOfTypes")]
+    pub member_of_types: BTreeSet<SmolStr>,
+    /// The shape, with common types inlined and entity types qualified.
+    pub shape: SchemaType,
+    /// The `enum` values, or `None` when the entity type is not declared
+    /// with `enum`.
+    #[serde(rename = "enum")]
+    pub enumeration: Option<BTreeSet<SmolStr>>,
+    /// The type of tag values, with common types inlined and entity types
+    /// qualified, or `None` when entities of this type have no tags.
+    pub tags: Option<SchemaType>,
+}
+
+/// An action in a `Lockfile`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedAction {
+    /// Fully qualified principal types, or `None` when the action applies to
+    /// any principal.
+    #[serde(rename = "principalTypes")]
+    pub principal_types: Option<BTreeSet<SmolStr>>,
+    /// Fully qualified resource types, or `None` when the action applies to
+    /// any resource.
+    #[serde(rename = "resourceTypes")]
+    pub resource_types: Option<BTreeSet<SmolStr>>,
+    /// The context, with common types inlined and entity types qualified.
+    pub context: SchemaType,
+    /// Parent actions, as fully qualified action entity UIDs.
+    #[serde(rename = "memberOf")]
+    pub member_of: BTreeSet<SmolStr>,
+    /// Action attributes.
+    pub attributes: BTreeMap<SmolStr, JSONValue>,
+}
+
+/// A fully resolved, canonical snapshot of a schema fragment, as produced by
+/// `SchemaFragment::to_lockfile`. Every name is fully qualified, common types
+/// are inlined, and omitted parts of declarations are written out with their
+/// default values, so two fragments which mean the same thing produce equal
+/// lockfiles. The lockfile also records a hash of its contents, which survives
+/// serializing and deserializing the lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lockfile {
+    /// Entity types by fully qualified name.
+    #[serde(rename = "entityTypes")]
+    pub entity_types: BTreeMap<SmolStr, LockedEntityType>,
+    /// Actions by fully qualified action entity UID, e.g. `App::Action::"view"`.
+    pub actions: BTreeMap<SmolStr, LockedAction>,
+    /// Hex encoded SHA-256 hash of the entity types and actions.
+    pub hash: String,
+}
+
+impl Lockfile {
+    fn new(
+        entity_types: BTreeMap<SmolStr, LockedEntityType>,
+        actions: BTreeMap<SmolStr, LockedAction>,
+    ) -> Result<Self> {
+        let hash = Self::content_hash(&entity_types, &actions)?;
+        Ok(Self {
+            entity_types,
+            actions,
+            hash,
+        })
+    }
+
+    fn content_hash(
+        entity_types: &BTreeMap<SmolStr, LockedEntityType>,
+        actions: &BTreeMap<SmolStr, LockedAction>,
+    ) -> Result<String> {
+        // Action attribute values may contain maps with no fixed order, so
+        // objects are sorted to make the serialization canonical.
+        let mut canonical = serde_json::json!({
+            "entityTypes": serde_json::to_value(entity_types)?,
+            "actions": serde_json::to_value(actions)?,
+        });
+        canonical.sort_all_objects();
+        let digest = Sha256::digest(canonical.to_string());
+        Ok(digest.iter().map(|byte| format!("{byte:02x}")).collect())
+    }
+
+    /// Check that the recorded hash matches the contents, i.e., that the
+    /// lockfile has not been edited since it was generated.
+    pub fn hash_matches(&self) -> bool {
+        Self::content_hash(&self.entity_types, &self.actions).is_ok_and(|hash| hash == self.hash)
+    }
+}
+
+impl SchemaFragment {
+    /// A `Lockfile` capturing the resolved meaning of this fragment. Fails if
+    /// a common type is undeclared or defined in terms of itself, or if an
+    /// entity type's additional attributes cannot be added to its shape.
+    pub fn to_lockfile(&self) -> Result<Lockfile> {
+        let mut entity_types = BTreeMap::new();
+        let mut actions = BTreeMap::new();
+        for (ns, ns_def) in &self.0 {
+            for (name, entity_type) in &ns_def.entity_types {
+                let locked = LockedEntityType {
+                    member_of_types: entity_type
+                        .member_of_types
+                        .iter()
+                        .map(|parent| fully_qualify(ns, parent))
+                        .collect(),
+                    shape: SchemaType::Type(entity_type.resolved_shape(self, ns)?),
+                    enumeration: entity_type
+                        .enumeration
+                        .as_ref()
+                        .map(|values| values.iter().cloned().collect()),
+                    tags: entity_type
+                        .tags
+                        .as_ref()
+                        .map(|tags| self.resolve_type(ns, tags))
+                        .transpose()?,
+                };
+                entity_types.insert(fully_qualify(ns, name), locked);
+            }
+            for (name, action) in &ns_def.actions {
+                let declared = ActionEntityUID::from_declared_name(name);
+                let ty = declared.ty.map(|ty| fully_qualify(ns, &ty));
+                let applies_to = action.applies_to.clone().unwrap_or(ApplySpec {
+                    resource_types: None,
+                    principal_types: None,
+                    context: AttributesOrContext::default(),
+                });
+                let qualify_all = |tys: Option<Vec<SmolStr>>| {
+                    tys.map(|tys| tys.iter().map(|ty| fully_qualify(ns, ty)).collect())
+                };
+                let locked = LockedAction {
+                    principal_types: qualify_all(applies_to.principal_types),
+                    resource_types: qualify_all(applies_to.resource_types),
+                    context: self.resolve_type(ns, &applies_to.context.0)?,
+                    member_of: action
+                        .member_of
+                        .iter()
+                        .flatten()
+                        .map(|parent| qualified_action(ns, parent.ty.as_ref(), &parent.id))
+                        .collect(),
+                    attributes: action
+                        .attributes
+                        .clone()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect(),
+                };
+                actions.insert(qualified_action(ns, ty.as_ref(), &declared.id), locked);
+            }
+        }
+        Lockfile::new(entity_types, actions)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn equivalent_fragments() {
+        let written = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Profile": { "type": "Record", "attributes": { "name": { "type": "String" } } }
+                },
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Group", "App::Group"], "shape": { "type": "Profile" } },
+                    "Group": {}
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User"] } },
+                    "edit": { "memberOf": [{ "id": "view" }] }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let explicit = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["App::Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "name": { "type": "String", "required": true } },
+                            "additionalAttributes": false
+                        }
+                    },
+                    "Group": { "shape": { "type": "Record", "attributes": {} } }
+                },
+                "actions": {
+                    "Action::\"view\"": {
+                        "appliesTo": {
+                            "principalTypes": ["App::User"],
+                            "context": { "type": "Record", "attributes": {} }
+                        },
+                        "memberOf": []
+                    },
+                    "edit": { "memberOf": [{ "id": "view", "type": "App::Action" }], "attributes": {} }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let lockfile = written.to_lockfile().expect("resolves");
+        assert_eq!(lockfile, explicit.to_lockfile().expect("resolves"));
+        assert!(lockfile.actions[r#"App::Action::"view""#]
+            .resource_types
+            .is_none());
+
+        let round_tripped: Lockfile =
+            serde_json::from_str(&serde_json::to_string(&lockfile).unwrap()).unwrap();
+        assert_eq!(round_tripped, lockfile);
+        assert!(round_tripped.hash_matches());
+
+        let mut edited = round_tripped;
+        edited.entity_types.remove("App::Group");
+        assert!(!edited.hash_matches());
+    }
+}
@@ -0,0 +1,722 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Merging of schema fragments which may declare the same entity types,
+//! common types and actions.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+
+use itertools::Itertools;
+use semver::Version;
+use smol_str::SmolStr;
+
+use super::references::qualified_action;
+use super::{
+    fully_qualify, ActionEntityUID, ActionType, ApplySpec, AttributesOrContext, EntityType,
+    NamespaceDefinition, SchemaFragment, SchemaType, SchemaTypeVariant, SharedSchemaType,
+    TypeOfAttribute, TypePath, TypePathRoot, TypePathSegment,
+};
+use crate::{Result, SchemaError};
+
+/// How `SchemaFragment::merge_with` handles a declaration which appears in
+/// both fragments with different definitions. Declarations which are
+/// identical in both fragments are never a conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Fail with a duplicate declaration error.
+    StrictReject,
+    /// Combine the two definitions. Record types keep the union of their
+    /// attributes (an attribute is only required if both sides require it),
+    /// entity types and actions keep the union of their parents and of the
+    /// entity types they apply to, and only genuinely incompatible types (e.g.,
+    /// `Long` and `String`) are an error.
+    DeepMerge,
+    /// Use the definition from the fragment being merged in.
+    RightWins,
+    /// Combine two definitions of the same action which differ only in their
+    /// `memberOf` lists, keeping the parents from both. Parents are compared
+    /// by their qualified entity UIDs, so `{"id": "read"}` and
+    /// `{"id": "read", "type": "App::Action"}` in namespace `App` are the
+    /// same parent. Differing `appliesTo` or `attributes` are a merge
+    /// conflict, and any other declaration which differs is rejected as by
+    /// `StrictReject`.
+    UnionActionParents,
+}
+
+impl SchemaFragment {
+    /// Merge `other` into this fragment, resolving declarations that appear
+    /// in both according to `strategy`.
+    pub fn merge_with(mut self, other: SchemaFragment, strategy: MergeStrategy) -> Result<Self> {
+        for (ns, theirs) in other.0.into_iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
+            match self.0.entry(ns) {
+                Entry::Vacant(e) => {
+                    e.insert(theirs);
+                }
+                Entry::Occupied(mut e) => {
+                    let ns = e.key().clone();
+                    e.get_mut().merge_with(&ns, theirs, strategy)?;
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Combine this fragment with `other`, which must declare different
+    /// namespaces. Namespaces declared by both are not merged; the first of
+    /// them in sorted order is reported with `SchemaError::DuplicateNamespace`,
+    /// as when a namespace appears twice in a single schema file. Use
+    /// `merge_with` to merge the contents of shared namespaces.
+    pub fn merge(mut self, other: SchemaFragment) -> Result<Self> {
+        if let Some(ns) = other.0.keys().filter(|ns| self.0.contains_key(*ns)).min() {
+            return Err(SchemaError::DuplicateNamespace(ns.to_string()));
+        }
+        self.0.extend(other.0);
+        Ok(self)
+    }
+
+    /// Combine `fragments`, such as the fragments loaded from a directory of
+    /// schema files, with `merge`, failing if any namespace is declared by
+    /// more than one of them. No fragments give an empty fragment.
+    pub fn merge_all(fragments: impl IntoIterator<Item = SchemaFragment>) -> Result<Self> {
+        fragments
+            .into_iter()
+            .try_fold(SchemaFragment(HashMap::new()), SchemaFragment::merge)
+    }
+
+    /// Split this fragment into one fragment per namespace, keyed by
+    /// namespace name. References to other namespaces are left as they are
+    /// written, so a piece may not be valid on its own, but merging the
+    /// pieces back together with `merge_with` gives this fragment.
+    pub fn split_by_namespace(&self) -> BTreeMap<SmolStr, SchemaFragment> {
+        self.0
+            .iter()
+            .map(|(ns, ns_def)| {
+                let piece = SchemaFragment(HashMap::from([(ns.clone(), ns_def.clone())]));
+                (ns.clone(), piece)
+            })
+            .collect()
+    }
+}
+
+impl NamespaceDefinition {
+    fn merge_with(
+        &mut self,
+        ns: &str,
+        other: NamespaceDefinition,
+        strategy: MergeStrategy,
+    ) -> Result<()> {
+        let type_strategy = match strategy {
+            MergeStrategy::UnionActionParents => MergeStrategy::StrictReject,
+            strategy => strategy,
+        };
+        merge_decls(
+            &mut self.common_types,
+            other.common_types,
+            type_strategy,
+            |name| SchemaError::DuplicateCommonType(fully_qualify(ns, name).to_string()),
+            |name, ours, theirs| {
+                let path = TypePath::new(TypePathRoot::CommonType(fully_qualify(ns, name)));
+                unify(&ours, &theirs, &path)
+            },
+        )?;
+        merge_decls(
+            &mut self.entity_types,
+            other.entity_types,
+            type_strategy,
+            |name| SchemaError::DuplicateEntityType(fully_qualify(ns, name).to_string()),
+            |name, ours, theirs| {
+                let path = TypePath::new(TypePathRoot::EntityType(fully_qualify(ns, name)));
+                Ok(EntityType {
+                    member_of_types: union(ours.member_of_types, theirs.member_of_types),
+                    shape: AttributesOrContext(unify(&ours.shape.0, &theirs.shape.0, &path)?),
+                    additional_attributes_definition: match (
+                        ours.additional_attributes_definition,
+                        theirs.additional_attributes_definition,
+                    ) {
+                        (Some(ours), Some(theirs)) => {
+                            Some(AttributesOrContext(unify(&ours.0, &theirs.0, &path)?))
+                        }
+                        (ours, theirs) => ours.or(theirs),
+                    },
+                    // An entity type declared without `enum` may have any
+                    // entities, so only two enumerations combine to one.
+                    enumeration: match (ours.enumeration, theirs.enumeration) {
+                        (Some(ours), Some(theirs)) => Some(union(ours, theirs)),
+                        _ => None,
+                    },
+                    tags: match (ours.tags, theirs.tags) {
+                        (Some(ours), Some(theirs)) => Some(unify(&ours, &theirs, &path)?),
+                        (ours, theirs) => ours.or(theirs),
+                    },
+                    since: earliest(ours.since, theirs.since),
+                })
+            },
+        )?;
+        merge_decls(
+            &mut self.actions,
+            other.actions,
+            strategy,
+            |name| SchemaError::DuplicateAction(qualified_action(ns, None, name).to_string()),
+            |name, ours, theirs| match strategy {
+                MergeStrategy::UnionActionParents => merge_action_parents(ns, name, ours, theirs),
+                _ => merge_actions(ns, name, ours, theirs),
+            },
+        )
+    }
+}
+
+/// Add the declarations in `theirs` to `ours`, calling `deep_merge` to
+/// combine two different definitions of the same name when `strategy` is
+/// `DeepMerge`.
+fn merge_decls<T: PartialEq>(
+    ours: &mut HashMap<SmolStr, T>,
+    theirs: HashMap<SmolStr, T>,
+    strategy: MergeStrategy,
+    duplicate: impl Fn(&SmolStr) -> SchemaError,
+    deep_merge: impl Fn(&SmolStr, T, T) -> Result<T>,
+) -> Result<()> {
+    for (name, theirs) in theirs.into_iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
+        match ours.remove(&name) {
+            Some(ours_def) if ours_def != theirs => {
+                let merged = match strategy {
+                    MergeStrategy::StrictReject => return Err(duplicate(&name)),
+                    MergeStrategy::DeepMerge | MergeStrategy::UnionActionParents => {
+                        deep_merge(&name, ours_def, theirs)?
+                    }
+                    MergeStrategy::RightWins => theirs,
+                };
+                ours.insert(name, merged);
+            }
+            _ => {
+                ours.insert(name, theirs);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn merge_actions(
+    ns: &str,
+    name: &SmolStr,
+    ours: ActionType,
+    theirs: ActionType,
+) -> Result<ActionType> {
+    let conflict = |msg: &str| {
+        SchemaError::MergeConflict(
+            qualified_action(ns, None, name).to_string(),
+            msg.to_string(),
+        )
+    };
+    let attributes = match (ours.attributes, theirs.attributes) {
+        (Some(a), Some(b)) if a != b => return Err(conflict("action attributes differ")),
+        (a, b) => a.or(b),
+    };
+    let applies_to = match (ours.applies_to, theirs.applies_to) {
+        (None, None) => None,
+        (a, b) => {
+            // A missing `appliesTo` applies to any principal and resource,
+            // with an empty context.
+            let any = || ApplySpec {
+                resource_types: None,
+                principal_types: None,
+                context: AttributesOrContext::default(),
+            };
+            let (a, b) = (a.unwrap_or_else(any), b.unwrap_or_else(any));
+            let union_types =
+                |a: Option<Vec<SmolStr>>, b: Option<Vec<SmolStr>>| Some(union(a?, b?));
+            let path = TypePath::new(TypePathRoot::ActionContext {
+                ty: fully_qualify(ns, "Action"),
+                id: name.clone(),
+            });
+            Some(ApplySpec {
+                principal_types: union_types(a.principal_types, b.principal_types),
+                resource_types: union_types(a.resource_types, b.resource_types),
+                context: AttributesOrContext(unify(&a.context.0, &b.context.0, &path)?),
+            })
+        }
+    };
+    let member_of = match (ours.member_of, theirs.member_of) {
+        (Some(a), Some(b)) => {
+            let mut member_of = a;
+            for parent in b {
+                if !member_of.contains(&parent) {
+                    member_of.push(parent);
+                }
+            }
+            Some(member_of)
+        }
+        (a, b) => a.or(b),
+    };
+    Ok(ActionType {
+        attributes,
+        applies_to,
+        member_of,
+        since: earliest(ours.since, theirs.since),
+    })
+}
+
+/// Combine two definitions of the action `name` in namespace `ns` for
+/// `MergeStrategy::UnionActionParents`.
+fn merge_action_parents(
+    ns: &str,
+    name: &SmolStr,
+    ours: ActionType,
+    theirs: ActionType,
+) -> Result<ActionType> {
+    let conflict = |msg: &str| {
+        SchemaError::MergeConflict(
+            qualified_action(ns, None, name).to_string(),
+            msg.to_string(),
+        )
+    };
+    if ours.applies_to != theirs.applies_to {
+        return Err(conflict("`appliesTo` differs"));
+    }
+    if ours.attributes != theirs.attributes {
+        return Err(conflict("action attributes differ"));
+    }
+    let member_of = match (ours.member_of, theirs.member_of) {
+        (Some(a), Some(b)) => {
+            let qualified =
+                |parent: &ActionEntityUID| qualified_action(ns, parent.ty.as_ref(), &parent.id);
+            let mut member_of = a;
+            for parent in b {
+                if !member_of.iter().any(|p| qualified(p) == qualified(&parent)) {
+                    member_of.push(parent);
+                }
+            }
+            Some(member_of)
+        }
+        (a, b) => a.or(b),
+    };
+    Ok(ActionType {
+        member_of,
+        since: earliest(ours.since, theirs.since),
+        ..ours
+    })
+}
+
+/// The earlier of two `since` versions, since a declaration made in both
+/// fragments was introduced when the first of them introduced it.
+fn earliest(ours: Option<Version>, theirs: Option<Version>) -> Option<Version> {
+    ours.into_iter().chain(theirs).min()
+}
+
+/// The elements of `ours` followed by any elements of `theirs` not already
+/// present.
+fn union<T: PartialEq>(mut ours: Vec<T>, theirs: Vec<T>) -> Vec<T> {
+    for name in theirs {
+        if !ours.contains(&name) {
+            ours.push(name);
+        }
+    }
+    ours
+}
+
+/// The most specific type which accepts every value accepted by either of
+/// two types for the same declaration, or a `SchemaError::MergeConflict`
+/// naming `path` if no such type exists in the schema language. Records
+/// unify attribute by attribute, and an attribute is only required in the
+/// result if it is required by both records. Two string enumerations unify
+/// to the union of their values, and a string enumeration unifies with an
+/// unrestricted string to an unrestricted string. A string `pattern` is kept only
+/// if both types have the same one, and two longs unify to the smallest
+/// range containing both of their ranges. Common types are not resolved,
+/// so two different common type names do not unify.
+pub(crate) fn unify(ours: &SchemaType, theirs: &SchemaType, path: &TypePath) -> Result<SchemaType> {
+    if ours == theirs {
+        return Ok(ours.clone());
+    }
+    let variant = match (ours, theirs) {
+        (
+            SchemaType::Type(SchemaTypeVariant::Set { element: ours }),
+            SchemaType::Type(SchemaTypeVariant::Set { element: theirs }),
+        ) => SchemaTypeVariant::Set {
+            element: SharedSchemaType::new(unify(
+                ours,
+                theirs,
+                &path.child(TypePathSegment::SetElement),
+            )?),
+        },
+        (
+            SchemaType::Type(SchemaTypeVariant::String {
+                enumeration: ours,
+                pattern: ours_pattern,
+            }),
+            SchemaType::Type(SchemaTypeVariant::String {
+                enumeration: theirs,
+                pattern: theirs_pattern,
+            }),
+        ) => SchemaTypeVariant::String {
+            enumeration: match (ours, theirs) {
+                (Some(ours), Some(theirs)) => Some(union(ours.clone(), theirs.clone())),
+                _ => None,
+            },
+            pattern: ours_pattern
+                .clone()
+                .filter(|_| ours_pattern == theirs_pattern),
+        },
+        (
+            SchemaType::Type(SchemaTypeVariant::Long {
+                min: ours_min,
+                max: ours_max,
+            }),
+            SchemaType::Type(SchemaTypeVariant::Long {
+                min: theirs_min,
+                max: theirs_max,
+            }),
+        ) => SchemaTypeVariant::Long {
+            min: ours_min
+                .zip(*theirs_min)
+                .map(|(ours, theirs)| ours.min(theirs)),
+            max: ours_max
+                .zip(*theirs_max)
+                .map(|(ours, theirs)| ours.max(theirs)),
+        },
+        (
+            SchemaType::Type(SchemaTypeVariant::Record {
+                attributes: ours,
+                additional_attributes: ours_open,
+            }),
+            SchemaType::Type(SchemaTypeVariant::Record {
+                attributes: theirs,
+                additional_attributes: theirs_open,
+            }),
+        ) => {
+            let mut attributes = BTreeMap::new();
+            for attr in ours.keys().chain(theirs.keys()).unique() {
+                let attr_ty = match (ours.get(attr), theirs.get(attr)) {
+                    (Some(a), Some(b)) => TypeOfAttribute {
+                        ty: unify(
+                            &a.ty,
+                            &b.ty,
+                            &path.child(TypePathSegment::Attribute(attr.clone())),
+                        )?
+                        .into(),
+                        // A `requiredIf` condition is kept only if both sides
+                        // agree on it; otherwise the attribute is required
+                        // only if both sides always require it.
+                        required: a.required
+                            && b.required
+                            && a.required_if.is_none()
+                            && b.required_if.is_none(),
+                        computed: a.computed || b.computed,
+                        example: a.example.clone().or_else(|| b.example.clone()),
+                        required_if: a
+                            .required_if
+                            .clone()
+                            .filter(|_| a.required_if == b.required_if),
+                        unit: a.unit.clone().or_else(|| b.unit.clone()),
+                    },
+                    (Some(a), None) | (None, Some(a)) => TypeOfAttribute {
+                        required: false,
+                        required_if: None,
+                        ..a.clone()
+                    },
+                    (None, None) => unreachable!("attribute comes from one of the records"),
+                };
+                attributes.insert(attr.clone(), attr_ty);
+            }
+            SchemaTypeVariant::Record {
+                attributes,
+                additional_attributes: *ours_open || *theirs_open,
+            }
+        }
+        (
+            SchemaType::Type(SchemaTypeVariant::Union { options: ours }),
+            SchemaType::Type(SchemaTypeVariant::Union { options: theirs }),
+        ) => SchemaTypeVariant::Union {
+            options: union(ours.clone(), theirs.clone()),
+        },
+        _ => {
+            return Err(SchemaError::MergeConflict(
+                path.to_string(),
+                format!(
+                    "types {} and {} are incompatible",
+                    describe(ours),
+                    describe(theirs)
+                ),
+            ))
+        }
+    };
+    Ok(SchemaType::Type(variant))
+}
+
+fn describe(ty: &SchemaType) -> String {
+    match ty {
+        SchemaType::TypeDef { type_name } => format!("`{type_name}`"),
+        SchemaType::Type(SchemaTypeVariant::String { .. }) => "`String`".to_string(),
+        SchemaType::Type(SchemaTypeVariant::Long { .. }) => "`Long`".to_string(),
+        SchemaType::Type(SchemaTypeVariant::Boolean) => "`Boolean`".to_string(),
+        SchemaType::Type(SchemaTypeVariant::Set { .. }) => "`Set`".to_string(),
+        SchemaType::Type(SchemaTypeVariant::Record { .. }) => "`Record`".to_string(),
+        SchemaType::Type(SchemaTypeVariant::Entity { name }) => format!("`Entity` `{name}`"),
+        SchemaType::Type(SchemaTypeVariant::Extension { name, .. }) => {
+            format!("`Extension` `{name}`")
+        }
+        SchemaType::Type(SchemaTypeVariant::Union { .. }) => "`Union`".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn user_fragment(attributes: serde_json::Value, parents: serde_json::Value) -> SchemaFragment {
+        SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": parents,
+                        "shape": { "type": "Record", "attributes": attributes }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error")
+    }
+
+    fn left() -> SchemaFragment {
+        user_fragment(
+            json!({
+                "name": { "type": "String" },
+                "age": { "type": "Long" },
+                "tags": { "type": "Set", "element": { "type": "String" } }
+            }),
+            json!(["Group"]),
+        )
+    }
+
+    #[test]
+    fn identical_declarations_never_conflict() {
+        let merged = left()
+            .merge_with(left(), MergeStrategy::StrictReject)
+            .expect("identical fragments should merge");
+        assert_eq!(merged, left());
+    }
+
+    #[test]
+    fn strategies() {
+        let right = user_fragment(
+            json!({
+                "name": { "type": "String" },
+                "email": { "type": "String" }
+            }),
+            json!(["Team"]),
+        );
+        assert!(matches!(
+            left().merge_with(right.clone(), MergeStrategy::StrictReject),
+            Err(SchemaError::DuplicateEntityType(name)) if name == "App::User"
+        ));
+        assert_eq!(
+            left()
+                .merge_with(right.clone(), MergeStrategy::RightWins)
+                .expect("right wins never conflicts"),
+            right
+        );
+        let expected = user_fragment(
+            json!({
+                "name": { "type": "String" },
+                "age": { "type": "Long", "required": false },
+                "email": { "type": "String", "required": false },
+                "tags": { "type": "Set", "element": { "type": "String" }, "required": false }
+            }),
+            json!(["Group", "Team"]),
+        );
+        assert_eq!(
+            left()
+                .merge_with(right, MergeStrategy::DeepMerge)
+                .expect("compatible shapes should merge"),
+            expected
+        );
+    }
+
+    #[test]
+    fn deep_merge_incompatible() {
+        let right = user_fragment(
+            json!({ "tags": { "type": "Set", "element": { "type": "Long" } } }),
+            json!([]),
+        );
+        match left().merge_with(right, MergeStrategy::DeepMerge) {
+            Err(SchemaError::MergeConflict(path, msg)) => {
+                assert_eq!(path, "App::User.tags[]");
+                assert_eq!(msg, "types `String` and `Long` are incompatible");
+            }
+            r => panic!("Expected a merge conflict, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn deep_merge_long_bounds() {
+        let bounded = |age: serde_json::Value| user_fragment(json!({ "age": age }), json!([]));
+        assert_eq!(
+            bounded(json!({ "type": "Long", "min": 0, "max": 120 }))
+                .merge_with(
+                    bounded(json!({ "type": "Long", "min": 18, "max": 150 })),
+                    MergeStrategy::DeepMerge
+                )
+                .expect("longs should merge"),
+            bounded(json!({ "type": "Long", "min": 0, "max": 150 }))
+        );
+        assert_eq!(
+            bounded(json!({ "type": "Long", "min": 0, "max": 120 }))
+                .merge_with(
+                    bounded(json!({ "type": "Long", "max": 100 })),
+                    MergeStrategy::DeepMerge
+                )
+                .expect("longs should merge"),
+            bounded(json!({ "type": "Long", "max": 120 }))
+        );
+    }
+
+    #[test]
+    fn deep_merge_enum_entity_types() {
+        let fragment = |region: serde_json::Value| {
+            SchemaFragment::from_json_value(json!({
+                "App": { "entityTypes": { "Region": region }, "actions": {} }
+            }))
+            .expect("Parse Error")
+        };
+        let merge = |ours, theirs| {
+            fragment(ours)
+                .merge_with(fragment(theirs), MergeStrategy::DeepMerge)
+                .expect("entity types should merge")
+        };
+        assert_eq!(
+            merge(json!({ "enum": ["us"] }), json!({ "enum": ["eu", "us"] })),
+            fragment(json!({ "enum": ["us", "eu"] }))
+        );
+        assert_eq!(
+            merge(
+                json!({ "enum": ["us"] }),
+                json!({ "memberOfTypes": ["Zone"] })
+            ),
+            fragment(json!({ "memberOfTypes": ["Zone"] }))
+        );
+    }
+
+    #[test]
+    fn union_action_parents() {
+        let view = |member_of: serde_json::Value, principals: serde_json::Value| {
+            SchemaFragment::from_json_value(json!({
+                "App": {
+                    "entityTypes": { "User": {}, "Admin": {} },
+                    "actions": {
+                        "read": {},
+                        "audited": {},
+                        "view": {
+                            "appliesTo": { "principalTypes": principals },
+                            "memberOf": member_of
+                        }
+                    }
+                }
+            }))
+            .expect("Parse Error")
+        };
+        let left = view(json!([{ "id": "read" }]), json!(["User"]));
+        let right = view(
+            json!([{ "id": "audited" }, { "id": "read", "type": "App::Action" }]),
+            json!(["User"]),
+        );
+        assert!(matches!(
+            left.clone()
+                .merge_with(right.clone(), MergeStrategy::StrictReject),
+            Err(SchemaError::DuplicateAction(_))
+        ));
+        assert_eq!(
+            left.clone()
+                .merge_with(right, MergeStrategy::UnionActionParents)
+                .expect("only the parents differ"),
+            view(
+                json!([{ "id": "read" }, { "id": "audited" }]),
+                json!(["User"])
+            )
+        );
+
+        let other_principals = view(json!([{ "id": "audited" }]), json!(["Admin"]));
+        match left.merge_with(other_principals, MergeStrategy::UnionActionParents) {
+            Err(SchemaError::MergeConflict(action, msg)) => {
+                assert_eq!(action, r#"App::Action::"view""#);
+                assert_eq!(msg, "`appliesTo` differs");
+            }
+            r => panic!("Expected a merge conflict, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn merge_disjoint_namespaces() {
+        let fragment = |ns: &str| {
+            SchemaFragment::from_json_value(json!({
+                ns: { "entityTypes": { "User": {} }, "actions": {} }
+            }))
+            .expect("Parse Error")
+        };
+        let merged = SchemaFragment::merge_all([fragment("Auth"), fragment("App"), fragment("")])
+            .expect("namespaces are disjoint");
+        assert_eq!(
+            merged.0.keys().sorted().collect::<Vec<_>>(),
+            vec!["", "App", "Auth"]
+        );
+        assert!(matches!(
+            merged.merge(fragment("App")),
+            Err(SchemaError::DuplicateNamespace(ns)) if ns == "App"
+        ));
+        assert_eq!(
+            SchemaFragment::merge_all([]).expect("nothing to merge"),
+            SchemaFragment(HashMap::new())
+        );
+    }
+
+    #[test]
+    fn split_by_namespace() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "Photo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "owner": { "type": "Entity", "name": "Auth::User" } }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": { "principalTypes": ["Auth::User"], "resourceTypes": ["Photo"] }
+                    }
+                }
+            },
+            "Auth": {
+                "entityTypes": { "User": {} },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let pieces = fragment.split_by_namespace();
+        assert_eq!(pieces.keys().collect::<Vec<_>>(), vec!["App", "Auth"]);
+        assert_eq!(pieces["Auth"].0.keys().collect::<Vec<_>>(), vec!["Auth"]);
+        let merged = pieces
+            .into_values()
+            .try_fold(SchemaFragment(HashMap::new()), |merged, piece| {
+                merged.merge_with(piece, MergeStrategy::StrictReject)
+            })
+            .expect("pieces should merge");
+        assert_eq!(merged, fragment);
+    }
+}
@@ -0,0 +1,205 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Rewriting of the names referenced in a schema fragment into their
+//! canonical spelling, so that names can be compared as strings.
+
+use std::collections::HashMap;
+
+use cedar_policy_core::parser::{err::ParseError, parse_name};
+use smol_str::SmolStr;
+
+use super::{SchemaFragment, SchemaType, SchemaTypeVariant};
+use crate::{Result, SchemaError};
+
+impl SchemaFragment {
+    /// Rewrite every name referenced in this fragment into its canonical
+    /// spelling, with no whitespace and with `::` between the components of
+    /// a qualified name, so that e.g. `Photo :: User` becomes `Photo::User`.
+    /// This covers `memberOfTypes`, `principalTypes` and `resourceTypes`,
+    /// the types of action `memberOf` entries, entity type names and common
+    /// type references inside types, and the targets of `imports`. Fails
+    /// with `SchemaError::EntityTypeParseError`, or
+    /// `SchemaError::CommonTypeParseError` for a common type reference, if a
+    /// name is not a valid Cedar path, in which case the fragment is left
+    /// unchanged. Declared names and namespace names are not changed.
+    pub fn normalize_name_syntax(&mut self) -> Result<()> {
+        let mut canonical = HashMap::new();
+        for ns_def in self.0.values() {
+            let mut types = Vec::new();
+            for ty in ns_def.common_types.values() {
+                types.push(ty);
+            }
+            for entity_type in ns_def.entity_types.values() {
+                types.push(&entity_type.shape.0);
+                types.extend(
+                    entity_type
+                        .additional_attributes_definition
+                        .iter()
+                        .map(|additional| &additional.0),
+                );
+                types.extend(entity_type.tags.iter());
+                for parent in &entity_type.member_of_types {
+                    canonicalize(&mut canonical, parent, SchemaError::EntityTypeParseError)?;
+                }
+            }
+            for action in ns_def.actions.values() {
+                if let Some(applies_to) = &action.applies_to {
+                    types.push(&applies_to.context.0);
+                    for ty in applies_to
+                        .principal_types
+                        .iter()
+                        .chain(applies_to.resource_types.iter())
+                        .flatten()
+                    {
+                        canonicalize(&mut canonical, ty, SchemaError::EntityTypeParseError)?;
+                    }
+                }
+                for parent in action.member_of.iter().flatten() {
+                    if let Some(ty) = &parent.ty {
+                        canonicalize(&mut canonical, ty, SchemaError::EntityTypeParseError)?;
+                    }
+                }
+            }
+            for target in ns_def.imports.values() {
+                canonicalize(&mut canonical, target, SchemaError::EntityTypeParseError)?;
+            }
+            for ty in types {
+                let mut names = Vec::new();
+                ty.walk(&mut |ty| match ty {
+                    SchemaType::TypeDef { type_name } => names.push((type_name, true)),
+                    SchemaType::Type(SchemaTypeVariant::Entity { name }) => {
+                        names.push((name, false))
+                    }
+                    SchemaType::Type(_) => (),
+                });
+                for (name, common_type) in names {
+                    if common_type {
+                        canonicalize(&mut canonical, name, SchemaError::CommonTypeParseError)?;
+                    } else {
+                        canonicalize(&mut canonical, name, SchemaError::EntityTypeParseError)?;
+                    }
+                }
+            }
+        }
+
+        let rename = |name: &SmolStr| canonical.get(name).unwrap_or(name).clone();
+        for ns_def in self.0.values_mut() {
+            ns_def.rename_references(&rename);
+        }
+        Ok(())
+    }
+}
+
+/// Record the canonical spelling of `name` in `canonical`, failing with the
+/// parse errors wrapped by `err` if it is not a valid Cedar path.
+fn canonicalize(
+    canonical: &mut HashMap<SmolStr, SmolStr>,
+    name: &SmolStr,
+    err: fn(Vec<ParseError>) -> SchemaError,
+) -> Result<()> {
+    if !canonical.contains_key(name) {
+        let parsed = parse_name(name).map_err(err)?;
+        canonical.insert(name.clone(), parsed.to_string().into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn normalize_name_syntax() {
+        let mut fragment = SchemaFragment::from_json_value(json!({
+            "Photo": {
+                "commonTypes": {
+                    "Owner": { "type": "Entity", "name": " Photo :: User " }
+                },
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Photo ::Team"] },
+                    "Team": {},
+                    "Album": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "owner": { "type": "Photo:: Owner" } }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["  Photo::User"],
+                            "resourceTypes": ["Album\t"]
+                        },
+                        "memberOf": [{ "id": "read", "type": "Photo :: Action" }]
+                    },
+                    "read": {}
+                }
+            }
+        }))
+        .expect("Parse Error");
+        fragment.normalize_name_syntax().expect("Valid names");
+        let expected = SchemaFragment::from_json_value(json!({
+            "Photo": {
+                "commonTypes": {
+                    "Owner": { "type": "Entity", "name": "Photo::User" }
+                },
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Photo::Team"] },
+                    "Team": {},
+                    "Album": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "owner": { "type": "Photo::Owner" } }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["Photo::User"],
+                            "resourceTypes": ["Album"]
+                        },
+                        "memberOf": [{ "id": "read", "type": "Photo::Action" }]
+                    },
+                    "read": {}
+                }
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(fragment, expected);
+    }
+
+    #[test]
+    fn invalid_name() {
+        let mut fragment = SchemaFragment::from_json_value(json!({
+            "": {
+                "entityTypes": { "User": { "memberOfTypes": ["Team::"] } },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let before = fragment.clone();
+        assert!(matches!(
+            fragment.normalize_name_syntax(),
+            Err(SchemaError::EntityTypeParseError(_))
+        ));
+        assert_eq!(fragment, before);
+    }
+}
@@ -0,0 +1,205 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Regular expressions constraining the values of `String` types.
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::SchemaTypeVariant;
+
+/// The regular expression given by the `pattern` property of a `String`
+/// type, e.g. `{ "type": "String", "pattern": "^[a-z]+$" }`. A string
+/// matches if the expression matches any part of it, so a pattern which
+/// must match the whole string should be anchored with `^` and `$`.
+///
+/// The expression is compiled when the schema is parsed, so a schema with an
+/// invalid pattern does not parse. Patterns are compared, and serialized, as
+/// the expression they were written as.
+#[derive(Debug, Clone)]
+pub struct StringPattern(Regex);
+
+impl StringPattern {
+    /// Compile `pattern` in the syntax of the `regex` crate.
+    pub fn new(pattern: &str) -> std::result::Result<Self, regex::Error> {
+        Regex::new(pattern).map(Self)
+    }
+
+    /// The expression as it was written.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// The compiled expression.
+    pub fn regex(&self) -> &Regex {
+        &self.0
+    }
+
+    /// True if `s` matches this pattern.
+    pub fn is_match(&self, s: &str) -> bool {
+        self.0.is_match(s)
+    }
+}
+
+impl PartialEq for StringPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for StringPattern {}
+
+impl PartialOrd for StringPattern {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for StringPattern {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl std::fmt::Display for StringPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for StringPattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for StringPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Self::new(&pattern)
+            .map_err(|e| serde::de::Error::custom(format!("invalid `pattern` \"{pattern}\": {e}")))
+    }
+}
+
+impl SchemaTypeVariant {
+    /// The compiled `pattern` of a `String` type, or `None` for a string
+    /// without a pattern or any other type. The expression is compiled once,
+    /// when the schema is parsed.
+    pub fn compiled_pattern(&self) -> Option<&Regex> {
+        match self {
+            Self::String {
+                pattern: Some(pattern),
+                ..
+            } => Some(pattern.regex()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{AttributeValidationMode, SchemaError, SchemaFragment, SchemaType};
+
+    #[test]
+    fn string_pattern() {
+        let ty: SchemaType =
+            serde_json::from_value(json!({ "type": "String", "pattern": "^[a-z]+$" }))
+                .expect("Parse Error");
+        let SchemaType::Type(variant) = &ty else {
+            panic!("Expected a builtin type, got {:?}", ty);
+        };
+        let regex = variant
+            .compiled_pattern()
+            .expect("the string has a pattern");
+        assert!(regex.is_match("alice"));
+        assert!(!regex.is_match("Alice"));
+        assert_eq!(
+            serde_json::to_value(&ty).expect("Serialize Error"),
+            json!({ "type": "String", "pattern": "^[a-z]+$" })
+        );
+
+        let unconstrained: SchemaTypeVariant =
+            serde_json::from_value(json!({ "type": "String" })).expect("Parse Error");
+        assert!(unconstrained.compiled_pattern().is_none());
+    }
+
+    #[test]
+    fn pattern_values() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "email": { "type": "String", "pattern": "^[^@]+@[^@]+$" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let user = &schema.0["App"].entity_types["User"];
+        let validate = |email: &str| {
+            user.validate_attributes(
+                &json!({ "email": email }),
+                &schema,
+                "App",
+                AttributeValidationMode::StoredValidation,
+            )
+        };
+        validate("alice@example.com").expect("matches the pattern");
+        match validate("alice") {
+            Err(SchemaError::ValueTypeMismatch(path, msg)) => {
+                assert_eq!(path, "attrs.email");
+                assert_eq!(
+                    msg,
+                    r#"expected a string matching "^[^@]+@[^@]+$", found "alice""#
+                );
+            }
+            r => panic!("Expected a value type mismatch, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let err = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "email": { "type": "String", "pattern": "^[a-z+$" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect_err("the pattern does not compile");
+        assert!(
+            err.to_string().contains("invalid `pattern` \"^[a-z+$\""),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,212 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The roles each entity type can play in each action, as a grid for access
+//! reviews.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+
+use super::csv::csv_row;
+use super::references::qualified_action;
+use super::{fully_qualify, SchemaFragment};
+
+/// The role an entity type can play in a request for an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// The entity type can be the principal, but not the resource.
+    Principal,
+    /// The entity type can be the resource, but not the principal.
+    Resource,
+    /// The entity type can be both the principal and the resource.
+    Both,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Principal => "principal",
+            Self::Resource => "resource",
+            Self::Both => "both",
+        })
+    }
+}
+
+/// The role of every entity type in every action of a schema fragment, as
+/// produced by `SchemaFragment::permission_matrix`. Entity types are named
+/// fully qualified, and actions by their fully qualified entity UIDs, e.g.
+/// `App::Action::"view"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PermissionMatrix {
+    /// Every entity type in the matrix, in sorted order.
+    #[serde(rename = "entityTypes")]
+    entity_types: BTreeSet<SmolStr>,
+    /// Every action in the matrix, in sorted order.
+    actions: BTreeSet<SmolStr>,
+    /// The role of each entity type in each action, leaving out the pairs
+    /// where it has none.
+    roles: BTreeMap<SmolStr, BTreeMap<SmolStr, Role>>,
+}
+
+impl PermissionMatrix {
+    /// The entity types in the matrix, in sorted order.
+    pub fn entity_types(&self) -> impl Iterator<Item = &SmolStr> {
+        self.entity_types.iter()
+    }
+
+    /// The actions in the matrix, in sorted order.
+    pub fn actions(&self) -> impl Iterator<Item = &SmolStr> {
+        self.actions.iter()
+    }
+
+    /// The role the entity type `entity_ty` can play in requests for
+    /// `action`, or `None` if it can be neither the principal nor the
+    /// resource, or either is not in the matrix.
+    pub fn role_of(&self, entity_ty: &str, action: &str) -> Option<Role> {
+        self.roles.get(entity_ty)?.get(action).copied()
+    }
+
+    /// The matrix as CSV, with a header row naming the actions, and then one
+    /// row per entity type giving its role in each action, or an empty field
+    /// if it has none. Rows end with `\n`, and fields are quoted as described
+    /// in RFC 4180.
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            csv_row(std::iter::once("entity type").chain(self.actions.iter().map(SmolStr::as_str)));
+        for entity_ty in &self.entity_types {
+            let roles = self
+                .actions
+                .iter()
+                .map(|action| {
+                    self.role_of(entity_ty, action)
+                        .map(|role| role.to_string())
+                        .unwrap_or_default()
+                })
+                .collect_vec();
+            csv.push_str(&csv_row(
+                std::iter::once(entity_ty.as_str()).chain(roles.iter().map(String::as_str)),
+            ));
+        }
+        csv
+    }
+}
+
+impl SchemaFragment {
+    /// The role of every entity type in every action declared in this
+    /// fragment. An action which applies to any principal or any resource,
+    /// because it does not list `principalTypes` or `resourceTypes`, gives
+    /// that role to every entity type declared in this fragment. Entity
+    /// types which are listed by an action but not declared are included.
+    pub fn permission_matrix(&self) -> PermissionMatrix {
+        let declared: BTreeSet<SmolStr> = self
+            .0
+            .iter()
+            .flat_map(|(ns, ns_def)| {
+                ns_def
+                    .entity_types
+                    .keys()
+                    .map(move |name| fully_qualify(ns, name))
+            })
+            .collect();
+        let mut matrix = PermissionMatrix {
+            entity_types: declared.clone(),
+            ..PermissionMatrix::default()
+        };
+        for (ns, ns_def) in &self.0 {
+            for (id, action) in &ns_def.actions {
+                let uid = qualified_action(ns, None, id);
+                let applies_to = action.applies_to.as_ref();
+                let expand = |tys: Option<&Vec<SmolStr>>| -> BTreeSet<SmolStr> {
+                    match tys {
+                        Some(tys) => tys.iter().map(|ty| fully_qualify(ns, ty)).collect(),
+                        None => declared.clone(),
+                    }
+                };
+                let principals = expand(applies_to.and_then(|a| a.principal_types.as_ref()));
+                let resources = expand(applies_to.and_then(|a| a.resource_types.as_ref()));
+                for entity_ty in principals.union(&resources) {
+                    let role = match (
+                        principals.contains(entity_ty),
+                        resources.contains(entity_ty),
+                    ) {
+                        (true, true) => Role::Both,
+                        (true, false) => Role::Principal,
+                        _ => Role::Resource,
+                    };
+                    matrix
+                        .roles
+                        .entry(entity_ty.clone())
+                        .or_default()
+                        .insert(uid.clone(), role);
+                    matrix.entity_types.insert(entity_ty.clone());
+                }
+                matrix.actions.insert(uid);
+            }
+        }
+        matrix
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn permission_matrix() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": { "User": {}, "Group": {}, "Photo": {} },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] } },
+                    "share": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["User", "Photo"] } },
+                    "audit": { "appliesTo": { "principalTypes": ["Group"] } }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let matrix = fragment.permission_matrix();
+        let view = r#"App::Action::"view""#;
+        let share = r#"App::Action::"share""#;
+        let audit = r#"App::Action::"audit""#;
+        assert_eq!(matrix.role_of("App::User", view), Some(Role::Principal));
+        assert_eq!(matrix.role_of("App::Photo", view), Some(Role::Resource));
+        assert_eq!(matrix.role_of("App::Group", view), None);
+        assert_eq!(matrix.role_of("App::User", share), Some(Role::Both));
+        assert_eq!(matrix.role_of("App::Group", audit), Some(Role::Both));
+        assert_eq!(matrix.role_of("App::Photo", audit), Some(Role::Resource));
+        assert_eq!(matrix.role_of("App::Album", view), None);
+
+        assert_eq!(
+            matrix.to_csv(),
+            "entity type,\"App::Action::\"\"audit\"\"\",\"App::Action::\"\"share\"\"\",\
+             \"App::Action::\"\"view\"\"\"\n\
+             App::Group,both,,\n\
+             App::Photo,resource,resource,resource\n\
+             App::User,resource,both,principal\n"
+        );
+        let round_trip: PermissionMatrix =
+            serde_json::from_value(serde_json::to_value(&matrix).expect("Serialize Error"))
+                .expect("Parse Error");
+        assert_eq!(round_trip, matrix);
+    }
+}
@@ -0,0 +1,357 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Profiles a namespace can declare to restrict which schema features it may
+//! use.
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    json_pointer, NamespaceDefinition, SchemaFragment, SchemaLocation, SchemaType,
+    SchemaTypeVariant, TypePathSegment,
+};
+use crate::{Result, SchemaError};
+
+/// A set of schema features a namespace declares that it keeps to, written
+/// as the `profile` of the namespace. Namespaces without a profile may use
+/// every feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaProfile {
+    /// Every feature except those which leave types open: records with
+    /// `additionalAttributes`, and actions which apply to any principal or
+    /// any resource because they do not list `principalTypes` or
+    /// `resourceTypes`.
+    Strict,
+    /// Every feature.
+    Lenient,
+    /// Only the features of the original schema format, so none of
+    /// `imports`, `constants`, `since`, `additionalAttributesDefinition`,
+    /// `enum` on entity types or strings, `tags` on entity types, `pattern`
+    /// on strings, `min` and `max` on longs, or the `computed`, `example`,
+    /// `requiredIf`, `unit` and `ipKind` attribute keys, or `Union` types.
+    Legacy,
+}
+
+impl std::fmt::Display for SchemaProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Strict => "strict",
+            Self::Lenient => "lenient",
+            Self::Legacy => "legacy",
+        })
+    }
+}
+
+impl NamespaceDefinition {
+    /// The first use of a feature in this namespace, which is named `ns`,
+    /// which `profile` does not allow, as its location and a description of
+    /// the feature.
+    fn profile_violation(
+        &self,
+        ns: &str,
+        profile: SchemaProfile,
+    ) -> Option<(SchemaLocation, String)> {
+        let pointer = |path: &[&str]| SchemaLocation::Pointer(json_pointer(path));
+        let mut violations = Vec::new();
+        for (mut path, ty) in self.declared_type_paths(ns) {
+            ty.walk_with_path(&mut path, &mut |path, ty| {
+                let SchemaType::Type(variant) = ty else {
+                    return;
+                };
+                let location = || SchemaLocation::Type(path.clone());
+                match (profile, variant) {
+                    (
+                        SchemaProfile::Strict,
+                        SchemaTypeVariant::Record {
+                            additional_attributes: true,
+                            ..
+                        },
+                    ) => violations.push((location(), "`additionalAttributes`".to_string())),
+                    (
+                        SchemaProfile::Legacy,
+                        SchemaTypeVariant::Extension {
+                            ip_kind: Some(_), ..
+                        },
+                    ) => violations.push((location(), "`ipKind`".to_string())),
+                    (
+                        SchemaProfile::Legacy,
+                        SchemaTypeVariant::String {
+                            enumeration: Some(_),
+                            ..
+                        },
+                    ) => violations.push((location(), "a string `enum`".to_string())),
+                    (
+                        SchemaProfile::Legacy,
+                        SchemaTypeVariant::String {
+                            pattern: Some(_), ..
+                        },
+                    ) => violations.push((location(), "a string `pattern`".to_string())),
+                    (SchemaProfile::Legacy, SchemaTypeVariant::Long { min, max })
+                        if min.is_some() || max.is_some() =>
+                    {
+                        violations.push((location(), "`min` or `max` on a `Long`".to_string()))
+                    }
+                    (SchemaProfile::Legacy, SchemaTypeVariant::Union { .. }) => {
+                        violations.push((location(), "a `Union` type".to_string()))
+                    }
+                    (SchemaProfile::Legacy, SchemaTypeVariant::Record { attributes, .. }) => {
+                        for (attr, attr_ty) in attributes {
+                            let features = [
+                                ("computed", attr_ty.computed),
+                                ("example", attr_ty.example.is_some()),
+                                ("requiredIf", attr_ty.required_if.is_some()),
+                                ("unit", attr_ty.unit.is_some()),
+                            ];
+                            for (feature, used) in features {
+                                if used {
+                                    violations.push((
+                                        SchemaLocation::Type(
+                                            path.child(TypePathSegment::Attribute(attr.clone())),
+                                        ),
+                                        format!("`{feature}`"),
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            });
+        }
+        match profile {
+            SchemaProfile::Strict => {
+                for (name, action) in self.actions.iter().sorted_by_key(|(name, _)| *name) {
+                    let applies_to = action.applies_to.as_ref();
+                    for (key, tys) in [
+                        ("principalTypes", applies_to.map(|a| &a.principal_types)),
+                        ("resourceTypes", applies_to.map(|a| &a.resource_types)),
+                    ] {
+                        if tys.is_none_or(Option::is_none) {
+                            violations.push((
+                                pointer(&[ns, "actions", name]),
+                                format!("an action without `{key}`"),
+                            ));
+                        }
+                    }
+                }
+            }
+            SchemaProfile::Legacy => {
+                if !self.imports.is_empty() {
+                    violations.push((pointer(&[ns, "imports"]), "`imports`".to_string()));
+                }
+                if !self.constants.is_empty() {
+                    violations.push((pointer(&[ns, "constants"]), "`constants`".to_string()));
+                }
+                for (name, entity_type) in self.entity_types.iter().sorted_by_key(|(name, _)| *name)
+                {
+                    if entity_type.since.is_some() {
+                        violations.push((
+                            pointer(&[ns, "entityTypes", name, "since"]),
+                            "`since`".to_string(),
+                        ));
+                    }
+                    if entity_type.additional_attributes_definition.is_some() {
+                        violations.push((
+                            pointer(&[ns, "entityTypes", name, "additionalAttributesDefinition"]),
+                            "`additionalAttributesDefinition`".to_string(),
+                        ));
+                    }
+                    if entity_type.enumeration.is_some() {
+                        violations.push((
+                            pointer(&[ns, "entityTypes", name, "enum"]),
+                            "`enum`".to_string(),
+                        ));
+                    }
+                    if entity_type.tags.is_some() {
+                        violations.push((
+                            pointer(&[ns, "entityTypes", name, "tags"]),
+                            "`tags`".to_string(),
+                        ));
+                    }
+                }
+                for (name, action) in self.actions.iter().sorted_by_key(|(name, _)| *name) {
+                    if action.since.is_some() {
+                        violations.push((
+                            pointer(&[ns, "actions", name, "since"]),
+                            "`since`".to_string(),
+                        ));
+                    }
+                }
+            }
+            SchemaProfile::Lenient => (),
+        }
+        violations.into_iter().next()
+    }
+}
+
+impl SchemaFragment {
+    /// Check that every namespace in this fragment which declares a
+    /// `profile` only uses the features that profile allows, failing with
+    /// `SchemaError::ProfileViolation` for the first feature found which it
+    /// does not. Namespaces are checked in sorted order. An unknown profile
+    /// is already rejected when the fragment is parsed.
+    pub fn validate_against_profile(&self) -> Result<()> {
+        for (ns, ns_def) in self.0.iter().sorted_by_key(|(ns, _)| *ns) {
+            let Some(profile) = ns_def.profile else {
+                continue;
+            };
+            if let Some((location, feature)) = ns_def.profile_violation(ns, profile) {
+                return Err(SchemaError::ProfileViolation(
+                    profile.to_string(),
+                    location,
+                    feature,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn violation(profile: &str, ns_def: serde_json::Value) -> Option<(String, String)> {
+        let mut ns_def = ns_def;
+        ns_def["profile"] = json!(profile);
+        let fragment =
+            SchemaFragment::from_json_value(json!({ "App": ns_def })).expect("Parse Error");
+        match fragment.validate_against_profile() {
+            Ok(()) => None,
+            Err(SchemaError::ProfileViolation(p, location, feature)) => {
+                assert_eq!(p, profile);
+                Some((location.to_string(), feature))
+            }
+            Err(e) => panic!("Expected a profile violation, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn strict() {
+        let open = json!({
+            "entityTypes": {
+                "User": { "shape": { "type": "Record", "attributes": {}, "additionalAttributes": true } }
+            },
+            "actions": {}
+        });
+        assert_eq!(
+            violation("strict", open.clone()),
+            Some(("App::User".into(), "`additionalAttributes`".into()))
+        );
+        assert_eq!(violation("lenient", open), None);
+        let open_tags = json!({
+            "entityTypes": {
+                "Doc": { "tags": { "type": "Record", "attributes": {}, "additionalAttributes": true } }
+            },
+            "actions": {}
+        });
+        assert_eq!(
+            violation("strict", open_tags),
+            Some(("tags App::Doc".into(), "`additionalAttributes`".into()))
+        );
+        let any_resource = json!({
+            "entityTypes": { "User": {} },
+            "actions": { "view": { "appliesTo": { "principalTypes": ["User"] } } }
+        });
+        assert_eq!(
+            violation("strict", any_resource),
+            Some((
+                "/App/actions/view".into(),
+                "an action without `resourceTypes`".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn legacy() {
+        let computed = json!({
+            "entityTypes": {
+                "User": {
+                    "shape": {
+                        "type": "Record",
+                        "attributes": { "size": { "type": "Long", "computed": true } }
+                    }
+                }
+            },
+            "actions": {}
+        });
+        assert_eq!(
+            violation("legacy", computed.clone()),
+            Some(("App::User.size".into(), "`computed`".into()))
+        );
+        assert_eq!(violation("strict", computed), None);
+        let since = json!({
+            "entityTypes": { "User": { "since": "1.2.0" } },
+            "actions": {}
+        });
+        assert_eq!(
+            violation("legacy", since),
+            Some(("/App/entityTypes/User/since".into(), "`since`".into()))
+        );
+        let tags = json!({
+            "entityTypes": { "Doc": { "tags": { "type": "String" } } },
+            "actions": {}
+        });
+        assert_eq!(
+            violation("legacy", tags),
+            Some(("/App/entityTypes/Doc/tags".into(), "`tags`".into()))
+        );
+        let pattern = json!({
+            "entityTypes": {
+                "User": {
+                    "shape": {
+                        "type": "Record",
+                        "attributes": { "email": { "type": "String", "pattern": "@" } }
+                    }
+                }
+            },
+            "actions": {}
+        });
+        assert_eq!(
+            violation("legacy", pattern),
+            Some(("App::User.email".into(), "a string `pattern`".into()))
+        );
+        let bounded = json!({
+            "entityTypes": {
+                "User": {
+                    "shape": {
+                        "type": "Record",
+                        "attributes": { "age": { "type": "Long", "min": 0 } }
+                    }
+                }
+            },
+            "actions": {}
+        });
+        assert_eq!(
+            violation("legacy", bounded),
+            Some(("App::User.age".into(), "`min` or `max` on a `Long`".into()))
+        );
+    }
+
+    #[test]
+    fn unknown_profile() {
+        assert!(matches!(
+            SchemaFragment::from_json_value(json!({
+                "App": { "profile": "relaxed", "entityTypes": {}, "actions": {} }
+            })),
+            Err(SchemaError::ParseFileFormat(_))
+        ));
+    }
+}
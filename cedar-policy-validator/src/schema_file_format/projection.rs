@@ -0,0 +1,188 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Projection of a schema fragment onto the attributes every entity and
+//! context is required to have.
+
+use super::{SchemaFragment, SchemaType, SchemaTypeVariant};
+
+impl SchemaFragment {
+    /// A copy of this fragment in which every record keeps only its required
+    /// attributes, recursing into the types of the attributes kept. This
+    /// applies to common types, entity shapes,
+    /// `additionalAttributesDefinition`s and tags, and action contexts. Attributes with a `requiredIf` condition
+    /// are only conditionally required, so they are dropped too. Records
+    /// inside sets, and all other types, are unchanged.
+    ///
+    /// Closed records still reject attributes they do not declare, so data
+    /// carrying optional attributes of the full schema must have them
+    /// removed before it is validated against the projection.
+    pub fn required_only_projection(&self) -> SchemaFragment {
+        let mut projected = self.clone();
+        for (ns, ns_def) in projected.0.iter_mut() {
+            for (_, ty) in ns_def.declared_type_paths_mut(ns) {
+                *ty = required_only(ty);
+            }
+        }
+        projected
+    }
+}
+
+/// `ty` with the attributes of records which are not unconditionally
+/// required removed, recursing into the types of the remaining attributes.
+fn required_only(ty: &SchemaType) -> SchemaType {
+    match ty {
+        SchemaType::Type(SchemaTypeVariant::Record {
+            attributes,
+            additional_attributes,
+        }) => SchemaType::Type(SchemaTypeVariant::Record {
+            attributes: attributes
+                .iter()
+                .filter(|(_, attr_ty)| attr_ty.required && attr_ty.required_if.is_none())
+                .map(|(attr, attr_ty)| {
+                    let mut attr_ty = attr_ty.clone();
+                    attr_ty.ty = required_only(&attr_ty.ty).into();
+                    (attr.clone(), attr_ty)
+                })
+                .collect(),
+            additional_attributes: *additional_attributes,
+        }),
+        _ => ty.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn required_only_projection() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Address": {
+                        "type": "Record",
+                        "attributes": {
+                            "city": { "type": "String" },
+                            "zip": { "type": "String", "required": false }
+                        }
+                    }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "nickname": { "type": "String", "required": false },
+                                "profile": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "age": { "type": "Long" },
+                                        "bio": { "type": "String", "required": false }
+                                    }
+                                },
+                                "home": { "type": "Address" },
+                                "history": {
+                                    "type": "Set",
+                                    "element": {
+                                        "type": "Record",
+                                        "attributes": { "note": { "type": "String", "required": false } }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "Doc": {
+                        "tags": {
+                            "type": "Record",
+                            "attributes": {
+                                "level": { "type": "Long" },
+                                "label": { "type": "String", "required": false }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "ip": { "type": "Extension", "name": "ipaddr" },
+                                    "reason": { "type": "String", "required": false }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let expected = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Address": {
+                        "type": "Record",
+                        "attributes": { "city": { "type": "String" } }
+                    }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "profile": {
+                                    "type": "Record",
+                                    "attributes": { "age": { "type": "Long" } }
+                                },
+                                "home": { "type": "Address" },
+                                "history": {
+                                    "type": "Set",
+                                    "element": {
+                                        "type": "Record",
+                                        "attributes": { "note": { "type": "String", "required": false } }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "Doc": {
+                        "tags": {
+                            "type": "Record",
+                            "attributes": { "level": { "type": "Long" } }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "ip": { "type": "Extension", "name": "ipaddr" } }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(fragment.required_only_projection(), expected);
+    }
+}
@@ -0,0 +1,227 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Reconciliation of an authored schema fragment with one inferred from
+//! entity data, to find where the schema and the data disagree.
+
+use super::{
+    diff::describe_type, fully_qualify, DiffDeclaration, SchemaChangeKind, SchemaFragment,
+    SchemaType, TypePath, TypePathRoot,
+};
+
+/// A place where an authored schema and the schema inferred from entity data
+/// disagree, as found by `SchemaFragment::reconcile_with_inferred`. A path
+/// with no segments refers to a whole entity type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconciliationFinding {
+    /// The data has an attribute, or entities of a type, which the authored
+    /// schema does not declare.
+    UndeclaredInData {
+        /// The path to the attribute or entity type.
+        path: TypePath,
+        /// The type inferred from the data.
+        observed: SchemaType,
+    },
+    /// The authored schema declares an attribute, or an entity type, which
+    /// never appears in the data.
+    DeclaredButUnused {
+        /// The path to the attribute or entity type.
+        path: TypePath,
+    },
+    /// The type inferred from the data differs from the declared type.
+    TypeMismatch {
+        /// The path to the type.
+        path: TypePath,
+        /// The declared type.
+        declared: SchemaType,
+        /// The type inferred from the data.
+        observed: SchemaType,
+    },
+}
+
+impl std::fmt::Display for ReconciliationFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UndeclaredInData { path, observed } => write!(
+                f,
+                "`{path}` appears in the data with type `{}` but is not declared",
+                describe_type(observed)
+            ),
+            Self::DeclaredButUnused { path } => {
+                write!(f, "`{path}` is declared but never appears in the data")
+            }
+            Self::TypeMismatch {
+                path,
+                declared,
+                observed,
+            } => write!(
+                f,
+                "`{path}` is declared with type `{}` but has type `{}` in the data",
+                describe_type(declared),
+                describe_type(observed)
+            ),
+        }
+    }
+}
+
+/// All findings from reconciling an authored schema with an inferred one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconciliationReport {
+    findings: Vec<ReconciliationFinding>,
+}
+
+impl ReconciliationReport {
+    /// All findings, ordered by namespace and then by entity type.
+    pub fn findings(&self) -> &[ReconciliationFinding] {
+        &self.findings
+    }
+
+    /// True when the schema and the data agree.
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+impl SchemaFragment {
+    /// Compare the entity types of this authored fragment with those of
+    /// `inferred`, a fragment inferred from entity data by
+    /// `SchemaFragment::infer_from_entities`, reporting attributes and
+    /// entity types which appear in only one of them and attributes whose
+    /// types differ. Common types are inlined and entity type names fully
+    /// qualified on both sides first, so that only differences in meaning
+    /// are reported; a fragment whose common types cannot be inlined is
+    /// compared as written.
+    ///
+    /// Differences in `required`, `additionalAttributes` and
+    /// `memberOfTypes` are not reported, since the data only shows which
+    /// attributes and parents happened to be present. Actions and common
+    /// types are ignored.
+    pub fn reconcile_with_inferred(&self, inferred: &SchemaFragment) -> ReconciliationReport {
+        let inline = |fragment: &SchemaFragment| {
+            fragment
+                .inline_common_types()
+                .map_or_else(|_| fragment.clone(), |(inlined, _)| inlined)
+        };
+        let (authored, inferred) = (inline(self), inline(inferred));
+        let findings = authored
+            .diff(&inferred)
+            .changes()
+            .iter()
+            .filter_map(|change| {
+                let DiffDeclaration::EntityType(name) = change.declaration() else {
+                    return None;
+                };
+                let entity_path = || {
+                    TypePath::new(TypePathRoot::EntityType(fully_qualify(
+                        change.namespace(),
+                        name,
+                    )))
+                };
+                match change.kind() {
+                    SchemaChangeKind::Added => Some(ReconciliationFinding::UndeclaredInData {
+                        path: entity_path(),
+                        observed: inferred.0[change.namespace()].entity_types[name]
+                            .shape
+                            .0
+                            .clone(),
+                    }),
+                    SchemaChangeKind::Removed => Some(ReconciliationFinding::DeclaredButUnused {
+                        path: entity_path(),
+                    }),
+                    SchemaChangeKind::AttributeAdded { path, attribute } => {
+                        Some(ReconciliationFinding::UndeclaredInData {
+                            path: path.clone(),
+                            observed: (*attribute.ty).clone(),
+                        })
+                    }
+                    SchemaChangeKind::AttributeRemoved { path, .. } => {
+                        Some(ReconciliationFinding::DeclaredButUnused { path: path.clone() })
+                    }
+                    SchemaChangeKind::TypeChanged { path, old, new } => {
+                        Some(ReconciliationFinding::TypeMismatch {
+                            path: path.clone(),
+                            declared: old.clone(),
+                            observed: new.clone(),
+                        })
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+        ReconciliationReport { findings }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn reconcile_with_inferred() {
+        let authored = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": { "Name": { "type": "String" } },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "Name" },
+                                "age": { "type": "String" },
+                                "manager": { "type": "Entity", "name": "User", "required": false },
+                                "legacyId": { "type": "Long", "required": false }
+                            }
+                        }
+                    },
+                    "Archive": {}
+                },
+                "actions": { "view": {} }
+            }
+        }))
+        .expect("Parse Error");
+        let data = json!([
+            {
+                "uid": { "type": "App::User", "id": "alice" },
+                "attrs": {
+                    "name": "Alice",
+                    "age": 30,
+                    "manager": { "__entity": { "type": "App::User", "id": "bob" } },
+                    "email": "alice@example.com"
+                },
+                "parents": [{ "type": "App::Team", "id": "admins" }]
+            }
+        ]);
+        let inferred = SchemaFragment::infer_from_entities(&data, "App").expect("Inference Error");
+        let report = authored.reconcile_with_inferred(&inferred);
+        assert_eq!(
+            report
+                .findings()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "`App::Archive` is declared but never appears in the data",
+                "`App::Team` appears in the data with type `{}` but is not declared",
+                "`App::User.age` is declared with type `String` but has type `Long` in the data",
+                "`App::User.email` appears in the data with type `String` but is not declared",
+                "`App::User.legacyId` is declared but never appears in the data",
+            ]
+        );
+        assert!(authored.reconcile_with_inferred(&authored).is_empty());
+    }
+}
@@ -0,0 +1,881 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Collects the declarations made by a schema fragment and the references to
+//! other declarations, with every name fully qualified, so that references can
+//! be checked without building a `ValidatorSchema`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use cedar_policy_core::extensions::Extensions;
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{
+    fully_qualify, json_pointer, ActionEntityUID, NamespaceDefinition, SchemaFragment,
+    SchemaLocation, SchemaType, SchemaTypeVariant, TypePathRoot,
+};
+use crate::{Result, SchemaError};
+
+/// A fully qualified name of something that can be declared in a schema.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum SchemaReference {
+    EntityType(SmolStr),
+    CommonType(SmolStr),
+    /// An action entity UID, written as `App::Action::"view"`.
+    Action(SmolStr),
+}
+
+impl std::fmt::Display for SchemaReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EntityType(name) => write!(f, "entity type `{name}`"),
+            Self::CommonType(name) => write!(f, "common type `{name}`"),
+            Self::Action(euid) => write!(f, "action `{euid}`"),
+        }
+    }
+}
+
+impl SchemaReference {
+    /// The namespace this reference is qualified with, or `None` if it is
+    /// unqualified and so refers to the empty namespace.
+    pub(crate) fn namespace(&self) -> Option<&str> {
+        let name = match self {
+            Self::EntityType(name) | Self::CommonType(name) => name.as_str(),
+            // The action id is quoted and may itself contain `::`.
+            Self::Action(euid) => euid.split_once("::\"").map_or(euid.as_str(), |(ty, _)| ty),
+        };
+        name.rsplit_once("::").map(|(ns, _)| ns)
+    }
+}
+
+/// The names of the extension types available in this build.
+pub(crate) fn extension_names() -> BTreeSet<String> {
+    Extensions::all_available()
+        .ext_names()
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Qualified action entity UID for an action declared as `id` in namespace
+/// `ns`, or referenced from `ns` with an optional explicit action type.
+pub(crate) fn qualified_action(ns: &str, ty: Option<&SmolStr>, id: &str) -> SmolStr {
+    let ty = ty.cloned().unwrap_or_else(|| fully_qualify(ns, "Action"));
+    format!("{ty}::\"{id}\"").into()
+}
+
+impl NamespaceDefinition {
+    /// The names of the entity types declared in this namespace, as they are
+    /// declared, in sorted order.
+    pub fn entity_type_names(&self) -> impl Iterator<Item = &SmolStr> {
+        self.entity_types.keys().sorted()
+    }
+
+    /// The ids of the actions declared in this namespace, in sorted order.
+    pub fn action_ids(&self) -> impl Iterator<Item = &SmolStr> {
+        self.actions.keys().sorted()
+    }
+
+    /// Everything declared by this namespace, which is named `ns`.
+    pub(crate) fn declarations(&self, ns: &str) -> BTreeSet<SchemaReference> {
+        let entity_types = self
+            .entity_types
+            .keys()
+            .map(|name| SchemaReference::EntityType(fully_qualify(ns, name)));
+        let common_types = self
+            .common_types
+            .keys()
+            .map(|name| SchemaReference::CommonType(fully_qualify(ns, name)));
+        let actions = self
+            .actions
+            .keys()
+            .map(|id| SchemaReference::Action(qualified_action(ns, None, id)));
+        entity_types.chain(common_types).chain(actions).collect()
+    }
+
+    /// Everything referenced by declarations in this namespace, which is
+    /// named `ns`. This includes references to things declared in the same
+    /// namespace.
+    pub(crate) fn references(&self, ns: &str) -> BTreeSet<SchemaReference> {
+        self.located_references(ns)
+            .into_iter()
+            .map(|(_, reference)| reference)
+            .collect()
+    }
+
+    /// Every reference made by declarations in this namespace, which is named
+    /// `ns`, paired with where it was made. A reference appears once for each
+    /// place it is made. References inside types are located by `TypePath`,
+    /// and all others by a JSON pointer into the schema.
+    pub(crate) fn located_references(&self, ns: &str) -> Vec<(SchemaLocation, SchemaReference)> {
+        let mut refs = Vec::new();
+        for (mut path, ty) in self.declared_type_paths(ns) {
+            ty.walk_with_path(&mut path, &mut |path, ty| {
+                let reference = match ty {
+                    SchemaType::TypeDef { type_name } => {
+                        SchemaReference::CommonType(fully_qualify(ns, type_name))
+                    }
+                    SchemaType::Type(SchemaTypeVariant::Entity { name }) => {
+                        SchemaReference::EntityType(fully_qualify(ns, name))
+                    }
+                    _ => return,
+                };
+                refs.push((SchemaLocation::Type(path.clone()), reference));
+            });
+        }
+        let pointer = |path: &[&str]| SchemaLocation::Pointer(json_pointer(path));
+        for (name, entity_type) in self.entity_types.iter().sorted_by_key(|(name, _)| *name) {
+            for (i, parent) in entity_type.member_of_types.iter().enumerate() {
+                refs.push((
+                    pointer(&[ns, "entityTypes", name, "memberOfTypes", &i.to_string()]),
+                    SchemaReference::EntityType(fully_qualify(ns, parent)),
+                ));
+            }
+        }
+        for (name, action) in self.actions.iter().sorted_by_key(|(name, _)| *name) {
+            if let Some(applies_to) = &action.applies_to {
+                for (key, tys) in [
+                    ("principalTypes", &applies_to.principal_types),
+                    ("resourceTypes", &applies_to.resource_types),
+                ] {
+                    for (i, ty) in tys.iter().flatten().enumerate() {
+                        refs.push((
+                            pointer(&[ns, "actions", name, "appliesTo", key, &i.to_string()]),
+                            SchemaReference::EntityType(fully_qualify(ns, ty)),
+                        ));
+                    }
+                }
+            }
+            for (i, parent) in action.member_of.iter().flatten().enumerate() {
+                refs.push((
+                    pointer(&[ns, "actions", name, "memberOf", &i.to_string()]),
+                    SchemaReference::Action(qualified_action(ns, parent.ty.as_ref(), &parent.id)),
+                ));
+            }
+        }
+        refs
+    }
+
+    /// Every `Entity` type in this namespace, which is named `ns`, whose name
+    /// is the name of an extension type, such as `decimal`, and which does
+    /// not name an entity type in `declared`, paired with its location and
+    /// the name as written.
+    pub(crate) fn extension_entity_references(
+        &self,
+        ns: &str,
+        declared: &BTreeSet<SchemaReference>,
+    ) -> Vec<(SchemaLocation, SmolStr)> {
+        let extensions = extension_names();
+        let mut found = Vec::new();
+        for (mut path, ty) in self.declared_type_paths(ns) {
+            ty.walk_with_path(&mut path, &mut |path, ty| {
+                if let SchemaType::Type(SchemaTypeVariant::Entity { name }) = ty {
+                    let reference = SchemaReference::EntityType(fully_qualify(ns, name));
+                    if extensions.contains(name.as_str()) && !declared.contains(&reference) {
+                        found.push((SchemaLocation::Type(path.clone()), name.clone()));
+                    }
+                }
+            });
+        }
+        found
+    }
+
+    /// Every `memberOf` entry of an action in this namespace, which is named
+    /// `ns`, whose explicit type is not an action entity type (`Action`,
+    /// possibly qualified by a namespace), paired with its location and
+    /// written as an entity UID.
+    pub(crate) fn non_action_member_of(&self, ns: &str) -> Vec<(SchemaLocation, SmolStr)> {
+        let mut found = Vec::new();
+        for (name, action) in self.actions.iter().sorted_by_key(|(name, _)| *name) {
+            for (i, parent) in action.member_of.iter().flatten().enumerate() {
+                if let Some(ty) = &parent.ty {
+                    if ty.rsplit("::").next() != Some("Action") {
+                        found.push((
+                            SchemaLocation::Pointer(json_pointer(&[
+                                ns,
+                                "actions",
+                                name,
+                                "memberOf",
+                                &i.to_string(),
+                            ])),
+                            qualified_action(ns, Some(ty), &parent.id),
+                        ));
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Every entity type referenced in this namespace, which is named `ns`,
+    /// and not in `declared`, fully qualified and paired with where it is
+    /// referenced, in the order of `located_references`.
+    fn undeclared_entity_types(
+        &self,
+        ns: &str,
+        declared: &BTreeSet<SchemaReference>,
+    ) -> Vec<(SchemaLocation, SmolStr)> {
+        self.located_references(ns)
+            .into_iter()
+            .filter(|(_, reference)| !declared.contains(reference))
+            .filter_map(|(location, reference)| match reference {
+                SchemaReference::EntityType(name) => Some((location, name)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Check that every entity type referenced in this namespace, in
+    /// `memberOfTypes`, `principalTypes`, `resourceTypes`, or as an `Entity`
+    /// type anywhere inside a common type, entity shape or action context,
+    /// is declared in this namespace. Qualified names are not checked, since
+    /// they may refer to other namespaces; use
+    /// `SchemaFragment::validate_references` to check them. Fails with
+    /// `SchemaError::UndeclaredEntityTypeReferences` listing every undeclared
+    /// name with where it is referenced, as a type path or as a JSON pointer
+    /// into this namespace definition.
+    pub fn validate_references(&self) -> Result<()> {
+        // Checked as the empty namespace, so that names stay as written and
+        // JSON pointers start with an empty segment for the namespace, which
+        // is dropped.
+        let undeclared = self
+            .undeclared_entity_types("", &self.declarations(""))
+            .into_iter()
+            .filter(|(_, name)| !name.contains("::"))
+            .map(|(location, name)| {
+                let location = match location {
+                    SchemaLocation::Pointer(pointer) => SchemaLocation::Pointer(
+                        pointer.strip_prefix('/').unwrap_or(&pointer).to_string(),
+                    ),
+                    location => location,
+                };
+                (name.to_string(), location)
+            })
+            .collect_vec();
+        if undeclared.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaError::UndeclaredEntityTypeReferences(undeclared))
+        }
+    }
+}
+
+impl SchemaFragment {
+    /// The fully qualified names of the entity types declared in this
+    /// fragment, such as `PhotoApp::Photo`, in sorted order.
+    pub fn entity_type_names(&self) -> impl Iterator<Item = SmolStr> {
+        self.0
+            .iter()
+            .flat_map(|(ns, ns_def)| {
+                ns_def
+                    .entity_types
+                    .keys()
+                    .map(move |name| fully_qualify(ns, name))
+            })
+            .sorted()
+    }
+
+    /// The entity UIDs of the actions declared in this fragment, with their
+    /// fully qualified action entity types, such as
+    /// `PhotoApp::Action::"viewPhoto"`, sorted by type and then by id.
+    pub fn action_uids(&self) -> impl Iterator<Item = ActionEntityUID> {
+        self.0
+            .iter()
+            .flat_map(|(ns, ns_def)| {
+                ns_def.actions.keys().map(move |id| ActionEntityUID {
+                    id: id.clone(),
+                    ty: Some(fully_qualify(ns, "Action")),
+                })
+            })
+            .sorted_by(|a, b| (&a.ty, &a.id).cmp(&(&b.ty, &b.id)))
+    }
+
+    /// Everything declared in this fragment.
+    pub(crate) fn declarations(&self) -> BTreeSet<SchemaReference> {
+        self.0
+            .iter()
+            .flat_map(|(ns, ns_def)| ns_def.declarations(ns))
+            .collect()
+    }
+
+    /// Everything referenced by declarations in this fragment.
+    pub(crate) fn references(&self) -> BTreeSet<SchemaReference> {
+        self.0
+            .iter()
+            .flat_map(|(ns, ns_def)| ns_def.references(ns))
+            .collect()
+    }
+
+    /// Check that every entity type, common type and action referenced in any
+    /// of `fragments` is declared in at least one of them, as if the fragments
+    /// had been merged, but without building the merged fragment. Dangling
+    /// references are reported together with the index of the fragment they
+    /// appear in. It is also an error for an action's `memberOf` entry to
+    /// have an explicit type which is not an action entity type, and a
+    /// reference qualified with a namespace which none of the fragments
+    /// defines is reported as `SchemaError::UndefinedNamespace` rather than
+    /// as a dangling reference. An `Entity` type naming an extension type,
+    /// such as `decimal`, is reported as `SchemaError::ExtensionAsEntityType`.
+    pub fn validate_set(fragments: &[SchemaFragment]) -> Result<()> {
+        if let Some((_, euid)) = fragments
+            .iter()
+            .flat_map(|f| f.0.iter())
+            .flat_map(|(ns, ns_def)| ns_def.non_action_member_of(ns))
+            .next()
+        {
+            return Err(SchemaError::NonActionMemberOf(euid.to_string()));
+        }
+        let declared: BTreeSet<SchemaReference> =
+            fragments.iter().flat_map(|f| f.declarations()).collect();
+        if let Some((location, name)) = fragments
+            .iter()
+            .flat_map(|f| f.0.iter())
+            .flat_map(|(ns, ns_def)| ns_def.extension_entity_references(ns, &declared))
+            .next()
+        {
+            return Err(SchemaError::ExtensionAsEntityType(
+                location,
+                name.to_string(),
+            ));
+        }
+        let namespaces: BTreeSet<&str> = fragments
+            .iter()
+            .flat_map(|f| f.0.keys())
+            .map(SmolStr::as_str)
+            .collect();
+        if let Some(e) = fragments
+            .iter()
+            .flat_map(|f| f.0.iter())
+            .flat_map(|(ns, ns_def)| ns_def.located_references(ns))
+            .filter(|(_, reference)| !declared.contains(reference))
+            .find_map(|(location, reference)| {
+                undefined_namespace(&namespaces, &location, &reference)
+            })
+        {
+            return Err(e);
+        }
+        let dangling = fragments
+            .iter()
+            .enumerate()
+            .flat_map(|(i, fragment)| {
+                fragment
+                    .references()
+                    .into_iter()
+                    .filter(|r| !declared.contains(r))
+                    .map(move |r| (i, r.to_string()))
+            })
+            .collect_vec();
+        if dangling.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaError::UndeclaredFragmentReferences(dangling))
+        }
+    }
+
+    /// Check that this fragment declares every entity type, common type and
+    /// action it references, so that it can be used without any other
+    /// fragment. Unlike `validate_set`, nothing may be declared elsewhere.
+    /// Fails with `SchemaError::ExternalReferences` listing every reference
+    /// which is not declared.
+    pub fn is_self_contained(&self) -> Result<()> {
+        let declared = self.declarations();
+        let external = self
+            .references()
+            .into_iter()
+            .filter(|reference| !declared.contains(reference))
+            .map(|reference| reference.to_string())
+            .collect_vec();
+        if external.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaError::ExternalReferences(external))
+        }
+    }
+
+    /// Check that every entity type referenced in this fragment, as by
+    /// `NamespaceDefinition::validate_references`, is declared in this
+    /// fragment. Names are qualified with the namespace they are written in
+    /// unless they are already qualified, so a reference may be to an entity
+    /// type in any namespace of the fragment. Fails with
+    /// `SchemaError::UndeclaredEntityTypeReferences` listing every undeclared
+    /// entity type, fully qualified, with where it is referenced. Namespaces
+    /// are checked in sorted order.
+    pub fn validate_references(&self) -> Result<()> {
+        let declared = self.declarations();
+        let undeclared = self
+            .0
+            .iter()
+            .sorted_by_key(|(ns, _)| *ns)
+            .flat_map(|(ns, ns_def)| ns_def.undeclared_entity_types(ns, &declared))
+            .map(|(location, name)| (name.to_string(), location))
+            .collect_vec();
+        if undeclared.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaError::UndeclaredEntityTypeReferences(undeclared))
+        }
+    }
+
+    /// The common types declared in this fragment which no entity shape or
+    /// action context uses, directly or through other common types, mapped
+    /// from each namespace with any such common types to their names as
+    /// declared, in sorted order. A common type used only by other unused
+    /// common types is itself unused, so the whole unreferenced part of the
+    /// common type graph is reported. Qualified references from other
+    /// namespaces count as uses.
+    pub fn unused_common_types(&self) -> BTreeMap<SmolStr, Vec<SmolStr>> {
+        let mut used = BTreeSet::new();
+        let mut uses: BTreeMap<SmolStr, Vec<SmolStr>> = BTreeMap::new();
+        for (ns, ns_def) in &self.0 {
+            for (location, reference) in ns_def.located_references(ns) {
+                if let SchemaReference::CommonType(name) = reference {
+                    match referencing_common_type(&location) {
+                        Some(user) => uses.entry(user.clone()).or_default().push(name),
+                        None => {
+                            used.insert(name);
+                        }
+                    }
+                }
+            }
+        }
+        let mut stack = used.iter().cloned().collect_vec();
+        while let Some(name) = stack.pop() {
+            for used_by_name in uses.get(&name).into_iter().flatten() {
+                if used.insert(used_by_name.clone()) {
+                    stack.push(used_by_name.clone());
+                }
+            }
+        }
+        self.0
+            .iter()
+            .filter_map(|(ns, ns_def)| {
+                let unused = ns_def
+                    .common_types
+                    .keys()
+                    .filter(|name| !used.contains(&fully_qualify(ns, name)))
+                    .cloned()
+                    .sorted()
+                    .collect_vec();
+                (!unused.is_empty()).then(|| (ns.clone(), unused))
+            })
+            .collect()
+    }
+
+    /// The fully qualified names of the entity types declared in this
+    /// fragment which are referenced only as the types of attributes (in
+    /// entity shapes, action contexts or common types), and never as a
+    /// principal type, resource type or `memberOfTypes` target. Entities of
+    /// these types can only be reached through the entities which refer to
+    /// them. Entity types which are not referenced at all are not included.
+    pub fn reference_only_types(&self) -> BTreeSet<SmolStr> {
+        let mut as_attribute = BTreeSet::new();
+        let mut otherwise = BTreeSet::new();
+        for (ns, ns_def) in &self.0 {
+            for (location, reference) in ns_def.located_references(ns) {
+                if let SchemaReference::EntityType(name) = reference {
+                    match location {
+                        SchemaLocation::Type(_) => as_attribute.insert(name),
+                        SchemaLocation::Pointer(_) => otherwise.insert(name),
+                    };
+                }
+            }
+        }
+        self.declarations()
+            .into_iter()
+            .filter_map(|decl| match decl {
+                SchemaReference::EntityType(name)
+                    if as_attribute.contains(&name) && !otherwise.contains(&name) =>
+                {
+                    Some(name)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// The fully qualified name of the common type whose definition `location`
+/// is inside, or `None` if it is anywhere else.
+fn referencing_common_type(location: &SchemaLocation) -> Option<&SmolStr> {
+    match location {
+        SchemaLocation::Type(path) => match path.root() {
+            TypePathRoot::CommonType(name) => Some(name),
+            _ => None,
+        },
+        SchemaLocation::Pointer(_) => None,
+    }
+}
+
+/// A `SchemaError::UndefinedNamespace` for the undeclared `reference`, made
+/// at `location`, if it is qualified with a namespace which is not in
+/// `namespaces`. Declared references are not checked, since a namespace
+/// flattened into the empty namespace declares qualified names itself.
+pub(crate) fn undefined_namespace(
+    namespaces: &BTreeSet<&str>,
+    location: &SchemaLocation,
+    reference: &SchemaReference,
+) -> Option<SchemaError> {
+    let namespace = reference.namespace()?;
+    (!namespaces.contains(namespace)).then(|| SchemaError::UndefinedNamespace {
+        referenced_in: location.to_string(),
+        namespace: namespace.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn photo_app() -> SchemaFragment {
+        SchemaFragment::from_json_value(json!({
+            "PhotoApp": {
+                "commonTypes": { "Tags": { "type": "Set", "element": { "type": "String" } } },
+                "entityTypes": { "User": {}, "Album": {} },
+                "actions": { "read": {} }
+            }
+        }))
+        .expect("Parse Error")
+    }
+
+    fn sharing() -> SchemaFragment {
+        SchemaFragment::from_json_value(json!({
+            "Sharing": {
+                "entityTypes": {
+                    "Link": {
+                        "memberOfTypes": ["PhotoApp::Album"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "owner": { "type": "Entity", "name": "PhotoApp::User" },
+                                "tags": { "type": "PhotoApp::Tags" }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "share": {
+                        "appliesTo": { "principalTypes": ["PhotoApp::User"], "resourceTypes": ["Link"] },
+                        "memberOf": [{ "id": "read", "type": "PhotoApp::Action" }]
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error")
+    }
+
+    #[test]
+    fn references_resolve_across_fragments() {
+        SchemaFragment::validate_set(&[photo_app(), sharing()]).expect("references should resolve");
+    }
+
+    #[test]
+    fn dangling_references_name_fragment() {
+        let empty_photo_app = SchemaFragment::from_json_value(json!({
+            "PhotoApp": { "entityTypes": {}, "actions": {} }
+        }))
+        .expect("Parse Error");
+        match SchemaFragment::validate_set(&[empty_photo_app, sharing()]) {
+            Err(SchemaError::UndeclaredFragmentReferences(dangling)) => assert_eq!(
+                dangling,
+                vec![
+                    (1, "entity type `PhotoApp::Album`".to_string()),
+                    (1, "entity type `PhotoApp::User`".to_string()),
+                    (1, "common type `PhotoApp::Tags`".to_string()),
+                    (1, r#"action `PhotoApp::Action::"read"`"#.to_string()),
+                ]
+            ),
+            r => panic!("Expected undeclared references, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn is_self_contained() {
+        photo_app()
+            .is_self_contained()
+            .expect("fragment declares everything it references");
+        let mut combined = sharing();
+        combined.0.extend(photo_app().0);
+        combined
+            .is_self_contained()
+            .expect("fragment declares everything it references");
+        match sharing().is_self_contained() {
+            Err(SchemaError::ExternalReferences(external)) => assert_eq!(
+                external,
+                vec![
+                    "entity type `PhotoApp::Album`",
+                    "entity type `PhotoApp::User`",
+                    "common type `PhotoApp::Tags`",
+                    r#"action `PhotoApp::Action::"read"`"#,
+                ]
+            ),
+            r => panic!("Expected external references, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn undefined_namespace() {
+        match SchemaFragment::validate_set(&[sharing()]) {
+            Err(SchemaError::UndefinedNamespace {
+                referenced_in,
+                namespace,
+            }) => {
+                assert_eq!(referenced_in, "Sharing::Link.owner");
+                assert_eq!(namespace, "PhotoApp");
+            }
+            r => panic!("Expected undefined namespace, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn extension_as_entity_type() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "Invoice": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "amount": { "type": "Entity", "name": "decimal" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        match SchemaFragment::validate_set(std::slice::from_ref(&fragment)) {
+            Err(SchemaError::ExtensionAsEntityType(path, name)) => {
+                assert_eq!(path.to_string(), "App::Invoice.amount");
+                assert_eq!(name, "decimal");
+            }
+            r => panic!("Expected an extension used as an entity type, got {:?}", r),
+        }
+        let report = fragment.validate_all();
+        assert_eq!(
+            report
+                .findings()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "error at `App::Invoice.amount`: Entity type at `App::Invoice.amount` names the \
+                 extension type `decimal`; use an `Extension` type instead"
+            ]
+        );
+    }
+
+    #[test]
+    fn member_of_non_action() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": { "User": {} },
+                "actions": {
+                    "read": {},
+                    "view": { "memberOf": [{ "id": "read", "type": "App::Action" }, { "id": "read", "type": "User" }] }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        match SchemaFragment::validate_set(&[fragment]) {
+            Err(SchemaError::NonActionMemberOf(euid)) => assert_eq!(euid, r#"User::"read""#),
+            r => panic!("Expected non-action memberOf error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn validate_references() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "Group": { "tags": { "type": "Entity", "name": "Usr" } },
+                    "User": {
+                        "memberOfTypes": ["Grop", "Auth::Team"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "albums": {
+                                    "type": "Set",
+                                    "element": {
+                                        "type": "Record",
+                                        "attributes": {
+                                            "album": { "type": "Entity", "name": "Albm" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User", "Auth::Usr"] } }
+                }
+            },
+            "Auth": { "entityTypes": { "Team": {} }, "actions": {} }
+        }))
+        .expect("Parse Error");
+        match fragment.0["App"].validate_references() {
+            Err(SchemaError::UndeclaredEntityTypeReferences(refs)) => assert_eq!(
+                refs.into_iter()
+                    .map(|(name, location)| (name, location.to_string()))
+                    .collect::<Vec<_>>(),
+                vec![
+                    ("Usr".to_string(), "tags Group".to_string()),
+                    ("Albm".to_string(), "User.albums[].album".to_string()),
+                    (
+                        "Grop".to_string(),
+                        "/entityTypes/User/memberOfTypes/0".to_string()
+                    ),
+                ]
+            ),
+            r => panic!("Expected undeclared entity types, got {r:?}"),
+        }
+        match fragment.validate_references() {
+            Err(SchemaError::UndeclaredEntityTypeReferences(refs)) => assert_eq!(
+                refs.into_iter()
+                    .map(|(name, location)| (name, location.to_string()))
+                    .collect::<Vec<_>>(),
+                vec![
+                    ("App::Usr".to_string(), "tags App::Group".to_string()),
+                    (
+                        "App::Albm".to_string(),
+                        "App::User.albums[].album".to_string()
+                    ),
+                    (
+                        "App::Grop".to_string(),
+                        "/App/entityTypes/User/memberOfTypes/0".to_string()
+                    ),
+                    (
+                        "Auth::Usr".to_string(),
+                        "/App/actions/view/appliesTo/principalTypes/1".to_string()
+                    ),
+                ]
+            ),
+            r => panic!("Expected undeclared entity types, got {r:?}"),
+        }
+        photo_app()
+            .validate_references()
+            .expect("every entity type is declared");
+    }
+
+    #[test]
+    fn declared_names() {
+        let fragment = SchemaFragment::merge_all([photo_app(), sharing()]).expect("Merge Error");
+        assert_eq!(
+            fragment.0["PhotoApp"]
+                .entity_type_names()
+                .collect::<Vec<_>>(),
+            vec!["Album", "User"]
+        );
+        assert_eq!(
+            fragment.0["PhotoApp"].action_ids().collect::<Vec<_>>(),
+            vec!["read"]
+        );
+        assert_eq!(
+            fragment.entity_type_names().collect::<Vec<_>>(),
+            vec!["PhotoApp::Album", "PhotoApp::User", "Sharing::Link"]
+        );
+        assert_eq!(
+            fragment
+                .action_uids()
+                .map(|uid| uid.to_string())
+                .collect::<Vec<_>>(),
+            vec![r#"PhotoApp::Action::"read""#, r#"Sharing::Action::"share""#]
+        );
+    }
+
+    #[test]
+    fn reference_only_types() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Address": {
+                        "type": "Record",
+                        "attributes": { "country": { "type": "Entity", "name": "Country" } }
+                    }
+                },
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Team"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "address": { "type": "Address" },
+                                "manager": { "type": "Entity", "name": "User" },
+                                "badge": { "type": "Entity", "name": "Badge" }
+                            }
+                        }
+                    },
+                    "Team": {},
+                    "Country": {},
+                    "Badge": {},
+                    "Unused": {}
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Team"] } }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(
+            fragment.reference_only_types(),
+            BTreeSet::from(["App::Badge".into(), "App::Country".into()])
+        );
+    }
+
+    #[test]
+    fn unused_common_types() {
+        let app = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Name": { "type": "String" },
+                    "Label": { "type": "String" },
+                    "Address": { "type": "Record", "attributes": { "city": { "type": "City" } } },
+                    "City": { "type": "String" },
+                    "Legacy": { "type": "Set", "element": { "type": "Old" } },
+                    "Old": { "type": "Set", "element": { "type": "Legacy" } }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "name": { "type": "Name" }, "home": { "type": "Shared::Home" } }
+                        },
+                        "tags": { "type": "Label" }
+                    }
+                },
+                "actions": {}
+            },
+            "Shared": {
+                "commonTypes": {
+                    "Home": { "type": "App::Address" },
+                    "Unused": { "type": "Long" }
+                },
+                "entityTypes": {},
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(
+            app.unused_common_types(),
+            BTreeMap::from([
+                ("App".into(), vec!["Legacy".into(), "Old".into()]),
+                ("Shared".into(), vec!["Unused".into()]),
+            ])
+        );
+    }
+}
@@ -0,0 +1,213 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Renaming of the namespaces in a schema fragment, along with every
+//! reference to them.
+
+use std::collections::HashMap;
+
+use cedar_policy_core::parser::parse_namespace;
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{flatten::rename_type, NamespaceDefinition, SchemaFragment};
+use crate::{Result, SchemaError};
+
+impl NamespaceDefinition {
+    /// Replace every name referenced in this namespace by `rename`: entity
+    /// type and common type names inside types, `memberOfTypes`,
+    /// `principalTypes` and `resourceTypes`, the types of action `memberOf`
+    /// entries, and the targets of `imports`. Declared names are unchanged.
+    pub(crate) fn rename_references(&mut self, rename: &impl Fn(&SmolStr) -> SmolStr) {
+        for ty in self.common_types.values_mut() {
+            *ty = rename_type(ty, rename);
+        }
+        for entity_type in self.entity_types.values_mut() {
+            entity_type.shape.0 = rename_type(&entity_type.shape.0, rename);
+            if let Some(additional) = &mut entity_type.additional_attributes_definition {
+                additional.0 = rename_type(&additional.0, rename);
+            }
+            if let Some(tags) = &mut entity_type.tags {
+                *tags = rename_type(tags, rename);
+            }
+            for parent in entity_type.member_of_types.iter_mut() {
+                *parent = rename(parent);
+            }
+        }
+        for action in self.actions.values_mut() {
+            if let Some(applies_to) = &mut action.applies_to {
+                applies_to.context.0 = rename_type(&applies_to.context.0, rename);
+                for ty in applies_to
+                    .principal_types
+                    .iter_mut()
+                    .chain(applies_to.resource_types.iter_mut())
+                    .flatten()
+                {
+                    *ty = rename(ty);
+                }
+            }
+            for parent in action.member_of.iter_mut().flatten() {
+                if let Some(ty) = &mut parent.ty {
+                    *ty = rename(ty);
+                }
+            }
+        }
+        for target in self.imports.values_mut() {
+            *target = rename(target);
+        }
+    }
+}
+
+impl SchemaFragment {
+    /// Rename every namespace in this fragment to the name `f` gives it, and
+    /// rewrite every qualified name referring to one of these namespaces to
+    /// use the new name. Qualified names referring to namespaces which are
+    /// not in this fragment are left as they are, as are unqualified names.
+    ///
+    /// Fails with the first error returned by `f`, with
+    /// `SchemaError::NamespaceParseError` if it returns a name which is not a
+    /// valid namespace, or with `SchemaError::DuplicateNamespace` if it
+    /// gives two namespaces the same name. Namespaces are renamed in sorted
+    /// order.
+    pub fn try_map_namespaces(self, f: impl Fn(&str) -> Result<SmolStr>) -> Result<SchemaFragment> {
+        let mut renamed = HashMap::new();
+        for ns in self.0.keys().sorted() {
+            let new_ns = f(ns)?;
+            if !new_ns.is_empty() {
+                parse_namespace(&new_ns).map_err(SchemaError::NamespaceParseError)?;
+            }
+            if renamed.values().contains(&new_ns) {
+                return Err(SchemaError::DuplicateNamespace(new_ns.to_string()));
+            }
+            renamed.insert(ns.clone(), new_ns);
+        }
+        let rename = |name: &SmolStr| match name.rsplit_once("::") {
+            Some((ns, basename)) => match renamed.get(ns) {
+                Some(new_ns) if new_ns.is_empty() => basename.into(),
+                Some(new_ns) => format!("{new_ns}::{basename}").into(),
+                None => name.clone(),
+            },
+            None => name.clone(),
+        };
+        let mut mapped = HashMap::new();
+        for (ns, mut ns_def) in self.0 {
+            ns_def.rename_references(&rename);
+            mapped.insert(renamed[&ns].clone(), ns_def);
+        }
+        Ok(SchemaFragment(mapped))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn try_map_namespaces() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Owner": { "type": "Entity", "name": "Auth::User" }
+                },
+                "entityTypes": {
+                    "Photo": {
+                        "memberOfTypes": ["Album"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "owner": { "type": "Owner" } }
+                        }
+                    },
+                    "Album": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": { "principalTypes": ["Auth::User"], "resourceTypes": ["Photo"] },
+                        "memberOf": [{ "id": "read", "type": "Auth::Action" }]
+                    }
+                }
+            },
+            "Auth": {
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["App::Album", "Other::Group"] }
+                },
+                "actions": { "read": {} }
+            }
+        }))
+        .expect("Parse Error");
+        let mapped = fragment
+            .try_map_namespaces(|ns| Ok(format!("{ns}Tenant1").into()))
+            .expect("Renaming succeeds");
+        let expected = SchemaFragment::from_json_value(json!({
+            "AppTenant1": {
+                "commonTypes": {
+                    "Owner": { "type": "Entity", "name": "AuthTenant1::User" }
+                },
+                "entityTypes": {
+                    "Photo": {
+                        "memberOfTypes": ["Album"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "owner": { "type": "Owner" } }
+                        }
+                    },
+                    "Album": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["AuthTenant1::User"],
+                            "resourceTypes": ["Photo"]
+                        },
+                        "memberOf": [{ "id": "read", "type": "AuthTenant1::Action" }]
+                    }
+                }
+            },
+            "AuthTenant1": {
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["AppTenant1::Album", "Other::Group"] }
+                },
+                "actions": { "read": {} }
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(mapped, expected);
+    }
+
+    #[test]
+    fn try_map_namespaces_errors() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": { "entityTypes": {}, "actions": {} },
+            "Auth": { "entityTypes": {}, "actions": {} }
+        }))
+        .expect("Parse Error");
+        assert!(matches!(
+            fragment.clone().try_map_namespaces(|_| Ok("Merged".into())),
+            Err(SchemaError::DuplicateNamespace(ns)) if ns == "Merged"
+        ));
+        assert!(matches!(
+            fragment
+                .clone()
+                .try_map_namespaces(|ns| Ok(format!("{ns}::").into())),
+            Err(SchemaError::NamespaceParseError(_))
+        ));
+        assert!(matches!(
+            fragment.try_map_namespaces(|ns| Err(SchemaError::DuplicateNamespace(ns.into()))),
+            Err(SchemaError::DuplicateNamespace(ns)) if ns == "App"
+        ));
+    }
+}
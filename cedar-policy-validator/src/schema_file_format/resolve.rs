@@ -0,0 +1,641 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Resolution of the types written in a schema fragment into self-contained
+//! types, with common types replaced by their definitions and every entity
+//! type name fully qualified.
+
+use std::collections::{BTreeMap, HashSet};
+
+use itertools::Itertools;
+use sha2::{Digest, Sha256};
+use smol_str::SmolStr;
+
+use super::{
+    diff::describe_type, fully_qualify, AttributesOrContext, EntityType, SchemaFragment,
+    SchemaType, SchemaTypeVariant, SharedSchemaType, TypeOfAttribute,
+};
+use crate::{Result, SchemaError};
+
+impl SchemaFragment {
+    /// The type `ty`, written in namespace `ns`, with every common type
+    /// replaced by its definition and every entity type name fully qualified,
+    /// so that the result means the same thing in any namespace.
+    pub(crate) fn resolve_type(&self, ns: &str, ty: &SchemaType) -> Result<SchemaType> {
+        self.resolve_type_inner(ns, ty, &mut Vec::new())
+    }
+
+    /// This fragment with the `additionalAttributesDefinition` of every entity
+    /// type which has one merged into its shape, as by
+    /// `EntityType::resolved_shape`. The merged shapes have their common
+    /// types resolved, so the common types they use must be declared in this
+    /// fragment.
+    pub(crate) fn with_additional_attributes_merged(mut self) -> Result<Self> {
+        let merged = self
+            .0
+            .iter()
+            .flat_map(|(ns, ns_def)| {
+                ns_def
+                    .entity_types
+                    .iter()
+                    .filter(|(_, et)| et.additional_attributes_definition.is_some())
+                    .map(|(name, et)| Ok((ns.clone(), name.clone(), et.resolved_shape(&self, ns)?)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (ns, name, shape) in merged {
+            if let Some(et) = self
+                .0
+                .get_mut(&ns)
+                .and_then(|ns_def| ns_def.entity_types.get_mut(&name))
+            {
+                et.shape = AttributesOrContext(SchemaType::Type(shape));
+                et.additional_attributes_definition = None;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Check that no entity type in this fragment has an open shape, that is
+    /// a shape record with `additionalAttributes`, since entities of such a
+    /// type may carry attributes the schema does not declare. Shapes are
+    /// checked after resolving common types and merging
+    /// `additionalAttributesDefinition`, as by `EntityType::resolved_shape`.
+    /// Fails with `SchemaError::OpenEntityShapes` listing every open entity
+    /// type in sorted order. Only the shape record itself is checked, not
+    /// records nested in its attributes, and action contexts may be open.
+    pub fn forbid_open_entity_shapes(&self) -> Result<()> {
+        let mut open = Vec::new();
+        for (ns, ns_def) in self.0.iter() {
+            for (name, et) in ns_def.entity_types.iter() {
+                if let SchemaTypeVariant::Record {
+                    additional_attributes: true,
+                    ..
+                } = et.resolved_shape(self, ns)?
+                {
+                    open.push(fully_qualify(ns, name).to_string());
+                }
+            }
+        }
+        if open.is_empty() {
+            Ok(())
+        } else {
+            open.sort();
+            Err(SchemaError::OpenEntityShapes(open))
+        }
+    }
+
+    fn resolve_type_inner(
+        &self,
+        ns: &str,
+        ty: &SchemaType,
+        expanding: &mut Vec<SmolStr>,
+    ) -> Result<SchemaType> {
+        let variant = match ty {
+            SchemaType::TypeDef { type_name } => {
+                let qualified = fully_qualify(ns, type_name);
+                if expanding.contains(&qualified) {
+                    return Err(SchemaError::CycleInCommonTypeReferences(
+                        qualified.to_string(),
+                    ));
+                }
+                let (def_ns, def) = self.lookup_common_type(ns, type_name).ok_or_else(|| {
+                    SchemaError::UndeclaredCommonType(HashSet::from([qualified.to_string()]))
+                })?;
+                expanding.push(qualified);
+                let resolved = self.resolve_type_inner(def_ns, def, expanding);
+                expanding.pop();
+                return resolved;
+            }
+            SchemaType::Type(SchemaTypeVariant::Set { element }) => SchemaTypeVariant::Set {
+                element: SharedSchemaType::new(self.resolve_type_inner(ns, element, expanding)?),
+            },
+            SchemaType::Type(SchemaTypeVariant::Union { options }) => SchemaTypeVariant::Union {
+                options: options
+                    .iter()
+                    .map(|option| Ok(self.resolve_type_inner(ns, option, expanding)?.into()))
+                    .collect::<Result<_>>()?,
+            },
+            SchemaType::Type(SchemaTypeVariant::Record {
+                attributes,
+                additional_attributes,
+            }) => SchemaTypeVariant::Record {
+                attributes: attributes
+                    .iter()
+                    .map(|(attr, attr_ty)| {
+                        Ok((
+                            attr.clone(),
+                            TypeOfAttribute {
+                                ty: self.resolve_type_inner(ns, &attr_ty.ty, expanding)?.into(),
+                                ..attr_ty.clone()
+                            },
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+                additional_attributes: *additional_attributes,
+            },
+            SchemaType::Type(SchemaTypeVariant::Entity { name }) => SchemaTypeVariant::Entity {
+                name: fully_qualify(ns, name),
+            },
+            SchemaType::Type(variant) => variant.clone(),
+        };
+        Ok(SchemaType::Type(variant))
+    }
+}
+
+impl EntityType {
+    /// The shape of this entity type, which is declared in namespace `ns` of
+    /// `fragment`, with common types resolved as by `resolve_type` and the
+    /// attributes of `additionalAttributesDefinition`, if any, added to it.
+    /// An attribute declared in both replaces the one from the shape, but
+    /// only if it has the same type, and otherwise this fails with
+    /// `SchemaError::MergeConflict`. When there are additional attributes,
+    /// both they and the shape must be records.
+    pub fn resolved_shape(&self, fragment: &SchemaFragment, ns: &str) -> Result<SchemaTypeVariant> {
+        let shape = match fragment.resolve_type(ns, &self.shape.0)? {
+            SchemaType::Type(variant) => variant,
+            SchemaType::TypeDef { .. } => return Err(SchemaError::ContextOrShapeNotRecord),
+        };
+        let Some(additional) = &self.additional_attributes_definition else {
+            return Ok(shape);
+        };
+        match (shape, fragment.resolve_type(ns, &additional.0)?) {
+            (
+                SchemaTypeVariant::Record {
+                    mut attributes,
+                    additional_attributes,
+                },
+                SchemaType::Type(SchemaTypeVariant::Record {
+                    attributes: local_attributes,
+                    additional_attributes: local_additional_attributes,
+                }),
+            ) => {
+                for (attr, local) in local_attributes {
+                    if let Some(shared) = attributes.get(&attr) {
+                        if shared.ty != local.ty {
+                            return Err(SchemaError::MergeConflict(
+                                attr.to_string(),
+                                format!(
+                                    "attribute has type `{}` in the shape but `{}` in \
+                                     `additionalAttributesDefinition`",
+                                    describe_type(&shared.ty),
+                                    describe_type(&local.ty)
+                                ),
+                            ));
+                        }
+                    }
+                    attributes.insert(attr, local);
+                }
+                Ok(SchemaTypeVariant::Record {
+                    attributes,
+                    additional_attributes: additional_attributes || local_additional_attributes,
+                })
+            }
+            _ => Err(SchemaError::ContextOrShapeNotRecord),
+        }
+    }
+
+    /// The attributes of this entity type, which is declared in namespace
+    /// `ns` of `fragment`, flattened into a map from dotted attribute path
+    /// (e.g. `address.city`) to the resolved type of the attribute, for
+    /// stores which flatten nested records into columns. Attributes whose
+    /// type is a record are replaced by their own attributes, while all
+    /// other types, including sets, are leaves. The flag paired with each
+    /// type says whether the leaf is required, which it is only if it and
+    /// every record attribute on the path to it are required without a
+    /// `requiredIf` condition. The shape is resolved as by `resolved_shape`
+    /// and must be a record.
+    pub fn flattened_attributes(
+        &self,
+        fragment: &SchemaFragment,
+        ns: &str,
+    ) -> Result<BTreeMap<SmolStr, (SchemaTypeVariant, bool)>> {
+        fn flatten(
+            prefix: &str,
+            attributes: BTreeMap<SmolStr, TypeOfAttribute>,
+            required: bool,
+            flattened: &mut BTreeMap<SmolStr, (SchemaTypeVariant, bool)>,
+        ) -> Result<()> {
+            for (attr, attr_ty) in attributes {
+                let path = if prefix.is_empty() {
+                    attr
+                } else {
+                    format!("{prefix}.{attr}").into()
+                };
+                let required = required && attr_ty.required && attr_ty.required_if.is_none();
+                match attr_ty.ty.into_inner() {
+                    SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+                        flatten(&path, attributes, required, flattened)?
+                    }
+                    SchemaType::Type(variant) => {
+                        flattened.insert(path, (variant, required));
+                    }
+                    SchemaType::TypeDef { type_name } => {
+                        return Err(SchemaError::UndeclaredCommonType(HashSet::from([
+                            type_name.to_string(),
+                        ])))
+                    }
+                }
+            }
+            Ok(())
+        }
+        let SchemaTypeVariant::Record { attributes, .. } = self.resolved_shape(fragment, ns)?
+        else {
+            return Err(SchemaError::ContextOrShapeNotRecord);
+        };
+        let mut flattened = BTreeMap::new();
+        flatten("", attributes, true, &mut flattened)?;
+        Ok(flattened)
+    }
+
+    /// A hash of the structure of this entity type, which is declared in
+    /// namespace `ns` of `fragment`. The hash covers the parent types, the
+    /// `enum` values, the shape and the tag type with all common types
+    /// resolved, so it changes exactly when the meaning of this entity type
+    /// changes and not when unrelated parts of the schema change. Names are
+    /// fully qualified before hashing, and the order in which parent types
+    /// and `enum` values are listed does not matter. Properties which only
+    /// document the entity type, namely `since` and the `example` and `unit`
+    /// of attributes, are not hashed.
+    pub fn structural_hash(&self, fragment: &SchemaFragment, ns: &str) -> Result<[u8; 32]> {
+        let mut resolved = EntityType {
+            member_of_types: self
+                .member_of_types
+                .iter()
+                .map(|parent| fully_qualify(ns, parent))
+                .sorted()
+                .dedup()
+                .collect(),
+            shape: AttributesOrContext(SchemaType::Type(self.resolved_shape(fragment, ns)?)),
+            additional_attributes_definition: None,
+            enumeration: self
+                .enumeration
+                .as_ref()
+                .map(|values| values.iter().cloned().sorted().dedup().collect()),
+            tags: self
+                .tags
+                .as_ref()
+                .map(|tags| fragment.resolve_type(ns, tags))
+                .transpose()?,
+            since: None,
+        };
+        strip_documentation(&mut resolved.shape.0);
+        if let Some(tags) = &mut resolved.tags {
+            strip_documentation(tags);
+        }
+        // `requiredIf` values may contain maps with no fixed order, so objects
+        // are sorted to make the serialization canonical.
+        let mut canonical = serde_json::to_value(resolved)?;
+        canonical.sort_all_objects();
+        Ok(Sha256::digest(canonical.to_string()).into())
+    }
+}
+
+/// Remove the attribute properties which only document a type, `example`
+/// and `unit`, from every record in `ty`.
+fn strip_documentation(ty: &mut SchemaType) {
+    match ty {
+        SchemaType::Type(SchemaTypeVariant::Set { element }) => strip_documentation(element),
+        SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+            for attr_ty in attributes.values_mut() {
+                attr_ty.example = None;
+                attr_ty.unit = None;
+                strip_documentation(&mut attr_ty.ty);
+            }
+        }
+        SchemaType::Type(SchemaTypeVariant::Union { options }) => options
+            .iter_mut()
+            .for_each(|option| strip_documentation(option)),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn hash(schema: serde_json::Value, ns: &str, name: &str) -> Result<[u8; 32]> {
+        let fragment = SchemaFragment::from_json_value(schema).expect("Parse Error");
+        fragment.0[ns].entity_types[name].structural_hash(&fragment, ns)
+    }
+
+    #[test]
+    fn hash_ignores_unrelated_changes() {
+        let before = json!({
+            "App": {
+                "commonTypes": {
+                    "Address": { "type": "Record", "attributes": { "city": { "type": "String" } } }
+                },
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group", "Team"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "home": { "type": "Address" } }
+                        }
+                    },
+                    "Group": {},
+                    "Team": {}
+                },
+                "actions": {}
+            }
+        });
+        // Inlining the common type, reordering parents, and changing another
+        // entity type and an action leave the hash of `User` unchanged.
+        let after = json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Team", "App::Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "home": {
+                                    "type": "Record",
+                                    "attributes": { "city": { "type": "String" } }
+                                }
+                            }
+                        }
+                    },
+                    "Group": { "memberOfTypes": ["Team"] },
+                    "Team": {}
+                },
+                "actions": { "view": {} }
+            }
+        });
+        assert_eq!(
+            hash(before.clone(), "App", "User").unwrap(),
+            hash(after, "App", "User").unwrap()
+        );
+
+        let mut changed = before.clone();
+        changed["App"]["commonTypes"]["Address"]["attributes"]["zip"] = json!({ "type": "Long" });
+        assert_ne!(
+            hash(before, "App", "User").unwrap(),
+            hash(changed, "App", "User").unwrap()
+        );
+    }
+
+    #[test]
+    fn hash_ignores_documentation() {
+        let schema = |amount: serde_json::Value, since: &str| {
+            json!({
+                "App": {
+                    "entityTypes": {
+                        "Invoice": {
+                            "since": since,
+                            "shape": {
+                                "type": "Record",
+                                "attributes": {
+                                    "lines": {
+                                        "type": "Set",
+                                        "element": {
+                                            "type": "Record",
+                                            "attributes": { "amount": amount }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "actions": {}
+                }
+            })
+        };
+        let plain = hash(schema(json!({ "type": "Long" }), "1.0.0"), "App", "Invoice").unwrap();
+        let documented = schema(
+            json!({ "type": "Long", "example": 250, "unit": "cents" }),
+            "2.1.0",
+        );
+        assert_eq!(plain, hash(documented, "App", "Invoice").unwrap());
+        let optional = schema(json!({ "type": "Long", "required": false }), "1.0.0");
+        assert_ne!(plain, hash(optional, "App", "Invoice").unwrap());
+    }
+
+    #[test]
+    fn hash_reports_bad_common_types() {
+        let schema = json!({
+            "": {
+                "commonTypes": {
+                    "A": { "type": "Set", "element": { "type": "B" } },
+                    "B": { "type": "A" }
+                },
+                "entityTypes": {
+                    "Cyclic": { "shape": { "type": "Record", "attributes": { "a": { "type": "A" } } } },
+                    "Missing": { "shape": { "type": "Record", "attributes": { "c": { "type": "C" } } } }
+                },
+                "actions": {}
+            }
+        });
+        assert!(matches!(
+            hash(schema.clone(), "", "Cyclic"),
+            Err(SchemaError::CycleInCommonTypeReferences(_))
+        ));
+        assert!(matches!(
+            hash(schema, "", "Missing"),
+            Err(SchemaError::UndeclaredCommonType(_))
+        ));
+    }
+
+    #[test]
+    fn additional_attributes_definition() {
+        let schema = json!({
+            "App": {
+                "commonTypes": {
+                    "Person": {
+                        "type": "Record",
+                        "attributes": {
+                            "name": { "type": "String" },
+                            "age": { "type": "Long", "required": false }
+                        }
+                    }
+                },
+                "entityTypes": {
+                    "Employee": {
+                        "shape": { "type": "Person" },
+                        "additionalAttributesDefinition": {
+                            "type": "Record",
+                            "attributes": {
+                                "age": { "type": "Long" },
+                                "manager": { "type": "Entity", "name": "Employee" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        });
+        let fragment = SchemaFragment::from_json_value(schema.clone()).expect("Parse Error");
+        let reparsed = SchemaFragment::from_json_value(
+            serde_json::to_value(&fragment).expect("Serialize Error"),
+        )
+        .expect("Parse Error");
+        assert_eq!(reparsed, fragment);
+        let employee = &fragment.0["App"].entity_types["Employee"];
+        let shape = employee
+            .resolved_shape(&fragment, "App")
+            .expect("shapes merge");
+        assert_eq!(
+            describe_type(&SchemaType::Type(shape)),
+            "{ age: Long, manager: App::Employee, name: String }"
+        );
+        let attrs = json!({
+            "name": "Alice",
+            "age": 40,
+            "manager": { "type": "App::Employee", "id": "bob" }
+        });
+        employee
+            .validate_attributes(
+                &attrs,
+                &fragment,
+                "App",
+                crate::AttributeValidationMode::StoredValidation,
+            )
+            .expect("additional attributes are part of the shape");
+
+        let validator_schema: crate::ValidatorSchema =
+            fragment.try_into().expect("schema should be valid");
+        let employee = validator_schema
+            .get_entity_type(&"App::Employee".parse().unwrap())
+            .expect("Employee is declared");
+        assert!(employee.attr("name").is_some());
+        assert!(employee.attr("age").is_some_and(|age| age.is_required));
+
+        let mut conflicting = schema;
+        conflicting["App"]["entityTypes"]["Employee"]["additionalAttributesDefinition"]
+            ["attributes"]["name"] = json!({ "type": "Long" });
+        let fragment = SchemaFragment::from_json_value(conflicting).expect("Parse Error");
+        assert!(matches!(
+            fragment.0["App"].entity_types["Employee"].resolved_shape(&fragment, "App"),
+            Err(SchemaError::MergeConflict(attr, _)) if attr == "name"
+        ));
+    }
+
+    #[test]
+    fn forbid_open_entity_shapes() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Open": { "type": "Record", "attributes": {}, "additionalAttributes": true }
+                },
+                "entityTypes": {
+                    "Closed": {
+                        "shape": { "type": "Record", "attributes": { "name": { "type": "String" } } }
+                    },
+                    "Tagged": { "shape": { "type": "Open" } }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": { "type": "Record", "attributes": {}, "additionalAttributes": true }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        assert!(matches!(
+            fragment.forbid_open_entity_shapes(),
+            Err(SchemaError::OpenEntityShapes(names)) if names == vec!["App::Tagged".to_string()]
+        ));
+        let mut closed = fragment;
+        closed
+            .0
+            .get_mut("App")
+            .expect("namespace")
+            .entity_types
+            .remove("Tagged");
+        assert!(closed.forbid_open_entity_shapes().is_ok());
+    }
+
+    #[test]
+    fn flattened_attributes() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Address": {
+                        "type": "Record",
+                        "attributes": {
+                            "city": { "type": "String" },
+                            "geo": {
+                                "type": "Record",
+                                "required": false,
+                                "attributes": {
+                                    "lat": { "type": "Long" },
+                                    "lng": { "type": "Long" }
+                                }
+                            }
+                        }
+                    }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "address": { "type": "Address" },
+                                "tags": { "type": "Set", "element": { "type": "String" } },
+                                "manager": { "type": "Entity", "name": "User", "required": false }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let flattened = fragment.0["App"].entity_types["User"]
+            .flattened_attributes(&fragment, "App")
+            .expect("Valid shape");
+        let string = SchemaTypeVariant::String {
+            enumeration: None,
+            pattern: None,
+        };
+        let long = SchemaTypeVariant::Long {
+            min: None,
+            max: None,
+        };
+        assert_eq!(
+            flattened.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("address.city".into(), (string.clone(), true)),
+                ("address.geo.lat".into(), (long.clone(), false)),
+                ("address.geo.lng".into(), (long, false)),
+                (
+                    "manager".into(),
+                    (
+                        SchemaTypeVariant::Entity {
+                            name: "App::User".into()
+                        },
+                        false
+                    )
+                ),
+                ("name".into(), (string.clone(), true)),
+                (
+                    "tags".into(),
+                    (
+                        SchemaTypeVariant::Set {
+                            element: SharedSchemaType::new(SchemaType::Type(string))
+                        },
+                        true
+                    )
+                ),
+            ]
+        );
+    }
+}
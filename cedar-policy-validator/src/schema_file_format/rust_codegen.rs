@@ -0,0 +1,731 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Generation of Rust source code for types mirroring the entity types
+//! declared in a schema fragment, meant to be called from a build script.
+
+use std::collections::{BTreeMap, HashMap};
+
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{
+    fully_qualify, SchemaFragment, SchemaType, SchemaTypeVariant, TypeOfAttribute, TypePath,
+    TypePathRoot, TypePathSegment,
+};
+use crate::{Result, SchemaError};
+
+/// Options for `SchemaFragment::to_rust_structs`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RustGenOpts {
+    uid_type: Option<String>,
+    extension_types: HashMap<SmolStr, String>,
+    derives: Vec<String>,
+}
+
+impl RustGenOpts {
+    /// Use the existing type at `path` for entity references instead of
+    /// generating an `EntityUid` struct. The path is written into the
+    /// generated code as is, so it should be absolute, e.g. `crate::Uid`.
+    pub fn with_uid_type(mut self, path: impl Into<String>) -> Self {
+        self.uid_type = Some(path.into());
+        self
+    }
+
+    /// Use the type at `path` for values of the extension type `name`, e.g.
+    /// `decimal`. Extension types without a configured type become `String`.
+    pub fn with_extension_type(
+        mut self,
+        name: impl Into<SmolStr>,
+        path: impl Into<String>,
+    ) -> Self {
+        self.extension_types.insert(name.into(), path.into());
+        self
+    }
+
+    /// Derive `derive` (e.g. `Debug`) on every generated struct, in addition
+    /// to `serde::Serialize` and `serde::Deserialize`.
+    pub fn with_derive(mut self, derive: impl Into<String>) -> Self {
+        self.derives.push(derive.into());
+        self
+    }
+}
+
+/// Generated items, and the modules nested inside, for one Rust module.
+#[derive(Default)]
+struct Module {
+    items: Vec<String>,
+    children: BTreeMap<String, Module>,
+    /// What each type and module declared in this module was generated for,
+    /// to report collisions between them.
+    declared: BTreeMap<String, String>,
+}
+
+impl Module {
+    /// Record that the type or module `ident` is declared in this module for
+    /// `what`, failing if it is already declared for something else.
+    fn declare(&mut self, ident: &str, what: String) -> Result<()> {
+        match self.declared.get(ident) {
+            Some(other) if *other != what => Err(SchemaError::RustIdentifierCollision(
+                ident.to_string(),
+                other.clone(),
+                what,
+            )),
+            Some(_) => Ok(()),
+            None => {
+                self.declared.insert(ident.to_string(), what);
+                Ok(())
+            }
+        }
+    }
+
+    /// Add `item` to this module.
+    fn push(&mut self, item: Item) -> Result<()> {
+        self.declare(&item.ident, item.what)?;
+        self.items.push(item.code);
+        Ok(())
+    }
+
+    fn render(&self, depth: usize, out: &mut Vec<String>) {
+        let indent = "    ".repeat(depth);
+        let mut first = true;
+        for item in &self.items {
+            if !std::mem::take(&mut first) {
+                out.push(String::new());
+            }
+            out.extend(item.lines().map(|line| format!("{indent}{line}")));
+        }
+        for (name, child) in &self.children {
+            if !std::mem::take(&mut first) {
+                out.push(String::new());
+            }
+            out.push(format!("{indent}pub mod {name} {{"));
+            child.render(depth + 1, out);
+            out.push(format!("{indent}}}"));
+        }
+    }
+}
+
+/// A generated struct or type alias.
+struct Item {
+    /// The name of the type it declares.
+    ident: String,
+    /// What it was generated for, e.g. ``entity type `App::User` ``.
+    what: String,
+    code: String,
+}
+
+/// Generates the items for one namespace.
+struct Generator<'a> {
+    opts: &'a RustGenOpts,
+    /// Components of the namespace the items are generated in.
+    ns: Vec<&'a str>,
+}
+
+impl<'a> Generator<'a> {
+    /// Path to the Rust type generated for the entity type or common type
+    /// `name` as written in this namespace.
+    fn type_path(&self, name: &str) -> String {
+        match name.rsplit_once("::") {
+            Some((ns, name)) if ns.split("::").ne(self.ns.iter().copied()) => {
+                let modules = ns.split("::").map(|c| format!("{}::", module_ident(c)));
+                format!(
+                    "{}{}{}",
+                    "super::".repeat(self.ns.len()),
+                    modules.collect::<String>(),
+                    type_ident(name)
+                )
+            }
+            Some((_, name)) => type_ident(name),
+            None => type_ident(name),
+        }
+    }
+
+    fn uid_path(&self) -> String {
+        match &self.opts.uid_type {
+            Some(path) => path.clone(),
+            None => format!("{}EntityUid", "super::".repeat(self.ns.len())),
+        }
+    }
+
+    /// The Rust type for `ty`, which is at `path`. Records become new
+    /// structs, which are named `name` and added to `items`.
+    fn rust_type(
+        &self,
+        ty: &SchemaType,
+        name: &str,
+        path: &TypePath,
+        items: &mut Vec<Item>,
+    ) -> Result<String> {
+        Ok(match ty {
+            SchemaType::TypeDef { type_name } => self.type_path(type_name),
+            SchemaType::Type(SchemaTypeVariant::String { .. }) => "String".to_string(),
+            SchemaType::Type(SchemaTypeVariant::Long { .. }) => "i64".to_string(),
+            SchemaType::Type(SchemaTypeVariant::Boolean) => "bool".to_string(),
+            SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+                let path = path.child(TypePathSegment::SetElement);
+                format!("Vec<{}>", self.rust_type(element, name, &path, items)?)
+            }
+            SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+                let what = format!("the record at `{path}`");
+                self.record_struct(name, what, None, path, attributes, items)?;
+                name.to_string()
+            }
+            SchemaType::Type(SchemaTypeVariant::Entity { .. }) => self.uid_path(),
+            SchemaType::Type(SchemaTypeVariant::Extension { name, .. }) => self
+                .opts
+                .extension_types
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| "String".to_string()),
+            // Unions of entity types are all entity UIDs, but there is no Rust
+            // type for other unions.
+            SchemaType::Type(SchemaTypeVariant::Union { options })
+                if options.iter().all(|option| {
+                    matches!(**option, SchemaType::Type(SchemaTypeVariant::Entity { .. }))
+                }) =>
+            {
+                self.uid_path()
+            }
+            SchemaType::Type(SchemaTypeVariant::Union { .. }) => "serde_json::Value".to_string(),
+        })
+    }
+
+    /// Add a struct named `name`, generated for `what`, with a field for each
+    /// of `attributes` to `items`, followed by the structs for any records
+    /// nested inside. The record is at `path`. Fails if two attributes would
+    /// get the same field name.
+    fn record_struct(
+        &self,
+        name: &str,
+        what: String,
+        doc: Option<String>,
+        path: &TypePath,
+        attributes: &BTreeMap<SmolStr, TypeOfAttribute>,
+        items: &mut Vec<Item>,
+    ) -> Result<()> {
+        let mut nested = Vec::new();
+        let mut fields: BTreeMap<String, TypePath> = BTreeMap::new();
+        let mut lines = doc
+            .into_iter()
+            .map(|doc| format!("/// {doc}"))
+            .collect_vec();
+        lines.push(self.derive_line());
+        if attributes.is_empty() {
+            lines.push(format!("pub struct {name} {{}}"));
+        } else {
+            lines.push(format!("pub struct {name} {{"));
+            for (attr, attr_ty) in attributes {
+                let field = field_ident(attr);
+                let attr_path = path.child(TypePathSegment::Attribute(attr.clone()));
+                if let Some(other) = fields.get(&field) {
+                    return Err(SchemaError::RustIdentifierCollision(
+                        field,
+                        format!("attribute `{other}`"),
+                        format!("attribute `{attr_path}`"),
+                    ));
+                }
+                let nested_name = format!("{name}{}", type_ident(attr));
+                let ty = self.rust_type(&attr_ty.ty, &nested_name, &attr_path, &mut nested)?;
+                if field.trim_start_matches("r#") != attr.as_str() {
+                    lines.push(format!("    #[serde(rename = {attr:?})]"));
+                }
+                if attr_ty.required {
+                    lines.push(format!("    pub {field}: {ty},"));
+                } else {
+                    lines.push(
+                        "    #[serde(default, skip_serializing_if = \"Option::is_none\")]"
+                            .to_string(),
+                    );
+                    lines.push(format!("    pub {field}: Option<{ty}>,"));
+                }
+                fields.insert(field, attr_path);
+            }
+            lines.push("}".to_string());
+        }
+        items.push(Item {
+            ident: name.to_string(),
+            what,
+            code: lines.join("\n"),
+        });
+        items.extend(nested);
+        Ok(())
+    }
+
+    fn derive_line(&self) -> String {
+        let derives = self
+            .opts
+            .derives
+            .iter()
+            .map(String::as_str)
+            .chain(["serde::Serialize", "serde::Deserialize"]);
+        format!("#[derive({})]", derives.format(", "))
+    }
+
+    /// Add the item for the declaration at `path` of `ty`, generated for
+    /// `what`, to `items`: a struct if it is a record, and otherwise a type
+    /// alias. Records nested in a type alias are named after it with an
+    /// `Element` suffix, since they can only be set elements.
+    fn declaration(
+        &self,
+        path: &TypePath,
+        what: String,
+        doc: String,
+        ty: &SchemaType,
+        items: &mut Vec<Item>,
+    ) -> Result<()> {
+        let ident = type_ident(path.owner());
+        match ty {
+            SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+                self.record_struct(&ident, what, Some(doc), path, attributes, items)
+            }
+            _ => {
+                let mut nested = Vec::new();
+                let nested_name = format!("{ident}Element");
+                let ty = self.rust_type(ty, &nested_name, path, &mut nested)?;
+                items.push(Item {
+                    ident: ident.clone(),
+                    what,
+                    code: format!("/// {doc}\npub type {ident} = {ty};"),
+                });
+                items.extend(nested);
+                Ok(())
+            }
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// `s` with characters that cannot appear in an identifier replaced by `_`,
+/// and escaped if it is a keyword.
+fn escape_ident(s: String) -> String {
+    let mut ident: String = s
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    match ident.as_str() {
+        // These keywords cannot be raw identifiers.
+        "_" | "crate" | "self" | "Self" | "super" => format!("{ident}_"),
+        kw if KEYWORDS.contains(&kw) => format!("r#{ident}"),
+        _ => ident,
+    }
+}
+
+/// `s` converted to `snake_case`, e.g. `firstName` becomes `first_name`.
+fn snake_case(s: &str) -> String {
+    let mut out = String::new();
+    let mut prev_lower = false;
+    for c in s.chars() {
+        if c.is_ascii_uppercase() && prev_lower {
+            out.push('_');
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        out.push(c.to_ascii_lowercase());
+    }
+    out
+}
+
+/// `s` converted to `UpperCamelCase`, e.g. `home_address` becomes
+/// `HomeAddress`.
+fn upper_camel_case(s: &str) -> String {
+    s.split(|c: char| !c.is_ascii_alphanumeric())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn field_ident(attr: &str) -> String {
+    escape_ident(snake_case(attr))
+}
+
+fn type_ident(name: &str) -> String {
+    escape_ident(upper_camel_case(name))
+}
+
+fn module_ident(component: &str) -> String {
+    escape_ident(snake_case(component))
+}
+
+impl SchemaFragment {
+    /// Rust source code declaring a struct for the attributes of each entity
+    /// type in this fragment, suitable for writing to a file from a build
+    /// script and `include!`ing. Each namespace becomes a module (so
+    /// `PhotoApp::User` becomes `photo_app::User`), and each common type
+    /// becomes a struct or type alias in its namespace's module. Records
+    /// nested inside attributes become structs named after the record and the
+    /// attribute, e.g. `UserAddress` for the `address` of `User`.
+    ///
+    /// `String`, `Long` and `Boolean` become `String`, `i64` and `bool`, sets
+    /// become `Vec`s, and optional attributes become `Option`s. Entity
+    /// references become an `EntityUid` struct generated at the top level, and
+    /// extension types become `String`; both may be replaced with existing
+    /// types through `opts`. Attribute names are converted to `snake_case`
+    /// and escaped if they are Rust keywords, with a `serde` rename to the
+    /// original name when it differs. Attributes beyond those declared in an
+    /// open record are ignored.
+    ///
+    /// Fails with `SchemaError::RustIdentifierCollision` if two things would
+    /// get the same Rust identifier, e.g. attributes `firstName` and
+    /// `first_name` of one record, or an entity type `UserAddress` and the
+    /// struct for a record nested in the `address` attribute of `User`.
+    pub fn to_rust_structs(&self, opts: RustGenOpts) -> Result<String> {
+        let mut root = Module::default();
+        if opts.uid_type.is_none() {
+            let derives = Generator {
+                opts: &opts,
+                ns: Vec::new(),
+            }
+            .derive_line();
+            root.push(Item {
+                ident: "EntityUid".to_string(),
+                what: "the entity UID struct".to_string(),
+                code: format!(
+                    "/// The type and id of an entity.\n{derives}\npub struct EntityUid {{\n    \
+                     #[serde(rename = \"type\")]\n    pub ty: String,\n    pub id: String,\n}}"
+                ),
+            })?;
+        }
+        for (ns, ns_def) in self.0.iter().sorted_by_key(|(ns, _)| *ns) {
+            let components = if ns.is_empty() {
+                Vec::new()
+            } else {
+                ns.split("::").collect()
+            };
+            let mut module = &mut root;
+            for (i, c) in components.iter().enumerate() {
+                let ident = module_ident(c);
+                module.declare(
+                    &ident,
+                    format!("namespace `{}`", components[..=i].join("::")),
+                )?;
+                module = module.children.entry(ident).or_default();
+            }
+            let generator = Generator {
+                opts: &opts,
+                ns: components,
+            };
+            let mut items = Vec::new();
+            for (name, ty) in ns_def.common_types.iter().sorted_by_key(|(n, _)| *n) {
+                let path = TypePath::new(TypePathRoot::CommonType(fully_qualify(ns, name)));
+                let what = format!("common type `{}`", fully_qualify(ns, name));
+                let doc = format!("Common type `{}`.", fully_qualify(ns, name));
+                generator.declaration(&path, what, doc, ty, &mut items)?;
+            }
+            for (name, et) in ns_def.entity_types.iter().sorted_by_key(|(n, _)| *n) {
+                let path = TypePath::new(TypePathRoot::EntityType(fully_qualify(ns, name)));
+                let what = format!("entity type `{}`", fully_qualify(ns, name));
+                let doc = format!("Attributes of entity type `{}`.", fully_qualify(ns, name));
+                generator.declaration(&path, what, doc, &et.shape.0, &mut items)?;
+            }
+            for item in items {
+                module.push(item)?;
+            }
+        }
+        let mut lines = vec![
+            "// Generated from a Cedar schema. Do not edit.".to_string(),
+            String::new(),
+        ];
+        root.render(0, &mut lines);
+        lines.push(String::new());
+        Ok(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn structs() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "PhotoApp": {
+                "commonTypes": {
+                    "Location": {
+                        "type": "Record",
+                        "attributes": {
+                            "lat": { "type": "Long" },
+                            "type": { "type": "String", "required": false }
+                        }
+                    }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "displayName": { "type": "String" },
+                                "friends": { "type": "Set", "element": { "type": "Entity", "name": "User" } },
+                                "home": { "type": "Location", "required": false },
+                                "balance": { "type": "Extension", "name": "decimal" },
+                                "self": { "type": "Boolean" },
+                                "badges": {
+                                    "type": "Set",
+                                    "element": { "type": "Record", "attributes": { "label": { "type": "String" } } }
+                                }
+                            }
+                        }
+                    },
+                    "Album": {}
+                },
+                "actions": {}
+            },
+            "": {
+                "entityTypes": {
+                    "Device": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "location": { "type": "PhotoApp::Location" } }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let opts = RustGenOpts::default()
+            .with_derive("Debug")
+            .with_extension_type("decimal", "crate::Decimal");
+        let expected = r#"// Generated from a Cedar schema. Do not edit.
+
+/// The type and id of an entity.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct EntityUid {
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub id: String,
+}
+
+/// Attributes of entity type `Device`.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Device {
+    pub location: photo_app::Location,
+}
+
+pub mod photo_app {
+    /// Common type `PhotoApp::Location`.
+    #[derive(Debug, serde::Serialize, serde::Deserialize)]
+    pub struct Location {
+        pub lat: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub r#type: Option<String>,
+    }
+
+    /// Attributes of entity type `PhotoApp::Album`.
+    #[derive(Debug, serde::Serialize, serde::Deserialize)]
+    pub struct Album {}
+
+    /// Attributes of entity type `PhotoApp::User`.
+    #[derive(Debug, serde::Serialize, serde::Deserialize)]
+    pub struct User {
+        pub badges: Vec<UserBadges>,
+        pub balance: crate::Decimal,
+        #[serde(rename = "displayName")]
+        pub display_name: String,
+        pub friends: Vec<super::EntityUid>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub home: Option<Location>,
+        #[serde(rename = "self")]
+        pub self_: bool,
+    }
+
+    #[derive(Debug, serde::Serialize, serde::Deserialize)]
+    pub struct UserBadges {
+        pub label: String,
+    }
+}
+"#;
+        assert_eq!(
+            fragment
+                .to_rust_structs(opts)
+                .expect("no identifiers collide"),
+            expected
+        );
+    }
+
+    #[test]
+    fn nested_namespaces() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "Org": {
+                "commonTypes": {
+                    "Badges": {
+                        "type": "Set",
+                        "element": { "type": "Record", "attributes": { "label": { "type": "String" } } }
+                    }
+                },
+                "entityTypes": {
+                    "Team": {
+                        "shape": { "type": "Record", "attributes": { "badges": { "type": "Badges" } } }
+                    }
+                },
+                "actions": {}
+            },
+            "Org::Photos": {
+                "entityTypes": {
+                    "Album": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "owner": { "type": "Entity", "name": "Org::Team" },
+                                "badges": { "type": "Org::Badges" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let expected = r#"// Generated from a Cedar schema. Do not edit.
+
+/// The type and id of an entity.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct EntityUid {
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub id: String,
+}
+
+pub mod org {
+    /// Common type `Org::Badges`.
+    pub type Badges = Vec<BadgesElement>;
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    pub struct BadgesElement {
+        pub label: String,
+    }
+
+    /// Attributes of entity type `Org::Team`.
+    #[derive(serde::Serialize, serde::Deserialize)]
+    pub struct Team {
+        pub badges: Badges,
+    }
+
+    pub mod photos {
+        /// Attributes of entity type `Org::Photos::Album`.
+        #[derive(serde::Serialize, serde::Deserialize)]
+        pub struct Album {
+            pub badges: super::super::org::Badges,
+            pub owner: super::super::EntityUid,
+        }
+    }
+}
+"#;
+        assert_eq!(
+            fragment
+                .to_rust_structs(RustGenOpts::default())
+                .expect("no identifiers collide"),
+            expected
+        );
+    }
+
+    fn collision(fragment: serde_json::Value, opts: RustGenOpts) -> String {
+        let fragment = SchemaFragment::from_json_value(fragment).expect("Parse Error");
+        match fragment.to_rust_structs(opts) {
+            Err(e @ SchemaError::RustIdentifierCollision(..)) => e.to_string(),
+            r => panic!("Expected an identifier collision, got {r:?}"),
+        }
+    }
+
+    #[test]
+    fn collisions() {
+        let shape = |attributes: serde_json::Value| json!({ "shape": { "type": "Record", "attributes": attributes } });
+        assert_eq!(
+            collision(
+                json!({ "App": {
+                    "entityTypes": {
+                        "User": shape(json!({
+                            "firstName": { "type": "String" },
+                            "first_name": { "type": "String" }
+                        }))
+                    },
+                    "actions": {}
+                } }),
+                RustGenOpts::default()
+            ),
+            "Generated Rust code would declare `first_name` for both attribute \
+             `App::User.firstName` and attribute `App::User.first_name`"
+        );
+        assert_eq!(
+            collision(
+                json!({ "App": {
+                    "commonTypes": { "Photo": { "type": "String" } },
+                    "entityTypes": { "photo": {} },
+                    "actions": {}
+                } }),
+                RustGenOpts::default()
+            ),
+            "Generated Rust code would declare `Photo` for both common type `App::Photo` and \
+             entity type `App::photo`"
+        );
+        assert_eq!(
+            collision(
+                json!({ "App": {
+                    "entityTypes": {
+                        "User": shape(json!({
+                            "address": {
+                                "type": "Record",
+                                "attributes": { "city": { "type": "String" } }
+                            }
+                        })),
+                        "UserAddress": {}
+                    },
+                    "actions": {}
+                } }),
+                RustGenOpts::default()
+            ),
+            "Generated Rust code would declare `UserAddress` for both the record at \
+             `App::User.address` and entity type `App::UserAddress`"
+        );
+        let entity_uid = json!({ "": { "entityTypes": { "EntityUid": {} }, "actions": {} } });
+        assert_eq!(
+            collision(entity_uid.clone(), RustGenOpts::default()),
+            "Generated Rust code would declare `EntityUid` for both the entity UID struct and \
+             entity type `EntityUid`"
+        );
+        SchemaFragment::from_json_value(entity_uid)
+            .expect("Parse Error")
+            .to_rust_structs(RustGenOpts::default().with_uid_type("crate::Uid"))
+            .expect("no `EntityUid` struct is generated");
+        assert_eq!(
+            collision(
+                json!({
+                    "PhotoApp": { "entityTypes": {}, "actions": {} },
+                    "photo_app": { "entityTypes": {}, "actions": {} }
+                }),
+                RustGenOpts::default()
+            ),
+            "Generated Rust code would declare `photo_app` for both namespace `PhotoApp` and \
+             namespace `photo_app`"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Typing of attribute paths starting from the variables in a policy scope,
+//! for schema-aware type inference of policies.
+
+use std::collections::HashSet;
+
+use itertools::Itertools;
+
+use super::{
+    diff::describe_type, fully_qualify, ActionEntityUID, ActionType, SchemaFragment, SchemaType,
+    SchemaTypeVariant,
+};
+use crate::{Result, SchemaError};
+
+/// A variable whose type is fixed by the action of a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ScopeVar {
+    /// `principal`
+    Principal,
+    /// `resource`
+    Resource,
+    /// `context`
+    Context,
+}
+
+impl std::fmt::Display for ScopeVar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Principal => "principal",
+            Self::Resource => "resource",
+            Self::Context => "context",
+        })
+    }
+}
+
+impl SchemaFragment {
+    /// The type of the attribute reached by following `path` from `var` in
+    /// a request for `action`, e.g. the type of `principal.department.name`
+    /// for the path `["department", "name"]`. Common types are resolved and
+    /// entity type names are fully qualified. An `action` without a type is
+    /// taken to be of the `Action` type in the empty namespace.
+    ///
+    /// The path is followed through records and through the shapes of
+    /// referenced entity types. For the principal and resource it is followed
+    /// from every entity type the action applies to, and the results must all
+    /// be the same type, except that differing entity types are combined into
+    /// a `Union` of those entity types.
+    ///
+    /// Fails with `SchemaError::UndeclaredActions` if the action is not
+    /// declared, and with `SchemaError::UnresolvableScopePath` if the action
+    /// applies to any principal or resource, so that `var` could be of any
+    /// entity type, or to none, if an attribute on the path is not declared, or if the
+    /// types reached from different entity types diverge.
+    pub fn resolve_scope_path(
+        &self,
+        action: &ActionEntityUID,
+        var: ScopeVar,
+        path: &[&str],
+    ) -> Result<SchemaType> {
+        let (ns, action_ty) = self
+            .lookup_action(action)
+            .ok_or_else(|| SchemaError::UndeclaredActions(HashSet::from([action.to_string()])))?;
+        let fail = |msg: String| {
+            SchemaError::UnresolvableScopePath(
+                std::iter::once(var.to_string())
+                    .chain(path.iter().map(ToString::to_string))
+                    .join("."),
+                action.to_string(),
+                msg,
+            )
+        };
+        let applies_to = action_ty.applies_to.as_ref();
+        let tys = match var {
+            ScopeVar::Context => {
+                let context = match applies_to {
+                    Some(applies_to) => self.resolve_type(ns, &applies_to.context.0)?,
+                    None => SchemaType::Type(SchemaTypeVariant::Record {
+                        attributes: Default::default(),
+                        additional_attributes: false,
+                    }),
+                };
+                return self.follow(context, path).map_err(fail);
+            }
+            ScopeVar::Principal => applies_to.and_then(|a| a.principal_types.as_ref()),
+            ScopeVar::Resource => applies_to.and_then(|a| a.resource_types.as_ref()),
+        };
+        let Some(tys) = tys else {
+            return Err(fail(format!(
+                "the action applies to any {var}, so `{var}` may be an entity of any type; \
+                 list the types it applies to in `appliesTo` to constrain it"
+            )));
+        };
+        if tys.is_empty() {
+            return Err(fail(format!("the action applies to no {var}")));
+        }
+        let results = tys
+            .iter()
+            .map(|ty| {
+                let start = SchemaType::Type(SchemaTypeVariant::Entity {
+                    name: fully_qualify(ns, ty),
+                });
+                self.follow(start, path)
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(fail)?;
+        unify_scope_types(results).map_err(fail)
+    }
+
+    /// The namespace and declaration of `action`.
+    fn lookup_action(&self, action: &ActionEntityUID) -> Option<(&str, &ActionType)> {
+        let ty = action.ty.as_deref().unwrap_or("Action");
+        let (ns, basename) = ty.rsplit_once("::").unwrap_or(("", ty));
+        if basename != "Action" {
+            return None;
+        }
+        let (ns, ns_def) = self.0.get_key_value(ns)?;
+        Some((ns, ns_def.actions.get(&action.id)?))
+    }
+
+    /// The type reached by following the attributes in `path` from a value of
+    /// the resolved type `ty`, or a description of why it cannot be.
+    fn follow(&self, ty: SchemaType, path: &[&str]) -> std::result::Result<SchemaType, String> {
+        let Some((attr, rest)) = path.split_first() else {
+            return Ok(ty);
+        };
+        let attributes = match ty {
+            SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => attributes,
+            SchemaType::Type(SchemaTypeVariant::Entity { name }) => {
+                let (ns, basename) = name.rsplit_once("::").unwrap_or(("", &name));
+                let (ns, entity_type) = self
+                    .0
+                    .get_key_value(ns)
+                    .and_then(|(ns, ns_def)| Some((ns, ns_def.entity_types.get(basename)?)))
+                    .ok_or_else(|| format!("entity type `{name}` is not declared"))?;
+                match entity_type.resolved_shape(self, ns) {
+                    Ok(SchemaTypeVariant::Record { attributes, .. }) => attributes,
+                    Ok(_) => return Err(format!("the shape of `{name}` is not a record")),
+                    Err(e) => return Err(e.to_string()),
+                }
+            }
+            SchemaType::Type(SchemaTypeVariant::Union { options }) => {
+                let results = options
+                    .into_iter()
+                    .map(|option| self.follow(option.into_inner(), path))
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                return unify_scope_types(results);
+            }
+            ty => {
+                return Err(format!(
+                    "`{}` has no attributes, so it has no attribute `{attr}`",
+                    describe_type(&ty)
+                ))
+            }
+        };
+        let attr_ty = attributes
+            .get(*attr)
+            .ok_or_else(|| format!("there is no attribute `{attr}`"))?;
+        self.follow((*attr_ty.ty).clone(), rest)
+    }
+}
+
+/// The single type of a path reached from several entity types, which is
+/// either the type they all reach or a `Union` of the entity types reached.
+fn unify_scope_types(mut tys: Vec<SchemaType>) -> std::result::Result<SchemaType, String> {
+    tys.sort();
+    tys.dedup();
+    if let [ty] = tys.as_slice() {
+        return Ok(ty.clone());
+    }
+    let all_entities = tys
+        .iter()
+        .all(|ty| matches!(ty, SchemaType::Type(SchemaTypeVariant::Entity { .. })));
+    if all_entities {
+        return Ok(SchemaType::Type(SchemaTypeVariant::Union {
+            options: tys.into_iter().map(Into::into).collect(),
+        }));
+    }
+    Err(format!(
+        "it has different types for different entity types: {}",
+        tys.iter()
+            .map(|ty| format!("`{}`", describe_type(ty)))
+            .sorted()
+            .join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn resolve_scope_path() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Name": { "type": "String" }
+                },
+                "entityTypes": {
+                    "Department": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "name": { "type": "Name" } }
+                        }
+                    },
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "department": { "type": "Entity", "name": "Department" },
+                                "level": { "type": "Long" },
+                                "manager": { "type": "Entity", "name": "User" }
+                            }
+                        }
+                    },
+                    "Service": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "department": { "type": "Entity", "name": "Department" },
+                                "level": { "type": "String" },
+                                "manager": { "type": "Entity", "name": "Service" }
+                            }
+                        }
+                    },
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User", "Service"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "request": {
+                                        "type": "Record",
+                                        "attributes": { "ip": { "type": "String" } }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let view = ActionEntityUID {
+            id: "view".into(),
+            ty: Some("App::Action".into()),
+        };
+        let string = SchemaType::Type(SchemaTypeVariant::String {
+            enumeration: None,
+            pattern: None,
+        });
+        let entity = |name: &str| SchemaType::Type(SchemaTypeVariant::Entity { name: name.into() });
+        assert_eq!(
+            fragment
+                .resolve_scope_path(&view, ScopeVar::Principal, &["department", "name"])
+                .expect("both principal types have a department"),
+            string
+        );
+        assert_eq!(
+            fragment
+                .resolve_scope_path(&view, ScopeVar::Principal, &["manager"])
+                .expect("the managers are entities"),
+            SchemaType::Type(SchemaTypeVariant::Union {
+                options: vec![entity("App::Service").into(), entity("App::User").into()]
+            })
+        );
+        assert_eq!(
+            fragment
+                .resolve_scope_path(&view, ScopeVar::Context, &["request", "ip"])
+                .expect("the context has a request"),
+            string
+        );
+
+        for (var, path, msg) in [
+            (
+                ScopeVar::Principal,
+                &["level"][..],
+                "it has different types for different entity types: `Long`, `String`",
+            ),
+            (
+                ScopeVar::Principal,
+                &["department", "budget"][..],
+                "there is no attribute `budget`",
+            ),
+            (
+                ScopeVar::Resource,
+                &["owner"][..],
+                "the action applies to any resource, so `resource` may be an entity of any \
+                 type; list the types it applies to in `appliesTo` to constrain it",
+            ),
+        ] {
+            match fragment.resolve_scope_path(&view, var, path) {
+                Err(SchemaError::UnresolvableScopePath(_, action, m)) => {
+                    assert_eq!(action, r#"App::Action::"view""#);
+                    assert_eq!(m, msg);
+                }
+                r => panic!("Expected an unresolvable path, got {:?}", r),
+            }
+        }
+        assert!(matches!(
+            fragment.resolve_scope_path(
+                &ActionEntityUID {
+                    id: "edit".into(),
+                    ty: Some("App::Action".into())
+                },
+                ScopeVar::Context,
+                &[]
+            ),
+            Err(SchemaError::UndeclaredActions(_))
+        ));
+    }
+}
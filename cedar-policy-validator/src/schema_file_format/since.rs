@@ -0,0 +1,114 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Queries over the `since` versions recorded on entity types and actions.
+
+use semver::Version;
+
+use super::{fully_qualify, ActionEntityUID, SchemaFragment, TypePath, TypePathRoot};
+
+impl SchemaFragment {
+    /// The entity types and actions whose `since` version is later than
+    /// `version`, in sorted order. An action is named by the path to its
+    /// context. Declarations without a `since` version are never included.
+    pub fn added_since(&self, version: &Version) -> Vec<TypePath> {
+        let mut added = Vec::new();
+        for (ns, ns_def) in &self.0 {
+            for (name, entity_type) in &ns_def.entity_types {
+                if entity_type
+                    .since
+                    .as_ref()
+                    .is_some_and(|since| since > version)
+                {
+                    let root = TypePathRoot::EntityType(fully_qualify(ns, name));
+                    added.push(TypePath::new(root));
+                }
+            }
+            for (name, action) in &ns_def.actions {
+                if action.since.as_ref().is_some_and(|since| since > version) {
+                    let declared = ActionEntityUID::from_declared_name(name);
+                    let ty = declared
+                        .ty
+                        .map_or_else(|| fully_qualify(ns, "Action"), |ty| fully_qualify(ns, &ty));
+                    let root = TypePathRoot::ActionContext {
+                        ty,
+                        id: declared.id,
+                    };
+                    added.push(TypePath::new(root));
+                }
+            }
+        }
+        added.sort();
+        added
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::SchemaError;
+
+    fn fragment() -> SchemaFragment {
+        SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {},
+                    "Album": { "since": "1.2.0" },
+                    "Photo": { "since": "1.3.0-beta.1" }
+                },
+                "actions": {
+                    "view": { "since": "1.0.0" },
+                    "share": { "since": "2.0.0" }
+                }
+            }
+        }))
+        .expect("Parse Error")
+    }
+
+    #[test]
+    fn added_since() {
+        let fragment = fragment();
+        let added = fragment
+            .added_since(&Version::new(1, 2, 0))
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(added, vec!["App::Photo", r#"App::Action::"share".context"#]);
+        assert!(fragment.added_since(&Version::new(2, 0, 0)).is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        let fragment = fragment();
+        let json = serde_json::to_value(&fragment).expect("Serialize Error");
+        assert_eq!(json["App"]["entityTypes"]["Album"]["since"], "1.2.0");
+        assert!(json["App"]["entityTypes"]["User"].get("since").is_none());
+        assert_eq!(
+            SchemaFragment::from_json_value(json).expect("Parse Error"),
+            fragment
+        );
+    }
+
+    #[test]
+    fn invalid_version() {
+        let result = SchemaFragment::from_json_value(json!({
+            "App": { "entityTypes": { "User": { "since": "1.2" } }, "actions": {} }
+        }));
+        assert!(matches!(result, Err(SchemaError::ParseFileFormat(_))));
+    }
+}
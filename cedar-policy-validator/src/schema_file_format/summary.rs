@@ -0,0 +1,263 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A plain text overview of a schema fragment, meant to be read by people
+//! rather than parsed.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{fully_qualify, NamespaceDefinition, SchemaFragment, SchemaType, SchemaTypeVariant};
+
+impl SchemaFragment {
+    /// A readable overview of this fragment. For each namespace it lists the
+    /// entity types as a tree following `memberOfTypes` (children are
+    /// indented under their parents), the actions with the entity types they
+    /// apply to, and the extension types used. Everything is listed in sorted
+    /// order, so the output is stable.
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        for (ns, ns_def) in self.0.iter().sorted_by_key(|(ns, _)| *ns) {
+            if ns.is_empty() {
+                lines.push("namespace (empty)".to_string());
+            } else {
+                lines.push(format!("namespace {ns}"));
+            }
+            self.summarize_entity_types(ns, ns_def, &mut lines);
+            self.summarize_actions(ns, ns_def, &mut lines);
+            let extensions = ns_def
+                .declared_type_paths(ns)
+                .flat_map(|(_, ty)| {
+                    let mut names = Vec::new();
+                    ty.walk(&mut |ty| {
+                        if let SchemaType::Type(SchemaTypeVariant::Extension { name, .. }) = ty {
+                            names.push(name.clone());
+                        }
+                    });
+                    names
+                })
+                .collect::<BTreeSet<_>>();
+            if !extensions.is_empty() {
+                lines.push(format!(
+                    "  extension types: {}",
+                    extensions.iter().join(", ")
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn summarize_entity_types(
+        &self,
+        ns: &str,
+        ns_def: &NamespaceDefinition,
+        lines: &mut Vec<String>,
+    ) {
+        lines.push(format!("  entity types ({})", ns_def.entity_types.len()));
+        // Parents declared in this namespace are shown by nesting; any other
+        // parents are listed next to the entity type.
+        let parents = |name: &SmolStr| -> (Vec<SmolStr>, Vec<SmolStr>) {
+            ns_def.entity_types[name]
+                .member_of_types
+                .iter()
+                .map(|parent| fully_qualify(ns, parent))
+                .sorted()
+                .dedup()
+                .partition(|parent| {
+                    ns_def
+                        .entity_types
+                        .keys()
+                        .any(|name| fully_qualify(ns, name) == *parent)
+                })
+        };
+        let mut children: BTreeMap<SmolStr, Vec<&SmolStr>> = BTreeMap::new();
+        for name in ns_def.entity_types.keys().sorted() {
+            for parent in parents(name).0 {
+                children.entry(parent).or_default().push(name);
+            }
+        }
+        let roots = ns_def
+            .entity_types
+            .keys()
+            .sorted()
+            .filter(|name| parents(name).0.is_empty());
+        // Types whose parents form a cycle have no root above them, so they
+        // are listed at the top level after everything reachable from a root.
+        let mut listed = BTreeSet::new();
+        let mut stack = Vec::new();
+        for name in roots.chain(ns_def.entity_types.keys().sorted()) {
+            if listed.contains(name) {
+                continue;
+            }
+            stack.push((name, 2));
+            while let Some((name, depth)) = stack.pop() {
+                let indent = "  ".repeat(depth);
+                if !listed.insert(name) {
+                    lines.push(format!("{indent}{name} (see above)"));
+                    continue;
+                }
+                let attrs = self
+                    .resolve_type(ns, &ns_def.entity_types[name].shape.0)
+                    .ok()
+                    .and_then(|shape| match shape {
+                        SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+                            Some(count(attributes.len(), "attribute"))
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or_else(|| "unknown attributes".to_string());
+                let external = parents(name).1;
+                if external.is_empty() {
+                    lines.push(format!("{indent}{name} ({attrs})"));
+                } else {
+                    lines.push(format!(
+                        "{indent}{name} ({attrs}, member of {})",
+                        external.iter().join(", ")
+                    ));
+                }
+                if let Some(children) = children.get(&fully_qualify(ns, name)) {
+                    stack.extend(children.iter().rev().map(|child| (*child, depth + 1)));
+                }
+            }
+        }
+    }
+
+    fn summarize_actions(&self, ns: &str, ns_def: &NamespaceDefinition, lines: &mut Vec<String>) {
+        lines.push(format!("  actions ({})", ns_def.actions.len()));
+        let entity_types = |tys: &Option<Vec<SmolStr>>| match tys {
+            None => "any".to_string(),
+            Some(tys) => format!("[{}]", tys.iter().sorted().join(", ")),
+        };
+        for (name, action) in ns_def.actions.iter().sorted_by_key(|(name, _)| *name) {
+            let mut parts = Vec::new();
+            match &action.applies_to {
+                Some(applies_to) => {
+                    parts.push(format!(
+                        "principals {}",
+                        entity_types(&applies_to.principal_types)
+                    ));
+                    parts.push(format!(
+                        "resources {}",
+                        entity_types(&applies_to.resource_types)
+                    ));
+                    if let Ok(SchemaType::Type(SchemaTypeVariant::Record { attributes, .. })) =
+                        self.resolve_type(ns, &applies_to.context.0)
+                    {
+                        if !attributes.is_empty() {
+                            parts.push(format!("context {}", count(attributes.len(), "attribute")));
+                        }
+                    }
+                }
+                None => parts.push("principals any, resources any".to_string()),
+            }
+            if let Some(member_of) = &action.member_of {
+                parts.push(format!(
+                    "member of {}",
+                    member_of
+                        .iter()
+                        .map(|parent| match &parent.ty {
+                            Some(ty) => format!("{ty}::\"{}\"", parent.id),
+                            None => parent.id.to_string(),
+                        })
+                        .join(", ")
+                ));
+            }
+            lines.push(format!("    {name}: {}", parts.join(", ")));
+        }
+    }
+}
+
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("{n} {noun}")
+    } else {
+        format!("{n} {noun}s")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn summary() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "ip": { "type": "Extension", "name": "ipaddr" }
+                            }
+                        }
+                    },
+                    "Group": { "memberOfTypes": ["Org::Team"] },
+                    "Photo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "owner": { "type": "Entity", "name": "User" } }
+                        }
+                    }
+                },
+                "actions": {
+                    "read": {},
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Photo"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "limit": { "type": "Extension", "name": "decimal" } }
+                            }
+                        },
+                        "memberOf": [{ "id": "read" }]
+                    }
+                }
+            },
+            "Org": {
+                "entityTypes": { "Team": {} },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(
+            fragment.summary(),
+            [
+                "namespace App",
+                "  entity types (3)",
+                "    Group (0 attributes, member of Org::Team)",
+                "      User (2 attributes)",
+                "    Photo (1 attribute)",
+                "  actions (2)",
+                "    read: principals any, resources any",
+                "    view: principals [User], resources [Photo], context 1 attribute, member of read",
+                "  extension types: decimal, ipaddr",
+                "namespace Org",
+                "  entity types (1)",
+                "    Team (0 attributes)",
+                "  actions (0)",
+            ]
+            .join("\n")
+        );
+    }
+}
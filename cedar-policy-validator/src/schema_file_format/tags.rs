@@ -0,0 +1,81 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Entity tags, a map from string keys to values of a single type, declared
+//! separately from the fixed attributes in an entity type's `shape`.
+
+use super::{EntityType, SchemaType};
+
+impl EntityType {
+    /// The type of the value of every tag on entities of this type, as
+    /// declared with `tags`, e.g. `{ "type": "String" }`. Tag keys are always
+    /// strings. Entity types declared without `tags` have no tags.
+    pub fn tags(&self) -> Option<&SchemaType> {
+        self.tags.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{SchemaFragment, SchemaTypeVariant, SharedSchemaType};
+
+    #[test]
+    fn tags() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "Doc": {
+                        "shape": { "type": "Record", "attributes": {} },
+                        "tags": { "type": "Set", "element": { "type": "String" } }
+                    },
+                    "User": {}
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let ns_def = &fragment.0["App"];
+        assert_eq!(
+            ns_def.entity_types["Doc"].tags(),
+            Some(&SchemaType::Type(SchemaTypeVariant::Set {
+                element: SharedSchemaType::new(SchemaType::Type(SchemaTypeVariant::String {
+                    enumeration: None,
+                    pattern: None,
+                })),
+            }))
+        );
+        assert_eq!(ns_def.entity_types["User"].tags(), None);
+
+        let json = fragment.to_json_value().expect("Serialize Error");
+        assert_eq!(
+            json["App"]["entityTypes"]["Doc"]["tags"],
+            json!({ "type": "Set", "element": { "type": "String" } })
+        );
+        assert!(json["App"]["entityTypes"]["User"].get("tags").is_none());
+        assert_eq!(
+            SchemaFragment::from_json_value(json).expect("Parse Error"),
+            fragment
+        );
+
+        assert!(serde_json::from_value::<EntityType>(json!({
+            "tag": { "type": "String" }
+        }))
+        .is_err());
+    }
+}
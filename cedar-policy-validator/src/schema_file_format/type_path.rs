@@ -0,0 +1,513 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Defines `TypePath`, which names a position inside a type declared in a
+//! schema fragment, along with a walk over the types in a fragment that
+//! tracks the path to each type.
+
+use cedar_policy_core::parser::parse_name;
+use itertools::Itertools;
+use smol_str::SmolStr;
+use thiserror::Error;
+
+use super::{fully_qualify, NamespaceDefinition, SchemaFragment, SchemaType, SchemaTypeVariant};
+
+/// The declaration a `TypePath` starts from. Names are fully qualified.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TypePathRoot {
+    /// The shape of an entity type.
+    EntityType(SmolStr),
+    /// The type of the tags of an entity type.
+    EntityTags(SmolStr),
+    /// The context of an action. The name is the fully qualified action
+    /// entity type, and the id is the action id.
+    ActionContext {
+        /// Fully qualified action entity type, e.g. `App::Action`.
+        ty: SmolStr,
+        /// The action id.
+        id: SmolStr,
+    },
+    /// A common type declaration.
+    CommonType(SmolStr),
+}
+
+/// One step from a type into a type nested inside it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TypePathSegment {
+    /// An attribute of a record.
+    Attribute(SmolStr),
+    /// The element type of a set.
+    SetElement,
+}
+
+/// The position of a type inside a schema fragment, written as the declaration
+/// it starts from followed by the attributes and set elements leading to the
+/// type, e.g. `App::User.addresses[].city`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TypePath {
+    root: TypePathRoot,
+    segments: Vec<TypePathSegment>,
+}
+
+impl TypePath {
+    /// A path naming the declaration `root` itself.
+    pub fn new(root: TypePathRoot) -> Self {
+        Self {
+            root,
+            segments: Vec::new(),
+        }
+    }
+
+    /// The declaration this path starts from.
+    pub fn root(&self) -> &TypePathRoot {
+        &self.root
+    }
+
+    /// The steps from the root declaration to the named type.
+    pub fn segments(&self) -> &[TypePathSegment] {
+        &self.segments
+    }
+
+    /// The namespace of the root declaration, which is empty for a
+    /// declaration in the empty namespace.
+    pub fn namespace(&self) -> &str {
+        let qualified = match &self.root {
+            TypePathRoot::EntityType(name)
+            | TypePathRoot::EntityTags(name)
+            | TypePathRoot::CommonType(name) => name,
+            TypePathRoot::ActionContext { ty, .. } => ty,
+        };
+        qualified
+            .rsplit_once("::")
+            .map_or("", |(namespace, _)| namespace)
+    }
+
+    /// The unqualified name of the root declaration: the entity type or
+    /// common type name, or the action id.
+    pub fn owner(&self) -> &str {
+        match &self.root {
+            TypePathRoot::EntityType(name)
+            | TypePathRoot::EntityTags(name)
+            | TypePathRoot::CommonType(name) => name
+                .rsplit_once("::")
+                .map_or(name.as_str(), |(_, name)| name),
+            TypePathRoot::ActionContext { id, .. } => id,
+        }
+    }
+
+    /// A description of the declaration the path starts from, for messages,
+    /// e.g. ``entity type `User` ``.
+    pub(crate) fn declaration(&self) -> String {
+        let kind = match &self.root {
+            TypePathRoot::CommonType(_) => "common type",
+            TypePathRoot::EntityType(_) => "entity type",
+            TypePathRoot::EntityTags(_) => "tags of entity type",
+            TypePathRoot::ActionContext { .. } => "context for action",
+        };
+        format!("{kind} `{}`", self.owner())
+    }
+
+    /// The attributes along the path, in order, leaving out set elements.
+    pub fn attributes(&self) -> Vec<SmolStr> {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                TypePathSegment::Attribute(attr) => Some(attr.clone()),
+                TypePathSegment::SetElement => None,
+            })
+            .collect()
+    }
+
+    /// The path extended by one more step.
+    pub fn child(&self, segment: TypePathSegment) -> Self {
+        let mut path = self.clone();
+        path.segments.push(segment);
+        path
+    }
+}
+
+impl std::fmt::Display for TypePathRoot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EntityType(name) => write!(f, "{name}"),
+            Self::EntityTags(name) => write!(f, "tags {name}"),
+            Self::ActionContext { ty, id } => write!(f, "{ty}::\"{id}\".context"),
+            Self::CommonType(name) => write!(f, "type {name}"),
+        }
+    }
+}
+
+impl std::fmt::Display for TypePathSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Attribute(attr) => write!(f, ".{attr}"),
+            Self::SetElement => write!(f, "[]"),
+        }
+    }
+}
+
+impl std::fmt::Display for TypePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.root)?;
+        for segment in &self.segments {
+            write!(f, "{segment}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when a string is not a type path written as `TypePath`
+/// displays it.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Invalid type path `{0}`")]
+pub struct TypePathParseError(String);
+
+impl std::str::FromStr for TypePath {
+    type Err = TypePathParseError;
+
+    /// Parse a path in the form produced by its `Display` implementation.
+    /// Attribute names containing `.` or `[` cannot be parsed, since they
+    /// are written without escaping, and neither can action ids containing
+    /// `".context`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || TypePathParseError(s.to_string());
+        let name = |name: &str| -> Result<SmolStr, TypePathParseError> {
+            parse_name(name).map_err(|_| err())?;
+            Ok(name.into())
+        };
+        let end_of_name = |s: &str| s.find(['.', '[']).unwrap_or(s.len());
+        let (root, mut rest) = if let Some(common) = s.strip_prefix("type ") {
+            let (ty, rest) = common.split_at(end_of_name(common));
+            (TypePathRoot::CommonType(name(ty)?), rest)
+        } else if let Some(tags) = s.strip_prefix("tags ") {
+            let (ty, rest) = tags.split_at(end_of_name(tags));
+            (TypePathRoot::EntityTags(name(ty)?), rest)
+        } else if let Some((ty, action)) = s.split_once("::\"") {
+            let (id, rest) = action.split_once("\".context").ok_or_else(err)?;
+            let root = TypePathRoot::ActionContext {
+                ty: name(ty)?,
+                id: id.into(),
+            };
+            (root, rest)
+        } else {
+            let (ty, rest) = s.split_at(end_of_name(s));
+            (TypePathRoot::EntityType(name(ty)?), rest)
+        };
+        let mut path = TypePath::new(root);
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("[]") {
+                path.segments.push(TypePathSegment::SetElement);
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('.') {
+                let (attr, after) = after.split_at(end_of_name(after));
+                if attr.is_empty() {
+                    return Err(err());
+                }
+                path.segments.push(TypePathSegment::Attribute(attr.into()));
+                rest = after;
+            } else {
+                return Err(err());
+            }
+        }
+        Ok(path)
+    }
+}
+
+impl SchemaType {
+    /// Call `f` on this type and every type nested inside it, as `walk` does,
+    /// along with the path to each type, given that this type is at `path`.
+    /// The options of a `Union` are at the same path as the union itself.
+    pub(crate) fn walk_with_path<'a>(
+        &'a self,
+        path: &mut TypePath,
+        f: &mut impl FnMut(&TypePath, &'a SchemaType),
+    ) {
+        f(path, self);
+        match self {
+            Self::Type(SchemaTypeVariant::Set { element }) => {
+                path.segments.push(TypePathSegment::SetElement);
+                element.walk_with_path(path, f);
+                path.segments.pop();
+            }
+            Self::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+                for (attr, attr_ty) in attributes {
+                    path.segments.push(TypePathSegment::Attribute(attr.clone()));
+                    attr_ty.ty.walk_with_path(path, f);
+                    path.segments.pop();
+                }
+            }
+            Self::Type(SchemaTypeVariant::Union { options }) => {
+                for option in options {
+                    option.walk_with_path(path, f);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl NamespaceDefinition {
+    /// Every type written at the top level of this namespace, which is named
+    /// `ns`, paired with the path naming it. This is the common types, then
+    /// the entity shapes, then the action contexts, each in sorted order. An
+    /// entity type's `additionalAttributesDefinition` follows its shape, with
+    /// the same path, and then the type of its tags.
+    pub(crate) fn declared_type_paths<'a>(
+        &'a self,
+        ns: &'a str,
+    ) -> impl Iterator<Item = (TypePath, &'a SchemaType)> {
+        let common_types = self
+            .common_types
+            .iter()
+            .sorted_by_key(|(name, _)| *name)
+            .map(move |(name, ty)| {
+                let root = TypePathRoot::CommonType(fully_qualify(ns, name));
+                (TypePath::new(root), ty)
+            });
+        let entity_types =
+            self.entity_types
+                .iter()
+                .sorted_by_key(|(name, _)| *name)
+                .flat_map(move |(name, et)| {
+                    let name = fully_qualify(ns, name);
+                    let root = TypePathRoot::EntityType(name.clone());
+                    let additional = et.additional_attributes_definition.iter();
+                    let tags = et.tags.iter().map(move |tags| {
+                        (TypePath::new(TypePathRoot::EntityTags(name.clone())), tags)
+                    });
+                    std::iter::once(&et.shape)
+                        .chain(additional)
+                        .map(move |shape| (TypePath::new(root.clone()), &shape.0))
+                        .chain(tags)
+                });
+        let actions =
+            self.actions
+                .iter()
+                .sorted_by_key(|(id, _)| *id)
+                .filter_map(move |(id, action)| {
+                    let root = TypePathRoot::ActionContext {
+                        ty: fully_qualify(ns, "Action"),
+                        id: id.clone(),
+                    };
+                    Some((TypePath::new(root), &action.applies_to.as_ref()?.context.0))
+                });
+        common_types.chain(entity_types).chain(actions)
+    }
+
+    /// The types of `declared_type_paths`, in the same order, which may be
+    /// modified in place.
+    pub(crate) fn declared_type_paths_mut<'a>(
+        &'a mut self,
+        ns: &'a str,
+    ) -> impl Iterator<Item = (TypePath, &'a mut SchemaType)> {
+        let common_types = self
+            .common_types
+            .iter_mut()
+            .sorted_by_key(|(name, _)| *name)
+            .map(move |(name, ty)| {
+                let root = TypePathRoot::CommonType(fully_qualify(ns, name));
+                (TypePath::new(root), ty)
+            });
+        let entity_types =
+            self.entity_types
+                .iter_mut()
+                .sorted_by_key(|(name, _)| *name)
+                .flat_map(move |(name, et)| {
+                    let name = fully_qualify(ns, name);
+                    let root = TypePathRoot::EntityType(name.clone());
+                    let additional = et.additional_attributes_definition.iter_mut();
+                    let tags = et.tags.iter_mut().map(move |tags| {
+                        (TypePath::new(TypePathRoot::EntityTags(name.clone())), tags)
+                    });
+                    std::iter::once(&mut et.shape)
+                        .chain(additional)
+                        .map(move |shape| (TypePath::new(root.clone()), &mut shape.0))
+                        .chain(tags)
+                });
+        let actions = self
+            .actions
+            .iter_mut()
+            .sorted_by_key(|(id, _)| *id)
+            .filter_map(move |(id, action)| {
+                let root = TypePathRoot::ActionContext {
+                    ty: fully_qualify(ns, "Action"),
+                    id: id.clone(),
+                };
+                Some((
+                    TypePath::new(root),
+                    &mut action.applies_to.as_mut()?.context.0,
+                ))
+            });
+        common_types.chain(entity_types).chain(actions)
+    }
+}
+
+impl SchemaFragment {
+    /// Visit every type at which a value may appear: the entity shapes and
+    /// `additionalAttributesDefinition`s, the types of entity tags and the
+    /// action contexts in this fragment, and all types nested inside them.
+    /// Common types are replaced by their definitions, so the visitor only sees
+    /// a `TypeDef` when it cannot be resolved (or is defined in terms of
+    /// itself). The visitor is also passed the namespace the visited type was
+    /// written in, which is needed to qualify any entity type names it
+    /// contains. Declarations are visited in sorted order.
+    pub(crate) fn walk_value_types<'a>(
+        &'a self,
+        f: &mut impl FnMut(&TypePath, &'a str, &'a SchemaType),
+    ) {
+        for (ns, ns_def) in self.0.iter().sorted_by_key(|(ns, _)| *ns) {
+            for (mut path, ty) in ns_def.declared_type_paths(ns) {
+                if !matches!(path.root(), TypePathRoot::CommonType(_)) {
+                    self.walk_resolved(ns, ty, &mut path, &mut Vec::new(), f);
+                }
+            }
+        }
+    }
+
+    fn walk_resolved<'a>(
+        &'a self,
+        ns: &'a str,
+        ty: &'a SchemaType,
+        path: &mut TypePath,
+        expanding: &mut Vec<SmolStr>,
+        f: &mut impl FnMut(&TypePath, &'a str, &'a SchemaType),
+    ) {
+        match ty {
+            SchemaType::TypeDef { type_name } => {
+                let qualified = fully_qualify(ns, type_name);
+                match self.lookup_common_type(ns, type_name) {
+                    Some((def_ns, def)) if !expanding.contains(&qualified) => {
+                        expanding.push(qualified);
+                        self.walk_resolved(def_ns, def, path, expanding, f);
+                        expanding.pop();
+                    }
+                    _ => f(path, ns, ty),
+                }
+            }
+            SchemaType::Type(variant) => {
+                f(path, ns, ty);
+                match variant {
+                    SchemaTypeVariant::Set { element } => {
+                        path.segments.push(TypePathSegment::SetElement);
+                        self.walk_resolved(ns, element, path, expanding, f);
+                        path.segments.pop();
+                    }
+                    SchemaTypeVariant::Record { attributes, .. } => {
+                        for (attr, attr_ty) in attributes {
+                            path.segments.push(TypePathSegment::Attribute(attr.clone()));
+                            self.walk_resolved(ns, &attr_ty.ty, path, expanding, f);
+                            path.segments.pop();
+                        }
+                    }
+                    SchemaTypeVariant::Union { options } => {
+                        for option in options {
+                            self.walk_resolved(ns, option, path, expanding, f);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let path = TypePath::new(TypePathRoot::EntityType("App::User".into()))
+            .child(TypePathSegment::Attribute("addresses".into()))
+            .child(TypePathSegment::SetElement)
+            .child(TypePathSegment::Attribute("city".into()));
+        assert_eq!(path.to_string(), "App::User.addresses[].city");
+
+        let path = TypePath::new(TypePathRoot::ActionContext {
+            ty: "Action".into(),
+            id: "view".into(),
+        })
+        .child(TypePathSegment::Attribute("ip".into()));
+        assert_eq!(path.to_string(), r#"Action::"view".context.ip"#);
+
+        let path = TypePath::new(TypePathRoot::CommonType("App::Location".into()));
+        assert_eq!(path.to_string(), "type App::Location");
+    }
+
+    #[test]
+    fn accessors() {
+        let path: TypePath = "App::Sub::User.addresses[].city"
+            .parse()
+            .expect("Valid path");
+        assert_eq!(path.namespace(), "App::Sub");
+        assert_eq!(path.owner(), "User");
+        assert_eq!(path.attributes(), vec!["addresses", "city"]);
+
+        let path: TypePath = r#"App::Action::"view".context.ip"#.parse().expect("Valid path");
+        assert_eq!(path.namespace(), "App");
+        assert_eq!(path.owner(), "view");
+        assert_eq!(path.attributes(), vec!["ip"]);
+
+        let path: TypePath = "tags App::Doc.owner".parse().expect("Valid path");
+        assert_eq!(path.root(), &TypePathRoot::EntityTags("App::Doc".into()));
+        assert_eq!(path.namespace(), "App");
+        assert_eq!(path.owner(), "Doc");
+
+        let path: TypePath = "type Location[]".parse().expect("Valid path");
+        assert_eq!(path.namespace(), "");
+        assert_eq!(path.owner(), "Location");
+        assert!(path.attributes().is_empty());
+    }
+
+    #[test]
+    fn from_str() {
+        for path in [
+            "App::User",
+            "App::User.addresses[].city",
+            "User.tags[][]",
+            r#"App::Action::"view".context.ip"#,
+            r#"Action::"view photo".context"#,
+            "type App::Location.lat",
+            "tags App::Doc[]",
+        ] {
+            assert_eq!(
+                path.parse::<TypePath>().expect("Valid path").to_string(),
+                path
+            );
+        }
+        let path = TypePath::new(TypePathRoot::ActionContext {
+            ty: "App::Action".into(),
+            id: "view".into(),
+        })
+        .child(TypePathSegment::SetElement)
+        .child(TypePathSegment::Attribute("ip".into()));
+        assert_eq!(path.to_string().parse::<TypePath>(), Ok(path));
+        for invalid in [
+            "",
+            "App::",
+            "App::User.",
+            "App::User..name",
+            "App::User[",
+            "type ",
+            "tags ",
+        ] {
+            assert_eq!(
+                invalid.parse::<TypePath>(),
+                Err(TypePathParseError(invalid.to_string()))
+            );
+        }
+    }
+}
@@ -0,0 +1,169 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The units numeric attributes are declared to be measured in.
+
+use smol_str::SmolStr;
+
+use super::{
+    diff::describe_type, SchemaFragment, SchemaType, SchemaTypeVariant, TypePath, TypePathSegment,
+};
+use crate::{Result, SchemaError};
+
+impl SchemaFragment {
+    /// Every attribute of an entity shape or action context with a `unit`,
+    /// paired with the unit, including attributes of common types used by
+    /// them. Attributes are returned in sorted order of declaration.
+    pub fn units(&self) -> Vec<(TypePath, SmolStr)> {
+        let mut units = Vec::new();
+        self.walk_value_types(&mut |path, _, ty| {
+            if let SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) = ty {
+                for (attr, attr_ty) in attributes {
+                    if let Some(unit) = &attr_ty.unit {
+                        let path = path.child(TypePathSegment::Attribute(attr.clone()));
+                        units.push((path, unit.clone()));
+                    }
+                }
+            }
+        });
+        units
+    }
+
+    /// Check that every attribute with a `unit` is a `Long` or `decimal`,
+    /// after resolving common types, failing with
+    /// `SchemaError::UnitOnNonNumeric` for the first one which is not.
+    /// Attributes whose type cannot be resolved are not checked.
+    pub(crate) fn validate_units(&self) -> Result<()> {
+        let mut result = Ok(());
+        self.walk_value_types(&mut |path, ns, ty| {
+            let SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) = ty else {
+                return;
+            };
+            for (attr, attr_ty) in attributes {
+                if result.is_err() || attr_ty.unit.is_none() {
+                    continue;
+                }
+                let Ok(resolved) = self.resolve_type(ns, &attr_ty.ty) else {
+                    continue;
+                };
+                let numeric = match &resolved {
+                    SchemaType::Type(SchemaTypeVariant::Long { .. }) => true,
+                    SchemaType::Type(SchemaTypeVariant::Extension { name, .. }) => {
+                        name == "decimal"
+                    }
+                    _ => false,
+                };
+                if !numeric {
+                    result = Err(SchemaError::UnitOnNonNumeric(
+                        path.child(TypePathSegment::Attribute(attr.clone())),
+                        describe_type(&resolved),
+                    ));
+                }
+            }
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::ValidatorSchema;
+
+    #[test]
+    fn units() {
+        let schema = json!({
+            "App": {
+                "commonTypes": {
+                    "Duration": { "type": "Long" }
+                },
+                "entityTypes": {
+                    "File": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "sizeBytes": { "type": "Long", "unit": "bytes" },
+                                "ttl": { "type": "Duration", "unit": "s" },
+                                "name": { "type": "String" }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "upload": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "durationMs": { "type": "Long", "unit": "ms", "required": false }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        let fragment = SchemaFragment::from_json_value(schema.clone()).expect("Parse Error");
+        let units = fragment
+            .units()
+            .into_iter()
+            .map(|(path, unit)| (path.to_string(), unit))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            units,
+            vec![
+                ("App::File.sizeBytes".to_string(), "bytes".into()),
+                ("App::File.ttl".to_string(), "s".into()),
+                (
+                    r#"App::Action::"upload".context.durationMs"#.to_string(),
+                    "ms".into()
+                ),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&fragment).expect("Serialize Error")["App"]["entityTypes"]["File"]
+                ["shape"]["attributes"]["sizeBytes"],
+            json!({ "type": "Long", "required": true, "unit": "bytes" })
+        );
+        ValidatorSchema::from_json_value(schema).expect("units are on numeric attributes");
+    }
+
+    #[test]
+    fn unit_on_non_numeric() {
+        let schema = json!({
+            "App": {
+                "entityTypes": {
+                    "File": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "name": { "type": "String", "unit": "chars" } }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        });
+        match ValidatorSchema::from_json_value(schema) {
+            Err(SchemaError::UnitOnNonNumeric(path, ty)) => {
+                assert_eq!(path.to_string(), "App::File.name");
+                assert_eq!(ty, "String");
+            }
+            r => panic!("Expected a unit on a non-numeric attribute, got {r:?}"),
+        }
+    }
+}
@@ -0,0 +1,666 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Batch validation of a schema fragment, collecting every problem found
+//! instead of stopping at the first one.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{
+    diff::describe_type,
+    fully_qualify,
+    references::{extension_names, undefined_namespace},
+    SchemaFragment, SchemaType, SchemaTypeVariant, TypeOfAttribute, TypePath, TypePathRoot,
+    TypePathSegment,
+};
+use crate::SchemaError;
+
+/// How serious a `ValidationFinding` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The schema cannot be used.
+    Error,
+    /// The schema can be used, but is probably not what was intended.
+    Warning,
+}
+
+/// Where in a schema a `ValidationFinding` was made.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SchemaLocation {
+    /// A position inside a declared type.
+    Type(TypePath),
+    /// A JSON pointer into the schema document, used for locations outside
+    /// of types, e.g. `/App/entityTypes/User/memberOfTypes/0`.
+    Pointer(String),
+}
+
+impl std::fmt::Display for SchemaLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Type(path) => write!(f, "{path}"),
+            Self::Pointer(pointer) => write!(f, "{pointer}"),
+        }
+    }
+}
+
+/// A JSON pointer made of `segments`, escaped as required by RFC 6901.
+pub(crate) fn json_pointer(segments: &[&str]) -> String {
+    segments
+        .iter()
+        .map(|s| format!("/{}", s.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// A single problem found by `SchemaFragment::validate_all`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationFinding {
+    severity: Severity,
+    location: SchemaLocation,
+    message: String,
+}
+
+impl ValidationFinding {
+    pub(crate) fn new(severity: Severity, location: SchemaLocation, message: String) -> Self {
+        Self {
+            severity,
+            location,
+            message,
+        }
+    }
+
+    /// How serious the problem is.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Where the problem was found.
+    pub fn location(&self) -> &SchemaLocation {
+        &self.location
+    }
+
+    /// Description of the problem.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for ValidationFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{severity} at `{}`: {}", self.location, self.message)
+    }
+}
+
+/// Every problem found by `SchemaFragment::validate_all`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    findings: Vec<ValidationFinding>,
+}
+
+impl ValidationReport {
+    /// Iterate over the findings in the report.
+    pub fn findings(&self) -> impl Iterator<Item = &ValidationFinding> {
+        self.findings.iter()
+    }
+
+    /// True if no finding has `Severity::Error`.
+    pub fn is_ok(&self) -> bool {
+        self.findings.iter().all(|f| f.severity != Severity::Error)
+    }
+
+    fn push(&mut self, severity: Severity, location: SchemaLocation, message: String) {
+        self.findings
+            .push(ValidationFinding::new(severity, location, message));
+    }
+}
+
+/// Options for `SchemaFragment::validate_all_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationOptions {
+    /// The severity with which string `enum`s listing a value more than once
+    /// are reported. The repeated values are redundant rather than harmful,
+    /// so this is `Severity::Warning` by default.
+    pub duplicate_enum_values: Severity,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        Self {
+            duplicate_enum_values: Severity::Warning,
+        }
+    }
+}
+
+impl SchemaFragment {
+    /// Check this fragment, reporting every problem found rather than
+    /// stopping at the first. This checks for
+    /// - actions declared more than once using different spellings,
+    /// - common types with the same name as an entity type or action in
+    ///   their namespace,
+    /// - `memberOf` entries whose type is not an action entity type,
+    /// - references to entity types, common types and actions which are not
+    ///   declared in this fragment, or which are qualified with a namespace
+    ///   this fragment does not define,
+    /// - `Entity` types naming an extension type, such as `decimal`, rather
+    ///   than an entity type,
+    /// - common types defined in terms of themselves,
+    /// - entity shapes and action contexts which are not records,
+    /// - unknown extension types, and `ipKind` on extension types other than
+    ///   `ipaddr`,
+    /// - string `enum`s with no values, which no value can satisfy, and
+    ///   string `enum`s listing a value more than once, which are reported as
+    ///   warnings unless configured otherwise with `validate_all_with`,
+    /// - sets whose element type is a common type defined as a set, which are
+    ///   reported as warnings since the nesting is easy to miss,
+    /// - optional attributes whose type is a common type defined as a record
+    ///   with required attributes, which are reported as warnings since
+    ///   those attributes are only required when the optional attribute is
+    ///   present,
+    /// - cycles in the action hierarchy, naming the actions on a cycle, and
+    /// - cycles in the entity type hierarchy, naming the entity types on the
+    ///   shortest cycle through each entity type on one. As for
+    ///   `NamespaceDefinition::check_entity_hierarchy_acyclic`, an entity type
+    ///   being a member of itself is not a cycle.
+    ///
+    /// Namespaces are checked in sorted order, so the report is
+    /// deterministic.
+    pub fn validate_all(&self) -> ValidationReport {
+        self.validate_all_with(ValidationOptions::default())
+    }
+
+    /// Check this fragment as `validate_all` does, with `options`.
+    pub fn validate_all_with(&self, options: ValidationOptions) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let declared = self.declarations();
+        let namespaces = self.0.keys().map(SmolStr::as_str).collect();
+        let extensions = extension_names();
+        for (ns, ns_def) in self.0.iter().sorted_by_key(|(ns, _)| *ns) {
+            let actions_location = SchemaLocation::Pointer(json_pointer(&[ns, "actions"]));
+            if let Err(e) = ns_def.validate_action_uniqueness() {
+                report.push(Severity::Error, actions_location.clone(), e.to_string());
+            }
+            if let Err(e) = ns_def.validate_no_name_shadowing() {
+                let location = SchemaLocation::Pointer(json_pointer(&[ns, "commonTypes"]));
+                report.push(Severity::Error, location, e.to_string());
+            }
+            if let Err(e) = ns_def.check_action_hierarchy_acyclic(ns) {
+                report.push(Severity::Error, actions_location, e.to_string());
+            }
+
+            for (location, euid) in ns_def.non_action_member_of(ns) {
+                let e = SchemaError::NonActionMemberOf(euid.to_string());
+                report.push(Severity::Error, location, e.to_string());
+            }
+            let extension_refs = ns_def.extension_entity_references(ns, &declared);
+            for (location, name) in &extension_refs {
+                let e = SchemaError::ExtensionAsEntityType(location.clone(), name.to_string());
+                report.push(Severity::Error, location.clone(), e.to_string());
+            }
+            for (location, reference) in ns_def.located_references(ns) {
+                if declared.contains(&reference)
+                    || extension_refs.iter().any(|(l, _)| *l == location)
+                {
+                    continue;
+                }
+                match undefined_namespace(&namespaces, &location, &reference) {
+                    Some(e) => report.push(Severity::Error, location, e.to_string()),
+                    None => {
+                        report.push(Severity::Error, location, format!("undeclared {reference}"))
+                    }
+                }
+            }
+
+            for (path, ty) in ns_def.declared_type_paths(ns) {
+                match (path.root(), self.resolve_type(ns, ty)) {
+                    (
+                        TypePathRoot::CommonType(name),
+                        Err(SchemaError::CycleInCommonTypeReferences(cycle)),
+                    ) if name == &cycle => {
+                        let e = SchemaError::CycleInCommonTypeReferences(cycle);
+                        report.push(
+                            Severity::Error,
+                            SchemaLocation::Type(path.clone()),
+                            e.to_string(),
+                        );
+                    }
+                    (
+                        TypePathRoot::EntityType(_) | TypePathRoot::ActionContext { .. },
+                        Ok(resolved),
+                    ) if !matches!(
+                        resolved,
+                        SchemaType::Type(SchemaTypeVariant::Record { .. })
+                    ) =>
+                    {
+                        report.push(
+                            Severity::Error,
+                            SchemaLocation::Type(path.clone()),
+                            SchemaError::ContextOrShapeNotRecord.to_string(),
+                        );
+                    }
+                    _ => (),
+                }
+                let mut path = path;
+                ty.walk_with_path(&mut path, &mut |path, ty| match ty {
+                    SchemaType::Type(SchemaTypeVariant::Extension { name, .. })
+                        if !extensions.contains(name.as_str()) =>
+                    {
+                        report.push(
+                            Severity::Error,
+                            SchemaLocation::Type(path.clone()),
+                            format!("unknown extension type `{name}`"),
+                        );
+                    }
+                    SchemaType::Type(SchemaTypeVariant::Extension {
+                        name,
+                        ip_kind: Some(_),
+                    }) if name != "ipaddr" => {
+                        report.push(
+                            Severity::Error,
+                            SchemaLocation::Type(path.clone()),
+                            SchemaError::IpKindOnNonIpaddr(name.to_string()).to_string(),
+                        );
+                    }
+                    SchemaType::Type(SchemaTypeVariant::String {
+                        enumeration: Some(values),
+                        ..
+                    }) => {
+                        let location = || SchemaLocation::Type(path.clone());
+                        if values.is_empty() {
+                            report.push(
+                                Severity::Error,
+                                location(),
+                                "string `enum` has no values, so no value can satisfy it"
+                                    .to_string(),
+                            );
+                        }
+                        let duplicates = values.iter().duplicates().collect_vec();
+                        if !duplicates.is_empty() {
+                            report.push(
+                                options.duplicate_enum_values,
+                                location(),
+                                format!(
+                                    "string `enum` lists {} more than once",
+                                    duplicates.iter().map(|v| format!("\"{v}\"")).join(", ")
+                                ),
+                            );
+                        }
+                    }
+                    SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+                        if let Some(message) = self.implicit_nested_set_message(ns, element) {
+                            report.push(
+                                Severity::Warning,
+                                SchemaLocation::Type(path.clone()),
+                                message,
+                            );
+                        }
+                    }
+                    SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+                        for (attr, attr_ty) in attributes {
+                            if let Some(message) = self.optional_wrapper_message(ns, attr_ty) {
+                                let path = path.child(TypePathSegment::Attribute(attr.clone()));
+                                report.push(Severity::Warning, SchemaLocation::Type(path), message);
+                            }
+                        }
+                    }
+                    _ => (),
+                });
+            }
+        }
+        self.report_entity_type_cycles(&mut report);
+        report
+    }
+
+    /// A description of how required-ness works out for `attr_ty`, written in
+    /// namespace `ns`, if it is an optional attribute whose type is a common
+    /// type resolving to a record with required attributes.
+    fn optional_wrapper_message(&self, ns: &str, attr_ty: &TypeOfAttribute) -> Option<String> {
+        let SchemaType::TypeDef { type_name } = &*attr_ty.ty else {
+            return None;
+        };
+        if attr_ty.required {
+            return None;
+        }
+        let resolved = self.resolve_type(ns, &attr_ty.ty).ok()?;
+        let SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) = &resolved else {
+            return None;
+        };
+        let required = attributes
+            .iter()
+            .filter(|(_, attr_ty)| attr_ty.required)
+            .map(|(attr, _)| format!("`{attr}`"))
+            .collect_vec();
+        if required.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "attribute is optional, but its type `{type_name}` requires {}, which are required \
+             only when the attribute is present; resolved type is `{}`",
+            required.join(", "),
+            describe_type(&resolved)
+        ))
+    }
+
+    /// A description of the nesting, if `element`, the element type of a set
+    /// written in namespace `ns`, is a common type which resolves to a set.
+    fn implicit_nested_set_message(&self, ns: &str, element: &SchemaType) -> Option<String> {
+        let SchemaType::TypeDef { type_name } = element else {
+            return None;
+        };
+        let resolved = self.resolve_type(ns, element).ok()?;
+        if !matches!(resolved, SchemaType::Type(SchemaTypeVariant::Set { .. })) {
+            return None;
+        }
+        Some(format!(
+            "set of common type `{type_name}`, which is itself a set; resolved type is `Set<{}>`",
+            describe_type(&resolved)
+        ))
+    }
+
+    fn report_entity_type_cycles(&self, report: &mut ValidationReport) {
+        let parents: BTreeMap<SmolStr, (SmolStr, &SmolStr, BTreeSet<SmolStr>)> = self
+            .0
+            .iter()
+            .flat_map(|(ns, ns_def)| {
+                ns_def.entity_types.iter().map(move |(name, et)| {
+                    let parents = et
+                        .member_of_types
+                        .iter()
+                        .map(|parent| fully_qualify(ns, parent))
+                        .collect();
+                    (fully_qualify(ns, name), (ns.clone(), name, parents))
+                })
+            })
+            .collect();
+        for (qualified, (ns, name, _)) in &parents {
+            if let Some(cycle) = entity_type_cycle(qualified, &parents) {
+                report.push(
+                    Severity::Error,
+                    SchemaLocation::Pointer(json_pointer(&[
+                        ns,
+                        "entityTypes",
+                        name,
+                        "memberOfTypes",
+                    ])),
+                    SchemaError::CycleInEntityTypeHierarchy(cycle).to_string(),
+                );
+            }
+        }
+    }
+}
+
+/// The shortest cycle from the entity type `start` back to itself through the
+/// graph `parents`, of qualified entity type names, with `start` at both ends.
+/// Entity types which are members of themselves are not followed.
+fn entity_type_cycle(
+    start: &SmolStr,
+    parents: &BTreeMap<SmolStr, (SmolStr, &SmolStr, BTreeSet<SmolStr>)>,
+) -> Option<Vec<String>> {
+    let mut previous: BTreeMap<&SmolStr, &SmolStr> = BTreeMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(ty) = queue.pop_front() {
+        let Some((_, _, direct)) = parents.get(ty) else {
+            continue;
+        };
+        for parent in direct.iter().filter(|parent| *parent != ty) {
+            if parent == start {
+                let mut chain = vec![start.to_string()];
+                let mut back = Vec::new();
+                let mut at = ty;
+                while at != start {
+                    back.push(at.to_string());
+                    at = previous[at];
+                }
+                chain.extend(back.into_iter().rev());
+                chain.push(start.to_string());
+                return Some(chain);
+            }
+            if !previous.contains_key(parent) {
+                previous.insert(parent, ty);
+                queue.push_back(parent);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn reports_every_finding() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Loop": { "type": "Set", "element": { "type": "Loop" } },
+                    "Name": { "type": "String" }
+                },
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group", "User"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "manager": { "type": "Entity", "name": "Manager" },
+                                "owner": { "type": "Entity", "name": "Other::User" },
+                                "ip": { "type": "Extension", "name": "ipv6" }
+                            }
+                        }
+                    },
+                    "Group": { "memberOfTypes": ["User"], "shape": { "type": "Name" } }
+                },
+                "actions": {
+                    "view": { "memberOf": [{ "id": "edit" }] },
+                    "edit": { "memberOf": [{ "id": "view" }, { "id": "admin" }] }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let report = fragment.validate_all();
+        assert!(!report.is_ok());
+        let findings = report
+            .findings()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            vec![
+                r#"error at `/App/actions`: Cycle in action groups: App::Action::"edit" -> App::Action::"view" -> App::Action::"edit""#,
+                "error at `App::User.manager`: undeclared entity type `App::Manager`",
+                "error at `App::User.owner`: Namespace `Other` referenced at `App::User.owner` \
+                 is not defined",
+                r#"error at `/App/actions/edit/memberOf/1`: undeclared action `App::Action::"admin"`"#,
+                "error at `type App::Loop`: Common type `App::Loop` is defined in terms of itself",
+                "error at `App::Group`: An entity shape or action context is declared with a type \
+                 other than `Record`",
+                "error at `App::User.ip`: unknown extension type `ipv6`",
+                "error at `/App/entityTypes/Group/memberOfTypes`: Cycle in entity type \
+                 hierarchy: App::Group -> App::User -> App::Group",
+                "error at `/App/entityTypes/User/memberOfTypes`: Cycle in entity type \
+                 hierarchy: App::User -> App::Group -> App::User",
+            ]
+        );
+    }
+
+    #[test]
+    fn self_membership_is_fine() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "": {
+                "entityTypes": { "Group": { "memberOfTypes": ["Group"] } },
+                "actions": { "view": {} }
+            }
+        }))
+        .expect("Parse Error");
+        let report = fragment.validate_all();
+        assert!(report.is_ok());
+        assert_eq!(report.findings().count(), 0);
+    }
+
+    #[test]
+    fn optional_common_type_with_required_attributes() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Address": {
+                        "type": "Record",
+                        "attributes": {
+                            "city": { "type": "String" },
+                            "zip": { "type": "Long", "required": false }
+                        }
+                    },
+                    "Note": { "type": "Record", "attributes": { "text": { "type": "String", "required": false } } }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "home": { "type": "Address", "required": false },
+                                "work": { "type": "Address" },
+                                "note": { "type": "Note", "required": false }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let report = fragment.validate_all();
+        assert!(report.is_ok());
+        assert_eq!(
+            report
+                .findings()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "warning at `App::User.home`: attribute is optional, but its type `Address` \
+                 requires `city`, which are required only when the attribute is present; \
+                 resolved type is `{ city: String, zip?: Long }`"
+            ]
+        );
+    }
+
+    #[test]
+    fn set_of_common_set_type() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Tags": { "type": "Set", "element": { "type": "String" } },
+                    "Name": { "type": "String" }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "tagGroups": { "type": "Set", "element": { "type": "Tags" } },
+                                "names": { "type": "Set", "element": { "type": "Name" } },
+                                "tags": { "type": "Tags" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(
+            fragment
+                .validate_all()
+                .findings()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "warning at `App::User.tagGroups`: set of common type `Tags`, which is itself a \
+                 set; resolved type is `Set<Set<String>>`"
+            ]
+        );
+    }
+
+    #[test]
+    fn string_enum_values() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "status": { "type": "String", "enum": [] },
+                                "role": {
+                                    "type": "String",
+                                    "enum": ["admin", "viewer", "admin", "viewer", "admin"]
+                                },
+                                "tier": { "type": "String", "enum": ["free", "paid"] }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let findings = |options| {
+            fragment
+                .validate_all_with(options)
+                .findings()
+                .map(|f| {
+                    (
+                        f.severity(),
+                        f.location().to_string(),
+                        f.message().to_string(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let duplicate = |severity| {
+            (
+                severity,
+                "App::User.role".to_string(),
+                "string `enum` lists \"admin\", \"viewer\" more than once".to_string(),
+            )
+        };
+        let empty = (
+            Severity::Error,
+            "App::User.status".to_string(),
+            "string `enum` has no values, so no value can satisfy it".to_string(),
+        );
+        assert_eq!(
+            findings(ValidationOptions::default()),
+            vec![duplicate(Severity::Warning), empty.clone()]
+        );
+        assert_eq!(
+            findings(ValidationOptions {
+                duplicate_enum_values: Severity::Error
+            }),
+            vec![duplicate(Severity::Error), empty]
+        );
+    }
+
+    #[test]
+    fn pointer_escaping() {
+        assert_eq!(json_pointer(&["a/b", "c~d"]), "/a~1b/c~0d");
+    }
+}
@@ -0,0 +1,1527 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Checks JSON values against types written in the schema file format,
+//! without first building a `ValidatorSchema`.
+
+use std::collections::{HashMap, HashSet};
+
+use cedar_policy_core::{
+    ast::{Name, Value},
+    entities::{
+        EntityUidJSON, JSONValue, JsonDeserializationErrorContext, SchemaType as CoreSchemaType,
+        ValueParser,
+    },
+    evaluator::RestrictedEvaluator,
+    extensions::Extensions,
+};
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::{
+    diff::describe_type, fully_qualify, ActionType, AttributesOrContext, EntityType, IpKind,
+    SchemaFragment, SchemaType, SchemaTypeVariant, TypeOfAttribute, TypePath, TypePathSegment,
+};
+use crate::{Result, SchemaError};
+
+impl ActionType {
+    /// Check that `context` is a valid context for this action, which is
+    /// declared in namespace `ns` of `schema`. Common types in the context
+    /// declaration are resolved against `schema`. Required attributes must be
+    /// present, attributes not declared in a closed record are rejected, and
+    /// extension values, including those nested in records and sets, must be
+    /// valid literals for their extension type. An extension value where the
+    /// context type does not have an extension type is rejected. Errors name
+    /// the path to the offending value, and for an invalid extension literal
+    /// describe the literals the extension type accepts. An action without a
+    /// declared context only accepts an empty record.
+    pub fn validate_context(
+        &self,
+        context: &serde_json::Value,
+        schema: &SchemaFragment,
+        ns: &str,
+    ) -> Result<()> {
+        let default_context = AttributesOrContext::default();
+        let context_ty = self
+            .applies_to
+            .as_ref()
+            .map_or(&default_context, |applies_to| &applies_to.context);
+        ValueChecker::new(schema).check(ns, &context_ty.0, context, "context")
+    }
+
+    /// Check that every attribute value declared for this action is a legal
+    /// Cedar value: escapes must be well formed, and extension constructor
+    /// calls (e.g., `decimal("1.2.3")`) must succeed. Attributes are checked in
+    /// sorted order and the first problem is reported.
+    pub fn validate_attribute_values(&self) -> Result<()> {
+        let extensions = Extensions::all_available();
+        let evaluator = RestrictedEvaluator::new(&extensions);
+        for (attr, value) in self
+            .attributes
+            .iter()
+            .flatten()
+            .sorted_by_key(|(attr, _)| *attr)
+        {
+            value
+                .clone()
+                .into_expr()
+                .map_err(|e| e.to_string())
+                .and_then(|rexpr| {
+                    evaluator
+                        .interpret(rexpr.as_borrowed())
+                        .map_err(|e| e.to_string())
+                })
+                .map_err(|e| SchemaError::InvalidActionAttribute(attr.to_string(), e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Selects how attributes marked `computed` are treated when validating
+/// entity data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeValidationMode {
+    /// Validating entity data supplied by a client. Computed attributes must
+    /// not be present, and are not required even if they are declared as
+    /// required.
+    InputValidation,
+    /// Validating entity data after the application has filled in computed
+    /// attributes. Computed attributes must be present.
+    StoredValidation,
+}
+
+impl EntityType {
+    /// Check that `attrs` is a valid attribute record for this entity type,
+    /// which is declared in namespace `ns` of `schema`. Attribute types are
+    /// checked as in `ActionType::validate_context`, and computed attributes
+    /// are checked according to `mode`, both at the top level and in nested
+    /// records. Any `additionalAttributesDefinition` is merged into the shape
+    /// first, as by `resolved_shape`.
+    pub fn validate_attributes(
+        &self,
+        attrs: &serde_json::Value,
+        schema: &SchemaFragment,
+        ns: &str,
+        mode: AttributeValidationMode,
+    ) -> Result<()> {
+        let checker = ValueChecker::new(schema).with_mode(mode);
+        match &self.additional_attributes_definition {
+            Some(_) => {
+                let shape = SchemaType::Type(self.resolved_shape(schema, ns)?);
+                checker.check(ns, &shape, attrs, "attrs")
+            }
+            None => checker.check(ns, &self.shape.0, attrs, "attrs"),
+        }
+    }
+
+    /// Check that `attrs` is a valid partial update to an entity of this
+    /// type, which is declared in namespace `ns` of `schema`, such as the
+    /// body of a PATCH request. Unlike `validate_attributes`, top-level
+    /// attributes which are missing from `attrs` are not an error, even when
+    /// they are required, since they keep their stored values. Attributes
+    /// which are present are checked in full, including any nested records
+    /// they hold, and attributes not declared in a closed shape are still
+    /// rejected. Computed attributes are not treated specially.
+    pub fn validate_partial(
+        &self,
+        attrs: &serde_json::Value,
+        schema: &SchemaFragment,
+        ns: &str,
+    ) -> Result<()> {
+        let SchemaTypeVariant::Record {
+            attributes,
+            additional_attributes,
+        } = self.resolved_shape(schema, ns)?
+        else {
+            return Err(SchemaError::ContextOrShapeNotRecord);
+        };
+        let optional = SchemaType::Type(SchemaTypeVariant::Record {
+            attributes: attributes
+                .into_iter()
+                .map(|(attr, attr_ty)| {
+                    let attr_ty = TypeOfAttribute {
+                        required: false,
+                        required_if: None,
+                        unit: None,
+                        ..attr_ty
+                    };
+                    (attr, attr_ty)
+                })
+                .collect(),
+            additional_attributes,
+        });
+        ValueChecker::new(schema).check(ns, &optional, attrs, "attrs")
+    }
+}
+
+/// The kind of a scalar value, as reported by
+/// `SchemaFragment::leaf_scalar_paths`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ScalarKind {
+    /// A `String`.
+    String,
+    /// A `Long`.
+    Long,
+    /// A `Boolean`.
+    Boolean,
+    /// A value of the named extension type.
+    Extension(SmolStr),
+}
+
+impl SchemaFragment {
+    /// Every position in an entity shape or action context where a value will
+    /// be parsed as an extension value, paired with the name of the extension
+    /// type. Sets, records and common types are followed, so an attribute
+    /// whose type is a common type defined as a set of `decimal` is reported
+    /// as `<attribute path>[]`.
+    pub fn extension_value_sites(&self) -> Vec<(TypePath, SmolStr)> {
+        let mut sites = Vec::new();
+        self.walk_value_types(&mut |path, _, ty| {
+            if let SchemaType::Type(SchemaTypeVariant::Extension { name, .. }) = ty {
+                sites.push((path.clone(), name.clone()));
+            }
+        });
+        sites
+    }
+
+    /// Every position in an entity shape or action context where a scalar
+    /// value appears, i.e., a `String`, `Long`, `Boolean` or extension value,
+    /// paired with the kind of scalar. Records, sets and common types are
+    /// followed as in `extension_value_sites`, so a position inside a set has
+    /// a `TypePathSegment::SetElement` in its path. Entity references are not
+    /// scalars. Positions are listed in declaration order, with declarations
+    /// and attributes sorted by name.
+    pub fn leaf_scalar_paths(&self) -> Vec<(TypePath, ScalarKind)> {
+        let mut leaves = Vec::new();
+        self.walk_value_types(&mut |path, _, ty| {
+            let kind = match ty {
+                SchemaType::Type(SchemaTypeVariant::String { .. }) => ScalarKind::String,
+                SchemaType::Type(SchemaTypeVariant::Long { .. }) => ScalarKind::Long,
+                SchemaType::Type(SchemaTypeVariant::Boolean) => ScalarKind::Boolean,
+                SchemaType::Type(SchemaTypeVariant::Extension { name, .. }) => {
+                    ScalarKind::Extension(name.clone())
+                }
+                _ => return,
+            };
+            leaves.push((path.clone(), kind));
+        });
+        leaves
+    }
+
+    /// Check that every extension type used by an entity shape or action
+    /// context is in `enabled`, e.g. the extensions compiled into the
+    /// evaluator the schema will be deployed with. Fails with
+    /// `SchemaError::ExtensionsNotEnabled` listing each use of an extension
+    /// which is not enabled, in the order of `extension_value_sites`.
+    /// Extension types are only the built-in ones, since the schema format
+    /// has no way to declare others.
+    pub fn require_extensions(&self, enabled: &HashSet<SmolStr>) -> Result<()> {
+        let disabled = self
+            .extension_value_sites()
+            .into_iter()
+            .filter(|(_, ext)| !enabled.contains(ext))
+            .map(|(path, ext)| (ext.to_string(), path))
+            .collect_vec();
+        if disabled.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaError::ExtensionsNotEnabled(disabled))
+        }
+    }
+
+    /// The positions returned by `extension_value_sites`, split into those
+    /// under a required attribute and those under an optional one, as
+    /// `(required, optional)`. A position is classified by the innermost
+    /// attribute containing it, so the elements of a set inherit the
+    /// requiredness of the attribute holding the set.
+    pub fn extension_sites_by_requiredness(&self) -> (Vec<TypePath>, Vec<TypePath>) {
+        let mut required_attrs = HashMap::new();
+        let mut required = Vec::new();
+        let mut optional = Vec::new();
+        self.walk_value_types(&mut |path, _, ty| match ty {
+            SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+                for (attr, attr_ty) in attributes {
+                    let attr_path = path.child(TypePathSegment::Attribute(attr.clone()));
+                    required_attrs.insert(attr_path, attr_ty.required);
+                }
+            }
+            SchemaType::Type(SchemaTypeVariant::Set { .. }) => {
+                if let Some(is_required) = required_attrs.get(path).copied() {
+                    required_attrs.insert(path.child(TypePathSegment::SetElement), is_required);
+                }
+            }
+            SchemaType::Type(SchemaTypeVariant::Extension { .. }) => {
+                if required_attrs.get(path).copied().unwrap_or(true) {
+                    required.push(path.clone());
+                } else {
+                    optional.push(path.clone());
+                }
+            }
+            _ => (),
+        });
+        (required, optional)
+    }
+
+    /// The example values given for attributes of entity shapes and action
+    /// contexts, paired with the path to the attribute. As with
+    /// `extension_value_sites`, examples on attributes of common types are
+    /// reported at each position where the common type is used.
+    pub fn examples(&self) -> Vec<(TypePath, JSONValue)> {
+        let mut examples = Vec::new();
+        self.walk_attribute_examples(&mut |path, _, _, example| {
+            examples.push((path, example.clone()));
+        });
+        examples
+    }
+
+    /// Check that every example value given for an attribute matches the
+    /// attribute type, reporting the first mismatch found. Unlike `examples`,
+    /// this visits each example once, where it is written: examples on
+    /// attributes of common types are checked in the common type declaration,
+    /// whether or not the common type is used.
+    pub(crate) fn validate_examples(&self) -> Result<()> {
+        let checker = ValueChecker::new(self);
+        for (ns, ns_def) in self.0.iter().sorted_by_key(|(ns, _)| *ns) {
+            for (mut path, ty) in ns_def.declared_type_paths(ns) {
+                let mut result = Ok(());
+                ty.walk_with_path(&mut path, &mut |path, ty| {
+                    let SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) = ty else {
+                        return;
+                    };
+                    for (attr, attr_ty) in attributes {
+                        let Some(example) = &attr_ty.example else {
+                            continue;
+                        };
+                        if result.is_ok() {
+                            let path = path.child(TypePathSegment::Attribute(attr.clone()));
+                            result = serde_json::to_value(example)
+                                .map_err(SchemaError::from)
+                                .and_then(|value| {
+                                    checker.check(ns, &attr_ty.ty, &value, &path.to_string())
+                                });
+                        }
+                    }
+                });
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that every `requiredIf` condition names a sibling attribute and
+    /// compares it with a value of the sibling's type, reporting the first
+    /// problem found.
+    pub(crate) fn validate_required_if(&self) -> Result<()> {
+        let checker = ValueChecker::new(self);
+        let mut result = Ok(());
+        self.walk_value_types(&mut |path, ns, ty| {
+            let SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) = ty else {
+                return;
+            };
+            for (attr, attr_ty) in attributes {
+                let Some(condition) = &attr_ty.required_if else {
+                    continue;
+                };
+                if result.is_err() {
+                    return;
+                }
+                let attr_path = path.child(TypePathSegment::Attribute(attr.clone()));
+                let invalid = |msg: String| SchemaError::InvalidRequiredIf(attr_path.clone(), msg);
+                result = match attributes.get(&condition.attribute) {
+                    None => Err(invalid(format!(
+                        "there is no sibling attribute `{}`",
+                        condition.attribute
+                    ))),
+                    Some(sibling) => serde_json::to_value(&condition.equals)
+                        .map_err(SchemaError::from)
+                        .and_then(|equals| {
+                            checker.check(ns, &sibling.ty, &equals, &condition.attribute)
+                        })
+                        .map_err(|e| invalid(e.to_string())),
+                };
+            }
+        });
+        result
+    }
+
+    fn walk_attribute_examples<'a>(
+        &'a self,
+        f: &mut impl FnMut(TypePath, &'a str, &'a SchemaType, &'a JSONValue),
+    ) {
+        self.walk_value_types(&mut |path, ns, ty| {
+            if let SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) = ty {
+                for (attr, attr_ty) in attributes {
+                    if let Some(example) = &attr_ty.example {
+                        let path = path.child(TypePathSegment::Attribute(attr.clone()));
+                        f(path, ns, &attr_ty.ty, example);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Checks JSON values against `SchemaType`s, resolving any common types
+/// against a `SchemaFragment`.
+/// Unless a mode is set, computed attributes are checked like any other
+/// attribute.
+pub(crate) struct ValueChecker<'a> {
+    schema: &'a SchemaFragment,
+    extensions: Extensions<'static>,
+    mode: Option<AttributeValidationMode>,
+}
+
+impl<'a> ValueChecker<'a> {
+    pub(crate) fn new(schema: &'a SchemaFragment) -> Self {
+        Self {
+            schema,
+            extensions: Extensions::all_available(),
+            mode: None,
+        }
+    }
+
+    /// Check computed attributes according to `mode`.
+    pub(crate) fn with_mode(mut self, mode: AttributeValidationMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Check that `value` has type `ty`, which was written in namespace `ns`.
+    /// `path` names the value in any error message.
+    pub(crate) fn check(
+        &self,
+        ns: &str,
+        ty: &SchemaType,
+        value: &serde_json::Value,
+        path: &str,
+    ) -> Result<()> {
+        self.check_inner(ns, ty, value, &mut path.to_string(), &mut Vec::new())
+    }
+
+    // `expanding` holds the common types we are currently inside of so that a
+    // common type defined in terms of itself is reported instead of looping.
+    fn check_inner(
+        &self,
+        ns: &str,
+        ty: &SchemaType,
+        value: &serde_json::Value,
+        path: &mut String,
+        expanding: &mut Vec<SmolStr>,
+    ) -> Result<()> {
+        let mismatch =
+            |path: &String, msg: String| SchemaError::ValueTypeMismatch(path.clone(), msg);
+        match ty {
+            SchemaType::TypeDef { type_name } => {
+                let qualified = fully_qualify(ns, type_name);
+                let (def_ns, def) =
+                    self.schema
+                        .lookup_common_type(ns, type_name)
+                        .ok_or_else(|| {
+                            SchemaError::UndeclaredCommonType([qualified.to_string()].into())
+                        })?;
+                if expanding.contains(&qualified) {
+                    return Err(mismatch(
+                        path,
+                        format!("common type `{qualified}` is defined in terms of itself"),
+                    ));
+                }
+                expanding.push(qualified);
+                let result = self.check_inner(def_ns, def, value, path, expanding);
+                expanding.pop();
+                result
+            }
+            SchemaType::Type(variant) => {
+                if !matches!(variant, SchemaTypeVariant::Extension { .. }) {
+                    if let Some(ext) = extension_payload(value) {
+                        return Err(mismatch(
+                            path,
+                            format!(
+                                "expected `{}`, found a `{ext}` extension value",
+                                describe_type(ty)
+                            ),
+                        ));
+                    }
+                }
+                self.check_variant(ns, variant, value, path, expanding)
+            }
+        }
+    }
+
+    fn check_variant(
+        &self,
+        ns: &str,
+        variant: &SchemaTypeVariant,
+        value: &serde_json::Value,
+        path: &mut String,
+        expanding: &mut Vec<SmolStr>,
+    ) -> Result<()> {
+        let mismatch =
+            |path: &String, msg: String| SchemaError::ValueTypeMismatch(path.clone(), msg);
+        match variant {
+            SchemaTypeVariant::String {
+                enumeration,
+                pattern,
+            } => match (value.as_str(), enumeration, pattern) {
+                (None, _, _) => Err(mismatch(path, format!("expected a string, found {value}"))),
+                (Some(s), Some(values), _) if !values.iter().any(|v| v == s) => Err(mismatch(
+                    path,
+                    format!(
+                        "expected one of {}, found {value}",
+                        values.iter().map(|v| format!("\"{v}\"")).join(", ")
+                    ),
+                )),
+                (Some(s), _, Some(pattern)) if !pattern.is_match(s) => Err(mismatch(
+                    path,
+                    format!("expected a string matching \"{pattern}\", found {value}"),
+                )),
+                (Some(_), _, _) => Ok(()),
+            },
+            SchemaTypeVariant::Long { min, max } => match value.as_i64() {
+                None => Err(mismatch(path, format!("expected a long, found {value}"))),
+                Some(n) if min.is_some_and(|min| n < min) || max.is_some_and(|max| n > max) => {
+                    Err(mismatch(
+                        path,
+                        format!(
+                            "expected a long between {} and {}, found {n}",
+                            min.unwrap_or(i64::MIN),
+                            max.unwrap_or(i64::MAX)
+                        ),
+                    ))
+                }
+                Some(_) => Ok(()),
+            },
+            SchemaTypeVariant::Boolean if !value.is_boolean() => {
+                Err(mismatch(path, format!("expected a boolean, found {value}")))
+            }
+            SchemaTypeVariant::Boolean => Ok(()),
+            SchemaTypeVariant::Set { element } => {
+                let elements = value
+                    .as_array()
+                    .ok_or_else(|| mismatch(path, format!("expected a set, found {value}")))?;
+                for (i, elem) in elements.iter().enumerate() {
+                    let len = path.len();
+                    path.push_str(&format!("[{i}]"));
+                    self.check_inner(ns, element, elem, path, expanding)?;
+                    path.truncate(len);
+                }
+                Ok(())
+            }
+            SchemaTypeVariant::Record {
+                attributes,
+                additional_attributes,
+            } => {
+                let record = value
+                    .as_object()
+                    .ok_or_else(|| mismatch(path, format!("expected a record, found {value}")))?;
+                for (attr, attr_ty) in attributes {
+                    let computed_mode = self.mode.filter(|_| attr_ty.computed);
+                    match (record.get(attr.as_str()), computed_mode) {
+                        (Some(_), Some(AttributeValidationMode::InputValidation)) => {
+                            return Err(mismatch(
+                                path,
+                                format!("computed attribute `{attr}` must not be supplied"),
+                            ))
+                        }
+                        (Some(attr_val), _) => {
+                            let len = path.len();
+                            path.push_str(&format!(".{attr}"));
+                            self.check_inner(ns, &attr_ty.ty, attr_val, path, expanding)?;
+                            path.truncate(len);
+                        }
+                        (None, Some(AttributeValidationMode::StoredValidation)) => {
+                            return Err(mismatch(
+                                path,
+                                format!("missing computed attribute `{attr}`"),
+                            ))
+                        }
+                        (None, Some(AttributeValidationMode::InputValidation)) => (),
+                        (None, None) if is_required(attr_ty, record) => {
+                            return Err(mismatch(
+                                path,
+                                format!("missing required attribute `{attr}`"),
+                            ))
+                        }
+                        (None, None) => (),
+                    }
+                }
+                if !additional_attributes {
+                    if let Some(attr) = record
+                        .keys()
+                        .find(|attr| !attributes.contains_key(attr.as_str()))
+                    {
+                        return Err(mismatch(path, format!("unexpected attribute `{attr}`")));
+                    }
+                }
+                Ok(())
+            }
+            SchemaTypeVariant::Entity { name } => {
+                let euid = serde_json::from_value::<EntityUidJSON>(value.clone())
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| {
+                        json.into_euid(|| JsonDeserializationErrorContext::Context)
+                            .map_err(|e| e.to_string())
+                    })
+                    .map_err(|e| mismatch(path, format!("expected an entity reference: {e}")))?;
+                let expected = fully_qualify(ns, name);
+                if euid.entity_type().to_string() == expected.as_str() {
+                    Ok(())
+                } else {
+                    Err(mismatch(
+                        path,
+                        format!("expected an entity of type `{expected}`, found `{euid}`"),
+                    ))
+                }
+            }
+            SchemaTypeVariant::Union { options } => {
+                // An option which fails part way through a value leaves the
+                // path to where it failed, so the path is reset after each.
+                let len = path.len();
+                if options.iter().any(|option| {
+                    let matched = self.check_inner(ns, option, value, path, expanding).is_ok();
+                    path.truncate(len);
+                    matched
+                }) {
+                    Ok(())
+                } else {
+                    Err(mismatch(
+                        path,
+                        format!(
+                            "expected one of {}, found {value}",
+                            options
+                                .iter()
+                                .map(|option| format!("`{}`", describe_type(option)))
+                                .join(", ")
+                        ),
+                    ))
+                }
+            }
+            SchemaTypeVariant::Extension { name, ip_kind } => {
+                let ext_name: Name = name.parse().map_err(SchemaError::ExtensionTypeParseError)?;
+                let expected = CoreSchemaType::Extension {
+                    name: ext_name.clone(),
+                };
+                let value = ValueParser::new(self.extensions.clone())
+                    .val_into_rexpr(value.clone(), Some(&expected), || {
+                        JsonDeserializationErrorContext::Context
+                    })
+                    .map_err(|e| e.to_string())
+                    .and_then(|rexpr| {
+                        RestrictedEvaluator::new(&self.extensions)
+                            .interpret(rexpr.as_borrowed())
+                            .map_err(|e| e.to_string())
+                    })
+                    .map_err(|e| {
+                        let expected = extension_format(name)
+                            .map(|format| format!(" (expected {format})"))
+                            .unwrap_or_default();
+                        mismatch(path, format!("invalid `{name}` value{expected}: {e}"))
+                    })?;
+                match value {
+                    Value::ExtensionValue(ev) if ev.typename() == ext_name => match ip_kind {
+                        Some(ip_kind) if ip_kind_of(&ev.to_string()) != *ip_kind => {
+                            let expected = match ip_kind {
+                                IpKind::Cidr => "a network in CIDR notation",
+                                IpKind::Address => "a single address",
+                            };
+                            Err(mismatch(path, format!("expected {expected}, found `{ev}`")))
+                        }
+                        _ => Ok(()),
+                    },
+                    _ => Err(mismatch(path, format!("expected a `{name}` value"))),
+                }
+            }
+        }
+    }
+}
+
+/// The extension function named by `value`, if it is an extension value
+/// written explicitly as `{ "__extn": { "fn": .., "arg": .. } }`.
+fn extension_payload(value: &serde_json::Value) -> Option<&str> {
+    value.get("__extn")?.get("fn")?.as_str()
+}
+
+/// A description of the literals accepted by the extension type `name`, for
+/// the extension types Cedar provides.
+fn extension_format(name: &str) -> Option<&'static str> {
+    match name {
+        "decimal" => Some("a decimal number with at most four digits after the point, e.g. `1.25`"),
+        "ipaddr" => {
+            Some("an IPv4 or IPv6 address or CIDR network, e.g. `10.0.0.1` or `10.0.0.0/8`")
+        }
+        _ => None,
+    }
+}
+
+/// Whether an attribute of type `attr_ty` must be present in `record`, taking
+/// any `requiredIf` condition into account.
+fn is_required(
+    attr_ty: &TypeOfAttribute,
+    record: &serde_json::Map<String, serde_json::Value>,
+) -> bool {
+    match &attr_ty.required_if {
+        None => attr_ty.required,
+        Some(condition) => record
+            .get(condition.attribute.as_str())
+            .is_some_and(|value| {
+                serde_json::to_value(&condition.equals).is_ok_and(|equals| *value == equals)
+            }),
+    }
+}
+
+/// Whether an `ipaddr` value, displayed as a network with an explicit prefix
+/// length, is a single address (i.e., its prefix covers the whole address) or
+/// a larger network.
+fn ip_kind_of(ipaddr: &str) -> IpKind {
+    let full_prefix = if ipaddr.contains(':') { "128" } else { "32" };
+    match ipaddr.rsplit_once('/') {
+        Some((_, prefix)) if prefix != full_prefix => IpKind::Cidr,
+        _ => IpKind::Address,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> SchemaFragment {
+        SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Location": {
+                        "type": "Record",
+                        "attributes": { "city": { "type": "String" } }
+                    }
+                },
+                "entityTypes": { "User": {} },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "count": { "type": "Long" },
+                                    "note": { "type": "String", "required": false },
+                                    "source": { "type": "Extension", "name": "ipaddr" },
+                                    "owner": { "type": "Entity", "name": "User" },
+                                    "where": { "type": "Location" }
+                                }
+                            }
+                        }
+                    },
+                    "list": {}
+                }
+            }
+        }))
+        .expect("Parse Error")
+    }
+
+    fn check(action: &str, context: serde_json::Value) -> Result<()> {
+        let schema = schema();
+        schema.0["App"].actions[action].validate_context(&context, &schema, "App")
+    }
+
+    #[test]
+    #[cfg(feature = "ipaddr")]
+    fn valid_context() {
+        check(
+            "view",
+            json!({
+                "count": 1,
+                "source": { "__extn": { "fn": "ip", "arg": "10.0.0.1" } },
+                "owner": { "type": "App::User", "id": "alice" },
+                "where": { "city": "Seattle" }
+            }),
+        )
+        .expect("context should be valid");
+    }
+
+    #[test]
+    #[cfg(feature = "ipaddr")]
+    fn invalid_context() {
+        let valid = json!({
+            "count": 1,
+            "source": { "fn": "ip", "arg": "10.0.0.1" },
+            "owner": { "__entity": { "type": "App::User", "id": "alice" } },
+            "where": { "city": "Seattle" }
+        });
+        let cases = [
+            ("count", json!("one"), "context.count"),
+            (
+                "source",
+                json!({ "fn": "ip", "arg": "not an ip" }),
+                "context.source",
+            ),
+            (
+                "owner",
+                json!({ "type": "Admin", "id": "alice" }),
+                "context.owner",
+            ),
+            (
+                "where",
+                json!({ "city": "Seattle", "zip": 1 }),
+                "context.where",
+            ),
+            ("extra", json!(true), "context"),
+        ];
+        for (attr, val, expected_path) in cases {
+            let mut context = valid.clone();
+            context[attr] = val;
+            match check("view", context) {
+                Err(SchemaError::ValueTypeMismatch(path, _)) => assert_eq!(path, expected_path),
+                r => panic!("Expected a type mismatch for `{attr}`, got {:?}", r),
+            }
+        }
+
+        let mut context = valid;
+        context.as_object_mut().unwrap().remove("count");
+        assert!(matches!(
+            check("view", context),
+            Err(SchemaError::ValueTypeMismatch(_, msg)) if msg == "missing required attribute `count`"
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "decimal")]
+    fn extension_values_in_context() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {},
+                "actions": {
+                    "buy": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "order": {
+                                        "type": "Record",
+                                        "attributes": {
+                                            "prices": {
+                                                "type": "Set",
+                                                "element": { "type": "Extension", "name": "decimal" }
+                                            },
+                                            "note": { "type": "String", "required": false }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "list": {}
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let check = |action: &str, context: serde_json::Value| {
+            schema.0["App"].actions[action].validate_context(&context, &schema, "App")
+        };
+        let price = |arg: &str| json!({ "__extn": { "fn": "decimal", "arg": arg } });
+        check(
+            "buy",
+            json!({ "order": { "prices": [price("1.25"), price("3.5")] } }),
+        )
+        .expect("context should be valid");
+        assert!(matches!(
+            check("buy", json!({ "order": { "prices": [price("1.25"), price("1.23456")] } })),
+            Err(SchemaError::ValueTypeMismatch(path, msg))
+                if path == "context.order.prices[1]"
+                    && msg.starts_with("invalid `decimal` value (expected a decimal number")
+        ));
+        assert!(matches!(
+            check("buy", json!({ "order": { "prices": [], "note": price("1.0") } })),
+            Err(SchemaError::ValueTypeMismatch(path, msg))
+                if path == "context.order.note"
+                    && msg == "expected `String`, found a `decimal` extension value"
+        ));
+        assert!(matches!(
+            check("list", json!({ "price": price("1.0") })),
+            Err(SchemaError::ValueTypeMismatch(path, msg))
+                if path == "context" && msg == "unexpected attribute `price`"
+        ));
+        assert!(matches!(
+            check("list", price("1.0")),
+            Err(SchemaError::ValueTypeMismatch(path, msg))
+                if path == "context" && msg == "expected `{}`, found a `decimal` extension value"
+        ));
+    }
+
+    #[test]
+    fn extension_sites() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Prices": { "type": "Set", "element": { "type": "Extension", "name": "decimal" } }
+                },
+                "entityTypes": {
+                    "Item": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "prices": { "type": "Prices" },
+                                "name": { "type": "String" },
+                                "origin": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "ip": { "type": "Extension", "name": "ipaddr" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "buy": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "limit": { "type": "Extension", "name": "decimal" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let sites = schema
+            .extension_value_sites()
+            .into_iter()
+            .map(|(path, ext)| (path.to_string(), ext.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sites,
+            vec![
+                ("App::Item.origin.ip".to_string(), "ipaddr".to_string()),
+                ("App::Item.prices[]".to_string(), "decimal".to_string()),
+                (
+                    r#"App::Action::"buy".context.limit"#.to_string(),
+                    "decimal".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn require_extensions() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Prices": { "type": "Set", "element": { "type": "Extension", "name": "decimal" } }
+                },
+                "entityTypes": {
+                    "Item": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "prices": { "type": "Prices" },
+                                "ip": { "type": "Extension", "name": "ipaddr" },
+                                "discount": { "type": "Extension", "name": "decimal" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let enabled = |exts: &[&str]| exts.iter().map(|ext| SmolStr::from(*ext)).collect();
+        schema
+            .require_extensions(&enabled(&["decimal", "ipaddr"]))
+            .expect("all extensions are enabled");
+        match schema.require_extensions(&enabled(&["ipaddr"])) {
+            Err(SchemaError::ExtensionsNotEnabled(disabled)) => assert_eq!(
+                disabled
+                    .into_iter()
+                    .map(|(ext, path)| (ext, path.to_string()))
+                    .collect::<Vec<_>>(),
+                vec![
+                    ("decimal".to_string(), "App::Item.discount".to_string()),
+                    ("decimal".to_string(), "App::Item.prices[]".to_string()),
+                ]
+            ),
+            r => panic!("Expected disabled extensions, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn leaf_scalar_paths() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Tags": { "type": "Set", "element": { "type": "String" } }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "tags": { "type": "Tags" },
+                                "age": { "type": "Long" },
+                                "manager": { "type": "Entity", "name": "User" },
+                                "login": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "ip": { "type": "Extension", "name": "ipaddr" },
+                                        "mfa": { "type": "Boolean" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let leaves = schema
+            .leaf_scalar_paths()
+            .into_iter()
+            .map(|(path, kind)| (path.to_string(), kind))
+            .collect_vec();
+        assert_eq!(
+            leaves,
+            vec![
+                ("App::User.age".to_string(), ScalarKind::Long),
+                (
+                    "App::User.login.ip".to_string(),
+                    ScalarKind::Extension("ipaddr".into())
+                ),
+                ("App::User.login.mfa".to_string(), ScalarKind::Boolean),
+                ("App::User.tags[]".to_string(), ScalarKind::String),
+            ]
+        );
+    }
+
+    #[test]
+    fn extension_sites_by_requiredness() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "commonTypes": {
+                    "Prices": { "type": "Set", "element": { "type": "Extension", "name": "decimal" } }
+                },
+                "entityTypes": {
+                    "Item": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "price": { "type": "Extension", "name": "decimal" },
+                                "discounts": { "type": "Prices", "required": false },
+                                "origin": {
+                                    "type": "Record",
+                                    "required": false,
+                                    "attributes": {
+                                        "ip": { "type": "Extension", "name": "ipaddr" },
+                                        "proxy": { "type": "Extension", "name": "ipaddr", "required": false }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let (required, optional) = schema.extension_sites_by_requiredness();
+        let to_strings = |paths: Vec<TypePath>| paths.iter().map(ToString::to_string).collect_vec();
+        assert_eq!(
+            to_strings(required),
+            vec!["App::Item.origin.ip", "App::Item.price"]
+        );
+        assert_eq!(
+            to_strings(optional),
+            vec!["App::Item.discounts[]", "App::Item.origin.proxy"]
+        );
+    }
+
+    #[test]
+    fn default_context_is_empty() {
+        check("list", json!({})).expect("empty context should be valid");
+        assert!(matches!(
+            check("list", json!({ "anything": 1 })),
+            Err(SchemaError::ValueTypeMismatch(_, _))
+        ));
+    }
+
+    fn examples_schema(example: serde_json::Value) -> Result<SchemaFragment> {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String", "example": "alice" },
+                                "tags": {
+                                    "type": "Set",
+                                    "element": { "type": "String" },
+                                    "example": example
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))?;
+        schema.validate_examples()?;
+        Ok(schema)
+    }
+
+    #[test]
+    fn examples() {
+        let schema = examples_schema(json!(["admin", "staff"])).expect("examples should be valid");
+        let examples = schema
+            .examples()
+            .into_iter()
+            .map(|(path, example)| (path.to_string(), serde_json::to_value(example).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            examples,
+            vec![
+                ("App::User.name".to_string(), json!("alice")),
+                ("App::User.tags".to_string(), json!(["admin", "staff"])),
+            ]
+        );
+        // The example is serialized alongside the attribute type.
+        let round_trip = serde_json::to_value(&schema).expect("Serialize Error");
+        assert_eq!(
+            round_trip["App"]["entityTypes"]["User"]["shape"]["attributes"]["name"],
+            json!({ "type": "String", "required": true, "example": "alice" })
+        );
+    }
+
+    #[test]
+    fn mismatched_example() {
+        match examples_schema(json!(["admin", 1])) {
+            Err(SchemaError::ValueTypeMismatch(path, _)) => assert_eq!(path, "App::User.tags[1]"),
+            r => panic!("Expected a value type mismatch, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn common_type_examples() {
+        let schema = |users: serde_json::Value| {
+            SchemaFragment::from_json_value(json!({
+                "App": {
+                    "commonTypes": {
+                        "Address": {
+                            "type": "Record",
+                            "attributes": { "zip": { "type": "Long", "example": "02139" } }
+                        }
+                    },
+                    "entityTypes": { "User": { "shape": users }, "Group": { "shape": users } },
+                    "actions": {}
+                }
+            }))
+            .expect("Parse Error")
+        };
+        // The example is checked where it is written, even if the common
+        // type is never used, and is reported once however often it is used.
+        let unused = schema(json!({ "type": "Record", "attributes": {} }));
+        let used = schema(json!({
+            "type": "Record",
+            "attributes": {
+                "home": { "type": "Address" },
+                "work": { "type": "Address" }
+            }
+        }));
+        for schema in [unused, used] {
+            match schema.validate_examples() {
+                Err(SchemaError::ValueTypeMismatch(path, _)) => {
+                    assert_eq!(path, "type App::Address.zip")
+                }
+                r => panic!("Expected a value type mismatch, got {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn computed_attributes() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "Doc": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "title": { "type": "String" },
+                                "size": { "type": "Long", "computed": true }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let doc = &schema.0["App"].entity_types["Doc"];
+        let validate = |attrs, mode| doc.validate_attributes(&attrs, &schema, "App", mode);
+        use AttributeValidationMode::*;
+
+        validate(json!({ "title": "a" }), InputValidation).expect("computed may be absent");
+        assert!(validate(json!({ "title": "a", "size": 1 }), InputValidation).is_err());
+        validate(json!({ "title": "a", "size": 1 }), StoredValidation)
+            .expect("computed is present");
+        assert!(validate(json!({ "title": "a" }), StoredValidation).is_err());
+        assert!(validate(json!({ "title": "a", "size": "big" }), StoredValidation).is_err());
+    }
+
+    #[test]
+    fn long_bounds() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "age": { "type": "Long", "min": 0, "max": 150 },
+                                "score": { "type": "Long", "min": 0 }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let user = &schema.0["App"].entity_types["User"];
+        let validate = |attrs| {
+            user.validate_attributes(
+                &attrs,
+                &schema,
+                "App",
+                AttributeValidationMode::StoredValidation,
+            )
+        };
+        validate(json!({ "age": 0, "score": 1000 })).expect("in range");
+        validate(json!({ "age": 150, "score": 0 })).expect("bounds are inclusive");
+        match validate(json!({ "age": 151, "score": 0 })) {
+            Err(SchemaError::ValueTypeMismatch(path, msg)) => {
+                assert_eq!(path, "attrs.age");
+                assert_eq!(msg, "expected a long between 0 and 150, found 151");
+            }
+            r => panic!("Expected a value type mismatch, got {:?}", r),
+        }
+        match validate(json!({ "age": 20, "score": -1 })) {
+            Err(SchemaError::ValueTypeMismatch(path, msg)) => {
+                assert_eq!(path, "attrs.score");
+                assert_eq!(
+                    msg,
+                    "expected a long between 0 and 9223372036854775807, found -1"
+                );
+            }
+            r => panic!("Expected a value type mismatch, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn union_values() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {},
+                    "Service": {},
+                    "Doc": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "owner": {
+                                    "type": "Union",
+                                    "options": [
+                                        { "type": "Entity", "name": "User" },
+                                        { "type": "Entity", "name": "Service" }
+                                    ]
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let doc = &schema.0["App"].entity_types["Doc"];
+        let validate = |owner| {
+            doc.validate_attributes(
+                &json!({ "owner": owner }),
+                &schema,
+                "App",
+                AttributeValidationMode::StoredValidation,
+            )
+        };
+        validate(json!({ "__entity": { "type": "App::User", "id": "alice" } }))
+            .expect("`User` is an option");
+        validate(json!({ "__entity": { "type": "App::Service", "id": "backup" } }))
+            .expect("`Service` is an option");
+        match validate(json!({ "__entity": { "type": "App::Doc", "id": "readme" } })) {
+            Err(SchemaError::ValueTypeMismatch(path, _)) => assert_eq!(path, "attrs.owner"),
+            r => panic!("Expected a value type mismatch, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn union_mismatch_path() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "": {
+                "entityTypes": {},
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "u": {
+                                        "type": "Union",
+                                        "options": [
+                                            { "type": "Set", "element": { "type": "Long" } },
+                                            { "type": "Long" }
+                                        ]
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        let context =
+            |u| schema.0[""].actions["view"].validate_context(&json!({ "u": u }), &schema, "");
+        context(json!([1, 2])).expect("`Set<Long>` is an option");
+        context(json!(1)).expect("`Long` is an option");
+        match context(json!(["x"])) {
+            Err(SchemaError::ValueTypeMismatch(path, _)) => assert_eq!(path, "context.u"),
+            r => panic!("Expected a value type mismatch, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn partial_update() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "city": { "type": "String" },
+                                        "zip": { "type": "String" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let user = &schema.0["App"].entity_types["User"];
+        let patch = json!({ "name": "Alice" });
+        user.validate_partial(&patch, &schema, "App")
+            .expect("missing required attributes are kept as stored");
+        assert!(user
+            .validate_attributes(
+                &patch,
+                &schema,
+                "App",
+                AttributeValidationMode::StoredValidation
+            )
+            .is_err());
+
+        for (patch, path) in [
+            (json!({ "name": 1 }), "attrs.name"),
+            (json!({ "address": { "city": "Paris" } }), "attrs.address"),
+            (json!({ "nickname": "Al" }), "attrs"),
+        ] {
+            match user.validate_partial(&patch, &schema, "App") {
+                Err(SchemaError::ValueTypeMismatch(p, _)) => assert_eq!(p, path),
+                r => panic!("Expected a value type mismatch, got {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "ipaddr")]
+    fn ip_kinds() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "Rule": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "network": { "type": "Extension", "name": "ipaddr", "ipKind": "cidr" },
+                                "host": { "type": "Extension", "name": "ipaddr", "ipKind": "address" },
+                                "any": { "type": "Extension", "name": "ipaddr" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        let rule = &schema.0["App"].entity_types["Rule"];
+        let ip = |arg: &str| json!({ "__extn": { "fn": "ip", "arg": arg } });
+        let validate = |network: &str, host: &str, any: &str| {
+            let attrs = json!({ "network": ip(network), "host": ip(host), "any": ip(any) });
+            rule.validate_attributes(
+                &attrs,
+                &schema,
+                "App",
+                AttributeValidationMode::StoredValidation,
+            )
+        };
+
+        validate("10.0.0.0/8", "10.0.0.1", "10.0.0.1").expect("kinds match");
+        validate("fe80::/10", "::1", "10.0.0.0/8").expect("kinds match");
+        validate("10.0.0.0/8", "10.0.0.1/32", "10.0.0.1").expect("a /32 is an address");
+        for (network, host, bad) in [
+            ("10.0.0.1", "10.0.0.1", "attrs.network"),
+            ("10.0.0.0/8", "10.0.0.0/24", "attrs.host"),
+            ("::1", "::1", "attrs.network"),
+        ] {
+            match validate(network, host, "10.0.0.1") {
+                Err(SchemaError::ValueTypeMismatch(path, _)) => assert_eq!(path, bad),
+                r => panic!("Expected a value type mismatch, got {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn ip_kind_on_other_extension() {
+        let schema = SchemaFragment::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "Item": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "price": { "type": "Extension", "name": "decimal", "ipKind": "cidr" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Parse Error");
+        assert!(matches!(
+            TryInto::<crate::ValidatorSchema>::try_into(schema.clone()),
+            Err(SchemaError::IpKindOnNonIpaddr(name)) if name == "decimal"
+        ));
+        assert!(!schema.validate_all().is_ok());
+    }
+
+    #[test]
+    fn required_if() {
+        let json = |condition: serde_json::Value| {
+            json!({
+                "App": {
+                    "entityTypes": {
+                        "Payment": {
+                            "shape": {
+                                "type": "Record",
+                                "attributes": {
+                                    "paymentType": { "type": "String" },
+                                    "cardNumber": { "type": "String", "requiredIf": condition }
+                                }
+                            }
+                        }
+                    },
+                    "actions": {}
+                }
+            })
+        };
+        let schema = SchemaFragment::from_json_value(json(
+            json!({ "attribute": "paymentType", "equals": "card" }),
+        ))
+        .expect("Parse Error");
+        schema.validate_required_if().expect("condition is valid");
+        let payment = &schema.0["App"].entity_types["Payment"];
+        let validate = |attrs| {
+            payment.validate_attributes(
+                &attrs,
+                &schema,
+                "App",
+                AttributeValidationMode::StoredValidation,
+            )
+        };
+        validate(json!({ "paymentType": "card", "cardNumber": "4111" })).expect("number given");
+        validate(json!({ "paymentType": "cash" })).expect("number not required");
+        match validate(json!({ "paymentType": "card" })) {
+            Err(SchemaError::ValueTypeMismatch(_, msg)) => {
+                assert_eq!(msg, "missing required attribute `cardNumber`")
+            }
+            r => panic!("Expected a value type mismatch, got {:?}", r),
+        }
+
+        for condition in [
+            json!({ "attribute": "kind", "equals": "card" }),
+            json!({ "attribute": "paymentType", "equals": 1 }),
+        ] {
+            let schema = SchemaFragment::from_json_value(json(condition)).expect("Parse Error");
+            match schema.validate_required_if() {
+                Err(SchemaError::InvalidRequiredIf(path, _)) => {
+                    assert_eq!(path.to_string(), "App::Payment.cardNumber")
+                }
+                r => panic!("Expected an invalid condition, got {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "decimal")]
+    fn action_attribute_values() {
+        let action = |attributes: serde_json::Value| -> ActionType {
+            serde_json::from_value(json!({ "attributes": attributes })).expect("Parse Error")
+        };
+        action(json!({
+            "level": 3,
+            "tags": ["a", "b"],
+            "owner": { "__entity": { "type": "User", "id": "alice" } },
+            "limit": { "__extn": { "fn": "decimal", "arg": "1.25" } }
+        }))
+        .validate_attribute_values()
+        .expect("attribute values are valid");
+
+        for (attrs, bad) in [
+            (
+                json!({ "limit": { "__extn": { "fn": "decimal", "arg": "1.2.3" } } }),
+                "limit",
+            ),
+            (json!({ "ok": true, "who": { "__expr": "User::" } }), "who"),
+            (
+                json!({ "f": { "__extn": { "fn": "nosuchfn", "arg": "x" } } }),
+                "f",
+            ),
+        ] {
+            match action(attrs).validate_attribute_values() {
+                Err(SchemaError::InvalidActionAttribute(attr, _)) => assert_eq!(attr, bad),
+                r => panic!("Expected an invalid attribute error, got {:?}", r),
+            }
+        }
+    }
+}
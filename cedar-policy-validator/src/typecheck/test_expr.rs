@@ -54,6 +54,10 @@ fn slot_in_typechecks() {
     let etype = EntityType {
         member_of_types: vec![],
         shape: AttributesOrContext::default(),
+        additional_attributes_definition: None,
+        enumeration: None,
+        tags: None,
+        since: None,
     };
     let schema = NamespaceDefinition::new([("typename".into(), etype)], []);
     assert_typechecks(
@@ -81,6 +85,10 @@ fn slot_equals_typechecks() {
     let etype = EntityType {
         member_of_types: vec![],
         shape: AttributesOrContext::default(),
+        additional_attributes_definition: None,
+        enumeration: None,
+        tags: None,
+        since: None,
     };
     let schema = NamespaceDefinition::new([("typename".into(), etype)], []);
     assert_typechecks(
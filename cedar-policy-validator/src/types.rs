@@ -192,6 +192,38 @@ impl Type {
         Type::ExtensionType { name }
     }
 
+    /// The type of values which may have any of the types in `options`,
+    /// computed without a schema, so only for options which are all the same
+    /// type, all entity types or all booleans. An entity type `Union` is the
+    /// least upper bound of its entity types. `None` if there are no options,
+    /// or the options are not one of these cases.
+    pub(crate) fn union_of(options: impl IntoIterator<Item = Type>) -> Option<Type> {
+        let mut options = options.into_iter();
+        let first = options.next()?;
+        options.try_fold(first, |acc, ty| match (acc, ty) {
+            (acc, ty) if acc == ty => Some(acc),
+            (
+                Type::True
+                | Type::False
+                | Type::Primitive {
+                    primitive_type: Primitive::Bool,
+                },
+                Type::True
+                | Type::False
+                | Type::Primitive {
+                    primitive_type: Primitive::Bool,
+                },
+            ) => Some(Type::primitive_boolean()),
+            (
+                Type::EntityOrRecord(EntityRecordKind::Entity(lub0)),
+                Type::EntityOrRecord(EntityRecordKind::Entity(lub1)),
+            ) => Some(Type::EntityOrRecord(EntityRecordKind::Entity(
+                lub0.least_upper_bound(&lub1),
+            ))),
+            _ => None,
+        })
+    }
+
     /// Implements a subtype relation for the type structure. This requires a
     /// `schema` so that the declared attributes for named entity types can be
     /// retrieved. This is used to determine subtyping between a named entity
@@ -550,9 +550,7 @@ impl FromStr for SchemaFragment {
     /// constructed.
     fn from_str(src: &str) -> Result<Self, Self::Err> {
         Ok(Self(
-            serde_json::from_str::<cedar_policy_validator::SchemaFragment>(src)
-                .map_err(cedar_policy_validator::SchemaError::from)?
-                .try_into()?,
+            cedar_policy_validator::SchemaFragment::from_json_str(src)?.try_into()?,
         ))
     }
 }
@@ -649,6 +647,11 @@ pub enum SchemaError {
     /// duplicate action.
     #[error("Duplicate action {0}")]
     DuplicateAction(String),
+    /// Two different spellings of an action identifier refer to the same
+    /// action once qualified with the implicit `Action` entity type. Arguments
+    /// are the qualified action and the original spellings.
+    #[error("Action {0} is written more than once using different spellings: [{}]", .1.iter().map(String::as_str).join(", "))]
+    ActionIdCollision(String, Vec<String>),
     /// Duplicate specifications for a reusable common type. Argument is the
     /// name of the duplicate type.
     #[error("Duplicate common type {0}")]
@@ -682,6 +685,170 @@ pub enum SchemaError {
     /// than `Record`.
     #[error("Action context or entity type shape is not a record")]
     ContextOrShapeNotRecord,
+    /// A JSON value does not match the type declared for it in the schema.
+    /// Arguments are the path to the offending value and a description of the
+    /// mismatch.
+    #[error("Value at `{0}` does not match the schema: {1}")]
+    ValueTypeMismatch(String, String),
+    /// A JSON Schema document being converted into a schema uses a construct
+    /// with no equivalent in the schema format. Arguments are the JSON pointer
+    /// to the construct and a description of it.
+    #[error("Unsupported JSON Schema construct at `{0}`: {1}")]
+    UnsupportedJsonSchema(String, String),
+    /// References which are not declared in any fragment of a set of schema
+    /// fragments. Each reference is paired with the index of the fragment it
+    /// appears in.
+    #[error("Undeclared references: [{}]", .0.iter().map(|(i, r)| format!("{r} in fragment {i}")).join(", "))]
+    UndeclaredFragmentReferences(Vec<(usize, String)>),
+    /// A common type is defined in terms of itself, so it cannot be resolved.
+    #[error("Common type `{0}` is defined in terms of itself")]
+    CycleInCommonTypeReferences(String),
+    /// A schema exceeds one of the limits it was parsed with. Arguments are
+    /// the name of the limit and its value.
+    #[error("Schema exceeds the `{0}` limit of {1}")]
+    SchemaLimitExceeded(String, usize),
+    /// A common type contains itself by value, so no value of the type can
+    /// be written down. Arguments are the common type and the path at which
+    /// it contains itself.
+    #[error("Common type `{0}` contains itself at `{1}`")]
+    SelfEmbeddingCommonType(String, String),
+    /// Two schema fragments could not be merged. Arguments are the
+    /// declaration or type path where they conflict and a description of the
+    /// conflict.
+    #[error("Cannot merge `{0}`: {1}")]
+    MergeConflict(String, String),
+    /// An action attribute value is not a legal Cedar value. Arguments are
+    /// the attribute name and a description of the problem.
+    #[error("Invalid value for action attribute `{0}`: {1}")]
+    InvalidActionAttribute(String, String),
+    /// An alias in a namespace's `imports` has the same name as an entity
+    /// type or common type declared in that namespace.
+    #[error("Import `{0}` shadows a type declared in the same namespace")]
+    ImportShadowsDeclaration(String),
+    /// An entry in an action's `memberOf` list has an explicit type which is
+    /// not an action entity type. The argument is the entry's entity UID.
+    #[error(
+        "`memberOf` entry `{0}` is not an action: its type must be `Action` or end in `::Action`"
+    )]
+    NonActionMemberOf(String),
+    /// A schema could not be inferred from entity data.
+    #[error("Cannot infer a schema from entity data: {0}")]
+    EntityInference(String),
+    /// A record type has more attributes than allowed. Arguments are the path
+    /// to the record, the number of attributes it has, and the maximum.
+    #[error("Record at `{0}` has {1} attributes, more than the maximum of {2}")]
+    RecordTooWide(String, usize, usize),
+    /// An extension type other than `ipaddr` has an `ipKind`. The argument
+    /// is the name of the extension type.
+    #[error("`ipKind` is only allowed on the `ipaddr` extension type, not `{0}`")]
+    IpKindOnNonIpaddr(String),
+    /// A `requiredIf` condition names an attribute which is not a sibling
+    /// of the conditional attribute, or compares it with a value of the wrong
+    /// type. Arguments are the path to the conditional attribute and a
+    /// description of the problem.
+    #[error("Invalid `requiredIf` on `{0}`: {1}")]
+    InvalidRequiredIf(String, String),
+    /// An `ActionEntityUID` could not be constructed. The argument describes
+    /// the problem.
+    #[error("Invalid action entity UID: {0}")]
+    InvalidActionEntityUID(String),
+    /// A qualified name refers to a namespace which is not defined. This is
+    /// reported instead of an undeclared entity type, common type or action
+    /// when the whole namespace is missing.
+    #[error("Namespace `{namespace}` referenced at `{referenced_in}` is not defined")]
+    UndefinedNamespace {
+        /// Where the qualified name appears.
+        referenced_in: String,
+        /// The namespace which is not defined.
+        namespace: String,
+    },
+    /// A namespace uses a feature which the profile it declares does not
+    /// allow. Arguments are the profile, the location of the feature, and
+    /// the feature.
+    #[error("Profile `{0}` does not allow {2}, used at `{1}`")]
+    ProfileViolation(String, String, String),
+    /// Extension types used by a schema which are not enabled. Each is
+    /// paired with the path to a type where it is used.
+    #[error("Extensions are used but not enabled: [{}]", .0.iter().map(|(ext, path)| format!("{ext} at `{path}`")).join(", "))]
+    ExtensionsNotEnabled(Vec<(String, String)>),
+    /// An entity of an enumerated entity type which is not one of the
+    /// entities the type declares, as the entity type and entity id.
+    #[error("`{1}` is not one of the declared entities of enumerated entity type `{0}`")]
+    EntityNotInEnum(String, String),
+    /// Entity types whose shape is a record with `additionalAttributes`, by
+    /// fully qualified name.
+    #[error("Entity types have open shapes: [{}]", .0.iter().join(", "))]
+    OpenEntityShapes(Vec<String>),
+    /// A schema file with no content other than whitespace and a byte order
+    /// mark.
+    #[error("Schema file is empty")]
+    EmptySchemaFile,
+    /// A `$` reference to a constant which is not declared in the namespace
+    /// it is used in.
+    #[error("Undeclared constant `{0}`")]
+    UndeclaredConstant(String),
+    /// A constant defined in terms of itself.
+    #[error("Constant `{0}` is defined in terms of itself")]
+    CycleInConstants(String),
+    /// Two namespaces with the same name.
+    #[error("Duplicate namespace {0}")]
+    DuplicateNamespace(String),
+    /// Entity types which are transitively members of themselves through
+    /// `memberOfTypes`, in the order of the cycle.
+    #[error("Cycle in entity type hierarchy: {}", .0.iter().join(" -> "))]
+    CycleInEntityTypeHierarchy(Vec<String>),
+    /// References to entity types, common types or actions which a schema
+    /// fragment does not declare itself, in sorted order.
+    #[error("Schema fragment is not self-contained; it references undeclared [{}]", .0.iter().join(", "))]
+    ExternalReferences(Vec<String>),
+    /// A record attribute which is not a `Long` or `decimal` has a `unit`.
+    /// The arguments are the path of the attribute and its type.
+    #[error("`unit` is only allowed on `Long` and `decimal` attributes, but `{0}` has type `{1}`")]
+    UnitOnNonNumeric(String, String),
+    /// An action is nested more deeply in the action hierarchy than allowed.
+    /// The arguments are the chain of actions from the action to a root of
+    /// the hierarchy, and the maximum depth.
+    #[error("Action hierarchy is deeper than {1}: {}", .0.iter().join(" -> "))]
+    ActionHierarchyTooDeep(Vec<String>, usize),
+    /// Entities of an entity type have parents of types which are not in
+    /// its `memberOfTypes`, as the entity type and the disallowed parent
+    /// types.
+    #[error("Entities of type `{0}` cannot have parents of types [{}]", .1.iter().join(", "))]
+    DisallowedParentTypes(String, Vec<String>),
+    /// A common type has the same name as an entity type or an action
+    /// declared in the same namespace. Arguments are the name and what else
+    /// declares it.
+    #[error("Common type `{0}` has the same name as {1} in the same namespace")]
+    NameShadowing(String, String),
+    /// An attribute path starting from a policy scope variable could not be
+    /// given a type for an action. Arguments are the path, written as in a
+    /// policy, the action, and a description of the problem.
+    #[error("Cannot resolve `{0}` for action `{1}`: {2}")]
+    UnresolvableScopePath(String, String, String),
+    /// An `Entity` type names an extension type, e.g. `decimal`, rather than
+    /// a declared entity type. Arguments are the path to the type and the
+    /// extension type name.
+    #[error(
+        "Entity type at `{0}` names the extension type `{1}`; use an `Extension` type instead"
+    )]
+    ExtensionAsEntityType(String, String),
+    /// An entity in entity data which is not valid under the schema, as the
+    /// entity uid, or the index of the entity if it has no valid uid, and a
+    /// description of the problem.
+    #[error("Entity `{0}` is not valid: {1}")]
+    InvalidEntityData(String, String),
+    /// Entity types which are referenced but not declared, each paired with
+    /// where it is referenced.
+    #[error("Undeclared entity types are referenced: [{}]", .0.iter().map(|(name, location)| format!("`{name}` at `{location}`")).join(", "))]
+    UndeclaredEntityTypeReferences(Vec<(String, String)>),
+    /// Actions which are transitively members of themselves through
+    /// `memberOf`, as action entity UIDs in the order of the cycle.
+    #[error("Cycle in action groups: {}", .0.iter().join(" -> "))]
+    CycleInActionGroups(Vec<String>),
+    /// Two parts of a schema would get the same identifier in generated Rust
+    /// code. Arguments are the identifier and descriptions of the two parts.
+    #[error("Generated Rust code would declare `{0}` for both {1} and {2}")]
+    RustIdentifierCollision(String, String, String),
     /// An Action Entity (transitively) has an attribute that is an empty set
     #[error("Action attribute is an empty set")]
     ActionEntityAttributeEmptySet,
@@ -717,6 +884,9 @@ impl From<cedar_policy_validator::SchemaError> for SchemaError {
                 Self::DuplicateEntityType(e)
             }
             cedar_policy_validator::SchemaError::DuplicateAction(e) => Self::DuplicateAction(e),
+            cedar_policy_validator::SchemaError::ActionIdCollision(a, spellings) => {
+                Self::ActionIdCollision(a, spellings)
+            }
             cedar_policy_validator::SchemaError::DuplicateCommonType(c) => {
                 Self::DuplicateCommonType(c)
             }
@@ -741,6 +911,123 @@ impl From<cedar_policy_validator::SchemaError> for SchemaError {
             cedar_policy_validator::SchemaError::ActionEntityAttributes(e) => {
                 Self::ActionEntityAttributes(e)
             }
+            cedar_policy_validator::SchemaError::ValueTypeMismatch(path, msg) => {
+                Self::ValueTypeMismatch(path, msg)
+            }
+            cedar_policy_validator::SchemaError::UnsupportedJsonSchema(path, construct) => {
+                Self::UnsupportedJsonSchema(path, construct)
+            }
+            cedar_policy_validator::SchemaError::UndeclaredFragmentReferences(refs) => {
+                Self::UndeclaredFragmentReferences(refs)
+            }
+            cedar_policy_validator::SchemaError::CycleInCommonTypeReferences(name) => {
+                Self::CycleInCommonTypeReferences(name)
+            }
+            cedar_policy_validator::SchemaError::SchemaLimitExceeded(limit, value) => {
+                Self::SchemaLimitExceeded(limit, value)
+            }
+            cedar_policy_validator::SchemaError::SelfEmbeddingCommonType(name, path) => {
+                Self::SelfEmbeddingCommonType(name, path.to_string())
+            }
+            cedar_policy_validator::SchemaError::MergeConflict(at, msg) => {
+                Self::MergeConflict(at, msg)
+            }
+            cedar_policy_validator::SchemaError::InvalidActionAttribute(attr, msg) => {
+                Self::InvalidActionAttribute(attr, msg)
+            }
+            cedar_policy_validator::SchemaError::ImportShadowsDeclaration(alias) => {
+                Self::ImportShadowsDeclaration(alias)
+            }
+            cedar_policy_validator::SchemaError::NonActionMemberOf(euid) => {
+                Self::NonActionMemberOf(euid)
+            }
+            cedar_policy_validator::SchemaError::EntityInference(msg) => Self::EntityInference(msg),
+            cedar_policy_validator::SchemaError::RecordTooWide(path, count, max) => {
+                Self::RecordTooWide(path.to_string(), count, max)
+            }
+            cedar_policy_validator::SchemaError::IpKindOnNonIpaddr(name) => {
+                Self::IpKindOnNonIpaddr(name)
+            }
+            cedar_policy_validator::SchemaError::InvalidRequiredIf(path, msg) => {
+                Self::InvalidRequiredIf(path.to_string(), msg)
+            }
+            cedar_policy_validator::SchemaError::InvalidActionEntityUID(msg) => {
+                Self::InvalidActionEntityUID(msg)
+            }
+            cedar_policy_validator::SchemaError::UndefinedNamespace {
+                referenced_in,
+                namespace,
+            } => Self::UndefinedNamespace {
+                referenced_in,
+                namespace,
+            },
+            cedar_policy_validator::SchemaError::ProfileViolation(profile, location, feature) => {
+                Self::ProfileViolation(profile, location.to_string(), feature)
+            }
+            cedar_policy_validator::SchemaError::ExtensionsNotEnabled(disabled) => {
+                Self::ExtensionsNotEnabled(
+                    disabled
+                        .into_iter()
+                        .map(|(ext, path)| (ext, path.to_string()))
+                        .collect(),
+                )
+            }
+            cedar_policy_validator::SchemaError::EntityNotInEnum(ty, id) => {
+                Self::EntityNotInEnum(ty, id)
+            }
+            cedar_policy_validator::SchemaError::OpenEntityShapes(names) => {
+                Self::OpenEntityShapes(names)
+            }
+            cedar_policy_validator::SchemaError::EmptySchemaFile => Self::EmptySchemaFile,
+            cedar_policy_validator::SchemaError::UndeclaredConstant(name) => {
+                Self::UndeclaredConstant(name)
+            }
+            cedar_policy_validator::SchemaError::CycleInConstants(name) => {
+                Self::CycleInConstants(name)
+            }
+            cedar_policy_validator::SchemaError::DuplicateNamespace(ns) => {
+                Self::DuplicateNamespace(ns)
+            }
+            cedar_policy_validator::SchemaError::CycleInEntityTypeHierarchy(cycle) => {
+                Self::CycleInEntityTypeHierarchy(cycle)
+            }
+            cedar_policy_validator::SchemaError::ExternalReferences(references) => {
+                Self::ExternalReferences(references)
+            }
+            cedar_policy_validator::SchemaError::UnitOnNonNumeric(path, ty) => {
+                Self::UnitOnNonNumeric(path.to_string(), ty)
+            }
+            cedar_policy_validator::SchemaError::ActionHierarchyTooDeep(chain, max_depth) => {
+                Self::ActionHierarchyTooDeep(chain, max_depth)
+            }
+            cedar_policy_validator::SchemaError::DisallowedParentTypes(ty, parents) => {
+                Self::DisallowedParentTypes(ty, parents)
+            }
+            cedar_policy_validator::SchemaError::NameShadowing(name, other) => {
+                Self::NameShadowing(name, other)
+            }
+            cedar_policy_validator::SchemaError::UnresolvableScopePath(path, action, msg) => {
+                Self::UnresolvableScopePath(path, action, msg)
+            }
+            cedar_policy_validator::SchemaError::ExtensionAsEntityType(path, name) => {
+                Self::ExtensionAsEntityType(path.to_string(), name)
+            }
+            cedar_policy_validator::SchemaError::InvalidEntityData(entity, msg) => {
+                Self::InvalidEntityData(entity, msg)
+            }
+            cedar_policy_validator::SchemaError::UndeclaredEntityTypeReferences(refs) => {
+                Self::UndeclaredEntityTypeReferences(
+                    refs.into_iter()
+                        .map(|(name, location)| (name, location.to_string()))
+                        .collect(),
+                )
+            }
+            cedar_policy_validator::SchemaError::CycleInActionGroups(cycle) => {
+                Self::CycleInActionGroups(cycle)
+            }
+            cedar_policy_validator::SchemaError::RustIdentifierCollision(ident, first, second) => {
+                Self::RustIdentifierCollision(ident, first, second)
+            }
             cedar_policy_validator::SchemaError::ContextOrShapeNotRecord
             | cedar_policy_validator::SchemaError::ActionEntityAttributeEmptySet
             | cedar_policy_validator::SchemaError::ActionEntityAttributeUnsupportedType => {